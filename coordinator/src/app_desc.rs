@@ -16,6 +16,7 @@
 
 use self::deserialize::Hex;
 use super::values::Value;
+use crate::desc_common::include::load_toml;
 pub use crate::desc_common::{Constructor, GlobalName, LocalName, Namespaced, SimpleName};
 pub use engine::Engine;
 pub use genesis::Genesis;
@@ -23,11 +24,13 @@ use primitives::H256;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::fmt::Debug;
-pub use tendermint::TendermintParams;
+use std::path::Path;
+pub use tendermint::{EmptyBlockPolicy, SealFormat, TendermintParams};
 
 mod deserialize;
 mod engine;
 mod genesis;
+mod hash;
 pub(self) mod params;
 mod tendermint;
 pub(self) mod validator;
@@ -53,6 +56,14 @@ impl AppDesc {
 
         Ok(app_desc)
     }
+
+    /// Reads the app descriptor at the path with the fragments it includes.
+    pub fn load(path: &Path) -> anyhow::Result<AppDesc> {
+        let app_desc: AppDesc = toml::Value::Table(load_toml(path)?).try_into()?;
+        app_desc.validate()?;
+
+        Ok(app_desc)
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -15,10 +15,10 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::tendermint::TendermintParams;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 /// Engine deserialization.
-#[derive(Debug, PartialEq, Deserialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(tag = "type", content = "params")]
 pub enum Engine {
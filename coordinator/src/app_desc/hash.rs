@@ -0,0 +1,142 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{AppDesc, Engine, Genesis};
+use crate::values::Value;
+use ccrypto::blake256;
+use primitives::H256;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::collections::BTreeMap;
+
+/// The parts of `AppDesc` that all the nodes in a chain must agree on.
+/// The maps are sorted so that the serialization doesn't depend on the order in the descriptor.
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Canonical<'a> {
+    modules: BTreeMap<&'a str, CanonicalModule<'a>>,
+    host_genesis_config: BTreeMap<&'a str, Sorted<'a>>,
+    engine: &'a Engine,
+    genesis: &'a Genesis,
+    transactions: BTreeMap<&'a str, &'a str>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct CanonicalModule<'a> {
+    hash: String,
+    transactions: Vec<&'a str>,
+    genesis_config: Sorted<'a>,
+    tags: BTreeMap<&'a str, Sorted<'a>>,
+}
+
+/// Serializes a `Value` with the keys of its maps sorted.
+struct Sorted<'a>(&'a Value);
+
+impl<'a> Serialize for Sorted<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Value::List(list) => serializer.collect_seq(list.iter().map(Sorted)),
+            Value::Map(map) => {
+                let sorted: BTreeMap<_, _> = map.iter().collect();
+                let mut serialized = serializer.serialize_map(Some(sorted.len()))?;
+                for (key, value) in sorted {
+                    serialized.serialize_entry(key, &Sorted(value))?;
+                }
+                serialized.end()
+            }
+            value => value.serialize(serializer),
+        }
+    }
+}
+
+impl AppDesc {
+    /// The hash of the canonical serialization of the consensus-relevant parts of the descriptor.
+    /// `param-defaults` is not included, because the params must be merged before the hash is computed.
+    pub fn hash(&self) -> H256 {
+        let canonical = Canonical {
+            modules: self
+                .modules
+                .iter()
+                .map(|(name, setup)| {
+                    (&***name, CanonicalModule {
+                        hash: format!("{:x}", setup.hash.value),
+                        transactions: setup.transactions.iter().map(|name| &***name).collect(),
+                        genesis_config: Sorted(&setup.genesis_config),
+                        tags: setup.tags.iter().map(|(tag, value)| (tag.as_str(), Sorted(value))).collect(),
+                    })
+                })
+                .collect(),
+            host_genesis_config: self
+                .host
+                .genesis_config
+                .iter()
+                .map(|(name, value)| (name.as_str(), Sorted(value)))
+                .collect(),
+            engine: &self.host.engine,
+            genesis: &self.host.genesis,
+            transactions: self.transactions.iter().map(|(tx_type, owner)| (tx_type.as_str(), &***owner)).collect(),
+        };
+        blake256(serde_cbor::to_vec(&canonical).expect("The app descriptor can be serialized"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unindent::unindent;
+
+    fn app_desc(transactions: &str) -> AppDesc {
+        AppDesc::from_str(&unindent(&format!(
+            r#"
+            [modules.module-account]
+            hash = "a010000000012345678901234567890123456789012345678901234567890123"
+            genesis-config = {{ a = 1, b = [2, 3], c = {{ d = 4, e = 5 }} }}
+
+            [modules.module-token]
+            hash = "a040000000012345678901234567890123456789012345678901234567890123"
+
+            [transactions]
+            {}
+
+            [param-defaults]
+            num-threads = "10"
+            "#,
+            transactions
+        )))
+        .unwrap()
+    }
+
+    #[test]
+    fn hash_is_deterministic() {
+        let hash = app_desc(r#"pay = "module-account""#).hash();
+        for _ in 0..10 {
+            assert_eq!(hash, app_desc(r#"pay = "module-account""#).hash());
+        }
+    }
+
+    #[test]
+    fn hash_depends_on_the_transactions() {
+        assert_ne!(app_desc(r#"pay = "module-account""#).hash(), app_desc(r#"pay = "module-token""#).hash());
+    }
+
+    #[test]
+    fn hash_ignores_param_defaults() {
+        let mut desc = app_desc(r#"pay = "module-account""#);
+        let hash = desc.hash();
+        desc.param_defaults.insert("num-threads".to_owned(), "20".to_owned());
+        assert_eq!(hash, desc.hash());
+    }
+}
@@ -21,10 +21,7 @@ use std::collections::BTreeMap;
 
 impl AppDesc {
     pub fn merge_params(&mut self, params: &BTreeMap<String, String>) -> anyhow::Result<()> {
-        let mut merged_params = self.param_defaults.clone();
-        merged_params.append(&mut params.clone());
-
-        let merger = Merger::new(&merged_params);
+        let merger = Merger::with_sources(&self.param_defaults, params);
 
         for (name, setup) in self.modules.iter_mut() {
             setup.merge_params(&merger).with_context(|| format!("module: {}", name))?;
@@ -14,10 +14,10 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 /// Tendermint params deserialization.
-#[derive(Debug, PartialEq, Deserialize, Default)]
+#[derive(Debug, PartialEq, Deserialize, Serialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct TendermintParams {
     /// Propose step timeout in milliseconds.
@@ -38,4 +38,67 @@ pub struct TendermintParams {
     pub allowed_past_timegap: Option<u64>,
     /// allowed future time gap in milliseconds.
     pub allowed_future_timegap: Option<u64>,
+    /// The encoding of precommit signatures in block seals.
+    pub seal_format: Option<SealFormat>,
+    /// When the proposer creates a block without transactions.
+    pub empty_block: Option<EmptyBlockPolicy>,
+}
+
+/// How the precommit signatures are put in a block seal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SealFormat {
+    /// A list of signatures, each of which is verified one by one.
+    Individual,
+    /// The concatenated signatures, which are verified in a batch.
+    BatchEd25519,
+}
+
+impl Default for SealFormat {
+    fn default() -> Self {
+        SealFormat::Individual
+    }
+}
+
+/// When the proposer creates a block without transactions.
+///
+/// The validators wait in the propose step instead of moving to the next view while an empty block is not allowed
+/// and the mem pool is empty.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EmptyBlockPolicy {
+    /// An empty block is created whenever there is no transaction.
+    Always,
+    /// An empty block is created if the given seconds have passed since the parent block.
+    Interval(u64),
+    /// An empty block is created only to apply the change of the validator set.
+    ValidatorSetChange,
+    /// A block is created only when the mem pool has transactions.
+    Never,
+}
+
+impl Default for EmptyBlockPolicy {
+    fn default() -> Self {
+        EmptyBlockPolicy::Always
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_block_policy_deserialization() {
+        let params: TendermintParams = toml::from_str("").unwrap();
+        assert_eq!(None, params.empty_block);
+
+        let params: TendermintParams = toml::from_str(r#"emptyBlock = "never""#).unwrap();
+        assert_eq!(Some(EmptyBlockPolicy::Never), params.empty_block);
+
+        let params: TendermintParams = toml::from_str(r#"emptyBlock = "validatorSetChange""#).unwrap();
+        assert_eq!(Some(EmptyBlockPolicy::ValidatorSetChange), params.empty_block);
+
+        let params: TendermintParams = toml::from_str("emptyBlock = { interval = 30 }").unwrap();
+        assert_eq!(Some(EmptyBlockPolicy::Interval(30)), params.empty_block);
+    }
 }
@@ -0,0 +1,218 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A single descriptor having both the app descriptor and the link descriptor.
+//!
+//! Each module has its app-level setup and its link-level setup in the same table:
+//!
+//! ```toml
+//! default-sandboxer = "single-process"
+//!
+//! [modules.module-account]
+//! hash = "a010000000012345678901234567890123456789012345678901234567890123"
+//! exports = { stateful.stateful = {}, tx-owner.tx-owner = {} }
+//! ```
+//!
+//! The combined descriptor is split into an `AppDesc` and a `LinkDesc` when it is loaded.
+//! The `AppDesc` keeps only the app-level setup, so the link-level setup, which differs between nodes,
+//! never affects what the nodes must agree on.
+
+use crate::app_desc::AppDesc;
+use crate::desc_common::include::load_toml;
+use crate::link_desc::LinkDesc;
+use crate::link_graph::HOST_NAME;
+use anyhow::{bail, Context as _};
+use std::path::Path;
+use toml::value::{Table, Value};
+
+const APP_KEYS: &[&str] = &["transactions", "param-defaults"];
+const LINK_KEYS: &[&str] = &["default-sandboxer", "param-defaults"];
+const APP_MODULE_KEYS: &[&str] = &["hash", "transactions", "genesis-config", "tags"];
+const LINK_MODULE_KEYS: &[&str] =
+    &["sandboxer", "exports", "imports", "init-config", "limits", "restart-node-on-crash"];
+const APP_HOST_KEYS: &[&str] = &["genesis-config", "engine", "genesis"];
+const LINK_HOST_KEYS: &[&str] = &["exports", "imports", "init-config"];
+
+/// Reads the combined descriptor at the path with the fragments it includes.
+pub fn load_combined_desc(path: &Path) -> anyhow::Result<(AppDesc, LinkDesc)> {
+    let (app, link) = split(load_toml(path)?)?;
+
+    let app_desc: AppDesc = Value::Table(app).try_into().context("app descriptor")?;
+    app_desc.validate()?;
+    let link_desc: LinkDesc = Value::Table(link).try_into().context("link descriptor")?;
+    link_desc.validate()?;
+
+    Ok((app_desc, link_desc))
+}
+
+fn split(combined: Table) -> anyhow::Result<(Table, Table)> {
+    let mut app = Table::new();
+    let mut link = Table::new();
+    let mut app_modules = Table::new();
+    let mut link_modules = Table::new();
+
+    for (key, value) in combined {
+        match key.as_str() {
+            "modules" => {
+                for (module, setup) in into_table(value, "modules")? {
+                    let context = format!("modules.{}", module);
+                    let (app_setup, link_setup) =
+                        split_table(into_table(setup, &context)?, APP_MODULE_KEYS, LINK_MODULE_KEYS, &context)?;
+                    app_modules.insert(module.clone(), Value::Table(app_setup));
+                    link_modules.insert(module, Value::Table(link_setup));
+                }
+            }
+            "host" => {
+                let (app_setup, link_setup) =
+                    split_table(into_table(value, "host")?, APP_HOST_KEYS, LINK_HOST_KEYS, "host")?;
+                app.insert(key, Value::Table(app_setup));
+                link_modules.insert(HOST_NAME.to_owned(), Value::Table(link_setup));
+            }
+            _ => {
+                let is_app = APP_KEYS.contains(&key.as_str());
+                let is_link = LINK_KEYS.contains(&key.as_str());
+                if !is_app && !is_link {
+                    bail!("Unknown key '{}' in the descriptor", key)
+                }
+                if is_link {
+                    link.insert(key.clone(), value.clone());
+                }
+                if is_app {
+                    app.insert(key, value);
+                }
+            }
+        }
+    }
+    // The weaver needs the host in the link descriptor even if nothing is linked to it.
+    link_modules.entry(HOST_NAME.to_owned()).or_insert_with(|| Value::Table(Table::new()));
+
+    app.insert("modules".to_owned(), Value::Table(app_modules));
+    link.insert("modules".to_owned(), Value::Table(link_modules));
+    Ok((app, link))
+}
+
+fn split_table(table: Table, app_keys: &[&str], link_keys: &[&str], context: &str) -> anyhow::Result<(Table, Table)> {
+    let mut app = Table::new();
+    let mut link = Table::new();
+    for (key, value) in table {
+        if app_keys.contains(&key.as_str()) {
+            app.insert(key, value);
+        } else if link_keys.contains(&key.as_str()) {
+            link.insert(key, value);
+        } else {
+            bail!("Unknown key '{}' in {}", key, context)
+        }
+    }
+    Ok((app, link))
+}
+
+fn into_table(value: Value, context: &str) -> anyhow::Result<Table> {
+    match value {
+        Value::Table(table) => Ok(table),
+        _ => bail!("{} must be a table", context),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unindent::unindent;
+
+    #[test]
+    fn split_into_app_and_link() {
+        let combined: Table = toml::from_str(&unindent(
+            r#"
+            default-sandboxer = "single-process"
+
+            [modules.module-account]
+            hash = "a010000000012345678901234567890123456789012345678901234567890123"
+            genesis-config = { amount = "{{amount}}" }
+            init-config = { thread-pool-size = 16 }
+
+            [modules.module-account.exports]
+            init-consensus.init-consensus = {}
+
+            [transactions]
+            pay = "module-account"
+
+            [param-defaults]
+            amount = "100"
+            "#,
+        ))
+        .unwrap();
+        let (app, link) = split(combined).unwrap();
+
+        let app_desc: AppDesc = Value::Table(app).try_into().unwrap();
+        app_desc.validate().unwrap();
+        let setup = &app_desc.modules["module-account"];
+        assert_eq!(
+            "a010000000012345678901234567890123456789012345678901234567890123",
+            format!("{:x}", setup.hash.value)
+        );
+        assert_eq!(Some("100"), app_desc.param_defaults.get("amount").map(String::as_str));
+
+        let link_desc: LinkDesc = Value::Table(link).try_into().unwrap();
+        link_desc.validate().unwrap();
+        assert_eq!("single-process", link_desc.default_sandboxer);
+        assert!(link_desc.get("module-account").unwrap().exports.contains_key("init-consensus"));
+        assert!(link_desc.get(HOST_NAME).is_some());
+    }
+
+    #[test]
+    fn limits_need_multi_process() {
+        let desc = |sandboxer: &str| {
+            let combined: Table = toml::from_str(&unindent(&format!(
+                r#"
+                default-sandboxer = "{}"
+
+                [modules.module-account]
+                hash = "a010000000012345678901234567890123456789012345678901234567890123"
+                limits = {{ memory = 1073741824, no-network = true, cgroup = {{ cpu-max = 50 }} }}
+                "#,
+                sandboxer
+            )))
+            .unwrap();
+            let (_, link) = split(combined).unwrap();
+            let link_desc: LinkDesc = Value::Table(link).try_into().unwrap();
+            link_desc
+        };
+
+        let link_desc = desc("multi-process");
+        link_desc.validate().unwrap();
+        let limits = &link_desc.get("module-account").unwrap().limits;
+        assert_eq!(Some(1 << 30), limits.memory);
+        assert!(limits.no_network);
+        assert_eq!(Some(50), limits.cgroup.as_ref().unwrap().cpu_max);
+
+        assert_eq!(
+            "The module 'module-account' has limits, which only the 'multi-process' sandboxer can enforce",
+            desc("single-process").validate().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn unknown_module_key() {
+        let combined: Table = toml::from_str(&unindent(
+            r#"
+            [modules.module-account]
+            hash = "a010000000012345678901234567890123456789012345678901234567890123"
+            sandbox = "single-process"
+            "#,
+        ))
+        .unwrap();
+        assert_eq!("Unknown key 'sandbox' in modules.module-account", split(combined).unwrap_err().to_string());
+    }
+}
@@ -26,6 +26,7 @@ use std::fmt::Debug;
 use std::ops::{Deref, DerefMut};
 use std::{fmt, fmt::Display, fmt::Formatter};
 
+pub(crate) mod include;
 pub(crate) mod params;
 
 macro_rules! module_delim {
@@ -0,0 +1,125 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use anyhow::{anyhow, bail, Context as _};
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::value::{Table, Value};
+
+const INCLUDE_KEY: &str = "include";
+
+/// Reads a descriptor and the fragments it includes.
+///
+/// A descriptor can have `include = ["fragment.toml", ...]`, whose paths are relative to the descriptor.
+/// The fragments are merged in order and then the descriptor is merged on them.
+/// Tables are merged recursively, but a value defined in two files is an error.
+pub(crate) fn load_toml(path: &Path) -> anyhow::Result<Table> {
+    load_toml_inner(path, &mut Vec::new())
+}
+
+fn load_toml_inner(path: &Path, including: &mut Vec<PathBuf>) -> anyhow::Result<Table> {
+    let canonical = fs::canonicalize(path).with_context(|| format!("Cannot find {}", path.display()))?;
+    if including.contains(&canonical) {
+        bail!("{} includes itself", path.display())
+    }
+
+    let source = fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    let mut table: Table = toml::from_str(&source).with_context(|| format!("Cannot parse {}", path.display()))?;
+    let includes = match table.remove(INCLUDE_KEY) {
+        None => return Ok(table),
+        Some(Value::Array(includes)) => includes,
+        Some(_) => bail!("'{}' in {} must be an array of paths", INCLUDE_KEY, path.display()),
+    };
+
+    including.push(canonical);
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut merged = Table::new();
+    for include in includes {
+        let include = include
+            .as_str()
+            .ok_or_else(|| anyhow!("'{}' in {} must be an array of paths", INCLUDE_KEY, path.display()))?;
+        let fragment = load_toml_inner(&base.join(include), including)?;
+        merge_table(&mut merged, fragment, "").with_context(|| format!("Cannot include {}", include))?;
+    }
+    including.pop();
+
+    merge_table(&mut merged, table, "").with_context(|| format!("Cannot merge {}", path.display()))?;
+    Ok(merged)
+}
+
+fn merge_table(base: &mut Table, other: Table, prefix: &str) -> anyhow::Result<()> {
+    for (key, value) in other {
+        if !base.contains_key(&key) {
+            base.insert(key, value);
+            continue
+        }
+        let path = format!("{}{}", prefix, key);
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(other)) => merge_table(base, other, &format!("{}.", path))?,
+            _ => bail!("'{}' is defined more than once", path),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_files(files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("fragments")).unwrap();
+        for (file, content) in files {
+            fs::write(dir.path().join(file), content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn fragments_are_merged() {
+        let dir = write_files(&[
+            ("desc.toml", "include = [\"fragments/a.toml\"]\n[modules.a]\nsandboxer = \"single-process\"\n"),
+            ("fragments/a.toml", "include = [\"b.toml\"]\n[modules.a]\nhash = \"00\"\n"),
+            ("fragments/b.toml", "[modules.b]\nhash = \"01\"\n"),
+        ]);
+        let table = load_toml(&dir.path().join("desc.toml")).unwrap();
+        let expected: Table =
+            toml::from_str("[modules.a]\nhash = \"00\"\nsandboxer = \"single-process\"\n[modules.b]\nhash = \"01\"\n")
+                .unwrap();
+        assert_eq!(expected, table);
+    }
+
+    #[test]
+    fn value_defined_twice() {
+        let dir = write_files(&[
+            ("desc.toml", "include = [\"fragments/a.toml\"]\n[modules.a]\nhash = \"01\"\n"),
+            ("fragments/a.toml", "[modules.a]\nhash = \"00\"\n"),
+        ]);
+        let error = load_toml(&dir.path().join("desc.toml")).unwrap_err();
+        assert!(format!("{:#}", error).contains("'modules.a.hash' is defined more than once"), "{:#}", error);
+    }
+
+    #[test]
+    fn recursive_include() {
+        let dir = write_files(&[
+            ("desc.toml", "include = [\"fragments/a.toml\"]\n"),
+            ("fragments/a.toml", "include = [\"../desc.toml\"]\n"),
+        ]);
+        let error = load_toml(&dir.path().join("desc.toml")).unwrap_err();
+        assert!(format!("{:#}", error).contains("includes itself"), "{:#}", error);
+    }
+}
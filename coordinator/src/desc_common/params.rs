@@ -18,6 +18,11 @@ use crate::values::TOMLValueDeserializer;
 use crate::values::Value;
 use handlebars::{no_escape, Context, Handlebars, TemplateRenderError};
 use std::collections::BTreeMap;
+use std::env;
+
+/// The prefix of the environment variables supplying params.
+/// `FOUNDRY_PARAM_NUM_THREADS=10` supplies `num-threads = "10"`.
+pub const PARAM_ENV_PREFIX: &str = "FOUNDRY_PARAM_";
 
 /// You use Handlebars style template in app descriptor.
 ///   some-config = "{{other-variable}}"
@@ -44,11 +49,28 @@ impl<'reg> Merger<'reg> {
         }
     }
 
+    /// Creates a merger with the params from all the sources.
+    /// The given params take precedence over the environment variables,
+    /// which take precedence over the defaults in the descriptor.
+    pub(crate) fn with_sources(defaults: &BTreeMap<String, String>, params: &BTreeMap<String, String>) -> Merger<'reg> {
+        let mut merged_params = defaults.clone();
+        merged_params.extend(params_from_env(env::vars()));
+        merged_params.extend(params.iter().map(|(name, value)| (name.clone(), value.clone())));
+        Merger::new(&merged_params)
+    }
+
     fn merge(&self, s: &str) -> Result<String, TemplateRenderError> {
         self.registry.render_template_with_context(s, &self.context)
     }
 }
 
+fn params_from_env(vars: impl Iterator<Item = (String, String)>) -> impl Iterator<Item = (String, String)> {
+    vars.filter_map(|(key, value)| {
+        let name = key.strip_prefix(PARAM_ENV_PREFIX)?;
+        Some((name.to_lowercase().replace('_', "-"), value))
+    })
+}
+
 impl Value {
     pub(crate) fn merge_params(&mut self, merger: &Merger) -> anyhow::Result<()> {
         match self {
@@ -85,8 +107,9 @@ impl Value {
 
 #[cfg(test)]
 mod tests {
-    use super::Merger;
+    use super::{params_from_env, Merger};
     use crate::values::Value;
+    use std::collections::BTreeMap;
 
     #[test]
     fn merge_into_string_value() {
@@ -169,6 +192,22 @@ mod tests {
         )
     }
 
+    #[test]
+    fn params_from_env_vars() {
+        let vars = vec![
+            ("FOUNDRY_PARAM_NUM_THREADS".to_owned(), "10".to_owned()),
+            ("FOUNDRY_PARAM_HELLO".to_owned(), "world".to_owned()),
+            ("HOME".to_owned(), "/root".to_owned()),
+        ];
+        let params: BTreeMap<_, _> = params_from_env(vars.into_iter()).collect();
+        assert_eq!(
+            params,
+            vec![("hello".to_owned(), "world".to_owned()), ("num-threads".to_owned(), "10".to_owned())]
+                .into_iter()
+                .collect()
+        );
+    }
+
     #[test]
     fn merge_atat() {
         let params = vec![("hello".to_owned(), "world".to_owned())].into_iter().collect();
@@ -18,8 +18,10 @@ use crate::context::StorageAccess;
 use crate::header::Header;
 use crate::transaction::{Transaction, TransactionWithMetadata};
 use crate::types::{
-    BlockOutcome, CloseBlockError, ErrorCode, FilteredTxs, HeaderError, TransactionOutcome, VerifiedCrime,
+    BlockOutcome, CloseBlockError, ErrorCode, FilteredTxs, HeaderError, ModuleCrash, TransactionOutcome, TxPriority,
+    TxSeq, VerifiedCrime,
 };
+use ckey::Ed25519Public as Public;
 use ctypes::{ChainParams, CompactValidatorSet};
 use std::sync::Arc;
 
@@ -62,6 +64,16 @@ pub trait TxFilter: Send + Sync {
         memory_limit: Option<usize>,
         size_limit: Option<usize>,
     ) -> FilteredTxs<'a>;
+
+    /// Returns `None` if the app doesn't tell the account and the sequence of the transaction.
+    fn account_and_seq(&self, transaction: &Transaction) -> Option<(Public, TxSeq)>;
+
+    /// Returns `None` if the app doesn't tell the priority of the transaction.
+    fn priority(&self, transaction: &Transaction) -> Option<TxPriority>;
+
+    /// Returns the sequences of the next transactions of the accounts in the order of `accounts`,
+    /// or `None` if the app doesn't tell them.
+    fn next_seqs(&self, storage: &mut dyn StorageAccess, accounts: &[Public]) -> Option<Vec<TxSeq>>;
 }
 
 pub trait GraphQlHandlerProvider: Send + Sync {
@@ -71,3 +83,13 @@ pub trait GraphQlHandlerProvider: Send + Sync {
     fn new_session_for_query(&self, storage: &mut dyn StorageAccess) -> crate::module::SessionId;
     fn end_session_for_query(&self, session: crate::module::SessionId);
 }
+
+pub type CrashListener = Box<dyn Fn(&ModuleCrash) + Send + Sync>;
+
+pub trait HealthChecker: Send + Sync {
+    /// The modules crashed so far. No block can be executed while it is not empty.
+    fn crashed_modules(&self) -> Vec<ModuleCrash>;
+
+    /// Registers a listener called whenever a module crashes.
+    fn add_crash_listener(&self, listener: CrashListener);
+}
@@ -0,0 +1,194 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::engine::CrashListener;
+use crate::types::ModuleCrash;
+use cmodule::sandbox::{subscribe_crashes, Crash};
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+/// How often the watcher checks whether its `Health` is dropped.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a failed call waits for the crash of the module to be reported.
+const CRASH_REPORT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The crashes of the modules of an app.
+pub(crate) struct Health {
+    /// The names of the modules by the paths they are loaded from.
+    module_paths: HashMap<PathBuf, String>,
+    /// The modules marked with `restart-node-on-crash` in the link descriptor.
+    restartable: HashSet<String>,
+    crashes: Mutex<Vec<ModuleCrash>>,
+    listeners: Mutex<Vec<CrashListener>>,
+}
+
+impl Health {
+    /// Creates a `Health` and a thread watching the crashes of the modules until the `Health` is dropped.
+    pub(crate) fn watch(module_paths: HashMap<PathBuf, String>, restartable: HashSet<String>) -> Arc<Self> {
+        let health = Arc::new(Self {
+            module_paths,
+            restartable,
+            crashes: Default::default(),
+            listeners: Default::default(),
+        });
+
+        let receiver = subscribe_crashes();
+        let weak = Arc::downgrade(&health);
+        std::thread::Builder::new()
+            .name("module watcher".to_string())
+            .spawn(move || loop {
+                let crash = receiver.recv_timeout(WATCH_INTERVAL);
+                let health = match Weak::upgrade(&weak) {
+                    Some(health) => health,
+                    None => break,
+                };
+                if let Ok(crash) = crash {
+                    health.on_crash(crash);
+                }
+            })
+            .expect("Failed to spawn the module watcher");
+        health
+    }
+
+    fn on_crash(&self, crash: Crash) {
+        let module = match self.module_paths.get(&crash.path) {
+            Some(module) => module.clone(),
+            // A module of another coordinator.
+            None => return,
+        };
+        cerror!(COORDINATOR, "The module {} crashed: {}", module, crash.reason);
+        let crash = ModuleCrash {
+            restartable: self.restartable.contains(&module),
+            module,
+            reason: crash.reason,
+        };
+        self.crashes.lock().push(crash.clone());
+        for listener in self.listeners.lock().iter() {
+            listener(&crash);
+        }
+    }
+
+    pub(crate) fn crashes(&self) -> Vec<ModuleCrash> {
+        self.crashes.lock().clone()
+    }
+
+    pub(crate) fn add_listener(&self, listener: CrashListener) {
+        self.listeners.lock().push(listener);
+    }
+
+    /// Returns an error describing the crashed modules if there is any.
+    pub(crate) fn check(&self) -> Result<(), String> {
+        let crashes = self.crashes.lock();
+        if crashes.is_empty() {
+            return Ok(())
+        }
+        let modules: Vec<_> = crashes.iter().map(|crash| format!("{} ({})", crash.module, crash.reason)).collect();
+        Err(format!("The modules crashed: {}", modules.join(", ")))
+    }
+
+    /// Calls a module, and turns a panic caused by a crashed module into an error.
+    ///
+    /// A call to a crashed module panics in its proxy. The crash may be reported a little after the panic,
+    /// so it waits for the report for a while. A panic not explained by a crash is propagated.
+    pub(crate) fn call<T>(&self, f: impl FnOnce() -> T) -> Result<T, String> {
+        let payload = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+            Ok(result) => return Ok(result),
+            Err(payload) => payload,
+        };
+        let deadline = Instant::now() + CRASH_REPORT_TIMEOUT;
+        loop {
+            if let Err(err) = self.check() {
+                return Err(err)
+            }
+            if Instant::now() >= deadline {
+                std::panic::resume_unwind(payload)
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cmodule::sandbox::report_crash;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn health(path: &str) -> Arc<Health> {
+        let module_paths = vec![(PathBuf::from(path), "module-account".to_owned())].into_iter().collect();
+        let restartable = vec!["module-account".to_owned()].into_iter().collect();
+        Health::watch(module_paths, restartable)
+    }
+
+    fn crash(path: &str) {
+        report_crash(Crash {
+            path: PathBuf::from(path),
+            reason: "killed by signal 9".to_owned(),
+        });
+    }
+
+    fn wait_for_crash(health: &Health) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while health.crashes().is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn crash_is_reported_to_the_listeners() {
+        let health = health("health-test-listener");
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_in_listener = Arc::clone(&calls);
+        health.add_listener(Box::new(move |crash| {
+            assert_eq!("module-account", crash.module);
+            calls_in_listener.fetch_add(1, Ordering::SeqCst);
+        }));
+        assert_eq!(Ok(()), health.check());
+
+        crash("health-test-listener");
+        wait_for_crash(&health);
+        assert_eq!(
+            vec![ModuleCrash {
+                module: "module-account".to_owned(),
+                reason: "killed by signal 9".to_owned(),
+                restartable: true,
+            }],
+            health.crashes()
+        );
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+        assert_eq!(Err("The modules crashed: module-account (killed by signal 9)".to_owned()), health.check());
+    }
+
+    #[test]
+    fn crash_of_another_app_is_ignored() {
+        let health = health("health-test-mine");
+        crash("health-test-others");
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(health.crashes().is_empty());
+    }
+
+    #[test]
+    fn panic_of_crashed_module_is_an_error() {
+        let health = health("health-test-panic");
+        crash("health-test-panic");
+        let result: Result<(), _> = health.call(|| panic!("The connection is closed"));
+        assert!(result.unwrap_err().contains("module-account"));
+    }
+}
@@ -22,10 +22,13 @@ extern crate log;
 #[macro_use]
 mod desc_common;
 pub mod app_desc;
+mod combined_desc;
 pub mod context;
 pub mod engine;
 mod header;
+mod health;
 mod link_desc;
+pub mod link_graph;
 mod linkable;
 pub mod module;
 pub mod test_coordinator;
@@ -35,20 +38,26 @@ pub mod values;
 mod weaver;
 
 pub use crate::app_desc::AppDesc;
+pub use crate::combined_desc::load_combined_desc;
 use crate::context::StorageAccess;
-use crate::engine::{BlockExecutor, ExecutionId, GraphQlHandlerProvider, Initializer, TxFilter};
+use crate::engine::{
+    BlockExecutor, CrashListener, ExecutionId, GraphQlHandlerProvider, HealthChecker, Initializer, TxFilter,
+};
 pub use crate::header::Header;
+use crate::health::Health;
 pub use crate::link_desc::LinkDesc;
+pub use crate::link_graph::LinkGraph;
 use crate::module::{
-    HandleCrimes, HandleGraphQlRequest, InitConsensus, InitGenesis, SessionId, SortedTxs, Stateful, TxOwner, TxSorter,
-    UpdateConsensus,
+    GetAccountAndSeq, GetPriority, GetSeq, HandleCrimes, HandleGraphQlRequest, InitConsensus, InitGenesis, SessionId,
+    SortedTxs, Stateful, TxOwner, TxSorter, UpdateConsensus,
 };
 pub use crate::transaction::{Transaction, TransactionWithMetadata, TxOrigin};
 use crate::types::{
-    BlockOutcome, CloseBlockError, ErrorCode, ExecuteTransactionError, FilteredTxs, HeaderError, TransactionOutcome,
-    VerifiedCrime,
+    BlockOutcome, CloseBlockError, ErrorCode, ExecuteTransactionError, FilteredTxs, HeaderError, ModuleCrash,
+    TransactionOutcome, TxPriority, TxSeq, VerifiedCrime,
 };
 use crate::weaver::Weaver;
+use ckey::Ed25519Public as Public;
 use cmodule::sandbox::Sandbox;
 use ctypes::StorageId;
 use ctypes::{ChainParams, CompactValidatorSet};
@@ -62,7 +71,7 @@ use std::sync::Arc;
 
 pub(crate) const HOST_ID: &str = "$";
 
-pub(crate) const TX_SERVICES_FOR_HOST: &[&str] = &["tx-owner"];
+pub(crate) const TX_SERVICES_FOR_HOST: &[&str] = &["tx-owner", "get-account-and-seq", "get-priority"];
 
 pub(crate) type Occurrences = (Bound<usize>, Bound<usize>);
 
@@ -72,6 +81,7 @@ pub(crate) static SERVICES_FOR_HOST: &[(Occurrences, &str)] = &[
     ((Included(0), Excluded(2)), "update-consensus"),
     ((Included(0), Unbounded), "stateful"),
     ((Included(0), Excluded(2)), "tx-sorter"),
+    ((Included(0), Excluded(2)), "get-seq"),
     ((Included(0), Excluded(2)), "handle-crimes"),
     ((Included(0), Unbounded), "handle-graphql-request"),
 ];
@@ -91,6 +101,9 @@ pub struct Coordinator {
 
     /// List of `Sandbox`es of the modules constituting the current application.
     _sandboxes: Vec<Box<dyn Sandbox>>,
+
+    /// The crashes of the modules.
+    health: Arc<Health>,
 }
 
 const SESSION_BITS_PER_SLOT: usize = mem::size_of::<SessionSlot>() * 8;
@@ -100,7 +113,13 @@ impl Coordinator {
         cmodule::init_modules();
 
         let weaver = Weaver::new();
-        let (sandboxes, mut services) = weaver.weave(app_desc, link_desc)?;
+        let (sandboxes, module_paths, mut services) = weaver.weave(app_desc, link_desc)?;
+        let restartable = link_desc
+            .modules
+            .iter()
+            .filter(|(_, setup)| setup.restart_node_on_crash)
+            .map(|(name, _)| (**name).clone())
+            .collect();
 
         // The order of stateful decides the assignment of substorage ids. It MUST be deterministic.
         services.stateful.lock().sort_by(|a, b| a.0.cmp(&b.0));
@@ -114,6 +133,7 @@ impl Coordinator {
         Ok(Coordinator {
             services,
             _sandboxes: sandboxes,
+            health: Health::watch(module_paths, restartable),
             sessions: RwLock::new(vec![0]),
         })
     }
@@ -190,6 +210,15 @@ pub struct Services {
     /// A service sorting Tx'es in the mempool.
     pub tx_sorter: Box<dyn TxSorter>,
 
+    /// A map from Tx type to the service telling the account and the sequence of a Tx.
+    pub get_account_and_seq: HashMap<String, Box<dyn GetAccountAndSeq>>,
+
+    /// A map from Tx type to the service telling the priority of a Tx.
+    pub get_priority: HashMap<String, Box<dyn GetPriority>>,
+
+    /// An optional service telling the sequence of the next Tx of an account.
+    pub get_seq: Option<Box<dyn GetSeq>>,
+
     /// A map from module name to its GraphQL handler
     pub handle_graphqls: Vec<(String, Arc<dyn HandleGraphQlRequest>)>,
 }
@@ -205,6 +234,9 @@ impl Default for Services {
             init_consensus: Box::new(PanickingInitConsensus) as Box<dyn InitConsensus>,
             update_consensus: Box::new(NoOpUpdateConsensus) as Box<dyn UpdateConsensus>,
             tx_sorter: Box::new(DefaultTxSorter) as Box<dyn TxSorter>,
+            get_account_and_seq: Default::default(),
+            get_priority: Default::default(),
+            get_seq: None,
             handle_graphqls: Default::default(),
         }
     }
@@ -286,13 +318,17 @@ impl BlockExecutor for Coordinator {
         cdebug!(COORDINATOR, "open block");
         let services = &self.services;
 
-        let session_id = self.new_session(storage);
+        self.health.check()?;
+        let session_id = self.health.call(|| self.new_session(storage))?;
 
-        services.handle_crimes.handle_crimes(session_id, verified_crimes);
+        self.health.call(|| -> Result<(), HeaderError> {
+            services.handle_crimes.handle_crimes(session_id, verified_crimes);
 
-        for owner in services.tx_owner.values() {
-            owner.block_opened(session_id, header)?;
-        }
+            for owner in services.tx_owner.values() {
+                owner.block_opened(session_id, header)?;
+            }
+            Ok(())
+        })??;
 
         Ok(session_id)
     }
@@ -313,7 +349,15 @@ impl BlockExecutor for Coordinator {
             match services.tx_owner.get(tx.tx_type()) {
                 Some(owner) => {
                     storage.create_checkpoint();
-                    match owner.execute_transaction(session_id, tx) {
+                    let result = match self.health.call(|| owner.execute_transaction(session_id, tx)) {
+                        Ok(result) => result,
+                        Err(err) => {
+                            storage.revert_to_the_checkpoint();
+                            cwarn!(COORDINATOR, "execute transaction stopped {}, {}: {}", tx.tx_type(), tx.hash(), err);
+                            return Err(())
+                        }
+                    };
+                    match result {
                         Ok(outcome) => {
                             outcomes.push(outcome);
                             storage.discard_checkpoint();
@@ -354,7 +398,13 @@ impl BlockExecutor for Coordinator {
         let SortedTxs {
             sorted,
             ..
-        } = services.tx_sorter.sort_txs(session_id, &owned_txs);
+        } = match self.health.call(|| services.tx_sorter.sort_txs(session_id, &owned_txs)) {
+            Ok(sorted_txs) => sorted_txs,
+            Err(err) => {
+                cwarn!(COORDINATOR, "prepare_block: cannot sort transactions: {}", err);
+                return Vec::new()
+            }
+        };
 
         let mut tx_n_outcomes: Vec<(&'a Transaction, TransactionOutcome)> = Vec::new();
         let mut remaining_block_space = storage.max_body_size();
@@ -369,7 +419,21 @@ impl BlockExecutor for Coordinator {
                     break
                 }
                 storage.create_checkpoint();
-                match owner.execute_transaction(session_id, &tx) {
+                let result = match self.health.call(|| owner.execute_transaction(session_id, &tx)) {
+                    Ok(result) => result,
+                    Err(err) => {
+                        storage.revert_to_the_checkpoint();
+                        cwarn!(
+                            COORDINATOR,
+                            "prepare_block: execute transaction stopped {}, {}: {}",
+                            tx.tx_type(),
+                            tx.hash(),
+                            err
+                        );
+                        break
+                    }
+                };
+                match result {
                     Ok(outcome) => {
                         cdebug!(
                             COORDINATOR,
@@ -409,13 +473,19 @@ impl BlockExecutor for Coordinator {
         let services = &self.services;
 
         let session_id = execution_id as SessionId;
-        let mut events = Vec::new();
-        for owner in services.tx_owner.values() {
-            events.extend(owner.block_closed(session_id)?.into_iter());
-        }
-        let (updated_validator_set, updated_chain_params) = services.update_consensus.update_consensus(session_id);
+        self.health.check()?;
+        let (events, updated_validator_set, updated_chain_params) =
+            self.health.call(|| -> Result<_, CloseBlockError> {
+                let mut events = Vec::new();
+                for owner in services.tx_owner.values() {
+                    events.extend(owner.block_closed(session_id)?.into_iter());
+                }
+                let (updated_validator_set, updated_chain_params) =
+                    services.update_consensus.update_consensus(session_id);
 
-        self.end_session(session_id);
+                self.end_session(session_id);
+                Ok((events, updated_validator_set, updated_chain_params))
+            })??;
 
         Ok(BlockOutcome {
             updated_validator_set,
@@ -478,6 +548,39 @@ impl TxFilter for Coordinator {
             low_priority,
         }
     }
+
+    fn account_and_seq(&self, tx: &Transaction) -> Option<(Public, TxSeq)> {
+        let get_account_and_seq = self.services.get_account_and_seq.get(tx.tx_type())?;
+        self.health.call(|| get_account_and_seq.get_account_and_seq(tx)).ok()?.ok()
+    }
+
+    fn priority(&self, tx: &Transaction) -> Option<TxPriority> {
+        let get_priority = self.services.get_priority.get(tx.tx_type())?;
+        self.health.call(|| get_priority.get_priority(tx)).ok()
+    }
+
+    fn next_seqs(&self, storage: &mut dyn StorageAccess, accounts: &[Public]) -> Option<Vec<TxSeq>> {
+        let get_seq = self.services.get_seq.as_ref()?;
+
+        self.health
+            .call(|| {
+                let session_id = self.new_session(storage);
+                let seqs = accounts.iter().map(|account| get_seq.get_seq(session_id, account)).collect();
+                self.end_session(session_id);
+                seqs
+            })
+            .ok()
+    }
+}
+
+impl HealthChecker for Coordinator {
+    fn crashed_modules(&self) -> Vec<ModuleCrash> {
+        self.health.crashes()
+    }
+
+    fn add_crash_listener(&self, listener: CrashListener) {
+        self.health.add_listener(listener)
+    }
 }
 
 impl GraphQlHandlerProvider for Coordinator {
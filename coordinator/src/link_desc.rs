@@ -15,9 +15,12 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::values::Value;
+use crate::desc_common::include::load_toml;
 use crate::desc_common::{Constructor, GlobalName, Namespaced, SimpleName};
+use cmodule::limits::ResourceLimits;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::path::Path;
 
 mod params;
 mod validator;
@@ -44,6 +47,13 @@ pub struct ModuleSetup {
     pub imports: Namespaced<GlobalName>,
     #[serde(default)]
     pub init_config: Value,
+    /// The limits on the resources of the module, which only the `multi-process` sandboxer can enforce.
+    #[serde(default)]
+    pub limits: ResourceLimits,
+    /// Whether the whole node, not only the sandbox of the module, restarts when the module crashes.
+    /// It must be set only for a module that keeps no state other than the storage.
+    #[serde(default)]
+    pub restart_node_on_crash: bool,
 }
 
 #[allow(clippy::should_implement_trait)]
@@ -55,6 +65,14 @@ impl LinkDesc {
         Ok(link_desc)
     }
 
+    /// Reads the link descriptor at the path with the fragments it includes.
+    pub fn load(path: &Path) -> anyhow::Result<LinkDesc> {
+        let link_desc: LinkDesc = toml::Value::Table(load_toml(path)?).try_into()?;
+        link_desc.validate()?;
+
+        Ok(link_desc)
+    }
+
     pub fn get(&self, module_name: &str) -> Option<&ModuleSetup> {
         self.modules.get(module_name)
     }
@@ -18,10 +18,13 @@ use crate::desc_common::GlobalName;
 use crate::{app_desc::Namespaced, LinkDesc};
 use anyhow::bail;
 
+const MULTI_PROCESS_SANDBOXER: &str = "multi-process";
+
 impl LinkDesc {
     pub fn validate(&self) -> anyhow::Result<()> {
         self.sandboxer_specified()?;
         self.module_imports_are_valid()?;
+        self.limits_are_enforceable()?;
 
         Ok(())
     }
@@ -50,6 +53,24 @@ impl LinkDesc {
         bail!("No sandboxer is specified for modules: {}", modules_without_sandboxer.join(", "))
     }
 
+    fn limits_are_enforceable(&self) -> anyhow::Result<()> {
+        for (module, setup) in self.modules.iter().filter(|(_, setup)| !setup.limits.is_unlimited()) {
+            let sandboxer = if setup.sandboxer.is_empty() {
+                &self.default_sandboxer
+            } else {
+                &setup.sandboxer
+            };
+            if sandboxer != MULTI_PROCESS_SANDBOXER {
+                bail!(
+                    "The module '{}' has limits, which only the '{}' sandboxer can enforce",
+                    module,
+                    MULTI_PROCESS_SANDBOXER
+                )
+            }
+        }
+        Ok(())
+    }
+
     fn module_imports_are_valid(&self) -> anyhow::Result<()> {
         for (module, setup) in self.modules.iter() {
             self.imports_are_valid(&format!("A module, '{}'", module), &setup.imports)?;
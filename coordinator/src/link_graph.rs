@@ -0,0 +1,221 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The links between modules, resolved from the descriptors without loading the modules.
+//!
+//! The weaver finds a missing import only when it links the loaded modules. `LinkGraph` resolves the links with
+//! the same `Resolution` as the weaver, so that the descriptors can be checked before a node boots.
+
+use crate::app_desc::AppDesc;
+use crate::link_desc::LinkDesc;
+use crate::weaver::Resolution;
+use crate::HOST_ID;
+use anyhow::anyhow;
+use std::fmt;
+
+/// The name of the host in the link descriptor.
+pub const HOST_NAME: &str = "host";
+
+/// A service imported by a module from another module.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Link {
+    /// The module importing the service.
+    pub importer: String,
+    /// The name the importer uses for the service.
+    pub slot: String,
+    /// The module exporting the service.
+    pub exporter: String,
+    /// The export name of the service.
+    pub export: String,
+}
+
+#[derive(Debug)]
+pub struct LinkGraph {
+    modules: Vec<String>,
+    links: Vec<Link>,
+}
+
+impl LinkGraph {
+    /// Resolves all the links the weaver would make from the descriptors.
+    /// It fails with every problem found, not only the first one.
+    pub fn build(app_desc: &AppDesc, link_desc: &LinkDesc) -> anyhow::Result<Self> {
+        let mut errors = Vec::new();
+        for name in link_desc.modules.keys() {
+            if !app_desc.modules.contains_key(&***name) && &***name != HOST_NAME {
+                errors.push(format!("The module '{}' is in the link descriptor but not in the app descriptor", name));
+            }
+        }
+        let resolution = Resolution::resolve(app_desc, link_desc);
+        if let Err(err) = &resolution {
+            errors.push(err.to_string());
+        }
+        if !errors.is_empty() {
+            return Err(anyhow!("{}", errors.join("\n")))
+        }
+        let resolution = resolution.expect("Checked above");
+
+        let mut modules: Vec<_> = resolution.modules().map(module_name).collect();
+        modules.sort();
+        let mut links: Vec<_> = resolution
+            .imports()
+            .map(|(importer, exporter, import)| Link {
+                importer: module_name(importer),
+                slot: import.to.clone(),
+                exporter: module_name(exporter),
+                export: import.from.clone(),
+            })
+            .collect();
+        links.sort();
+        Ok(Self {
+            modules,
+            links,
+        })
+    }
+
+    /// The modules in the graph including the host, sorted by name.
+    pub fn modules(&self) -> &[String] {
+        &self.modules
+    }
+
+    /// The links sorted by the importer.
+    pub fn links(&self) -> &[Link] {
+        &self.links
+    }
+
+    /// Renders the graph in the DOT language. An edge points from the importer to the exporter.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph foundry {\n");
+        for module in &self.modules {
+            dot.push_str(&format!("    \"{}\";\n", module));
+        }
+        for link in &self.links {
+            dot.push_str(&format!("    \"{}\" -> \"{}\" [label=\"{}\"];\n", link.importer, link.exporter, link.export));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// The weaver names the host `HOST_ID`, while the link descriptor names it `HOST_NAME`.
+fn module_name(name: &str) -> String {
+    if name == HOST_ID {
+        HOST_NAME.to_owned()
+    } else {
+        name.to_owned()
+    }
+}
+
+impl fmt::Display for LinkGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for module in &self.modules {
+            writeln!(f, "{}", module)?;
+            for link in self.links.iter().filter(|link| link.importer == *module) {
+                writeln!(f, "    {} <- {}/{}", link.slot, link.exporter, link.export)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unindent::unindent;
+
+    fn app_desc() -> AppDesc {
+        AppDesc::from_str(&unindent(
+            r#"
+            [modules.module-account]
+            hash = "a010000000012345678901234567890123456789012345678901234567890123"
+
+            [modules.module-staking]
+            hash = "a020000000012345678901234567890123456789012345678901234567890123"
+
+            [modules.module-sorting]
+            hash = "a050000000012345678901234567890123456789012345678901234567890123"
+            transactions = ["get-account-and-seq"]
+
+            [transactions]
+            pay = "module-account"
+            "#,
+        ))
+        .unwrap()
+    }
+
+    fn link_desc(staking_imports: &str) -> LinkDesc {
+        toml::from_str(&unindent(&format!(
+            r#"
+            default-sandboxer = "single-process"
+
+            [modules.module-account.exports]
+            tx-owner.tx-owner = {{}}
+            account-manager.account-manager = {{}}
+            get-account-and-seq.get-account-and-seq = {{}}
+
+            [modules.module-staking.exports]
+            init-consensus.init-consensus = {{}}
+
+            [modules.module-staking.imports]
+            {}
+
+            [modules.module-sorting.exports]
+            tx-sorter.tx-sorter = {{}}
+
+            [modules.host]
+            "#,
+            staking_imports
+        )))
+        .unwrap()
+    }
+
+    #[test]
+    fn resolve_links() {
+        let graph =
+            LinkGraph::build(&app_desc(), &link_desc(r#"account-manager = "module-account/account-manager""#)).unwrap();
+        assert_eq!(&["host", "module-account", "module-sorting", "module-staking"], graph.modules());
+
+        let links: Vec<_> =
+            graph.links().iter().map(|link| (&*link.importer, &*link.slot, &*link.exporter, &*link.export)).collect();
+        assert_eq!(
+            vec![
+                ("host", "@tx/pay/get-account-and-seq", "module-account", "get-account-and-seq"),
+                ("host", "@tx/pay/tx-owner", "module-account", "tx-owner"),
+                ("host", "init-consensus/module-staking", "module-staking", "init-consensus"),
+                ("host", "tx-sorter/module-sorting", "module-sorting", "tx-sorter"),
+                ("module-sorting", "@tx/pay/get-account-and-seq", "module-account", "get-account-and-seq"),
+                ("module-staking", "account-manager", "module-account", "account-manager"),
+            ],
+            links
+        );
+        assert!(graph.to_dot().contains("\"module-staking\" -> \"module-account\" [label=\"account-manager\"];"));
+    }
+
+    #[test]
+    fn report_all_problems() {
+        let link_desc = link_desc(
+            r#"account-manager = "module-account/account-manager"
+            token-manager = "module-token/token-manager"
+            sequence = "module-account/sequence""#,
+        );
+        let error = LinkGraph::build(&app_desc(), &link_desc).unwrap_err().to_string();
+        assert!(
+            error.contains("imports 'token-manager' from 'module-token', which is not a module of the app"),
+            "{}",
+            error
+        );
+        assert!(error.contains("imports non-existing service 'sequence' from 'module-account'"), "{}", error);
+    }
+}
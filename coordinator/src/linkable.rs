@@ -46,11 +46,20 @@ impl UserModule for HostModule {
         let services = guard.as_mut().unwrap();
 
         if let Some(cap) = TX_SERVICE_RE.captures(name) {
-            if &cap[2] == "tx-owner" {
-                services.tx_owner.insert(cap[1].to_owned(), import_service_from_handle(rto_context, handle));
-                return
+            let tx_type = cap[1].to_owned();
+            match &cap[2] {
+                "tx-owner" => {
+                    services.tx_owner.insert(tx_type, import_service_from_handle(rto_context, handle));
+                }
+                "get-account-and-seq" => {
+                    services.get_account_and_seq.insert(tx_type, import_service_from_handle(rto_context, handle));
+                }
+                "get-priority" => {
+                    services.get_priority.insert(tx_type, import_service_from_handle(rto_context, handle));
+                }
+                _ => panic!("Unknown import: {}", name),
             }
-            panic!("Unknown import: {}", name)
+            return
         }
         if let Some(cap) = SERVICE_RE.captures(name) {
             let module = &cap[2];
@@ -70,6 +79,9 @@ impl UserModule for HostModule {
                 "tx-sorter" => {
                     services.tx_sorter = import_service_from_handle(rto_context, handle);
                 }
+                "get-seq" => {
+                    services.get_seq = Some(import_service_from_handle(rto_context, handle));
+                }
                 "handle-graphql-request" => {
                     services.handle_graphqls.push((module.to_owned(), import_service_from_handle(rto_context, handle)));
                 }
@@ -30,8 +30,11 @@
 
 use super::context::SubStorageAccess;
 use crate::transaction::{Transaction, TransactionWithMetadata};
-use crate::types::{CloseBlockError, ErrorCode, Event, HeaderError, TransactionOutcome, VerifiedCrime};
+use crate::types::{
+    CloseBlockError, ErrorCode, Event, HeaderError, TransactionOutcome, TxPriority, TxSeq, VerifiedCrime,
+};
 use crate::Header;
+use ckey::Ed25519Public as Public;
 use ctypes::{ChainParams, CompactValidatorSet};
 use remote_trait_object::{service, Service, ServiceRef};
 use serde::{Deserialize, Serialize};
@@ -119,6 +122,9 @@ pub trait InitConsensus: Service {
 #[service]
 pub trait UpdateConsensus: Service {
     /// Decides the consensus parmaters for this block and returns them.
+    ///
+    /// The consensus timeouts in the returned `ChainParams` are applied from the next height.
+    /// They are ignored unless `ConsensusTimeouts::verify` accepts them.
     fn update_consensus(&self, session_id: SessionId) -> (Option<CompactValidatorSet>, Option<ChainParams>);
 }
 
@@ -145,6 +151,32 @@ pub struct SortedTxs {
     pub sorted: Vec<usize>,
 }
 
+/// A transaction service telling the account which sent a transaction and its sequence.
+///
+/// The mem pool keeps a queue of transactions for each account with it.
+#[service]
+pub trait GetAccountAndSeq: Service {
+    fn get_account_and_seq(&self, tx: &Transaction) -> Result<(Public, TxSeq), ()>;
+}
+
+/// A transaction service telling the priority of a transaction.
+///
+/// A pending transaction in the mem pool is replaced by another one of the same account and sequence
+/// only if the priority of the new one is higher.
+/// Without it, the mem pool keeps both of them.
+#[service]
+pub trait GetPriority: Service {
+    fn get_priority(&self, tx: &Transaction) -> TxPriority;
+}
+
+/// A service telling the sequence of the next transaction of an account.
+///
+/// The mem pool holds the transactions whose sequences are ahead of it until the missing ones arrive.
+#[service]
+pub trait GetSeq: Service {
+    fn get_seq(&self, session_id: SessionId, account: &Public) -> TxSeq;
+}
+
 /// A service to handle crimes reported from the consensus engine.
 #[service]
 pub trait HandleCrimes: Service {
@@ -15,13 +15,16 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::context::StorageAccess;
-use crate::engine::{BlockExecutor, ExecutionId, GraphQlHandlerProvider, Initializer, TxFilter};
+use crate::engine::{
+    BlockExecutor, CrashListener, ExecutionId, GraphQlHandlerProvider, HealthChecker, Initializer, TxFilter,
+};
 use crate::header::Header;
 use crate::transaction::{Transaction, TransactionWithMetadata};
 use crate::types::{
-    BlockOutcome, CloseBlockError, ErrorCode, ExecuteTransactionError, FilteredTxs, HeaderError, TransactionOutcome,
-    VerifiedCrime,
+    BlockOutcome, CloseBlockError, ErrorCode, ExecuteTransactionError, FilteredTxs, HeaderError, ModuleCrash,
+    TransactionOutcome, TxPriority, TxSeq, VerifiedCrime,
 };
+use ckey::Ed25519Public as Public;
 use ctypes::{ChainParams, CompactValidatorSet};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
@@ -138,6 +141,18 @@ impl TxFilter for TestCoordinator {
             low_priority,
         }
     }
+
+    fn account_and_seq(&self, _transaction: &Transaction) -> Option<(Public, TxSeq)> {
+        None
+    }
+
+    fn priority(&self, _transaction: &Transaction) -> Option<TxPriority> {
+        None
+    }
+
+    fn next_seqs(&self, _storage: &mut dyn StorageAccess, _accounts: &[Public]) -> Option<Vec<TxSeq>> {
+        None
+    }
 }
 
 impl GraphQlHandlerProvider for TestCoordinator {
@@ -151,3 +166,11 @@ impl GraphQlHandlerProvider for TestCoordinator {
 
     fn end_session_for_query(&self, _session: crate::module::SessionId) {}
 }
+
+impl HealthChecker for TestCoordinator {
+    fn crashed_modules(&self) -> Vec<ModuleCrash> {
+        Vec::new()
+    }
+
+    fn add_crash_listener(&self, _listener: CrashListener) {}
+}
@@ -21,6 +21,7 @@ use crate::Transaction;
 use ctypes::{ChainParams, CompactValidatorSet};
 use serde::{Deserialize, Serialize};
 
+/// The indices are of the validator set at the height.
 #[derive(Serialize, Deserialize)]
 pub enum VerifiedCrime {
     DoubleVote {
@@ -28,6 +29,20 @@ pub enum VerifiedCrime {
         author_index: usize,
         criminal_index: usize,
     },
+    DoubleProposal {
+        height: u64,
+        view: u64,
+        criminal_index: usize,
+    },
+    InvalidBlockVote {
+        height: u64,
+        criminal_index: usize,
+    },
+    /// The validators signed a block conflicting with the canonical block at the same view.
+    LightClientAttack {
+        height: u64,
+        criminal_indices: Vec<usize>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Default, Debug)]
@@ -53,6 +68,22 @@ pub struct BlockOutcome {
 
 pub type ErrorCode = u32;
 
+/// The sequence of a transaction among the transactions of its account.
+pub type TxSeq = u64;
+
+/// A transaction replaces a pending one of the same account and sequence only if its priority is higher.
+pub type TxPriority = u64;
+
+/// A module whose sandbox stopped while the node was running.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleCrash {
+    pub module: String,
+    /// How the module stopped, e.g. the signal that killed it.
+    pub reason: String,
+    /// Whether the module is marked with `restart-node-on-crash` in the link descriptor.
+    pub restartable: bool,
+}
+
 pub struct FilteredTxs<'a> {
     pub invalid: Vec<&'a Transaction>,
     pub low_priority: Vec<&'a Transaction>,
@@ -18,9 +18,10 @@ use parking_lot::RwLock;
 use std::cell::RefCell;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ops::RangeBounds;
+use std::path::PathBuf;
 use std::sync::Arc;
 
-use anyhow::{anyhow, bail, Context};
+use anyhow::{anyhow, Context};
 
 use cmodule::link::{best_linker, Port};
 use cmodule::sandbox::{sandboxer, Sandbox};
@@ -40,21 +41,32 @@ type ServiceSpec<'a> = (&'a str, &'a dyn erased_serde::Serialize);
 
 #[derive(Default)]
 pub(super) struct Weaver {
-    modules: HashMap<String, LinkInfo>,
-    tx_owners: HashMap<String, String>,
+    linkables: HashMap<String, RefCell<Box<dyn Sandbox>>>,
+    module_paths: HashMap<PathBuf, String>,
     services: Arc<RwLock<Option<Services>>>,
 }
 
-struct LinkInfo {
-    linkable: RefCell<Box<dyn Sandbox>>,
+/// The exports and the imports of a module.
+struct Ports {
     exports: ExportIdMap,
-    imports: RefCell<HashMap<String, Vec<Import>>>,
+    /// The imports grouped by the exporting module.
+    imports: HashMap<String, Vec<Import>>,
 }
 
 #[derive(Debug)]
-struct Import {
-    from: String,
-    to: String,
+pub(crate) struct Import {
+    /// The export name of the service.
+    pub(crate) from: String,
+    /// The name the importer uses for the service.
+    pub(crate) to: String,
+}
+
+/// Every link between the modules, resolved from the descriptors.
+/// The weaver links the loaded modules as resolved here, and `LinkGraph` shows them without loading the modules.
+#[derive(Default)]
+pub(crate) struct Resolution {
+    modules: HashMap<String, Ports>,
+    tx_owners: HashMap<String, String>,
 }
 
 impl Weaver {
@@ -66,28 +78,22 @@ impl Weaver {
         mut self,
         app_desc: &AppDesc,
         link_desc: &LinkDesc,
-    ) -> anyhow::Result<(Vec<Box<dyn Sandbox>>, Services)> {
-        self.modules.reserve(app_desc.modules.len());
+    ) -> anyhow::Result<(Vec<Box<dyn Sandbox>>, HashMap<PathBuf, String>, Services)> {
+        let resolution = Resolution::resolve(app_desc, link_desc)?;
+        self.linkables.reserve(app_desc.modules.len() + 1);
 
-        let host_module = link_desc.get("host").ok_or_else(|| anyhow!("can't find host module in app descriptor"))?;
+        let host_module = link_desc.get("host").expect("The resolution checked the host module");
         self.process_host(host_module);
         self.process_modules(app_desc, link_desc)?;
-        self.tx_owners =
-            app_desc.transactions.iter().map(|(tx_type, module)| (tx_type.clone(), (**module).clone())).collect();
-        self.import_tx_services_for_modules(&app_desc.modules);
-        self.import_tx_services(HOST_ID, TX_SERVICES_FOR_HOST);
-        self.import_services(HOST_ID, SERVICES_FOR_HOST)?;
-        self.link_all()?;
+        self.link_all(&resolution)?;
 
-        let linkables = self.modules.into_iter().map(|(_, link_info)| link_info.linkable.into_inner()).collect();
+        let linkables = self.linkables.into_iter().map(|(_, linkable)| linkable.into_inner()).collect();
 
-        Ok((linkables, self.services.write().take().unwrap()))
+        Ok((linkables, self.module_paths, self.services.write().take().unwrap()))
     }
 
     fn process_host(&mut self, link: &link_desc::ModuleSetup) {
-        let (exports, init_exports) = Self::process_exports(&link.exports);
-        let imports = Self::process_imports(&link.imports);
-        let imports = RefCell::new(imports);
+        let (_, init_exports) = process_exports(&link.exports);
 
         let init_exports: Vec<(String, Vec<u8>)> = init_exports
             .iter()
@@ -108,13 +114,7 @@ impl Weaver {
             &init_exports,
         );
 
-        let linkable = RefCell::new(Box::new(linkable) as Box<dyn Sandbox>);
-
-        self.modules.insert(HOST_ID.to_owned(), LinkInfo {
-            linkable,
-            exports,
-            imports,
-        });
+        self.linkables.insert(HOST_ID.to_owned(), RefCell::new(Box::new(linkable) as Box<dyn Sandbox>));
     }
 
     fn process_modules(&mut self, app_desc: &AppDesc, link_desc: &LinkDesc) -> anyhow::Result<()> {
@@ -122,11 +122,7 @@ impl Weaver {
             let link =
                 link_desc.get(name).ok_or_else(|| anyhow!("Failed to find module {} in the link descriptor", name))?;
 
-            let sandboxer_id = if link.sandboxer.is_empty() {
-                &link_desc.default_sandboxer
-            } else {
-                &link.sandboxer
-            };
+            let sandboxer_id = sandboxer_id(link_desc, link);
             let sandboxer = sandboxer(sandboxer_id).ok_or_else(|| anyhow!("Sandboxer unknown: {}", sandboxer_id))?;
             // FIXME: assumes that path is not used to locate a module here. Fix this later when we
             //        introduce a proper module registry.
@@ -135,34 +131,17 @@ impl Weaver {
             } else {
                 format!("{:x}", &setup.hash.value)
             };
-            let (exports, init_exports) = Self::process_exports(&link.exports);
-            let imports = RefCell::new(Self::process_imports(&link.imports));
-            let linkable = RefCell::new(sandboxer.load(&path, &link.init_config, &*init_exports)?);
-
-            self.modules.insert((*name).clone(), LinkInfo {
-                linkable,
-                exports,
-                imports,
-            });
+            let (_, init_exports) = process_exports(&link.exports);
+            let linkable = RefCell::new(sandboxer.load(&path, &link.init_config, &*init_exports, &link.limits)?);
+            self.module_paths.insert(PathBuf::from(path), (*name).clone());
+
+            self.linkables.insert((*name).clone(), linkable);
         }
 
         Ok(())
     }
 
-    fn import_tx_services_for_modules(&mut self, modules: &HashMap<SimpleName, ModuleSetup>) {
-        for (module, services) in modules.iter().filter_map(|(module, setup)| {
-            if setup.transactions.is_empty() {
-                None
-            } else {
-                Some((module, &setup.transactions))
-            }
-        }) {
-            let exports: Box<_> = services.iter().map(|name| &**name as &str).collect();
-            self.import_tx_services(&**module, &exports);
-        }
-    }
-
-    fn link_all(&mut self) -> anyhow::Result<()> {
+    fn link_all(&mut self, resolution: &Resolution) -> anyhow::Result<()> {
         let mut linked_pairs = HashSet::new();
 
         fn set_imports(
@@ -187,9 +166,8 @@ impl Weaver {
 
         static NO_IMPORT: Vec<Import> = Vec::new();
 
-        for (a, link_info_a) in self.modules.iter() {
-            let imports_into_a = link_info_a.imports.borrow();
-            for (b, imports_from_b) in imports_into_a.iter() {
+        for (a, ports_a) in resolution.modules.iter() {
+            for (b, imports_from_b) in ports_a.imports.iter() {
                 let pair = if a < b {
                     (a.clone(), b.clone())
                 } else {
@@ -200,11 +178,11 @@ impl Weaver {
                     continue
                 }
 
-                let link_info_b = &self.modules[b];
-                let exports_from_b = &link_info_b.exports;
+                let ports_b = &resolution.modules[b];
+                let exports_from_b = &ports_b.exports;
 
-                let mut linkable_a = link_info_a.linkable.borrow_mut();
-                let mut linkable_b = link_info_b.linkable.borrow_mut();
+                let mut linkable_a = self.linkables[a].borrow_mut();
+                let mut linkable_b = self.linkables[b].borrow_mut();
 
                 let linker = best_linker(&**linkable_a, &**linkable_b)
                     .with_context(|| format!("no linker for a pair: {} - {}", a, b))?;
@@ -214,9 +192,8 @@ impl Weaver {
 
                 set_imports(exports_from_b, &mut port_a, &mut port_b, imports_from_b);
 
-                let exports_from_a = &link_info_a.exports;
-                let imports_into_b = link_info_b.imports.borrow();
-                let imports_from_a = imports_into_b.get(a).unwrap_or(&NO_IMPORT);
+                let exports_from_a = &ports_a.exports;
+                let imports_from_a = ports_b.imports.get(a).unwrap_or(&NO_IMPORT);
 
                 set_imports(exports_from_a, &mut port_b, &mut port_a, imports_from_a);
 
@@ -224,53 +201,120 @@ impl Weaver {
             }
         }
 
-        self.modules.iter().for_each(|(_, link_info)| link_info.linkable.borrow_mut().seal());
+        self.linkables.iter().for_each(|(_, linkable)| linkable.borrow_mut().seal());
 
         Ok(())
     }
+}
+
+impl Resolution {
+    /// Resolves the links from the descriptors without loading the modules.
+    /// It fails with every problem found, not only the first one.
+    pub(crate) fn resolve(app_desc: &AppDesc, link_desc: &LinkDesc) -> anyhow::Result<Self> {
+        let mut resolution = Self::default();
+        let mut errors = Vec::new();
+
+        match link_desc.get("host") {
+            Some(host_module) => resolution.add_module(HOST_ID.to_owned(), host_module),
+            None => errors.push("can't find host module in app descriptor".to_owned()),
+        }
+        for name in app_desc.modules.keys() {
+            let link = match link_desc.get(name) {
+                Some(link) => link,
+                None => {
+                    errors.push(format!("Failed to find module {} in the link descriptor", name));
+                    continue
+                }
+            };
+            let sandboxer_id = sandboxer_id(link_desc, link);
+            if sandboxer(sandboxer_id).is_none() {
+                errors.push(format!("Sandboxer unknown: {} of module {}", sandboxer_id, name));
+            }
+            resolution.add_module((*name).clone(), link);
+        }
+        if !errors.is_empty() {
+            return Err(anyhow!("{}", errors.join("\n")))
+        }
 
-    fn process_exports(exports: &Namespaced<Constructor>) -> (ExportIdMap, Vec<ServiceSpec>) {
-        let mut export_ids = BTreeMap::new();
-        let mut init_exports: Vec<ServiceSpec> = Vec::with_capacity(exports.len());
-
-        for (
-            i,
-            (
-                export_name,
-                Constructor {
-                    name,
-                    args,
-                },
-            ),
-        ) in exports.iter().enumerate()
-        {
-            export_ids.insert(export_name.clone(), i);
-            init_exports.push((name, args));
+        resolution.tx_owners =
+            app_desc.transactions.iter().map(|(tx_type, module)| (tx_type.clone(), (**module).clone())).collect();
+        resolution.import_tx_services_for_modules(&app_desc.modules);
+        resolution.import_tx_services(HOST_ID, TX_SERVICES_FOR_HOST);
+        if let Err(err) = resolution.import_services(HOST_ID, SERVICES_FOR_HOST) {
+            errors.push(err.to_string());
+        }
+        errors.extend(resolution.unresolved_imports());
+        if !errors.is_empty() {
+            return Err(anyhow!("{}", errors.join("\n")))
         }
 
-        (export_ids, init_exports)
+        Ok(resolution)
+    }
+
+    /// The modules including the host, which is named `HOST_ID`.
+    pub(crate) fn modules(&self) -> impl Iterator<Item = &str> {
+        self.modules.keys().map(String::as_str)
     }
 
-    fn process_imports(imports: &Namespaced<GlobalName>) -> HashMap<String, Vec<Import>> {
-        let mut imports_per_module = HashMap::with_capacity(imports.len());
-        for (import, export) in imports.iter() {
-            let export_from = export.module().to_owned();
-            let export_name = export.name().to_owned();
-            let import_list = imports_per_module.entry(export_from).or_insert_with(Vec::new);
-            import_list.push(Import {
-                from: export_name,
-                to: import.clone(),
-            });
+    /// The imports as (importer, exporter, import).
+    pub(crate) fn imports(&self) -> impl Iterator<Item = (&str, &str, &Import)> {
+        self.modules.iter().flat_map(|(importer, ports)| {
+            ports.imports.iter().flat_map(move |(exporter, imports)| {
+                imports.iter().map(move |import| (importer.as_str(), exporter.as_str(), import))
+            })
+        })
+    }
+
+    fn add_module(&mut self, name: String, link: &link_desc::ModuleSetup) {
+        let (exports, _) = process_exports(&link.exports);
+        let imports = process_imports(&link.imports);
+        self.modules.insert(name, Ports {
+            exports,
+            imports,
+        });
+    }
+
+    /// The imports from a module which doesn't exist or doesn't export the service.
+    fn unresolved_imports(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (importer, exporter, import) in self.imports() {
+            match self.modules.get(exporter) {
+                Some(ports) if ports.exports.contains_key(&import.from) => {}
+                Some(_) => errors.push(format!(
+                    "The module '{}' imports non-existing service '{}' from '{}'",
+                    importer, import.from, exporter
+                )),
+                None => errors.push(format!(
+                    "The module '{}' imports '{}' from '{}', which is not a module of the app",
+                    importer, import.from, exporter
+                )),
+            }
         }
+        errors.sort();
+        errors
+    }
 
-        imports_per_module
+    fn import_tx_services_for_modules(&mut self, modules: &HashMap<SimpleName, ModuleSetup>) {
+        for (module, services) in modules.iter().filter_map(|(module, setup)| {
+            if setup.transactions.is_empty() {
+                None
+            } else {
+                Some((module, &setup.transactions))
+            }
+        }) {
+            let exports: Box<_> = services.iter().map(|name| &**name as &str).collect();
+            self.import_tx_services(&**module, &exports);
+        }
     }
 
     fn import_tx_services(&mut self, module: &str, services: &[&str]) {
-        let imports = &mut self.modules[module].imports.borrow_mut();
+        let mut imports = HashMap::<String, Vec<Import>>::new();
 
         for (tx_type, tx_owner) in self.tx_owners.iter().filter(|(_, owner)| *owner != module) {
-            let exports = &self.modules[tx_owner].exports;
+            let exports = match self.modules.get(tx_owner) {
+                Some(ports) => &ports.exports,
+                None => continue,
+            };
 
             for &service in services {
                 let type_specific_export = format!("{}.{}", service, tx_type);
@@ -289,14 +333,19 @@ impl Weaver {
                 });
             }
         }
+
+        let module_imports = &mut self.modules.get_mut(module).expect("The importer must exist").imports;
+        for (exporter, imports) in imports {
+            module_imports.entry(exporter).or_default().extend(imports);
+        }
     }
 
     fn import_services(&mut self, module: &str, services: &[(Occurrences, &str)]) -> anyhow::Result<()> {
-        let imports = &mut self.modules[module].imports.borrow_mut();
+        let mut imports = HashMap::<String, Vec<Import>>::new();
         let mut counts = HashMap::with_capacity(services.len());
 
-        for (module, link_info) in self.modules.iter().filter(|(name, _)| *name != module) {
-            let exports = &link_info.exports;
+        for (module, ports) in self.modules.iter().filter(|(name, _)| *name != module) {
+            let exports = &ports.exports;
             for (_bounds, service) in services {
                 if exports.contains_key(*service) {
                     imports.entry(module.clone()).or_default().push(Import {
@@ -309,18 +358,70 @@ impl Weaver {
             }
         }
 
+        let module_imports = &mut self.modules.get_mut(module).expect("The importer must exist").imports;
+        for (exporter, imports) in imports {
+            module_imports.entry(exporter).or_default().extend(imports);
+        }
+
+        let mut errors = Vec::new();
         for (bounds, service) in services {
             let count = counts.get(*service).unwrap_or(&0);
             if !bounds.contains(count) {
-                bail!(
+                errors.push(format!(
                     "The number of '{}' ({}) instances doesn't conform to the specification of '{:?}'",
-                    *service,
-                    count,
-                    bounds
-                )
+                    *service, count, bounds
+                ));
             }
         }
+        if !errors.is_empty() {
+            return Err(anyhow!("{}", errors.join("\n")))
+        }
 
         Ok(())
     }
 }
+
+fn sandboxer_id<'a>(link_desc: &'a LinkDesc, link: &'a link_desc::ModuleSetup) -> &'a str {
+    if link.sandboxer.is_empty() {
+        &link_desc.default_sandboxer
+    } else {
+        &link.sandboxer
+    }
+}
+
+fn process_exports(exports: &Namespaced<Constructor>) -> (ExportIdMap, Vec<ServiceSpec>) {
+    let mut export_ids = BTreeMap::new();
+    let mut init_exports: Vec<ServiceSpec> = Vec::with_capacity(exports.len());
+
+    for (
+        i,
+        (
+            export_name,
+            Constructor {
+                name,
+                args,
+            },
+        ),
+    ) in exports.iter().enumerate()
+    {
+        export_ids.insert(export_name.clone(), i);
+        init_exports.push((name, args));
+    }
+
+    (export_ids, init_exports)
+}
+
+fn process_imports(imports: &Namespaced<GlobalName>) -> HashMap<String, Vec<Import>> {
+    let mut imports_per_module = HashMap::with_capacity(imports.len());
+    for (import, export) in imports.iter() {
+        let export_from = export.module().to_owned();
+        let export_name = export.name().to_owned();
+        let import_list = imports_per_module.entry(export_from).or_insert_with(Vec::new);
+        import_list.push(Import {
+            from: export_name,
+            to: import.clone(),
+        });
+    }
+
+    imports_per_module
+}
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::{Import, LinkInfo, Weaver};
+use super::{Import, Ports, Resolution, Weaver};
 use cmodule::link;
 use cmodule::link::{Linkable, Linker, Port, LINKERS};
 use cmodule::sandbox::Sandbox;
@@ -163,15 +163,21 @@ type LinkerName = str;
 type ImportDef = (&'static str, &'static str);
 type TxType = str;
 
-fn build_modules(list: &[ModuleDef]) -> Vec<(String, Arc<RwLock<LinkRecordPerExporter>>, LinkInfo)> {
+type MockModule = (String, Arc<RwLock<LinkRecordPerExporter>>, RefCell<Box<dyn Sandbox>>, Ports);
+
+fn build_modules(list: &[ModuleDef]) -> Vec<MockModule> {
     list.iter()
         .map(|(name, linkers, exports, imports)| {
             let sandbox = MockSandbox::new(*name, linkers);
-            ((*name).to_owned(), Arc::clone(&sandbox.mappings), LinkInfo {
-                linkable: RefCell::new(Box::new(sandbox)),
-                exports: build_exports(*exports),
-                imports: RefCell::new(build_imports(*imports)),
-            })
+            (
+                (*name).to_owned(),
+                Arc::clone(&sandbox.mappings),
+                RefCell::new(Box::new(sandbox) as Box<dyn Sandbox>),
+                Ports {
+                    exports: build_exports(*exports),
+                    imports: build_imports(*imports),
+                },
+            )
         })
         .collect()
 }
@@ -201,7 +207,7 @@ fn tx_owners(list: &[(&TxType, &ModuleName)]) -> HashMap<String, String> {
     list.iter().map(|(ty, owner)| ((*ty).to_owned(), (*owner).to_owned())).collect()
 }
 
-fn new_test_weaver_with_exports() -> Weaver {
+fn new_test_resolution_with_exports() -> Resolution {
     let modules = build_modules(&[
         ("a", &[], &["service-a", "service-b.tx-type-a", "service-b.tx-type-b"], &[]),
         ("b", &[], &["service-a", "service-b"], &[]),
@@ -209,13 +215,12 @@ fn new_test_weaver_with_exports() -> Weaver {
         ("d", &[], &[], &[]),
     ])
     .into_iter()
-    .map(|(module, _, link_info)| (module, link_info))
+    .map(|(module, _, _, ports)| (module, ports))
     .collect();
 
     let tx_owners = tx_owners(&[("tx-type-a", "a"), ("tx-type-b", "a"), ("tx-type-c", "b")]);
 
-    Weaver {
-        services: Default::default(),
+    Resolution {
         modules,
         tx_owners,
     }
@@ -223,9 +228,9 @@ fn new_test_weaver_with_exports() -> Weaver {
 
 #[test]
 fn import_single_tx_service_from_multi_tx_owner() {
-    let mut weaver = new_test_weaver_with_exports();
-    weaver.import_tx_services("c", &["service-a"]);
-    let imports = weaver.modules["c"].imports.borrow();
+    let mut resolution = new_test_resolution_with_exports();
+    resolution.import_tx_services("c", &["service-a"]);
+    let imports = &resolution.modules["c"].imports;
 
     assert!(!imports.contains_key("c"));
     assert!(!imports.contains_key("d"));
@@ -249,9 +254,9 @@ fn import_single_tx_service_from_multi_tx_owner() {
 
 #[test]
 fn import_per_tx_service_from_multi_tx_owner() {
-    let mut weaver = new_test_weaver_with_exports();
-    weaver.import_tx_services("c", &["service-b"]);
-    let imports = weaver.modules["c"].imports.borrow();
+    let mut resolution = new_test_resolution_with_exports();
+    resolution.import_tx_services("c", &["service-b"]);
+    let imports = &resolution.modules["c"].imports;
 
     assert!(!imports.contains_key("c"));
     assert!(!imports.contains_key("d"));
@@ -276,23 +281,23 @@ fn import_per_tx_service_from_multi_tx_owner() {
 
 #[test]
 fn host_possibly_imports_no_service() {
-    let mut weaver = new_test_weaver_with_exports();
-    weaver
+    let mut resolution = new_test_resolution_with_exports();
+    resolution
         .import_services("d", &[((Included(0), Excluded(2)), "non-existing-service")])
         .expect("should be ok without an instance of the designated service");
 }
 
 #[test]
 fn host_must_import_at_least_a_service() {
-    let mut weaver = new_test_weaver_with_exports();
-    weaver
+    let mut resolution = new_test_resolution_with_exports();
+    resolution
         .import_services("d", &[((Included(1), Unbounded), "non-existing-service")])
         .expect_err("should be at least one instance of the designated service");
-    weaver
+    resolution
         .import_services("d", &[((Included(1), Unbounded), "service-c")])
         .expect("should be at least one instance of the designated service");
 
-    let imports = weaver.modules["d"].imports.borrow();
+    let imports = &resolution.modules["d"].imports;
     let imports_from_c = imports.get("c").expect("there must be an import from 'c'");
 
     assert_eq!(imports_from_c.len(), 1);
@@ -308,15 +313,15 @@ fn host_must_import_at_least_a_service() {
 
 #[test]
 fn host_imports_multiple_instances_of_a_service() {
-    let mut weaver = new_test_weaver_with_exports();
-    weaver
+    let mut resolution = new_test_resolution_with_exports();
+    resolution
         .import_services("d", &[((Included(1), Unbounded), "service-a")])
         .expect("should be at least one instance of the designated service");
-    weaver
+    resolution
         .import_services("d", &[((Included(1), Unbounded), "service-b")])
         .expect("should be at least one instance of the designated service");
 
-    let imports = weaver.modules["d"].imports.borrow();
+    let imports = &resolution.modules["d"].imports;
 
     let imports_from_a = imports.get("a").expect("there must be an import from 'a'");
     let imports_from_b = imports.get("b").expect("there must be an import from 'b'");
@@ -361,17 +366,23 @@ fn link_complex() {
         .map(|(module, record_map, _)| (module.clone(), Arc::clone(record_map)))
         .collect::<HashMap<_, _>>();
 
-    let modules = modules.into_iter().map(|(module, _, link_info)| (module, link_info)).collect();
-
-    let tx_owners = tx_owners(&[]);
+    let mut linkables = HashMap::new();
+    let mut ports = HashMap::new();
+    for (module, _, linkable, module_ports) in modules {
+        linkables.insert(module.clone(), linkable);
+        ports.insert(module, module_ports);
+    }
 
+    let resolution = Resolution {
+        modules: ports,
+        tx_owners: tx_owners(&[]),
+    };
     let mut weaver = Weaver {
-        services: Default::default(),
-        modules,
-        tx_owners,
+        linkables,
+        ..Default::default()
     };
 
-    weaver.link_all().expect("should complete without an error");
+    weaver.link_all(&resolution).expect("should complete without an error");
 
     let a = records.get("a").expect("must be a LinkRecord for a").read();
     assert_eq!(a.len(), 2);
@@ -14,10 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use ckey::{
-    Ed25519KeyPair as KeyPair, Ed25519Private as Private, Ed25519Public as Public, Error as KeyError, Generator,
-    Password, Random,
-};
+use ckey::{Ed25519Private as Private, Ed25519Public as Public, Error as KeyError, Password};
 use ckeystore::accounts_dir::MemoryDirectory;
 use ckeystore::{DecryptedAccount, Error as KeystoreError, KeyStore, SecretStore, SimpleSecretStore};
 use parking_lot::RwLock;
@@ -106,8 +103,7 @@ impl AccountProvider {
     }
 
     pub fn new_account_and_public(&self, password: &Password) -> Result<Public, Error> {
-        let acc: KeyPair = Random.generate().expect("ed25519 context has generation capabilities; qed");
-        self.insert_account(acc.get_private(), password)
+        Ok(self.keystore.new_account(password)?)
     }
 
     pub fn insert_account(&self, private: Private, password: &Password) -> Result<Public, Error> {
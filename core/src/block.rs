@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::consensus::validator_set::select_proposer;
 use crate::consensus::{ConsensusEngine, Evidence, TendermintSealView};
 use crate::error::{BlockError, Error};
 use ccrypto::BLAKE_NULL_RLP;
@@ -21,7 +22,10 @@ use ckey::Ed25519Public as Public;
 use coordinator::engine::{BlockExecutor, ExecutionId};
 use coordinator::types::Event;
 use coordinator::{Header as PreHeader, Transaction, TransactionWithMetadata};
-use cstate::{CurrentValidatorSet, NextValidatorSet, StateDB, StateError, StateWithCache, TopLevelState, TopState};
+use cstate::{
+    CurrentValidatorSet, NextValidatorSet, ProposerPriorities, StateDB, StateError, StateWithCache, TopLevelState,
+    TopState,
+};
 use ctypes::header::{Header, Seal};
 use ctypes::util::unexpected::Mismatch;
 use ctypes::{ChainParams, CompactValidatorSet, TxHash};
@@ -158,6 +162,7 @@ impl OpenBlock {
 
     pub fn open(&mut self, block_executor: &dyn BlockExecutor, engine: &dyn ConsensusEngine) -> Result<(), Error> {
         self.update_current_validator_set()?;
+        self.update_proposer_priorities()?;
 
         let last_committed_validators = {
             let validator_bitset = TendermintSealView::new(self.header().seal())
@@ -300,6 +305,26 @@ impl OpenBlock {
         Ok(())
     }
 
+    // called on open_block
+    // The proposer of this block is selected again to store the priorities for the next block.
+    fn update_proposer_priorities(&mut self) -> Result<(), Error> {
+        let author_view = TendermintSealView::new(self.header().seal())
+            .author_view()
+            .map_err(|_| Error::Block(BlockError::InvalidSeal))?;
+        let validators: Vec<_> = NextValidatorSet::load_from_state(self.state())?
+            .iter()
+            .map(|validator| (*validator.pubkey(), validator.delegation()))
+            .collect();
+        if validators.is_empty() {
+            return Ok(())
+        }
+        let previous = ProposerPriorities::load_from_state(self.state())?;
+        let (_, priorities) = select_proposer(&validators, &previous, author_view + 1);
+        ProposerPriorities::from_vector(priorities).save_to_state(self.inner_mut().state_mut())?;
+
+        Ok(())
+    }
+
     // called on close_block
     fn update_next_block_state(
         &mut self,
@@ -22,7 +22,7 @@ use super::{
 use crate::block::{Block, ClosedBlock, IsBlock, OpenBlock};
 use crate::blockchain::{BlockChain, BlockProvider, BodyProvider, EventProvider, HeaderProvider, TransactionAddress};
 use crate::client::{ConsensusClient, SnapshotClient};
-use crate::consensus::{ConsensusEngine, EngineError};
+use crate::consensus::{ConsensusEngine, ConsensusState, EngineError};
 use crate::encoded;
 use crate::error::{BlockImportError, Error, ImportError, SchemeError};
 use crate::event::EventSource;
@@ -30,6 +30,7 @@ use crate::miner::{Miner, MinerService};
 use crate::service::ClientIoMessage;
 use crate::transaction::{LocalizedTransaction, PendingTransactions};
 use crate::{
+    db::COL_EXTRA,
     genesis::Genesis,
     types::{BlockStatus, TransactionId, VerificationQueueInfo as BlockQueueInfo},
 };
@@ -37,16 +38,17 @@ use cdb::{new_journaldb, Algorithm, AsHashDB};
 use cio::IoChannel;
 use ckey::{Ed25519Public as Public, NetworkId, PlatformAddress};
 use coordinator::context::{ChainHistoryAccess, MemPoolAccess};
-use coordinator::engine::{BlockExecutor, GraphQlHandlerProvider, Initializer};
+use coordinator::engine::{BlockExecutor, GraphQlHandlerProvider, HealthChecker, Initializer};
 use coordinator::module::SessionId;
-use coordinator::types::Event;
+use coordinator::types::{Event, ModuleCrash};
 use coordinator::Transaction;
 use cstate::{StateDB, TopLevelState, TopStateView};
 use ctimer::{TimeoutHandler, TimerApi, TimerScheduleError, TimerToken};
+use ctypes::util::unexpected::Mismatch;
 use ctypes::{BlockHash, BlockId, BlockNumber, ChainParams, Header, SyncHeader, TxHash};
 use kvdb::{DBTransaction, KeyValueDB};
 use parking_lot::{Mutex, RwLock, RwLockReadGuard};
-use primitives::Bytes;
+use primitives::{Bytes, H256};
 use rlp::Rlp;
 use std::collections::HashMap;
 use std::ops::Range;
@@ -54,6 +56,7 @@ use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 use std::sync::{Arc, Weak};
 
 const MAX_MEM_POOL_SIZE: usize = 4096;
+const APP_DESC_HASH_KEY: &[u8] = b"app-desc-hash";
 
 pub struct Client {
     engine: Arc<dyn ConsensusEngine>,
@@ -81,12 +84,16 @@ pub struct Client {
     /// Timer for reseal_min_period on miner client
     reseal_timer: TimerApi,
 
+    /// The hash of the app descriptor the chain is created with, which is saved in COL_EXTRA.
+    app_desc_hash: Option<H256>,
+
+    health_checker: Arc<dyn HealthChecker>,
     session_allocator: Arc<dyn GraphQlHandlerProvider>,
     graphql_handlers: HashMap<String, Arc<dyn coordinator::module::HandleGraphQlRequest>>,
 }
 
 impl Client {
-    pub fn try_new<C: 'static + Initializer + BlockExecutor + GraphQlHandlerProvider>(
+    pub fn try_new<C: 'static + Initializer + BlockExecutor + GraphQlHandlerProvider + HealthChecker>(
         config: &ClientConfig,
         engine: Arc<dyn ConsensusEngine>,
         genesis: &Genesis,
@@ -107,9 +114,25 @@ impl Client {
             db.write(batch)?
         }
 
+        let saved_app_desc_hash =
+            db.get(COL_EXTRA, APP_DESC_HASH_KEY)?.map(|hash| rlp::decode::<H256>(&hash)).transpose()?;
+        if let (Some(expected), Some(found)) = (genesis.app_desc_hash(), saved_app_desc_hash) {
+            if expected != found {
+                return Err(SchemeError::AppDescMismatch(Mismatch {
+                    expected,
+                    found,
+                })
+                .into())
+            }
+        }
         if !genesis.check_genesis_root(state_db.as_hashdb()) {
             return Err(SchemeError::InvalidState.into())
         }
+        if let (Some(app_desc_hash), None) = (genesis.app_desc_hash(), saved_app_desc_hash) {
+            let mut batch = DBTransaction::new();
+            batch.put(COL_EXTRA, APP_DESC_HASH_KEY, &rlp::encode(&app_desc_hash));
+            db.write(batch)?;
+        }
 
         let gb = genesis.block();
         let chain = BlockChain::new(&gb, db.clone());
@@ -120,6 +143,7 @@ impl Client {
             message_channel.clone(),
             Arc::clone(&miner),
             Arc::clone(&coordinator) as Arc<dyn BlockExecutor>,
+            Arc::clone(&coordinator) as Arc<dyn HealthChecker>,
         )?;
 
         let client = Arc::new(Client {
@@ -133,6 +157,8 @@ impl Client {
             importer,
             miner,
             reseal_timer,
+            app_desc_hash: genesis.app_desc_hash(),
+            health_checker: Arc::clone(&coordinator) as Arc<dyn HealthChecker>,
             session_allocator: Arc::clone(&coordinator) as Arc<dyn GraphQlHandlerProvider>,
             graphql_handlers: GraphQlHandlerProvider::get(coordinator.as_ref()).into_iter().collect(),
         });
@@ -147,6 +173,11 @@ impl Client {
         &*self.engine
     }
 
+    /// The hash of the app descriptor the chain is created with.
+    pub fn app_desc_hash(&self) -> Option<H256> {
+        self.app_desc_hash
+    }
+
     /// Adds an actor to be notified on certain events
     pub fn add_notify(&self, target: Weak<dyn ChainNotify>) {
         self.notify.write().push(target);
@@ -359,6 +390,10 @@ impl EngineInfo for Client {
     fn validator_set(&self, block_number: Option<u64>) -> Result<Option<ctypes::CompactValidatorSet>, EngineError> {
         Ok(self.engine().current_validator_set(block_number)?)
     }
+
+    fn consensus_state(&self) -> Option<ConsensusState> {
+        self.engine().consensus_state()
+    }
 }
 
 impl EngineClient for Client {
@@ -608,6 +643,10 @@ impl BlockChainClient for Client {
         let source = EventSource::Block(*hash);
         chain.events(&source)
     }
+
+    fn crashed_modules(&self) -> Vec<ModuleCrash> {
+        self.health_checker.crashed_modules()
+    }
 }
 
 impl BlockProducer for Client {
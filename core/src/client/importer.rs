@@ -27,7 +27,7 @@ use crate::verification::queue::{BlockQueue, HeaderQueue};
 use crate::verification::{PreverifiedBlock, Verifier};
 use crate::views::{BlockView, HeaderView};
 use cio::IoChannel;
-use coordinator::engine::BlockExecutor;
+use coordinator::engine::{BlockExecutor, HealthChecker};
 use ctypes::header::{Header, Seal};
 use ctypes::{BlockHash, BlockId, SyncHeader};
 use kvdb::DBTransaction;
@@ -57,6 +57,9 @@ pub struct Importer {
     /// Validator used to execute transactions
     pub block_executor: Arc<dyn BlockExecutor>,
 
+    /// Used to stop importing blocks while the modules are crashed
+    health_checker: Arc<dyn HealthChecker>,
+
     /// CodeChain engine to be used during import
     pub engine: Arc<dyn ConsensusEngine>,
 }
@@ -68,6 +71,7 @@ impl Importer {
         message_channel: IoChannel<ClientIoMessage>,
         miner: Arc<Miner>,
         block_executor: Arc<dyn BlockExecutor>,
+        health_checker: Arc<dyn HealthChecker>,
     ) -> Result<Importer, Error> {
         let block_queue = BlockQueue::new(&config.queue, engine.clone(), message_channel.clone());
 
@@ -80,6 +84,7 @@ impl Importer {
             header_queue,
             miner,
             block_executor,
+            health_checker,
             engine,
         })
     }
@@ -92,6 +97,12 @@ impl Importer {
             let mut invalid_blocks = HashSet::new();
             let mut update_results = Vec::with_capacity(MAX_BLOCKS_TO_IMPORT);
 
+            if !self.health_checker.crashed_modules().is_empty() {
+                // The blocks stay in the queue, because they are not invalid but cannot be executed.
+                cwarn!(CLIENT, "No block is imported while the modules are crashed");
+                return 0
+            }
+
             let import_lock = self.import_lock.lock();
             let blocks = self.block_queue.drain(MAX_BLOCKS_TO_IMPORT);
             if blocks.is_empty() {
@@ -104,7 +115,8 @@ impl Importer {
                 self.import_verified_headers(headers, client, &import_lock);
             }
 
-            for block in blocks {
+            let mut blocks = blocks.into_iter();
+            while let Some(block) = blocks.next() {
                 let header = &block.header;
                 ctrace!(CLIENT, "Importing block {}", header.number());
                 let is_invalid = invalid_blocks.contains(header.parent_hash());
@@ -116,6 +128,11 @@ impl Importer {
                     imported_blocks.push(header.hash());
                     let update_result = self.commit_block(&closed_block, &header, &block.bytes, client);
                     update_results.push(update_result);
+                } else if !self.health_checker.crashed_modules().is_empty() {
+                    // The block may be valid. It failed because a module crashed while executing it.
+                    cwarn!(CLIENT, "A module crashed while importing block {}", header.number());
+                    self.block_queue.requeue(std::iter::once(block).chain(blocks).collect());
+                    break
                 } else {
                     invalid_blocks.insert(header.hash());
                 }
@@ -255,6 +272,16 @@ impl Importer {
             );
         })?;
 
+        self.verifier.verify_evidences(header, &block.evidences, engine).map_err(|e| {
+            cwarn!(
+                CLIENT,
+                "Stage 4 evidence verification failed for #{} ({})\nError: {:?}",
+                header.number(),
+                header.hash(),
+                e
+            );
+        })?;
+
         // Enact Verified Block
         let db = client.state_db().read().clone(&parent.state_root());
 
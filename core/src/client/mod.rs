@@ -30,14 +30,14 @@ pub use self::test_client::TestBlockChainClient;
 
 use crate::block::{Block, ClosedBlock, OpenBlock};
 use crate::blockchain_info::BlockChainInfo;
-use crate::consensus::EngineError;
+use crate::consensus::{ConsensusState, EngineError};
 use crate::encoded;
 use crate::error::{BlockImportError, Error as GenericError};
 use crate::transaction::{LocalizedTransaction, PendingTransactions};
 use crate::types::{BlockStatus, TransactionId, VerificationQueueInfo as BlockQueueInfo};
 use cdb::DatabaseError;
 use ckey::{Ed25519Public as Public, NetworkId, PlatformAddress};
-use coordinator::types::Event;
+use coordinator::types::{Event, ModuleCrash};
 use coordinator::Transaction;
 use cstate::{TopLevelState, TopStateView};
 use ctypes::{BlockHash, BlockId, BlockNumber, ChainParams, CompactValidatorSet, Header, SyncHeader, TxHash};
@@ -75,6 +75,7 @@ pub trait EngineInfo: Send + Sync {
     fn chain_params(&self, block_id: BlockId) -> Option<ChainParams>;
     fn possible_authors(&self, block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>, EngineError>;
     fn validator_set(&self, block_number: Option<u64>) -> Result<Option<CompactValidatorSet>, EngineError>;
+    fn consensus_state(&self) -> Option<ConsensusState>;
 }
 
 /// Client facilities used by internally sealing Engines.
@@ -185,6 +186,9 @@ pub trait BlockChainClient: Sync + Send + BlockChainTrait + ImportBlock {
 
     /// get events emitted by given block
     fn events_by_block_hash(&self, hash: &BlockHash) -> Vec<Event>;
+
+    /// Get the modules crashed while the node is running.
+    fn crashed_modules(&self) -> Vec<ModuleCrash>;
 }
 
 /// Result of import block operation.
@@ -35,7 +35,7 @@ use crate::client::{
     BlockChainClient, BlockChainTrait, BlockProducer, BlockStatus, ConsensusClient, EngineInfo, ImportBlock,
     ImportResult, MiningBlockChainClient, StateInfo,
 };
-use crate::consensus::EngineError;
+use crate::consensus::{ConsensusState, EngineError};
 use crate::db::{COL_STATE, NUM_COLUMNS};
 use crate::encoded;
 use crate::error::{BlockImportError, Error as GenericError};
@@ -49,7 +49,7 @@ use crate::{ConsensusEngine, LocalizedTransaction, PendingTransactions, Tendermi
 use ccrypto::BLAKE_NULL_RLP;
 use ckey::{Ed25519Private as Private, Ed25519Public as Public, NetworkId, PlatformAddress};
 use coordinator::test_coordinator::TestCoordinator;
-use coordinator::types::Event;
+use coordinator::types::{Event, ModuleCrash};
 use coordinator::Transaction;
 use cstate::tests::helpers::empty_top_state_with_metadata;
 use cstate::{NextValidatorSet, StateDB, TopLevelState};
@@ -125,7 +125,7 @@ impl TestBlockChainClient {
     pub fn new_with_extra_and_db(extra_data: Bytes, db: Arc<dyn KeyValueDB>) -> Self {
         let coordinator = TestCoordinator::default();
 
-        let genesis = Genesis::new(Default::default(), &coordinator);
+        let genesis = Genesis::new(Default::default(), None, &coordinator);
         let genesis_block = genesis.block();
         let genesis_header = genesis.header();
         let genesis_hash = genesis_header.hash();
@@ -502,6 +502,10 @@ impl BlockChainClient for TestBlockChainClient {
     fn events_by_block_hash(&self, _hash: &BlockHash) -> Vec<Event> {
         unimplemented!()
     }
+
+    fn crashed_modules(&self) -> Vec<ModuleCrash> {
+        Vec::new()
+    }
 }
 
 impl TimeoutHandler for TestBlockChainClient {
@@ -537,6 +541,10 @@ impl EngineInfo for TestBlockChainClient {
     fn validator_set(&self, _block_number: Option<u64>) -> Result<Option<ctypes::CompactValidatorSet>, EngineError> {
         unimplemented!()
     }
+
+    fn consensus_state(&self) -> Option<ConsensusState> {
+        unimplemented!()
+    }
 }
 
 impl ConsensusClient for TestBlockChainClient {}
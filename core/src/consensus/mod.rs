@@ -19,18 +19,21 @@ mod null_engine;
 pub(crate) mod signer;
 mod solo;
 pub(crate) mod tendermint;
-mod validator_set;
+pub(crate) mod validator_set;
 
 pub use self::null_engine::NullEngine;
 pub use self::solo::Solo;
 pub use self::tendermint::{
-    types::TendermintSealView, ConsensusMessage, Height, Step, Tendermint, TendermintParams, TimeGapParams, View,
-    VoteOn, VoteStep,
+    types::TendermintSealView, verify_precommits, ConsensusMessage, ConsensusState, Height, PeerConsensusState,
+    SealFormat, Step, StepVotes, Tendermint, TendermintParams, TimeGapParams, View, VoteOn, VoteStep,
 };
 pub use self::validator_set::{DynamicValidator, ValidatorSet};
 
-use self::bit_set::BitSet;
-use crate::account_provider::AccountProvider;
+pub use self::bit_set::BitSet;
+pub use self::signer::{
+    ConsensusSigner, EngineSigner, Error as SignerError, LocalSigner, RemoteSigner, SignState, SignerAddress,
+    SignerKey, SignerServer,
+};
 use crate::block::{ClosedBlock, ExecutedBlock};
 use crate::client::snapshot_notify::NotifySender as SnapshotNotifySender;
 use crate::client::ConsensusClient;
@@ -44,6 +47,8 @@ use ctypes::util::unexpected::{Mismatch, OutOfBounds};
 use ctypes::{BlockHash, CompactValidatorSet, Header, SyncHeader};
 use primitives::Bytes;
 use std::fmt;
+use std::io;
+use std::path::Path;
 use std::sync::{Arc, Weak};
 
 pub enum Seal {
@@ -53,6 +58,7 @@ pub enum Seal {
         cur_view: View,
         precommits: Vec<Signature>,
         precommit_bitset: BitSet,
+        format: SealFormat,
     },
     None,
 }
@@ -67,10 +73,11 @@ impl Seal {
                 cur_view,
                 precommits,
                 precommit_bitset,
+                format,
             } => Some(vec![
                 ::rlp::encode(prev_view),
                 ::rlp::encode(cur_view),
-                ::rlp::encode_list(precommits),
+                tendermint::types::encode_precommits(*format, precommits),
                 ::rlp::encode(precommit_bitset),
             ]),
         }
@@ -186,14 +193,19 @@ pub trait ConsensusEngine: Sync + Send {
 
     fn remove_published_evidences(&self, _published: Vec<Evidence>) {}
 
+    /// Phase 4 verification. Verify the evidences in the block against potentially external data.
+    fn verify_evidences(&self, _header: &Header, _evidences: &[Evidence]) -> Result<(), Error> {
+        Ok(())
+    }
+
     /// Find out if the block is a proposal block and should not be inserted into the DB.
     /// Takes a header of a fully verified block.
     fn is_proposal(&self, _verified_header: &Header) -> bool {
         false
     }
 
-    /// Register an account which signs consensus messages.
-    fn set_signer(&self, _ap: Arc<AccountProvider>, _pubkey: Public) {}
+    /// Register the signer of consensus messages.
+    fn set_signer(&self, _signer: Arc<dyn ConsensusSigner>) {}
 
     fn register_network_extension_to_service(&self, _: &NetworkService) {}
 
@@ -201,6 +213,12 @@ pub trait ConsensusEngine: Sync + Send {
 
     fn register_chain_notify(&self, _: &Client) {}
 
+    /// Open the write-ahead log of the consensus messages.
+    /// It should be called before `complete_register`, which restores the state from the log.
+    fn register_consensus_wal(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
     fn complete_register(&self) {}
 
     fn register_snapshot_notify_sender(&self, _sender: SnapshotNotifySender) {}
@@ -225,6 +243,11 @@ pub trait ConsensusEngine: Sync + Send {
     fn possible_authors(&self, block_number: Option<u64>) -> Result<Option<Vec<Public>>, EngineError>;
 
     fn current_validator_set(&self, block_number: Option<u64>) -> Result<Option<CompactValidatorSet>, EngineError>;
+
+    /// The snapshot of the consensus state for debugging. `None` if the engine doesn't have a state.
+    fn consensus_state(&self) -> Option<ConsensusState> {
+        None
+    }
 }
 
 /// Voting errors.
@@ -257,6 +280,10 @@ pub enum EngineError {
     /// Malformed consensus message.
     MalformedMessage(String),
     CannotOpenBlock,
+    /// The evidence doesn't prove a misbehavior.
+    InvalidEvidence(String),
+    /// The state of the block is pruned or not imported yet.
+    StateNotAvailable(BlockHash),
 }
 
 impl fmt::Display for EngineError {
@@ -282,6 +309,8 @@ impl fmt::Display for EngineError {
             BadSealFieldSize(oob) => format!("Seal field has an unexpected length: {}", oob),
             MalformedMessage(msg) => format!("Received malformed consensus message: {}", msg),
             CannotOpenBlock => "Cannot open a block".to_string(),
+            InvalidEvidence(reason) => format!("Invalid evidence: {}", reason),
+            StateNotAvailable(hash) => format!("The state of block {} is not available", hash),
         };
 
         f.write_fmt(format_args!("Engine error ({})", msg))
@@ -0,0 +1,48 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{ConsensusSigner, Error};
+use crate::account_provider::{AccountProvider, Error as AccountProviderError};
+use crate::consensus::VoteOn;
+use ckey::{Ed25519Public as Public, Signature};
+use ckeystore::DecryptedAccount;
+
+/// Signs with an account unlocked in the local keystore.
+pub struct LocalSigner {
+    public: Public,
+    account: DecryptedAccount,
+}
+
+impl LocalSigner {
+    // TODO: remove decrypted_account after some timeout
+    pub fn new(ap: &AccountProvider, public: Public) -> Result<Self, AccountProviderError> {
+        let account = ap.get_unlocked_account(&public)?.disclose();
+        Ok(Self {
+            public,
+            account,
+        })
+    }
+}
+
+impl ConsensusSigner for LocalSigner {
+    fn public(&self) -> Public {
+        self.public
+    }
+
+    fn sign(&self, vote_on: &VoteOn) -> Result<Signature, Error> {
+        Ok(self.account.sign(&vote_on.hash()).map_err(AccountProviderError::from)?)
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright 2018-2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod local;
+mod remote;
+
+pub use self::local::LocalSigner;
+pub use self::remote::{RemoteSigner, SignState, SignerAddress, SignerKey, SignerServer};
+use super::VoteOn;
+use crate::account_provider::Error as AccountProviderError;
+use ckey::{Ed25519Public as Public, Signature};
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+/// Signs consensus messages with the key of a validator.
+/// The key may be kept in the local keystore or on a separate host.
+pub trait ConsensusSigner: Send + Sync {
+    /// Public Key of signer.
+    fn public(&self) -> Public;
+
+    /// Sign the vote.
+    /// The signer may refuse to sign a vote conflicting with the votes it signed before.
+    fn sign(&self, vote_on: &VoteOn) -> Result<Signature, Error>;
+}
+
+#[derive(Debug)]
+pub enum Error {
+    AccountProvider(AccountProviderError),
+    /// Failed to communicate with the remote signer.
+    Io(io::Error),
+    /// The remote signer refused to sign.
+    Rejected(String),
+}
+
+impl From<AccountProviderError> for Error {
+    fn from(err: AccountProviderError) -> Self {
+        Error::AccountProvider(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::AccountProvider(err) => err.fmt(f),
+            Error::Io(err) => write!(f, "Cannot communicate with the remote signer: {}", err),
+            Error::Rejected(reason) => write!(f, "The remote signer refused to sign: {}", reason),
+        }
+    }
+}
+
+/// Everything that an Engine needs to sign messages.
+#[derive(Default)]
+pub struct EngineSigner {
+    signer: Option<Arc<dyn ConsensusSigner>>,
+    public: Option<Public>,
+}
+
+impl EngineSigner {
+    pub fn set_signer(&mut self, signer: Arc<dyn ConsensusSigner>) {
+        let public = signer.public();
+        self.signer = Some(signer);
+        self.public = Some(public);
+        cinfo!(ENGINE, "Setting Engine signer to {:?}", public);
+    }
+
+    /// Sign a vote with Ed25519.
+    pub fn sign(&self, vote_on: &VoteOn) -> Result<Signature, Error> {
+        match &self.signer {
+            Some(signer) => signer.sign(vote_on),
+            None => Err(AccountProviderError::NotFound.into()),
+        }
+    }
+
+    /// Public Key of signer.
+    pub fn public(&self) -> Option<&Public> {
+        self.public.as_ref()
+    }
+
+    /// Check if the given pubkey is the signing address.
+    pub fn is_signer(&self, pubkey: &Public) -> bool {
+        self.public.map_or(false, |signer| *pubkey == signer)
+    }
+}
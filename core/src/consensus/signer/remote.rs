@@ -0,0 +1,734 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The remote signer keeps the key of a validator on a separate host.
+//!
+//! A node connects to the signer over a Unix socket or TCP, and asks it to sign each vote.
+//! The signer remembers the last vote it signed and refuses to sign a conflicting one,
+//! so a misconfigured or compromised node cannot make the validator double-sign.
+//!
+//! A message is framed by its 4-byte big-endian length followed by the RLP-encoded message.
+//!
+//! The node and the signer share a secret key. When they connect, each of them sends a random challenge and
+//! proves that it knows the key by the keyed hash of the challenge. After that, every message is followed by the
+//! keyed hash of the message and its sequence number, so that a message cannot be forged, replayed or reordered.
+//! The messages are not encrypted, since the votes and their signatures are broadcast anyway.
+
+use super::{ConsensusSigner, Error};
+use crate::consensus::VoteOn;
+use ccrypto::Blake;
+use ckey::{verify, Ed25519Public as Public, Signature};
+use parking_lot::Mutex;
+use primitives::H256;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+const MAX_MESSAGE_SIZE: usize = 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(3);
+const SIGNER_PROOF_TAG: &[u8] = b"signer";
+const NODE_PROOF_TAG: &[u8] = b"node";
+const SESSION_KEY_TAG: &[u8] = b"session";
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+enum MessageID {
+    RequestPublic = 0x01,
+    Public = 0x02,
+    RequestSignature = 0x03,
+    Signature = 0x04,
+    Rejected = 0x05,
+    Challenge = 0x06,
+    Proof = 0x07,
+}
+
+impl Encodable for MessageID {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.append_single_value(&(*self as u8));
+    }
+}
+
+impl Decodable for MessageID {
+    fn decode(rlp: &Rlp<'_>) -> Result<Self, DecoderError> {
+        let tag = rlp.as_val()?;
+        match tag {
+            0x01u8 => Ok(MessageID::RequestPublic),
+            0x02 => Ok(MessageID::Public),
+            0x03 => Ok(MessageID::RequestSignature),
+            0x04 => Ok(MessageID::Signature),
+            0x05 => Ok(MessageID::Rejected),
+            0x06 => Ok(MessageID::Challenge),
+            0x07 => Ok(MessageID::Proof),
+            _ => Err(DecoderError::Custom("Unexpected MessageID Value")),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Message {
+    RequestPublic,
+    Public(Public),
+    RequestSignature(VoteOn),
+    Signature(Signature),
+    Rejected(String),
+    Challenge(H256),
+    Proof(H256),
+}
+
+impl Encodable for Message {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            Message::RequestPublic => {
+                s.begin_list(1);
+                s.append(&MessageID::RequestPublic);
+            }
+            Message::Public(public) => {
+                s.begin_list(2);
+                s.append(&MessageID::Public).append(public);
+            }
+            Message::RequestSignature(vote_on) => {
+                s.begin_list(2);
+                s.append(&MessageID::RequestSignature).append(vote_on);
+            }
+            Message::Signature(signature) => {
+                s.begin_list(2);
+                s.append(&MessageID::Signature).append(signature);
+            }
+            Message::Rejected(reason) => {
+                s.begin_list(2);
+                s.append(&MessageID::Rejected).append(reason);
+            }
+            Message::Challenge(nonce) => {
+                s.begin_list(2);
+                s.append(&MessageID::Challenge).append(nonce);
+            }
+            Message::Proof(proof) => {
+                s.begin_list(2);
+                s.append(&MessageID::Proof).append(proof);
+            }
+        }
+    }
+}
+
+impl Decodable for Message {
+    fn decode(rlp: &Rlp<'_>) -> Result<Self, DecoderError> {
+        let id = rlp.val_at(0)?;
+        let expected = match id {
+            MessageID::RequestPublic => 1,
+            _ => 2,
+        };
+        let item_count = rlp.item_count()?;
+        if item_count != expected {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected,
+            })
+        }
+        Ok(match id {
+            MessageID::RequestPublic => Message::RequestPublic,
+            MessageID::Public => Message::Public(rlp.val_at(1)?),
+            MessageID::RequestSignature => Message::RequestSignature(rlp.val_at(1)?),
+            MessageID::Signature => Message::Signature(rlp.val_at(1)?),
+            MessageID::Rejected => Message::Rejected(rlp.val_at(1)?),
+            MessageID::Challenge => Message::Challenge(rlp.val_at(1)?),
+            MessageID::Proof => Message::Proof(rlp.val_at(1)?),
+        })
+    }
+}
+
+fn write_message(stream: &mut impl Write, message: &Message) -> io::Result<()> {
+    let bytes = message.rlp_bytes();
+    stream.write_all(&(bytes.len() as u32).to_be_bytes())?;
+    stream.write_all(&bytes)?;
+    stream.flush()
+}
+
+fn read_frame(stream: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Too large message: {} bytes", len)))
+    }
+    let mut bytes = vec![0u8; len];
+    stream.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_message(stream: &mut impl Read) -> io::Result<Message> {
+    decode_message(&read_frame(stream)?)
+}
+
+fn decode_message(bytes: &[u8]) -> io::Result<Message> {
+    rlp::decode(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err)))
+}
+
+fn unauthenticated(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied, reason)
+}
+
+/// The secret key shared by a node and its remote signer.
+/// It is stored in a file as 32 bytes in hex, e.g. the output of `openssl rand -hex 32`.
+#[derive(Clone)]
+pub struct SignerKey(H256);
+
+impl SignerKey {
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let key = contents.trim().trim_start_matches("0x").parse().map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidData, "The signer key should be 32 bytes in hex".to_string())
+        })?;
+        Ok(SignerKey(key))
+    }
+
+    fn hash(&self, tag: &[u8], first: &H256, second: &H256) -> H256 {
+        let data = [tag, first.as_ref(), second.as_ref()].concat();
+        Blake::blake_with_key(&data, self.0.as_ref())
+    }
+}
+
+/// A connection on which both the node and the signer proved that they know the signer key.
+struct Channel {
+    stream: Stream,
+    session_key: H256,
+    tag: &'static [u8],
+    peer_tag: &'static [u8],
+    sent: u64,
+    received: u64,
+}
+
+impl Channel {
+    /// Authenticates the connection as a node.
+    fn connect(mut stream: Stream, key: &SignerKey) -> io::Result<Self> {
+        let challenge = H256::random();
+        write_message(&mut stream, &Message::Challenge(challenge))?;
+        let peer_challenge = match read_message(&mut stream)? {
+            Message::Challenge(peer_challenge) => peer_challenge,
+            message => return Err(unexpected_message(&message)),
+        };
+        match read_message(&mut stream)? {
+            Message::Proof(proof) if proof == key.hash(SIGNER_PROOF_TAG, &challenge, &peer_challenge) => {}
+            Message::Proof(_) => return Err(unauthenticated("The signer has a different signer key")),
+            message => return Err(unexpected_message(&message)),
+        }
+        write_message(&mut stream, &Message::Proof(key.hash(NODE_PROOF_TAG, &peer_challenge, &challenge)))?;
+        Ok(Self {
+            stream,
+            session_key: key.hash(SESSION_KEY_TAG, &challenge, &peer_challenge),
+            tag: NODE_PROOF_TAG,
+            peer_tag: SIGNER_PROOF_TAG,
+            sent: 0,
+            received: 0,
+        })
+    }
+
+    /// Authenticates the connection as a signer.
+    fn accept(mut stream: Stream, key: &SignerKey) -> io::Result<Self> {
+        let peer_challenge = match read_message(&mut stream)? {
+            Message::Challenge(peer_challenge) => peer_challenge,
+            message => return Err(unexpected_message(&message)),
+        };
+        let challenge = H256::random();
+        write_message(&mut stream, &Message::Challenge(challenge))?;
+        write_message(&mut stream, &Message::Proof(key.hash(SIGNER_PROOF_TAG, &peer_challenge, &challenge)))?;
+        match read_message(&mut stream)? {
+            Message::Proof(proof) if proof == key.hash(NODE_PROOF_TAG, &challenge, &peer_challenge) => {}
+            Message::Proof(_) => return Err(unauthenticated("The node has a different signer key")),
+            message => return Err(unexpected_message(&message)),
+        }
+        Ok(Self {
+            stream,
+            session_key: key.hash(SESSION_KEY_TAG, &peer_challenge, &challenge),
+            tag: SIGNER_PROOF_TAG,
+            peer_tag: NODE_PROOF_TAG,
+            sent: 0,
+            received: 0,
+        })
+    }
+
+    fn mac(&self, tag: &[u8], sequence: u64, bytes: &[u8]) -> H256 {
+        let data = [tag, &sequence.to_be_bytes(), bytes].concat();
+        Blake::blake_with_key(&data, self.session_key.as_ref())
+    }
+
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        let bytes = message.rlp_bytes();
+        let mac = self.mac(self.tag, self.sent, &bytes);
+        self.sent += 1;
+        self.stream.write_all(&(bytes.len() as u32).to_be_bytes())?;
+        self.stream.write_all(&bytes)?;
+        self.stream.write_all(mac.as_ref())?;
+        self.stream.flush()
+    }
+
+    fn receive(&mut self) -> io::Result<Message> {
+        let bytes = read_frame(&mut self.stream)?;
+        let mut mac = H256::zero();
+        self.stream.read_exact(mac.as_mut())?;
+        if mac != self.mac(self.peer_tag, self.received, &bytes) {
+            return Err(unauthenticated("The message is not authenticated"))
+        }
+        self.received += 1;
+        decode_message(&bytes)
+    }
+}
+
+/// The address of a remote signer: `unix:<path>` or `<host>:<port>`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SignerAddress {
+    Unix(PathBuf),
+    Tcp(String),
+}
+
+impl FromStr for SignerAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            if path.is_empty() {
+                return Err("The path of the Unix socket is empty".to_string())
+            }
+            return Ok(SignerAddress::Unix(PathBuf::from(path)))
+        }
+        if !s.contains(':') {
+            return Err(format!("{} is neither unix:<path> nor <host>:<port>", s))
+        }
+        Ok(SignerAddress::Tcp(s.to_string()))
+    }
+}
+
+impl fmt::Display for SignerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignerAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+            SignerAddress::Tcp(address) => write!(f, "{}", address),
+        }
+    }
+}
+
+impl SignerAddress {
+    fn connect(&self) -> io::Result<Stream> {
+        let stream = match self {
+            SignerAddress::Unix(path) => Stream::Unix(UnixStream::connect(path)?),
+            SignerAddress::Tcp(address) => Stream::Tcp(TcpStream::connect(address)?),
+        };
+        stream.set_timeout(Some(REQUEST_TIMEOUT))?;
+        Ok(stream)
+    }
+
+    fn bind(&self) -> io::Result<Listener> {
+        Ok(match self {
+            SignerAddress::Unix(path) => {
+                // The socket file left by the previous run should be removed before binding.
+                if path.exists() {
+                    fs::remove_file(path)?;
+                }
+                Listener::Unix(UnixListener::bind(path)?)
+            }
+            SignerAddress::Tcp(address) => Listener::Tcp(TcpListener::bind(address)?),
+        })
+    }
+}
+
+enum Stream {
+    Unix(UnixStream),
+    Tcp(TcpStream),
+}
+
+impl Stream {
+    fn set_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Unix(stream) => {
+                stream.set_read_timeout(timeout)?;
+                stream.set_write_timeout(timeout)
+            }
+            Stream::Tcp(stream) => {
+                stream.set_read_timeout(timeout)?;
+                stream.set_write_timeout(timeout)
+            }
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Unix(stream) => stream.read(buf),
+            Stream::Tcp(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Unix(stream) => stream.write(buf),
+            Stream::Tcp(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Unix(stream) => stream.flush(),
+            Stream::Tcp(stream) => stream.flush(),
+        }
+    }
+}
+
+enum Listener {
+    Unix(UnixListener),
+    Tcp(TcpListener),
+}
+
+impl Listener {
+    fn accept(&self) -> io::Result<Stream> {
+        Ok(match self {
+            Listener::Unix(listener) => Stream::Unix(listener.accept()?.0),
+            Listener::Tcp(listener) => Stream::Tcp(listener.accept()?.0),
+        })
+    }
+}
+
+/// Signs votes by asking a remote signer.
+pub struct RemoteSigner {
+    address: SignerAddress,
+    key: SignerKey,
+    public: Public,
+    channel: Mutex<Option<Channel>>,
+}
+
+impl RemoteSigner {
+    /// Connects to the signer and fetches the public key of the validator.
+    pub fn connect(address: SignerAddress, key: SignerKey) -> Result<Self, Error> {
+        let mut channel = Channel::connect(address.connect()?, &key)?;
+        channel.send(&Message::RequestPublic)?;
+        let public = match channel.receive()? {
+            Message::Public(public) => public,
+            Message::Rejected(reason) => return Err(Error::Rejected(reason)),
+            message => return Err(unexpected_message(&message).into()),
+        };
+        cinfo!(ENGINE, "Connected to the remote signer {} of {:?}", address, public);
+        Ok(Self {
+            address,
+            key,
+            public,
+            channel: Mutex::new(Some(channel)),
+        })
+    }
+
+    fn request(&self, request: &Message) -> io::Result<Message> {
+        let mut guard = self.channel.lock();
+        if guard.is_none() {
+            cinfo!(ENGINE, "Reconnecting to the remote signer {}", self.address);
+            *guard = Some(Channel::connect(self.address.connect()?, &self.key)?);
+        }
+        let channel = guard.as_mut().expect("Connected above");
+        let result = channel.send(request).and_then(|_| channel.receive());
+        if result.is_err() {
+            // The connection may be broken. Reconnect on the next request.
+            *guard = None;
+        }
+        result
+    }
+}
+
+fn unexpected_message(message: &Message) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Unexpected message {:?}", message))
+}
+
+impl ConsensusSigner for RemoteSigner {
+    fn public(&self) -> Public {
+        self.public
+    }
+
+    fn sign(&self, vote_on: &VoteOn) -> Result<Signature, Error> {
+        match self.request(&Message::RequestSignature(vote_on.clone()))? {
+            Message::Signature(signature) => {
+                if !verify(&signature, vote_on.hash().as_ref(), &self.public) {
+                    return Err(Error::Rejected(format!("Invalid signature on {:?}", vote_on)))
+                }
+                Ok(signature)
+            }
+            Message::Rejected(reason) => Err(Error::Rejected(reason)),
+            message => Err(unexpected_message(&message).into()),
+        }
+    }
+}
+
+/// The last vote signed by a signer.
+/// It is written to the disk before the signature is returned, so that the signer doesn't sign a conflicting vote
+/// even after it restarts.
+pub struct SignState {
+    path: PathBuf,
+    last_vote: Option<VoteOn>,
+}
+
+impl SignState {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let last_vote = match fs::read(path) {
+            Ok(bytes) => Some(
+                rlp::decode(&bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err)))?,
+            ),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            last_vote,
+        })
+    }
+
+    pub fn last_vote(&self) -> Option<&VoteOn> {
+        self.last_vote.as_ref()
+    }
+
+    /// Records the vote if it doesn't conflict with the last vote.
+    /// Signing the same vote again is allowed because it cannot be a double sign.
+    pub fn update(&mut self, vote_on: &VoteOn) -> Result<(), String> {
+        if let Some(last_vote) = &self.last_vote {
+            match last_vote.step.cmp(&vote_on.step) {
+                Ordering::Less => {}
+                Ordering::Equal if last_vote.block_hash == vote_on.block_hash => return Ok(()),
+                Ordering::Equal => {
+                    return Err(format!(
+                        "Double sign at {:?}: {:?} is already signed",
+                        vote_on.step, last_vote.block_hash
+                    ))
+                }
+                Ordering::Greater => {
+                    return Err(format!("Vote regression: {:?} is requested after {:?}", vote_on.step, last_vote.step))
+                }
+            }
+        }
+        self.write(vote_on).map_err(|err| format!("Cannot write the sign state: {}", err))?;
+        self.last_vote = Some(vote_on.clone());
+        Ok(())
+    }
+
+    fn write(&self, vote_on: &VoteOn) -> io::Result<()> {
+        let temp_path = self.path.with_extension("tmp");
+        {
+            let mut temp = File::create(&temp_path)?;
+            temp.write_all(&vote_on.rlp_bytes())?;
+            temp.sync_all()?;
+        }
+        fs::rename(&temp_path, &self.path)?;
+        // Persist the rename itself, or the signer may find the previous vote after a power failure.
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        File::open(dir)?.sync_all()
+    }
+}
+
+/// Serves the requests of a node with the key of a validator.
+pub struct SignerServer {
+    signer: Box<dyn ConsensusSigner>,
+    state: SignState,
+    key: SignerKey,
+}
+
+impl SignerServer {
+    pub fn new(signer: Box<dyn ConsensusSigner>, state: SignState, key: SignerKey) -> Self {
+        Self {
+            signer,
+            state,
+            key,
+        }
+    }
+
+    /// Serves the nodes connecting to the address one by one.
+    pub fn run(&mut self, address: &SignerAddress) -> io::Result<()> {
+        let listener = address.bind()?;
+        cinfo!(ENGINE, "The remote signer of {:?} is listening on {}", self.signer.public(), address);
+        loop {
+            let stream = listener.accept()?;
+            cinfo!(ENGINE, "A node is connected to the remote signer");
+            if let Err(err) = self.serve(stream) {
+                cwarn!(ENGINE, "The connection to the node is closed: {}", err);
+            }
+        }
+    }
+
+    fn serve(&mut self, stream: Stream) -> io::Result<()> {
+        // A peer which doesn't authenticate itself in time is disconnected.
+        stream.set_timeout(Some(REQUEST_TIMEOUT))?;
+        let mut channel = Channel::accept(stream, &self.key)?;
+        channel.stream.set_timeout(None)?;
+        loop {
+            let request = match channel.receive() {
+                Ok(request) => request,
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(err) => return Err(err),
+            };
+            let response = self.handle(request);
+            channel.send(&response)?;
+        }
+    }
+
+    fn handle(&mut self, request: Message) -> Message {
+        match request {
+            Message::RequestPublic => Message::Public(self.signer.public()),
+            Message::RequestSignature(vote_on) => {
+                if let Err(reason) = self.state.update(&vote_on) {
+                    cerror!(ENGINE, "Refuse to sign {:?}: {}", vote_on, reason);
+                    return Message::Rejected(reason)
+                }
+                match self.signer.sign(&vote_on) {
+                    Ok(signature) => {
+                        cinfo!(ENGINE, "Signed {:?}", vote_on);
+                        Message::Signature(signature)
+                    }
+                    Err(err) => Message::Rejected(err.to_string()),
+                }
+            }
+            message => Message::Rejected(format!("Unexpected message {:?}", message)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::LocalSigner;
+    use super::*;
+    use crate::account_provider::AccountProvider;
+    use crate::consensus::{Step, VoteStep};
+    use ckey::Ed25519Private as Private;
+    use rlp::rlp_encode_and_decode_test;
+    use std::thread;
+    use tempfile::TempDir;
+
+    /// Returns the path of a sign state in a new directory, which is removed with the `TempDir`.
+    fn state_path() -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("sign_state");
+        (dir, path)
+    }
+
+    fn vote_on(height: u64, view: u64, step: Step, block_hash: H256) -> VoteOn {
+        VoteOn {
+            step: VoteStep::new(height, view, step),
+            block_hash: Some(block_hash.into()),
+        }
+    }
+
+    fn local_signer() -> Box<dyn ConsensusSigner> {
+        let ap = AccountProvider::transient_provider();
+        let public = ap.insert_account(Private::random(), &"password".into()).unwrap();
+        ap.unlock_account_permanently(public, "password".into()).unwrap();
+        Box::new(LocalSigner::new(&ap, public).unwrap())
+    }
+
+    #[test]
+    fn encode_and_decode_messages() {
+        rlp_encode_and_decode_test!(Message::RequestPublic);
+        rlp_encode_and_decode_test!(Message::Public(Public::random()));
+        rlp_encode_and_decode_test!(Message::RequestSignature(vote_on(3, 1, Step::Prevote, H256::random())));
+        rlp_encode_and_decode_test!(Message::Signature(Signature::random()));
+        rlp_encode_and_decode_test!(Message::Rejected("Double sign".to_string()));
+        rlp_encode_and_decode_test!(Message::Challenge(H256::random()));
+        rlp_encode_and_decode_test!(Message::Proof(H256::random()));
+    }
+
+    #[test]
+    fn parse_signer_address() {
+        assert_eq!(Ok(SignerAddress::Unix(PathBuf::from("/tmp/signer.sock"))), "unix:/tmp/signer.sock".parse());
+        assert_eq!(Ok(SignerAddress::Tcp("10.0.0.1:26659".to_string())), "10.0.0.1:26659".parse());
+        assert!("unix:".parse::<SignerAddress>().is_err());
+        assert!("localhost".parse::<SignerAddress>().is_err());
+    }
+
+    #[test]
+    fn sign_state_refuses_conflicting_votes() {
+        let (_dir, path) = state_path();
+        let mut state = SignState::open(&path).unwrap();
+        let block_hash = H256::random();
+        state.update(&vote_on(3, 1, Step::Prevote, block_hash)).unwrap();
+        state.update(&vote_on(3, 1, Step::Prevote, block_hash)).unwrap();
+        assert!(state.update(&vote_on(3, 1, Step::Prevote, H256::random())).is_err());
+        assert!(state.update(&vote_on(3, 0, Step::Precommit, block_hash)).is_err());
+        state.update(&vote_on(3, 1, Step::Precommit, block_hash)).unwrap();
+
+        let mut state = SignState::open(&path).unwrap();
+        assert_eq!(Some(&vote_on(3, 1, Step::Precommit, block_hash)), state.last_vote());
+        assert!(state.update(&vote_on(3, 1, Step::Prevote, block_hash)).is_err());
+    }
+
+    #[test]
+    fn read_signer_key() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("signer_key");
+        let key = H256::random();
+        fs::write(&path, format!("{:x}\n", key)).unwrap();
+        assert_eq!(key, SignerKey::read(&path).unwrap().0);
+
+        fs::write(&path, "secret").unwrap();
+        assert!(SignerKey::read(&path).is_err());
+    }
+
+    #[test]
+    fn remote_signer_signs_through_the_server() {
+        let signer = local_signer();
+        let public = signer.public();
+        let key = SignerKey(H256::random());
+        let (_dir, path) = state_path();
+        let mut server = SignerServer::new(signer, SignState::open(&path).unwrap(), key.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = SignerAddress::Tcp(listener.local_addr().unwrap().to_string());
+        let join = thread::spawn(move || {
+            let stream = Stream::Tcp(listener.accept().unwrap().0);
+            server.serve(stream).unwrap();
+        });
+
+        let remote = RemoteSigner::connect(address, key).unwrap();
+        assert_eq!(public, remote.public());
+
+        let vote = vote_on(3, 1, Step::Prevote, H256::random());
+        let signature = remote.sign(&vote).unwrap();
+        assert!(verify(&signature, vote.hash().as_ref(), &public));
+
+        match remote.sign(&vote_on(3, 1, Step::Prevote, H256::random())) {
+            Err(Error::Rejected(_)) => {}
+            result => panic!("Double sign must be rejected: {:?}", result),
+        }
+
+        drop(remote);
+        join.join().unwrap();
+    }
+
+    #[test]
+    fn remote_signer_with_another_key_is_refused() {
+        let (_dir, path) = state_path();
+        let mut server = SignerServer::new(local_signer(), SignState::open(&path).unwrap(), SignerKey(H256::random()));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = SignerAddress::Tcp(listener.local_addr().unwrap().to_string());
+        let join = thread::spawn(move || {
+            let stream = Stream::Tcp(listener.accept().unwrap().0);
+            server.serve(stream)
+        });
+
+        assert!(RemoteSigner::connect(address, SignerKey(H256::random())).is_err());
+        assert!(join.join().unwrap().is_err());
+    }
+}
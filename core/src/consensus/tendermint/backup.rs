@@ -85,7 +85,7 @@ pub fn restore(db: &dyn KeyValueDB) -> Option<BackupDataV1> {
     load_v1(db)
 }
 
-fn find_proposal(votes: &[ConsensusMessage], height: Height, view: View) -> Option<BlockHash> {
+pub fn find_proposal(votes: &[ConsensusMessage], height: Height, view: View) -> Option<BlockHash> {
     votes
         .iter()
         .rev()
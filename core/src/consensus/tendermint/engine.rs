@@ -17,22 +17,26 @@
 use super::super::{ConsensusEngine, EngineError, Seal};
 use super::network::TendermintExtension;
 pub use super::params::{TendermintParams, TimeoutParams};
-use super::{worker, Evidence};
-use super::{ChainNotify, Step, Tendermint, VoteOn, VoteStep, SEAL_FIELDS};
-use crate::account_provider::AccountProvider;
+use super::wal::Wal;
+use super::{verify_precommits, ChainNotify, SealFormat, Step, Tendermint, VoteOn, VoteStep, SEAL_FIELDS};
+use super::{worker, ConsensusState, Evidence};
 use crate::block::*;
 use crate::client::snapshot_notify::NotifySender as SnapshotNotifySender;
 use crate::client::{Client, ConsensusClient};
+use crate::consensus::signer::ConsensusSigner;
 use crate::consensus::tendermint::params::TimeGapParams;
+use crate::consensus::validator_set::check_two_thirds_majority;
 use crate::consensus::{EngineType, TendermintSealView};
 use crate::error::{BlockError, Error};
 use crate::views::HeaderView;
-use ckey::{verify, Ed25519Public as Public};
+use ckey::Ed25519Public as Public;
 use cnetwork::NetworkService;
 use crossbeam_channel as crossbeam;
 use cstate::CurrentValidators;
-use ctypes::{util::unexpected::OutOfBounds, BlockHash, BlockId, CompactValidatorSet, Header, SyncHeader};
+use ctypes::{BlockHash, BlockId, CompactValidatorSet, Header, SyncHeader};
+use std::io;
 use std::iter::Iterator;
+use std::path::Path;
 use std::sync::atomic::Ordering as AtomicOrdering;
 use std::sync::{Arc, Weak};
 
@@ -91,8 +95,18 @@ impl ConsensusEngine for Tendermint {
             return Ok(())
         }
         let seal_view = TendermintSealView::new(header.seal());
+        let seal_format = seal_view.format()?;
+        if seal_format != SealFormat::Individual && seal_format != self.seal_format {
+            cwarn!(
+                ENGINE,
+                "verify_header_seal: The header({}) is sealed in {:?}, which is not allowed",
+                header.hash(),
+                seal_format
+            );
+            return Err(BlockError::InvalidSeal.into())
+        }
         let bitset_count = seal_view.bitset()?.count();
-        let precommits_count = seal_view.precommits().item_count()?;
+        let precommits_count = seal_view.precommits_count()?;
 
         if bitset_count < precommits_count {
             cwarn!(
@@ -119,6 +133,7 @@ impl ConsensusEngine for Tendermint {
         };
 
         let mut signed_delegation: u64 = 0;
+        let mut signatures = Vec::with_capacity(precommits_count);
         for (bitset_index, signature) in seal_view.signatures()? {
             if validator_set.len() <= bitset_index {
                 cwarn!(
@@ -132,24 +147,15 @@ impl ConsensusEngine for Tendermint {
             }
             let public = validator_set[bitset_index].public_key;
             let delegation = validator_set[bitset_index].delegation;
-            if !verify(&signature, precommit_vote_on.hash().as_ref(), &public) {
-                return Err(EngineError::BlockNotAuthorized(public).into())
-            }
+            signatures.push((signature, public));
             signed_delegation += delegation;
         }
+        verify_precommits(seal_format, precommit_vote_on.hash().as_ref(), &signatures)
+            .map_err(EngineError::BlockNotAuthorized)?;
 
         let total_delegation: u64 = validator_set.iter().map(|entry| entry.delegation).sum();
-
-        if signed_delegation * 3 > total_delegation * 2 {
-            Ok(())
-        } else {
-            Err(EngineError::BadSealFieldSize(OutOfBounds {
-                min: Some(total_delegation as usize * 2 / 3),
-                max: Some(total_delegation as usize),
-                found: signed_delegation as usize,
-            })
-            .into())
-        }
+        check_two_thirds_majority(signed_delegation, total_delegation)?;
+        Ok(())
     }
 
     fn verify_block_external(&self, header: &Header) -> Result<(), Error> {
@@ -190,6 +196,21 @@ impl ConsensusEngine for Tendermint {
             .unwrap();
     }
 
+    fn verify_evidences(&self, header: &Header, evidences: &[Evidence]) -> Result<(), Error> {
+        if evidences.is_empty() {
+            return Ok(())
+        }
+        let (result, receiver) = crossbeam::bounded(1);
+        self.inner
+            .send(worker::Event::VerifyEvidences {
+                header: Box::from(header.clone()),
+                evidences: evidences.to_vec(),
+                result,
+            })
+            .unwrap();
+        receiver.recv().unwrap()
+    }
+
     fn is_proposal(&self, header: &Header) -> bool {
         let (result, receiver) = crossbeam::bounded(1);
         self.inner
@@ -202,14 +223,9 @@ impl ConsensusEngine for Tendermint {
         receiver.recv().unwrap()
     }
 
-    fn set_signer(&self, ap: Arc<AccountProvider>, pubkey: Public) {
+    fn set_signer(&self, signer: Arc<dyn ConsensusSigner>) {
         self.has_signer.store(true, AtomicOrdering::SeqCst);
-        self.inner
-            .send(worker::Event::SetSigner {
-                ap,
-                pubkey,
-            })
-            .unwrap();
+        self.inner.send(worker::Event::SetSigner(signer)).unwrap();
     }
 
     fn register_network_extension_to_service(&self, service: &NetworkService) {
@@ -229,6 +245,12 @@ impl ConsensusEngine for Tendermint {
         client.add_notify(Arc::downgrade(&self.chain_notify) as Weak<dyn ChainNotify>);
     }
 
+    fn register_consensus_wal(&self, path: &Path) -> io::Result<()> {
+        let wal = Wal::open(path)?;
+        self.inner.send(worker::Event::SetWal(Box::new(wal))).unwrap();
+        Ok(())
+    }
+
     fn complete_register(&self) {
         let (result, receiver) = crossbeam::bounded(1);
         self.inner.send(worker::Event::Restore(result)).unwrap();
@@ -287,6 +309,16 @@ impl ConsensusEngine for Tendermint {
         }
     }
 
+    fn consensus_state(&self) -> Option<ConsensusState> {
+        let (result, receiver) = crossbeam::bounded(1);
+        self.inner
+            .send(worker::Event::ConsensusState {
+                result,
+            })
+            .unwrap();
+        receiver.recv().ok()
+    }
+
     /// grand_parent === none only when parent is genesis
     fn verify_header_family(
         &self,
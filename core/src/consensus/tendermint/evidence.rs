@@ -0,0 +1,325 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::message::ConsensusMessage;
+use super::types::{Height, Step, TendermintSealView};
+use super::vote_collector::DoubleVote;
+use coordinator::types::VerifiedCrime;
+use ctypes::Header;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+
+/// An evidence is encoded as a list of the version, the kind and two items of the contents.
+/// Nodes reject evidences of an unknown version, but accept the double votes encoded before the version was added.
+pub const EVIDENCE_VERSION: u8 = 1;
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+enum EvidenceKind {
+    DoubleVote = 0x01,
+    DoubleProposal = 0x02,
+    InvalidBlockVote = 0x03,
+    LightClientAttack = 0x04,
+}
+
+impl Encodable for EvidenceKind {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.append_single_value(&(*self as u8));
+    }
+}
+
+impl Decodable for EvidenceKind {
+    fn decode(rlp: &Rlp<'_>) -> Result<Self, DecoderError> {
+        let tag = rlp.as_val()?;
+        match tag {
+            0x01u8 => Ok(EvidenceKind::DoubleVote),
+            0x02 => Ok(EvidenceKind::DoubleProposal),
+            0x03 => Ok(EvidenceKind::InvalidBlockVote),
+            0x04 => Ok(EvidenceKind::LightClientAttack),
+            _ => Err(DecoderError::Custom("Unexpected EvidenceKind Value")),
+        }
+    }
+}
+
+/// A proof of a misbehavior of validators.
+/// Evidences are gossiped, included in blocks, and handed to `HandleCrimes` when the block is opened.
+#[derive(Clone, Debug)]
+pub enum Evidence {
+    /// A validator voted on different blocks at the same step.
+    DoubleVote(DoubleVote),
+    /// A proposer proposed different blocks at the same height and view.
+    DoubleProposal(DoubleVote),
+    /// A double vote encoded as a list of the two votes, before the evidences were versioned.
+    /// It is encoded in the same form again, so that the evidences root of the blocks including it doesn't change.
+    LegacyDoubleVote(DoubleVote),
+    /// A validator voted on a block whose header has a fault every node finds in the same way,
+    /// e.g. it is proposed by a validator who is not the proposer of the view.
+    InvalidBlockVote {
+        vote: ConsensusMessage,
+        header: Header,
+    },
+    /// Validators precommitted a block conflicting with the canonical block at the same view,
+    /// which can deceive light clients.
+    /// The commits are the children whose seals have the precommits.
+    LightClientAttack {
+        conflicting_commit: Header,
+        canonical_commit: Header,
+    },
+}
+
+impl From<DoubleVote> for Evidence {
+    fn from(double_vote: DoubleVote) -> Self {
+        if double_vote.round().step == Step::Propose {
+            Evidence::DoubleProposal(double_vote)
+        } else {
+            Evidence::DoubleVote(double_vote)
+        }
+    }
+}
+
+impl Evidence {
+    /// The height where the misbehavior happened.
+    pub fn height(&self) -> Height {
+        match self {
+            Evidence::DoubleVote(double_vote)
+            | Evidence::DoubleProposal(double_vote)
+            | Evidence::LegacyDoubleVote(double_vote) => double_vote.round().height,
+            Evidence::InvalidBlockVote {
+                vote,
+                ..
+            } => vote.height(),
+            Evidence::LightClientAttack {
+                canonical_commit,
+                ..
+            } => canonical_commit.number().saturating_sub(1),
+        }
+    }
+}
+
+/// Returns the indices of the validators who signed both commits.
+pub fn equivocators(conflicting_commit: &Header, canonical_commit: &Header) -> Result<Vec<usize>, DecoderError> {
+    let conflicting = TendermintSealView::new(conflicting_commit.seal()).bitset()?;
+    let canonical = TendermintSealView::new(canonical_commit.seal()).bitset()?;
+    Ok(conflicting.true_index_iter().filter(|index| canonical.is_set(*index)).collect())
+}
+
+impl From<&Evidence> for VerifiedCrime {
+    fn from(evidence: &Evidence) -> Self {
+        match evidence {
+            Evidence::DoubleVote(double_vote) | Evidence::LegacyDoubleVote(double_vote) => double_vote.into(),
+            Evidence::DoubleProposal(double_vote) => {
+                let round = double_vote.round();
+                Self::DoubleProposal {
+                    height: round.height,
+                    view: round.view,
+                    criminal_index: double_vote.votes().0.signer_index(),
+                }
+            }
+            Evidence::InvalidBlockVote {
+                vote,
+                ..
+            } => Self::InvalidBlockVote {
+                height: vote.height(),
+                criminal_index: vote.signer_index(),
+            },
+            Evidence::LightClientAttack {
+                conflicting_commit,
+                canonical_commit,
+            } => Self::LightClientAttack {
+                height: evidence.height(),
+                criminal_indices: equivocators(conflicting_commit, canonical_commit).unwrap_or_default(),
+            },
+        }
+    }
+}
+
+impl PartialEq for Evidence {
+    fn eq(&self, other: &Self) -> bool {
+        self.rlp_bytes() == other.rlp_bytes()
+    }
+}
+
+impl Eq for Evidence {}
+
+impl Encodable for Evidence {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        if let Evidence::LegacyDoubleVote(double_vote) = self {
+            double_vote.rlp_append(s);
+            return
+        }
+        s.begin_list(4);
+        s.append(&EVIDENCE_VERSION);
+        match self {
+            Evidence::DoubleVote(double_vote) => {
+                let (vote_one, vote_two) = double_vote.votes();
+                s.append(&EvidenceKind::DoubleVote).append(vote_one).append(vote_two);
+            }
+            Evidence::DoubleProposal(double_vote) => {
+                let (vote_one, vote_two) = double_vote.votes();
+                s.append(&EvidenceKind::DoubleProposal).append(vote_one).append(vote_two);
+            }
+            Evidence::InvalidBlockVote {
+                vote,
+                header,
+            } => {
+                s.append(&EvidenceKind::InvalidBlockVote).append(vote).append(header);
+            }
+            Evidence::LightClientAttack {
+                conflicting_commit,
+                canonical_commit,
+            } => {
+                s.append(&EvidenceKind::LightClientAttack).append(conflicting_commit).append(canonical_commit);
+            }
+            Evidence::LegacyDoubleVote(_) => unreachable!("Encoded above"),
+        }
+    }
+}
+
+impl Decodable for Evidence {
+    fn decode(rlp: &Rlp<'_>) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count == 2 {
+            return Ok(Evidence::LegacyDoubleVote(rlp.as_val()?))
+        }
+        if item_count != 4 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 4,
+            })
+        }
+        let version: u8 = rlp.val_at(0)?;
+        if version != EVIDENCE_VERSION {
+            return Err(DecoderError::Custom("Unsupported evidence version"))
+        }
+        Ok(match rlp.val_at(1)? {
+            EvidenceKind::DoubleVote => Evidence::DoubleVote(DoubleVote::new(rlp.val_at(2)?, rlp.val_at(3)?)),
+            EvidenceKind::DoubleProposal => Evidence::DoubleProposal(DoubleVote::new(rlp.val_at(2)?, rlp.val_at(3)?)),
+            EvidenceKind::InvalidBlockVote => Evidence::InvalidBlockVote {
+                vote: rlp.val_at(2)?,
+                header: rlp.val_at(3)?,
+            },
+            EvidenceKind::LightClientAttack => Evidence::LightClientAttack {
+                conflicting_commit: rlp.val_at(2)?,
+                canonical_commit: rlp.val_at(3)?,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::message::{VoteOn, VoteStep};
+    use super::super::types::encode_precommits;
+    use super::*;
+    use crate::consensus::{BitSet, SealFormat};
+    use ckey::Signature;
+    use ctypes::BlockHash;
+    use primitives::H256;
+    use rlp::rlp_encode_and_decode_test;
+
+    fn vote(step: VoteStep, block_hash: BlockHash, signer_index: usize) -> ConsensusMessage {
+        ConsensusMessage {
+            on: VoteOn {
+                step,
+                block_hash: Some(block_hash),
+            },
+            signature: Signature::random(),
+            signer_index,
+        }
+    }
+
+    fn double_vote(step: Step) -> DoubleVote {
+        let step = VoteStep::new(3, 1, step);
+        DoubleVote::new(vote(step, H256::random().into(), 2), vote(step, H256::random().into(), 2))
+    }
+
+    fn commit(number: u64, signers: &[usize]) -> Header {
+        let mut header = Header::default();
+        header.set_number(number);
+        header.set_parent_hash(H256::random().into());
+        header.set_seal(vec![
+            rlp::encode(&1u64),
+            rlp::encode(&0u64),
+            encode_precommits(SealFormat::Individual, &vec![Signature::random(); signers.len()]),
+            rlp::encode(&BitSet::new_with_indices(signers)),
+        ]);
+        header
+    }
+
+    #[test]
+    fn double_proposal_is_distinguished_from_double_vote() {
+        assert!(matches!(Evidence::from(double_vote(Step::Propose)), Evidence::DoubleProposal(_)));
+        assert!(matches!(Evidence::from(double_vote(Step::Prevote)), Evidence::DoubleVote(_)));
+        assert!(matches!(Evidence::from(double_vote(Step::Precommit)), Evidence::DoubleVote(_)));
+    }
+
+    #[test]
+    fn encode_and_decode_evidences() {
+        rlp_encode_and_decode_test!(Evidence::from(double_vote(Step::Prevote)));
+        rlp_encode_and_decode_test!(Evidence::from(double_vote(Step::Propose)));
+
+        let mut header = Header::default();
+        header.set_number(3);
+        rlp_encode_and_decode_test!(Evidence::InvalidBlockVote {
+            vote: vote(VoteStep::new(3, 0, Step::Prevote), header.hash(), 1),
+            header,
+        });
+        rlp_encode_and_decode_test!(Evidence::LightClientAttack {
+            conflicting_commit: commit(4, &[0, 1, 2]),
+            canonical_commit: commit(4, &[1, 2, 3]),
+        });
+    }
+
+    #[test]
+    fn legacy_double_vote_keeps_its_encoding() {
+        let double_vote = double_vote(Step::Propose);
+        let legacy = rlp::encode(&double_vote);
+        let evidence: Evidence = rlp::decode(&legacy).unwrap();
+        assert_eq!(Evidence::LegacyDoubleVote(double_vote), evidence);
+        assert_eq!(legacy, evidence.rlp_bytes());
+        assert_eq!(3, evidence.height());
+    }
+
+    #[test]
+    fn unknown_version_is_rejected() {
+        let evidence = Evidence::from(double_vote(Step::Prevote));
+        let (vote_one, vote_two) = match &evidence {
+            Evidence::DoubleVote(double_vote) => double_vote.votes(),
+            _ => unreachable!(),
+        };
+        let mut s = RlpStream::new_list(4);
+        s.append(&(EVIDENCE_VERSION + 1)).append(&EvidenceKind::DoubleVote).append(vote_one).append(vote_two);
+        assert_eq!(Err(DecoderError::Custom("Unsupported evidence version")), Rlp::new(&s.out()).as_val::<Evidence>());
+    }
+
+    #[test]
+    fn light_client_attack_punishes_the_validators_who_signed_both() {
+        let evidence = Evidence::LightClientAttack {
+            conflicting_commit: commit(4, &[0, 1, 2]),
+            canonical_commit: commit(4, &[1, 2, 3]),
+        };
+        assert_eq!(3, evidence.height());
+        match VerifiedCrime::from(&evidence) {
+            VerifiedCrime::LightClientAttack {
+                height,
+                criminal_indices,
+            } => {
+                assert_eq!(3, height);
+                assert_eq!(vec![1, 2], criminal_indices);
+            }
+            _ => panic!("Unexpected crime"),
+        }
+    }
+}
@@ -14,26 +14,92 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::vote_collector::DoubleVote;
-use std::mem::take;
+use super::evidence::Evidence;
+use std::collections::VecDeque;
 
-pub type Evidence = DoubleVote; // This may be generalized in the future
+/// The number of published evidences to remember, so that the evidences gossiped again are not republished.
+const MAX_PUBLISHED_EVIDENCES: usize = 1024;
 
 #[derive(Default)]
 pub struct EvidenceCollector {
     evidences: Vec<Evidence>,
+    published: VecDeque<Evidence>,
 }
 
 impl EvidenceCollector {
-    pub fn insert_double_vote(&mut self, double_vote: DoubleVote) {
-        self.evidences.push(double_vote);
+    /// Returns false if the evidence is already known.
+    pub fn insert(&mut self, evidence: Evidence) -> bool {
+        if self.contains(&evidence) {
+            return false
+        }
+        self.evidences.push(evidence);
+        true
     }
 
-    pub fn fetch_evidences(&mut self) -> Vec<Evidence> {
-        take(&mut self.evidences)
+    pub fn contains(&self, evidence: &Evidence) -> bool {
+        self.evidences.contains(evidence) || self.published.contains(evidence)
+    }
+
+    /// The evidences are kept until a block including them is imported.
+    pub fn fetch_evidences(&self) -> Vec<Evidence> {
+        self.evidences.clone()
     }
 
     pub fn remove_published_evidences(&mut self, published: Vec<Evidence>) {
         self.evidences.retain(|e| !published.contains(e));
+        for evidence in published {
+            if self.published.contains(&evidence) {
+                continue
+            }
+            if self.published.len() == MAX_PUBLISHED_EVIDENCES {
+                self.published.pop_front();
+            }
+            self.published.push_back(evidence);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::message::{ConsensusMessage, VoteOn, VoteStep};
+    use super::super::types::Step;
+    use super::super::vote_collector::DoubleVote;
+    use super::*;
+    use ckey::Signature;
+    use primitives::H256;
+
+    fn evidence() -> Evidence {
+        let step = VoteStep::new(1, 0, Step::Prevote);
+        let vote = || ConsensusMessage {
+            on: VoteOn {
+                step,
+                block_hash: Some(H256::random().into()),
+            },
+            signature: Signature::random(),
+            signer_index: 0,
+        };
+        DoubleVote::new(vote(), vote()).into()
+    }
+
+    #[test]
+    fn known_evidence_is_not_inserted_again() {
+        let mut collector = EvidenceCollector::default();
+        let evidence = evidence();
+        assert!(collector.insert(evidence.clone()));
+        assert!(!collector.insert(evidence.clone()));
+        assert_eq!(vec![evidence], collector.fetch_evidences());
+    }
+
+    #[test]
+    fn published_evidence_is_not_inserted_again() {
+        let mut collector = EvidenceCollector::default();
+        let published = evidence();
+        let pending = evidence();
+        assert!(collector.insert(published.clone()));
+        assert!(collector.insert(pending.clone()));
+
+        collector.remove_published_evidences(vec![published.clone()]);
+        assert!(!collector.insert(published));
+        assert_eq!(vec![pending], collector.fetch_evidences());
     }
 }
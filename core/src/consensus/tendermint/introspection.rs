@@ -0,0 +1,133 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::message::{ConsensusMessage, VoteStep};
+use super::types::{Height, Step, View};
+use cnetwork::NodeId;
+use ctypes::BlockHash;
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+/// A snapshot of the consensus state, which is used to debug a stalled network.
+#[derive(Clone, Debug)]
+pub struct ConsensusState {
+    pub height: Height,
+    pub view: View,
+    pub step: Step,
+    /// The detailed state of the worker, e.g. whether it waits for the proposal to be imported.
+    pub state: String,
+    /// The proposal of the current view.
+    pub proposal: Option<BlockHash>,
+    /// The view and the block of the last two-thirds majority of prevotes.
+    /// The block is `None` if the majority voted nil, which unlocks the node.
+    pub lock: Option<(View, Option<BlockHash>)>,
+    /// The votes of the current height.
+    pub votes: Vec<StepVotes>,
+    /// The states of the peers reported by them.
+    pub peers: Vec<PeerConsensusState>,
+    /// The time when the current step times out.
+    pub step_deadline: Option<SystemTime>,
+    /// The time when the empty proposal is generated if there is no transaction.
+    pub empty_proposal_deadline: Option<SystemTime>,
+}
+
+/// The votes of a step grouped by the blocks they voted on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepVotes {
+    pub step: VoteStep,
+    /// The block and the indices of the signers. The block is `None` for the nil votes.
+    pub votes: Vec<(Option<BlockHash>, Vec<usize>)>,
+}
+
+impl StepVotes {
+    /// Groups the messages by the steps and the blocks.
+    pub fn collect<I: IntoIterator<Item = ConsensusMessage>>(messages: I) -> Vec<Self> {
+        let mut steps: BTreeMap<VoteStep, Vec<(Option<BlockHash>, Vec<usize>)>> = BTreeMap::new();
+        for message in messages {
+            let votes = steps.entry(message.on.step).or_default();
+            match votes.iter_mut().find(|(block_hash, _)| *block_hash == message.on.block_hash) {
+                Some((_, signers)) => signers.push(message.signer_index),
+                None => votes.push((message.on.block_hash, vec![message.signer_index])),
+            }
+        }
+        steps
+            .into_iter()
+            .map(|(step, mut votes)| {
+                for (_, signers) in votes.iter_mut() {
+                    signers.sort_unstable();
+                }
+                StepVotes {
+                    step,
+                    votes,
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PeerConsensusState {
+    pub node_id: NodeId,
+    pub vote_step: VoteStep,
+    pub proposal: Option<BlockHash>,
+    /// The indices of the signers whose votes the peer has.
+    pub messages: Vec<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::tendermint::message::VoteOn;
+    use ckey::Signature;
+    use primitives::H256;
+
+    fn vote(step: VoteStep, block_hash: Option<BlockHash>, signer_index: usize) -> ConsensusMessage {
+        ConsensusMessage {
+            on: VoteOn {
+                step,
+                block_hash,
+            },
+            signature: Signature::random(),
+            signer_index,
+        }
+    }
+
+    #[test]
+    fn votes_are_grouped_by_steps_and_blocks() {
+        let prevote = VoteStep::new(3, 0, Step::Prevote);
+        let precommit = VoteStep::new(3, 0, Step::Precommit);
+        let block_hash = Some(BlockHash::from(H256::random()));
+        let votes = StepVotes::collect(vec![
+            vote(precommit, block_hash, 1),
+            vote(prevote, block_hash, 2),
+            vote(prevote, None, 3),
+            vote(prevote, block_hash, 0),
+        ]);
+        assert_eq!(
+            vec![
+                StepVotes {
+                    step: prevote,
+                    votes: vec![(block_hash, vec![0, 2]), (None, vec![3])],
+                },
+                StepVotes {
+                    step: precommit,
+                    votes: vec![(block_hash, vec![1])],
+                },
+            ],
+            votes
+        );
+    }
+}
@@ -15,7 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::super::BitSet;
-use super::{Height, Step, View};
+use super::{Evidence, Height, Step, View};
 use ccrypto::blake256;
 use ckey::{verify, Ed25519Public as Public, Signature};
 use ctypes::BlockHash;
@@ -79,6 +79,7 @@ enum MessageID {
     RequestProposal = 0x05,
     RequestCommit = 0x06,
     Commit = 0x07,
+    Evidences = 0x08,
 }
 
 impl Encodable for MessageID {
@@ -98,6 +99,7 @@ impl Decodable for MessageID {
             0x05 => Ok(MessageID::RequestProposal),
             0x06 => Ok(MessageID::RequestCommit),
             0x07 => Ok(MessageID::Commit),
+            0x08 => Ok(MessageID::Evidences),
             _ => Err(DecoderError::Custom("Unexpected MessageID Value")),
         }
     }
@@ -132,6 +134,7 @@ pub enum TendermintMessage {
         block: Bytes,
         votes: Vec<ConsensusMessage>,
     },
+    Evidences(Vec<Evidence>),
 }
 
 impl Encodable for TendermintMessage {
@@ -206,6 +209,11 @@ impl Encodable for TendermintMessage {
                 s.append(block);
                 s.append_list(votes);
             }
+            TendermintMessage::Evidences(evidences) => {
+                s.begin_list(2);
+                s.append(&MessageID::Evidences);
+                s.append_list(evidences);
+            }
         }
     }
 }
@@ -327,6 +335,16 @@ impl Decodable for TendermintMessage {
                     votes,
                 }
             }
+            MessageID::Evidences => {
+                let item_count = rlp.item_count()?;
+                if item_count != 2 {
+                    return Err(DecoderError::RlpIncorrectListLen {
+                        got: item_count,
+                        expected: 2,
+                    })
+                }
+                TendermintMessage::Evidences(rlp.list_at(1)?)
+            }
         })
     }
 }
@@ -379,6 +397,7 @@ impl ConsensusMessage {
 
 #[cfg(test)]
 mod tests {
+    use super::super::vote_collector::DoubleVote;
     use super::super::Step;
     use super::*;
     use rlp::rlp_encode_and_decode_test;
@@ -480,6 +499,23 @@ mod tests {
         });
     }
 
+    #[test]
+    fn encode_and_decode_tendermint_message_8() {
+        let vote = |block_hash: H256| ConsensusMessage {
+            signature: Signature::random(),
+            signer_index: 3,
+            on: VoteOn {
+                step: VoteStep::new(2, 3, Step::Prevote),
+                block_hash: Some(block_hash.into()),
+            },
+        };
+        rlp_encode_and_decode_test!(TendermintMessage::Evidences(vec![DoubleVote::new(
+            vote(H256::random()),
+            vote(H256::random())
+        )
+        .into()]));
+    }
+
     #[test]
     fn encode_and_decode_consensus_message_1() {
         let message = ConsensusMessage::default();
@@ -17,25 +17,30 @@
 mod backup;
 mod chain_notify;
 mod engine;
+mod evidence;
 mod evidence_collector;
+mod introspection;
 mod message;
 mod network;
 mod params;
 pub mod types;
 pub mod vote_collector;
 mod vote_regression_checker;
+mod wal;
 mod worker;
 
 use self::chain_notify::TendermintChainNotify;
-pub use self::evidence_collector::Evidence;
+pub use self::evidence::{Evidence, EVIDENCE_VERSION};
+pub use self::introspection::{ConsensusState, PeerConsensusState, StepVotes};
 pub use self::message::{ConsensusMessage, VoteOn, VoteStep};
-pub use self::params::{TendermintParams, TimeGapParams, TimeoutParams};
+pub use self::params::{EmptyBlockPolicy, SealFormat, TendermintParams, TimeGapParams, TimeoutParams};
 pub use self::types::{Height, Step, View};
 pub use super::ValidatorSet;
 use crate::client::ConsensusClient;
 use crate::consensus::DynamicValidator;
 use crate::snapshot_notify::NotifySender as SnapshotNotifySender;
 use crate::{ChainNotify, ConsensusEngine};
+use ckey::{verify, verify_batch, Ed25519Public as Public, Signature};
 use crossbeam_channel as crossbeam;
 use ctimer::TimerToken;
 use parking_lot::RwLock;
@@ -60,6 +65,7 @@ pub struct Tendermint {
     extension_initializer: crossbeam::Sender<(crossbeam::Sender<network::Event>, Weak<dyn ConsensusClient>)>,
     snapshot_notify_sender_initializer: crossbeam::Sender<SnapshotNotifySender>,
     timeouts: TimeoutParams,
+    seal_format: SealFormat,
     join: Option<JoinHandle<()>>,
     quit_tendermint: crossbeam::Sender<()>,
     inner: crossbeam::Sender<worker::Event>,
@@ -83,6 +89,8 @@ impl Tendermint {
     pub fn new(our_params: TendermintParams) -> Arc<Self> {
         let validators = Arc::new(DynamicValidator::default());
         let timeouts = our_params.timeouts;
+        let seal_format = our_params.seal_format;
+        let empty_block = our_params.empty_block;
 
         let (
             join,
@@ -91,7 +99,7 @@ impl Tendermint {
             snapshot_notify_sender_initializer,
             inner,
             quit_tendermint,
-        ) = worker::spawn(Arc::clone(&validators));
+        ) = worker::spawn(Arc::clone(&validators), seal_format, timeouts, empty_block);
         let chain_notify = Arc::new(TendermintChainNotify::new(inner.clone()));
 
         Arc::new(Tendermint {
@@ -100,6 +108,7 @@ impl Tendermint {
             extension_initializer,
             snapshot_notify_sender_initializer,
             timeouts,
+            seal_format,
             join: Some(join),
             quit_tendermint,
             inner,
@@ -121,6 +130,22 @@ impl Tendermint {
 
 const SEAL_FIELDS: usize = 4;
 
+/// Verifies the precommit signatures of a seal, and returns the signer of an invalid signature if there is one.
+///
+/// The signatures of an individual seal are verified one by one with `verify`, and the ones of a batchEd25519 seal
+/// are verified at once with the ZIP-215 rules. Every node verifies a seal of the same format with the same rules.
+pub fn verify_precommits(format: SealFormat, message: &[u8], signatures: &[(Signature, Public)]) -> Result<(), Public> {
+    match format {
+        SealFormat::Individual => {
+            match signatures.iter().find(|(signature, public)| !verify(signature, message, public)) {
+                Some((_, public)) => Err(*public),
+                None => Ok(()),
+            }
+        }
+        SealFormat::BatchEd25519 => verify_batch(message, signatures).map_err(|index| signatures[index].1),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use ckey::{Ed25519Private as Private, Ed25519Public as Public};
@@ -130,7 +155,7 @@ mod tests {
     use super::message::VoteStep;
     use crate::account_provider::AccountProvider;
     use crate::client::TestBlockChainClient;
-    use crate::consensus::Seal;
+    use crate::consensus::{Seal, TendermintSealView};
     use crate::error::BlockError;
     use crate::error::Error;
 
@@ -195,6 +220,7 @@ mod tests {
             cur_view: 0,
             precommits: vec![signature2],
             precommit_bitset: BitSet::new_with_indices(&[2]),
+            format: SealFormat::Individual,
         }
         .seal_fields()
         .unwrap();
@@ -203,4 +229,75 @@ mod tests {
         println!(".....");
         assert!(engine.verify_block_external(&header).is_err());
     }
+
+    fn seal_fields(format: SealFormat, precommits: Vec<Signature>, indices: &[usize]) -> Vec<primitives::Bytes> {
+        Seal::Tendermint {
+            prev_view: 1,
+            cur_view: 2,
+            precommits,
+            precommit_bitset: BitSet::new_with_indices(indices),
+            format,
+        }
+        .seal_fields()
+        .unwrap()
+    }
+
+    #[test]
+    fn decode_seal_of_each_format() {
+        let precommits = vec![Signature::random(), Signature::random(), Signature::random()];
+        for &format in &[SealFormat::Individual, SealFormat::BatchEd25519] {
+            let fields = seal_fields(format, precommits.clone(), &[0, 2, 5]);
+            let seal_view = TendermintSealView::new(&fields);
+            assert_eq!(format, seal_view.format().unwrap());
+            assert_eq!(1, seal_view.parent_block_finalized_view().unwrap());
+            assert_eq!(2, seal_view.author_view().unwrap());
+            assert_eq!(3, seal_view.precommits_count().unwrap());
+            assert_eq!(
+                vec![(0, precommits[0]), (2, precommits[1]), (5, precommits[2])],
+                seal_view.signatures().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn decode_seal_sealed_before_the_seal_format() {
+        let precommits = vec![Signature::random(), Signature::random()];
+        let fields = vec![
+            rlp::encode(&1u64),
+            rlp::encode(&2u64),
+            rlp::encode_list(&precommits),
+            rlp::encode(&BitSet::new_with_indices(&[1, 3])),
+        ];
+        assert_eq!(seal_fields(SealFormat::Individual, precommits.clone(), &[1, 3]), fields);
+        let seal_view = TendermintSealView::new(&fields);
+        assert_eq!(SealFormat::Individual, seal_view.format().unwrap());
+        assert_eq!(vec![(1, precommits[0]), (3, precommits[1])], seal_view.signatures().unwrap());
+    }
+
+    #[test]
+    fn reject_seal_of_unknown_version() {
+        let mut fields = seal_fields(SealFormat::BatchEd25519, vec![Signature::random()], &[0]);
+        fields[2] = rlp::encode(&vec![2u8; 1 + ckey::SIGNATURE_LENGTH]);
+        assert!(TendermintSealView::new(&fields).format().is_err());
+    }
+
+    #[test]
+    fn verify_precommits_finds_the_invalid_signer() {
+        let message = b"precommit";
+        let mut signatures: Vec<_> = (0..4)
+            .map(|_| {
+                let private = Private::random();
+                (ckey::sign(message, &private), private.public_key())
+            })
+            .collect();
+        for &format in &[SealFormat::Individual, SealFormat::BatchEd25519] {
+            assert_eq!(Ok(()), verify_precommits(format, message, &signatures));
+        }
+
+        signatures[2].0 = Signature::random();
+        let invalid_signer = signatures[2].1;
+        for &format in &[SealFormat::Individual, SealFormat::BatchEd25519] {
+            assert_eq!(Err(invalid_signer), verify_precommits(format, message, &signatures));
+        }
+    }
 }
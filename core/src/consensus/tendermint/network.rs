@@ -15,10 +15,11 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::super::BitSet;
+use super::introspection::{ConsensusState, PeerConsensusState};
 use super::message::*;
 use super::params::TimeoutParams;
 use super::types::{Height, PeerState, Step, View};
-use super::worker;
+use super::{worker, Evidence};
 use super::{
     ENGINE_TIMEOUT_BROADCAST_STEP_STATE, ENGINE_TIMEOUT_BROADCAT_STEP_STATE_INTERVAL, ENGINE_TIMEOUT_EMPTY_PROPOSAL,
     ENGINE_TIMEOUT_TOKEN_NONCE_BASE,
@@ -37,13 +38,15 @@ use std::cmp;
 use std::collections::HashMap;
 use std::iter::Iterator;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 pub struct TendermintExtension {
     inner: crossbeam::Sender<worker::Event>,
     peers: HashMap<NodeId, PeerState>,
     api: Box<dyn Api>,
     timeouts: TimeoutParams,
+    step_deadline: Option<SystemTime>,
+    empty_proposal_deadline: Option<SystemTime>,
 }
 
 const MIN_PEERS_PROPAGATION: usize = 4;
@@ -64,6 +67,8 @@ impl TendermintExtension {
             peers: Default::default(),
             api,
             timeouts,
+            step_deadline: Some(SystemTime::now() + initial),
+            empty_proposal_deadline: None,
         }
     }
 
@@ -146,6 +151,14 @@ impl TendermintExtension {
         }
     }
 
+    fn broadcast_evidences(&self, evidences: Vec<Evidence>) {
+        let tokens = self.select_random_peers();
+        let message = Arc::new(TendermintMessage::Evidences(evidences).rlp_bytes());
+        for token in tokens {
+            self.api.send(&token, Arc::clone(&message));
+        }
+    }
+
     fn request_proposal_to_any(&self, height: Height, view: View) {
         for (token, peer) in &self.peers {
             let is_future_height_and_view = {
@@ -200,19 +213,37 @@ impl TendermintExtension {
         self.api.send(&token, message);
     }
 
-    fn set_timer_step(&self, step: Step, view: View, expired_token_nonce: TimerToken) {
+    fn set_timer_step(&mut self, step: Step, view: View, expired_token_nonce: TimerToken) {
         self.api.clear_timer(ENGINE_TIMEOUT_EMPTY_PROPOSAL).expect("Timer clear succeeds");
         self.api.clear_timer(expired_token_nonce).expect("Timer clear succeeds");
-        self.api
-            .set_timer_once(expired_token_nonce + 1, self.timeouts.timeout(step, view))
-            .expect("Timer set succeeds");
+        let timeout = self.timeouts.timeout(step, view);
+        self.api.set_timer_once(expired_token_nonce + 1, timeout).expect("Timer set succeeds");
+        self.step_deadline = Some(SystemTime::now() + timeout);
+        self.empty_proposal_deadline = None;
     }
 
-    fn set_timer_empty_proposal(&self, view: View) {
+    fn set_timer_empty_proposal(&mut self, view: View) {
         self.api.clear_timer(ENGINE_TIMEOUT_EMPTY_PROPOSAL).expect("Timer clear succeeds");
-        self.api
-            .set_timer_once(ENGINE_TIMEOUT_EMPTY_PROPOSAL, self.timeouts.timeout(Step::Propose, view) / 2)
-            .expect("Timer set succeeds");
+        let timeout = self.timeouts.timeout(Step::Propose, view) / 2;
+        self.api.set_timer_once(ENGINE_TIMEOUT_EMPTY_PROPOSAL, timeout).expect("Timer set succeeds");
+        self.empty_proposal_deadline = Some(SystemTime::now() + timeout);
+    }
+
+    fn report_consensus_state(&self, mut state: ConsensusState, result: crossbeam::Sender<ConsensusState>) {
+        state.peers = self
+            .peers
+            .iter()
+            .map(|(node_id, peer)| PeerConsensusState {
+                node_id: *node_id,
+                vote_step: peer.vote_step,
+                proposal: peer.proposal,
+                messages: peer.messages.true_index_iter().collect(),
+            })
+            .collect();
+        state.step_deadline = self.step_deadline;
+        state.empty_proposal_deadline = self.empty_proposal_deadline;
+        // The requester may have given up waiting.
+        let _ = result.send(state);
     }
 }
 
@@ -395,6 +426,14 @@ impl NetworkExtension<Event> for TendermintExtension {
                     }
                 }
             }
+            Ok(TendermintMessage::Evidences(evidences)) => {
+                ctrace!(ENGINE, "Received evidences({}) from {:?}", evidences.len(), token);
+                self.inner
+                    .send(worker::Event::HandleEvidences {
+                        evidences,
+                    })
+                    .unwrap();
+            }
             _ => cinfo!(ENGINE, "Invalid message from peer {}", token),
         }
     }
@@ -452,6 +491,22 @@ impl NetworkExtension<Event> for TendermintExtension {
             } => {
                 self.broadcast_proposal_block(signature, view, message);
             }
+            Event::BroadcastEvidences {
+                evidences,
+            } => {
+                self.broadcast_evidences(evidences);
+            }
+            Event::SetTimeouts {
+                timeouts,
+            } => {
+                self.timeouts = timeouts;
+            }
+            Event::ReportConsensusState {
+                state,
+                result,
+            } => {
+                self.report_consensus_state(*state, result);
+            }
         }
     }
 }
@@ -487,4 +542,14 @@ pub enum Event {
         view: View,
         message: Bytes,
     },
+    BroadcastEvidences {
+        evidences: Vec<Evidence>,
+    },
+    SetTimeouts {
+        timeouts: TimeoutParams,
+    },
+    ReportConsensusState {
+        state: Box<ConsensusState>,
+        result: crossbeam::Sender<ConsensusState>,
+    },
 }
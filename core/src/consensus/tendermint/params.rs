@@ -16,12 +16,18 @@
 
 use super::types::View;
 use super::Step;
+pub use coordinator::app_desc::{EmptyBlockPolicy, SealFormat};
+use ctypes::ConsensusTimeouts;
 use std::time::Duration;
 
 /// `Tendermint` params.
 pub struct TendermintParams {
     /// Timeout durations for different steps.
     pub timeouts: TimeoutParams,
+    /// The encoding of precommit signatures in block seals.
+    pub seal_format: SealFormat,
+    /// When the proposer creates a block without transactions.
+    pub empty_block: EmptyBlockPolicy,
 }
 
 impl From<coordinator::app_desc::TendermintParams> for TendermintParams {
@@ -37,6 +43,8 @@ impl From<coordinator::app_desc::TendermintParams> for TendermintParams {
                 precommit_delta: p.timeout_precommit_delta.map_or(dt.precommit_delta, Duration::from_millis),
                 commit: p.timeout_commit.map_or(dt.commit, Duration::from_millis),
             },
+            seal_format: p.seal_format.unwrap_or_default(),
+            empty_block: p.empty_block.unwrap_or_default(),
         }
     }
 }
@@ -47,7 +55,7 @@ pub struct TimeGapParams {
 }
 
 /// Base timeout of each step in ms.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct TimeoutParams {
     pub propose: Duration,
     pub propose_delta: Duration,
@@ -72,6 +80,20 @@ impl Default for TimeoutParams {
     }
 }
 
+impl From<ConsensusTimeouts> for TimeoutParams {
+    fn from(t: ConsensusTimeouts) -> Self {
+        TimeoutParams {
+            propose: Duration::from_millis(t.propose),
+            propose_delta: Duration::from_millis(t.propose_delta),
+            prevote: Duration::from_millis(t.prevote),
+            prevote_delta: Duration::from_millis(t.prevote_delta),
+            precommit: Duration::from_millis(t.precommit),
+            precommit_delta: Duration::from_millis(t.precommit_delta),
+            commit: Duration::from_millis(t.commit),
+        }
+    }
+}
+
 impl TimeoutParams {
     pub fn initial(&self) -> Duration {
         self.propose
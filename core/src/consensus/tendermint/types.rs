@@ -16,8 +16,9 @@
 
 use super::super::BitSet;
 use super::message::VoteStep;
+use super::params::SealFormat;
 use crate::block::{ClosedBlock, IsBlock};
-use ckey::Signature;
+use ckey::{Signature, SIGNATURE_LENGTH};
 use ctypes::BlockHash;
 use primitives::Bytes;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
@@ -207,6 +208,27 @@ impl PeerState {
     }
 }
 
+/// The first byte of the precommits field in a batch-verified seal.
+///
+/// The precommits field of an individual seal is an RLP list, so a seal whose precommits field is a byte string
+/// has a versioned encoding.
+const BATCH_ED25519_SEAL_VERSION: u8 = 1;
+
+/// Encodes the precommits field of a seal.
+pub fn encode_precommits(format: SealFormat, precommits: &[Signature]) -> Bytes {
+    match format {
+        SealFormat::Individual => rlp::encode_list(precommits),
+        SealFormat::BatchEd25519 => {
+            let mut concatenated = Vec::with_capacity(1 + precommits.len() * SIGNATURE_LENGTH);
+            concatenated.push(BATCH_ED25519_SEAL_VERSION);
+            for precommit in precommits {
+                concatenated.extend_from_slice(precommit.as_ref());
+            }
+            rlp::encode(&concatenated)
+        }
+    }
+}
+
 pub struct TendermintSealView<'a> {
     seal: &'a [Bytes],
 }
@@ -240,24 +262,54 @@ impl<'a> TendermintSealView<'a> {
         Rlp::new(view_rlp.as_slice()).as_val()
     }
 
-    pub fn precommits(&self) -> Rlp<'a> {
+    fn precommits(&self) -> Rlp<'a> {
         Rlp::new(
             &self.seal.get(2).expect("block went through verify_block_basic; block has .seal_fields() fields; qed"),
         )
     }
 
-    pub fn signatures(&self) -> Result<Vec<(usize, Signature)>, DecoderError> {
+    /// The headers sealed before the seal format was introduced are decoded as `SealFormat::Individual`.
+    pub fn format(&self) -> Result<SealFormat, DecoderError> {
         let precommits = self.precommits();
+        if precommits.is_list() {
+            return Ok(SealFormat::Individual)
+        }
+        match precommits.data()?.first() {
+            Some(&BATCH_ED25519_SEAL_VERSION) => Ok(SealFormat::BatchEd25519),
+            _ => Err(DecoderError::Custom("Unknown seal version")),
+        }
+    }
+
+    pub fn precommits_count(&self) -> Result<usize, DecoderError> {
+        match self.format()? {
+            SealFormat::Individual => self.precommits().item_count(),
+            SealFormat::BatchEd25519 => Ok(self.batch_signature_bytes()?.len() / SIGNATURE_LENGTH),
+        }
+    }
+
+    fn batch_signature_bytes(&self) -> Result<&'a [u8], DecoderError> {
+        let signature_bytes = &self.precommits().data()?[1..];
+        if signature_bytes.len() % SIGNATURE_LENGTH != 0 {
+            return Err(DecoderError::Custom("Invalid length of concatenated signatures"))
+        }
+        Ok(signature_bytes)
+    }
+
+    pub fn signatures(&self) -> Result<Vec<(usize, Signature)>, DecoderError> {
         let bitset = self.bitset()?;
-        debug_assert_eq!(bitset.count(), precommits.item_count()?);
+        debug_assert_eq!(bitset.count(), self.precommits_count()?);
 
         let bitset_iter = bitset.true_index_iter();
 
-        let signatures = precommits.iter().map(|rlp| rlp.as_val::<Signature>());
-        bitset_iter
-            .zip(signatures)
-            .map(|(index, signature)| signature.map(|signature| (index, signature)))
-            .collect::<Result<_, _>>()
+        let signatures: Vec<Signature> = match self.format()? {
+            SealFormat::Individual => self.precommits().as_list()?,
+            SealFormat::BatchEd25519 => self
+                .batch_signature_bytes()?
+                .chunks(SIGNATURE_LENGTH)
+                .map(|bytes| Signature::from_slice(bytes).expect("The length is checked"))
+                .collect(),
+        };
+        Ok(bitset_iter.zip(signatures).collect())
     }
 }
 
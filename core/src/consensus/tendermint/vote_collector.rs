@@ -50,8 +50,33 @@ impl Encodable for DoubleVote {
 }
 
 impl Decodable for DoubleVote {
-    fn decode(_rlp: &Rlp) -> Result<Self, DecoderError> {
-        todo!()
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 2 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 2,
+            })
+        }
+        Ok(Self::new(rlp.val_at(0)?, rlp.val_at(1)?))
+    }
+}
+
+impl DoubleVote {
+    pub fn new(vote_one: ConsensusMessage, vote_two: ConsensusMessage) -> Self {
+        Self {
+            author_index: vote_two.signer_index(),
+            vote_one,
+            vote_two,
+        }
+    }
+
+    pub fn votes(&self) -> (&ConsensusMessage, &ConsensusMessage) {
+        (&self.vote_one, &self.vote_two)
+    }
+
+    pub fn round(&self) -> &VoteStep {
+        self.vote_one.round()
     }
 }
 
@@ -79,11 +104,7 @@ impl StepCollector {
         self.messages.push(message.clone());
         if let Some(previous) = self.voted.insert(message.signer_index(), message.clone()) {
             // Bad validator sent a different message.
-            Err(DoubleVote {
-                author_index: message.signer_index(),
-                vote_one: previous,
-                vote_two: message,
-            })
+            Err(DoubleVote::new(previous, message))
         } else {
             self.block_votes
                 .entry(message.block_hash())
@@ -12,13 +12,15 @@ impl VoteRegressionChecker {
         }
     }
 
-    pub fn check(&mut self, vote_on: &VoteOn) -> bool {
+    /// Checks the vote without recording it.
+    /// The vote should be recorded with `check` only after it is signed.
+    pub fn is_monotonic(&self, vote_on: &VoteOn) -> bool {
         assert!(
             matches!(vote_on.step.step, Step::Propose | Step::Prevote | Step::Precommit),
             "We don't vote on Commit. Check your code"
         );
 
-        let monotonic = if let Some(last_vote) = &self.last_vote {
+        if let Some(last_vote) = &self.last_vote {
             match last_vote.step.cmp(&vote_on.step) {
                 Ordering::Less => true,
                 Ordering::Greater => false,
@@ -26,8 +28,11 @@ impl VoteRegressionChecker {
             }
         } else {
             true
-        };
+        }
+    }
 
+    pub fn check(&mut self, vote_on: &VoteOn) -> bool {
+        let monotonic = self.is_monotonic(vote_on);
         if monotonic {
             self.last_vote = Some(vote_on.clone());
         }
@@ -55,6 +60,25 @@ mod tests {
         }))
     }
 
+    #[test]
+    fn test_is_monotonic_does_not_record() {
+        let mut checker = VoteRegressionChecker::new();
+
+        checker.check(&VoteOn {
+            step: VoteStep::new(100, 10, Step::Prevote),
+            block_hash: Some(H256::from_uint(&u256_from_u128(1u128)).into()),
+        });
+
+        assert!(checker.is_monotonic(&VoteOn {
+            step: VoteStep::new(100, 10, Step::Precommit),
+            block_hash: Some(H256::from_uint(&u256_from_u128(2u128)).into()),
+        }));
+        assert!(checker.check(&VoteOn {
+            step: VoteStep::new(100, 10, Step::Precommit),
+            block_hash: Some(H256::from_uint(&u256_from_u128(3u128)).into()),
+        }));
+    }
+
     #[test]
     #[should_panic]
     fn test_panic_on_commit() {
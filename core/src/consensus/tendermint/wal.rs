@@ -0,0 +1,373 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The write-ahead log of the consensus.
+//!
+//! Every consensus message, step transition and timeout is appended and fsynced before the worker acts on it.
+//! On restart, the worker restores the last state in the log and replays the messages after it, so it never signs
+//! a vote conflicting with the votes it signed before the crash.
+//!
+//! A record is the 4-byte big-endian length of the payload, the first 4 bytes of the blake256 hash of the payload,
+//! and the RLP-encoded entry. A torn record at the end, which is left by a crash while appending, is truncated
+//! when the log is opened.
+
+use super::message::ConsensusMessage;
+use super::types::{Height, Step, View};
+use ccrypto::blake256;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+const HEADER_SIZE: usize = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WalEntry {
+    /// The state of the worker after a step transition.
+    State {
+        height: Height,
+        view: View,
+        step: Step,
+        finalized_view_of_previous_block: View,
+        finalized_view_of_current_block: Option<View>,
+    },
+    /// A message received from a peer or signed by this node.
+    Message(ConsensusMessage),
+    /// The timeout of the step.
+    Timeout {
+        height: Height,
+        view: View,
+        step: Step,
+    },
+}
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+enum EntryID {
+    State = 0x01,
+    Message = 0x02,
+    Timeout = 0x03,
+}
+
+impl Encodable for EntryID {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.append_single_value(&(*self as u8));
+    }
+}
+
+impl Decodable for EntryID {
+    fn decode(rlp: &Rlp<'_>) -> Result<Self, DecoderError> {
+        let tag = rlp.as_val()?;
+        match tag {
+            0x01u8 => Ok(EntryID::State),
+            0x02 => Ok(EntryID::Message),
+            0x03 => Ok(EntryID::Timeout),
+            _ => Err(DecoderError::Custom("Unexpected EntryID Value")),
+        }
+    }
+}
+
+impl Encodable for WalEntry {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            WalEntry::State {
+                height,
+                view,
+                step,
+                finalized_view_of_previous_block,
+                finalized_view_of_current_block,
+            } => {
+                s.begin_list(6);
+                s.append(&EntryID::State);
+                s.append(height).append(view).append(step);
+                s.append(finalized_view_of_previous_block).append(finalized_view_of_current_block);
+            }
+            WalEntry::Message(message) => {
+                s.begin_list(2);
+                s.append(&EntryID::Message);
+                s.append(message);
+            }
+            WalEntry::Timeout {
+                height,
+                view,
+                step,
+            } => {
+                s.begin_list(4);
+                s.append(&EntryID::Timeout);
+                s.append(height).append(view).append(step);
+            }
+        }
+    }
+}
+
+impl Decodable for WalEntry {
+    fn decode(rlp: &Rlp<'_>) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        let expected = match rlp.val_at(0)? {
+            EntryID::State => 6,
+            EntryID::Message => 2,
+            EntryID::Timeout => 4,
+        };
+        if item_count != expected {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected,
+            })
+        }
+        Ok(match rlp.val_at(0)? {
+            EntryID::State => WalEntry::State {
+                height: rlp.val_at(1)?,
+                view: rlp.val_at(2)?,
+                step: rlp.val_at(3)?,
+                finalized_view_of_previous_block: rlp.val_at(4)?,
+                finalized_view_of_current_block: rlp.val_at(5)?,
+            },
+            EntryID::Message => WalEntry::Message(rlp.val_at(1)?),
+            EntryID::Timeout => WalEntry::Timeout {
+                height: rlp.val_at(1)?,
+                view: rlp.val_at(2)?,
+                step: rlp.val_at(3)?,
+            },
+        })
+    }
+}
+
+pub struct Wal {
+    path: PathBuf,
+    file: File,
+    entries: Vec<WalEntry>,
+}
+
+impl Wal {
+    /// Opens the log, creating it if it doesn't exist.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().read(true).append(true).create(true).open(path)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+
+        let (entries, valid_len) = decode_records(&bytes);
+        if valid_len != bytes.len() {
+            cwarn!(
+                ENGINE,
+                "The consensus WAL {} has a torn record at {}. Truncate {} bytes",
+                path.display(),
+                valid_len,
+                bytes.len() - valid_len
+            );
+            file.set_len(valid_len as u64)?;
+            file.sync_all()?;
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            entries,
+        })
+    }
+
+    /// The entries in the log when it is opened.
+    pub fn entries(&self) -> &[WalEntry] {
+        &self.entries
+    }
+
+    /// Appends the entry and waits until it is written to the disk.
+    pub fn append(&mut self, entry: &WalEntry) -> io::Result<()> {
+        self.file.write_all(&encode_record(entry))?;
+        self.file.sync_data()
+    }
+
+    /// Replaces the whole log with the given entries.
+    /// It is called when the height changes, so that the log doesn't grow forever.
+    pub fn checkpoint(&mut self, entries: &[WalEntry]) -> io::Result<()> {
+        let temp_path = self.path.with_extension("tmp");
+        {
+            let mut temp = File::create(&temp_path)?;
+            for entry in entries {
+                temp.write_all(&encode_record(entry))?;
+            }
+            temp.sync_all()?;
+        }
+        fs::rename(&temp_path, &self.path)?;
+        // The rename is durable only after the directory is synced.
+        let dir = match self.path.parent() {
+            Some(dir) if dir != Path::new("") => dir,
+            _ => Path::new("."),
+        };
+        File::open(dir)?.sync_all()?;
+        self.file = OpenOptions::new().read(true).append(true).open(&self.path)?;
+        Ok(())
+    }
+}
+
+fn checksum(payload: &[u8]) -> [u8; 4] {
+    let hash = blake256(payload);
+    let mut checksum = [0u8; 4];
+    checksum.copy_from_slice(&hash.as_ref()[..4]);
+    checksum
+}
+
+fn encode_record(entry: &WalEntry) -> Vec<u8> {
+    let payload = entry.rlp_bytes();
+    let mut record = Vec::with_capacity(HEADER_SIZE + payload.len());
+    record.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    record.extend_from_slice(&checksum(&payload));
+    record.extend_from_slice(&payload);
+    record
+}
+
+/// Returns the decoded entries and the length of the valid records.
+fn decode_records(bytes: &[u8]) -> (Vec<WalEntry>, usize) {
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while bytes.len() >= offset + HEADER_SIZE {
+        let mut len = [0u8; 4];
+        len.copy_from_slice(&bytes[offset..offset + 4]);
+        let len = u32::from_be_bytes(len) as usize;
+        let start = offset + HEADER_SIZE;
+        if bytes.len() < start + len {
+            break
+        }
+        let payload = &bytes[start..start + len];
+        if bytes[offset + 4..start] != checksum(payload) {
+            break
+        }
+        match rlp::decode(payload) {
+            Ok(entry) => entries.push(entry),
+            Err(_) => break,
+        }
+        offset = start + len;
+    }
+    (entries, offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::message::{VoteOn, VoteStep};
+    use super::*;
+    use ckey::Signature;
+    use primitives::H256;
+    use rlp::rlp_encode_and_decode_test;
+    use tempfile::TempDir;
+
+    /// Returns the path of a WAL in a new directory, which is removed with the `TempDir`.
+    fn wal_path() -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("consensus.wal");
+        (dir, path)
+    }
+
+    fn entries() -> Vec<WalEntry> {
+        vec![
+            WalEntry::State {
+                height: 3,
+                view: 1,
+                step: Step::Prevote,
+                finalized_view_of_previous_block: 0,
+                finalized_view_of_current_block: None,
+            },
+            WalEntry::Message(ConsensusMessage {
+                on: VoteOn {
+                    step: VoteStep::new(3, 1, Step::Prevote),
+                    block_hash: Some(H256::random().into()),
+                },
+                signature: Signature::random(),
+                signer_index: 2,
+            }),
+            WalEntry::Timeout {
+                height: 3,
+                view: 1,
+                step: Step::Prevote,
+            },
+        ]
+    }
+
+    #[test]
+    fn encode_and_decode_entries() {
+        for entry in entries() {
+            rlp_encode_and_decode_test!(entry);
+        }
+    }
+
+    #[test]
+    fn entries_survive_reopening() {
+        let (_dir, path) = wal_path();
+        let mut wal = Wal::open(&path).unwrap();
+        assert!(wal.entries().is_empty());
+        for entry in &entries() {
+            wal.append(entry).unwrap();
+        }
+        drop(wal);
+
+        let wal = Wal::open(&path).unwrap();
+        assert_eq!(entries(), wal.entries());
+    }
+
+    #[test]
+    fn torn_record_is_truncated() {
+        let (_dir, path) = wal_path();
+        let mut wal = Wal::open(&path).unwrap();
+        let entries = entries();
+        wal.append(&entries[0]).unwrap();
+        wal.append(&entries[1]).unwrap();
+        drop(wal);
+
+        let valid_len = fs::metadata(&path).unwrap().len();
+        let record = encode_record(&entries[2]);
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(&record[..record.len() - 1]).unwrap();
+
+        let mut wal = Wal::open(&path).unwrap();
+        assert_eq!(&entries[..2], wal.entries());
+        assert_eq!(valid_len, fs::metadata(&path).unwrap().len());
+
+        wal.append(&entries[2]).unwrap();
+        drop(wal);
+        assert_eq!(entries, Wal::open(&path).unwrap().entries());
+    }
+
+    #[test]
+    fn corrupted_record_is_truncated() {
+        let (_dir, path) = wal_path();
+        let mut wal = Wal::open(&path).unwrap();
+        for entry in &entries() {
+            wal.append(entry).unwrap();
+        }
+        drop(wal);
+
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        fs::write(&path, &bytes).unwrap();
+
+        assert_eq!(&entries()[..2], Wal::open(&path).unwrap().entries());
+    }
+
+    #[test]
+    fn checkpoint_replaces_the_log() {
+        let (_dir, path) = wal_path();
+        let mut wal = Wal::open(&path).unwrap();
+        let entries = entries();
+        for entry in &entries {
+            wal.append(entry).unwrap();
+        }
+        wal.checkpoint(&entries[..1]).unwrap();
+        wal.append(&entries[2]).unwrap();
+        drop(wal);
+
+        assert_eq!(vec![entries[0].clone(), entries[2].clone()], Wal::open(&path).unwrap().entries());
+    }
+}
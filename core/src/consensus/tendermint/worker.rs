@@ -15,21 +15,24 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::super::BitSet;
-use super::backup::{backup, restore, BackupView};
-use super::evidence_collector::{Evidence, EvidenceCollector};
+use super::backup::{backup, find_proposal, restore, BackupView};
+use super::evidence::{equivocators, Evidence};
+use super::evidence_collector::EvidenceCollector;
+use super::introspection::{ConsensusState, StepVotes};
 use super::message::*;
 use super::network;
-use super::params::TimeGapParams;
+use super::params::{EmptyBlockPolicy, SealFormat, TimeGapParams, TimeoutParams};
 use super::types::{Height, Proposal, Step, TendermintSealView, TendermintState, TwoThirdsMajority, View};
 use super::vote_collector::VoteCollector;
 use super::vote_regression_checker::VoteRegressionChecker;
+use super::wal::{Wal, WalEntry};
 use super::{
-    ENGINE_TIMEOUT_BROADCAST_STEP_STATE, ENGINE_TIMEOUT_EMPTY_PROPOSAL, ENGINE_TIMEOUT_TOKEN_NONCE_BASE, SEAL_FIELDS,
+    verify_precommits, ENGINE_TIMEOUT_BROADCAST_STEP_STATE, ENGINE_TIMEOUT_EMPTY_PROPOSAL,
+    ENGINE_TIMEOUT_TOKEN_NONCE_BASE, SEAL_FIELDS,
 };
-use crate::account_provider::AccountProvider;
 use crate::block::*;
 use crate::client::ConsensusClient;
-use crate::consensus::signer::EngineSigner;
+use crate::consensus::signer::{ConsensusSigner, EngineSigner};
 use crate::consensus::validator_set::{DynamicValidator, ValidatorSet};
 use crate::consensus::{EngineError, Seal};
 use crate::encoded;
@@ -37,15 +40,17 @@ use crate::error::{BlockError, Error};
 use crate::snapshot_notify::NotifySender as SnapshotNotifySender;
 use crate::types::BlockStatus;
 use crate::views::BlockView;
-use ckey::{verify, Ed25519Public as Public, Signature};
+use ckey::{Ed25519Public as Public, Signature};
 use cnetwork::{EventSender, NodeId};
 use crossbeam_channel as crossbeam;
+use cstate::{CurrentValidatorSet, NextValidatorSet};
 use ctypes::util::unexpected::Mismatch;
 use ctypes::{BlockHash, BlockId, BlockNumber, Header};
 use primitives::Bytes;
 use rlp::{Encodable, Rlp};
 use std::cell::Cell;
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::iter::Iterator;
 use std::mem;
 use std::sync::{Arc, Weak};
@@ -61,8 +66,13 @@ type SpawnResult = (
     crossbeam::Sender<()>,
 );
 
-pub fn spawn(validators: Arc<DynamicValidator>) -> SpawnResult {
-    Worker::spawn(validators)
+pub fn spawn(
+    validators: Arc<DynamicValidator>,
+    seal_format: SealFormat,
+    timeouts: TimeoutParams,
+    empty_block: EmptyBlockPolicy,
+) -> SpawnResult {
+    Worker::spawn(validators, seal_format, timeouts, empty_block)
 }
 
 struct Worker {
@@ -79,6 +89,8 @@ struct Worker {
     votes: VoteCollector,
     /// evidence accumulator
     evidences: EvidenceCollector,
+    /// The headers of the proposals in the current height, which failed the verification.
+    invalid_proposals: HashMap<BlockHash, Header>,
     /// Used to sign messages and proposals.
     signer: EngineSigner,
     /// Last majority
@@ -95,9 +107,21 @@ struct Worker {
     /// Channel to the network extension, must be set later.
     extension: EventSender<network::Event>,
     time_gap_params: TimeGapParams,
+    /// The format of the seals generated by this node.
+    seal_format: SealFormat,
+    /// The timeouts in the app descriptor, which are used until the chain decides them.
+    default_timeouts: TimeoutParams,
+    /// The timeouts of the current height.
+    timeouts: TimeoutParams,
+    /// When this node creates a block without transactions.
+    empty_block: EmptyBlockPolicy,
     timeout_token_nonce: usize,
     vote_regression_checker: VoteRegressionChecker,
     snapshot_notify_sender: SnapshotNotifySender,
+    /// The write-ahead log of the consensus. The backup in the DB is used if it is not registered.
+    wal: Option<Wal>,
+    /// The height of the last checkpoint of the WAL.
+    wal_checkpoint_height: Height,
 }
 
 pub enum Event {
@@ -127,18 +151,27 @@ pub enum Event {
     FetchEvidences {
         result: crossbeam::Sender<Vec<Evidence>>,
     },
+    ConsensusState {
+        result: crossbeam::Sender<ConsensusState>,
+    },
     RemovePublishedEvidences {
         published: Vec<Evidence>,
     },
+    VerifyEvidences {
+        header: Box<Header>,
+        evidences: Vec<Evidence>,
+        result: crossbeam::Sender<Result<(), Error>>,
+    },
+    HandleEvidences {
+        evidences: Vec<Evidence>,
+    },
     IsProposal {
         block_number: BlockNumber,
         block_hash: BlockHash,
         result: crossbeam::Sender<bool>,
     },
-    SetSigner {
-        ap: Arc<AccountProvider>,
-        pubkey: Public,
-    },
+    SetSigner(Arc<dyn ConsensusSigner>),
+    SetWal(Box<Wal>),
     Restore(crossbeam::Sender<()>),
     ProposalBlock {
         signature: Signature,
@@ -183,6 +216,9 @@ impl Worker {
         extension: EventSender<network::Event>,
         client: Weak<dyn ConsensusClient>,
         time_gap_params: TimeGapParams,
+        seal_format: SealFormat,
+        timeouts: TimeoutParams,
+        empty_block: EmptyBlockPolicy,
         snapshot_notify_sender: SnapshotNotifySender,
     ) -> Self {
         Worker {
@@ -192,6 +228,7 @@ impl Worker {
             step: TendermintState::Propose,
             votes: Default::default(),
             evidences: Default::default(),
+            invalid_proposals: Default::default(),
             signer: Default::default(),
             last_two_thirds_majority: TwoThirdsMajority::Empty,
             proposal: Proposal::None,
@@ -201,13 +238,24 @@ impl Worker {
             extension,
             votes_received: MutTrigger::new(BitSet::new()),
             time_gap_params,
+            seal_format,
+            default_timeouts: timeouts,
+            timeouts,
+            empty_block,
             timeout_token_nonce: ENGINE_TIMEOUT_TOKEN_NONCE_BASE,
             vote_regression_checker: VoteRegressionChecker::new(),
             snapshot_notify_sender,
+            wal: None,
+            wal_checkpoint_height: 0,
         }
     }
 
-    fn spawn(validators: Arc<DynamicValidator>) -> SpawnResult {
+    fn spawn(
+        validators: Arc<DynamicValidator>,
+        seal_format: SealFormat,
+        timeouts: TimeoutParams,
+        empty_block: EmptyBlockPolicy,
+    ) -> SpawnResult {
         let (sender, receiver) = crossbeam::unbounded();
         let (quit, quit_receiver) = crossbeam::bounded(1);
         let (external_params_initializer, external_params_receiver) = crossbeam::bounded(1);
@@ -278,7 +326,16 @@ impl Worker {
                 }
                 };
                 validators.register_client(Weak::clone(&client));
-                let mut inner = Self::new(validators, extension, client, time_gap_params, snapshot_notify_sender);
+                let mut inner = Self::new(
+                    validators,
+                    extension,
+                    client,
+                    time_gap_params,
+                    seal_format,
+                    timeouts,
+                    empty_block,
+                    snapshot_notify_sender,
+                );
                 loop {
                     crossbeam::select! {
                     recv(receiver) -> msg => {
@@ -304,7 +361,11 @@ impl Worker {
                                 result.send(inner.verify_header_basic(&*header)).unwrap();
                             }
                             Ok(Event::VerifyBlockExternal{header, result, }) => {
-                                result.send(inner.verify_block_external(&*header)).unwrap();
+                                let verified = inner.verify_block_external(&*header);
+                                if verified.is_err() {
+                                    inner.on_invalid_proposal(*header);
+                                }
+                                result.send(verified).unwrap();
                             }
                             Ok(Event::OnTimeout(token)) => {
                                 inner.on_timeout(token);
@@ -317,6 +378,11 @@ impl Worker {
                                     result.send(inner.handle_message(&message, false)).unwrap();
                                 }
                             }
+                            Ok(Event::ConsensusState {
+                                result,
+                            }) => {
+                                inner.report_consensus_state(result);
+                            }
                             Ok(Event::FetchEvidences {
                                 result,
                             }) => {
@@ -327,6 +393,18 @@ impl Worker {
                             }) => {
                                 inner.remove_published_evidences(published);
                             },
+                            Ok(Event::VerifyEvidences {
+                                header,
+                                evidences,
+                                result,
+                            }) => {
+                                result.send(inner.verify_evidences(&*header, &evidences)).unwrap();
+                            }
+                            Ok(Event::HandleEvidences {
+                                evidences,
+                            }) => {
+                                inner.handle_evidences(evidences);
+                            }
                             Ok(Event::IsProposal {
                                 block_number,
                                 block_hash,
@@ -334,14 +412,15 @@ impl Worker {
                             }) => {
                                 result.send(inner.is_proposal(block_number, block_hash)).unwrap();
                             }
-                            Ok(Event::SetSigner {
-                                ap,
-                                pubkey,
-                            }) => {
-                                inner.set_signer(ap, pubkey);
+                            Ok(Event::SetSigner(signer)) => {
+                                inner.set_signer(signer);
+                            }
+                            Ok(Event::SetWal(wal)) => {
+                                inner.wal = Some(*wal);
                             }
                             Ok(Event::Restore(result)) => {
                                 inner.restore();
+                                inner.update_timeouts();
                                 result.send(()).unwrap();
                             }
                             Ok(Event::ProposalBlock {
@@ -460,8 +539,10 @@ impl Worker {
     }
 
     /// Find the designated for the given view.
-    fn view_proposer(&self, prev_block_hash: &BlockHash, view: View) -> Public {
-        self.validators.next_block_proposer(prev_block_hash, view)
+    fn view_proposer(&self, prev_block_hash: &BlockHash, view: View) -> Result<Public, EngineError> {
+        self.validators
+            .next_block_proposer(prev_block_hash, view)
+            .ok_or_else(|| EngineError::StateNotAvailable(*prev_block_hash))
     }
 
     fn first_proposal_at(&self, height: Height, view: View) -> Option<(Signature, usize, Bytes)> {
@@ -521,7 +602,7 @@ impl Worker {
 
     /// Check if pubkey is a proposer for given view.
     fn check_view_proposer(&self, parent: &BlockHash, view: View, pubkey: &Public) -> Result<(), EngineError> {
-        let proposer = self.view_proposer(parent, view);
+        let proposer = self.view_proposer(parent, view)?;
         if proposer == *pubkey {
             Ok(())
         } else {
@@ -534,8 +615,13 @@ impl Worker {
 
     /// Check if current signer is the current proposer.
     fn is_signer_proposer(&self, bh: &BlockHash) -> bool {
-        let proposer = self.view_proposer(bh, self.view);
-        self.signer.is_signer(&proposer)
+        match self.view_proposer(bh, self.view) {
+            Ok(proposer) => self.signer.is_signer(&proposer),
+            Err(err) => {
+                cwarn!(ENGINE, "Cannot find the proposer on {}: {}", bh, err);
+                false
+            }
+        }
     }
 
     fn is_step(&self, message: &ConsensusMessage) -> bool {
@@ -617,7 +703,52 @@ impl Worker {
     }
 
     fn update_sealing(&self, parent_block_hash: BlockHash) {
-        self.client().update_sealing(BlockId::Hash(parent_block_hash), true);
+        let allow_empty_block = self.allows_empty_block(parent_block_hash);
+        self.client().update_sealing(BlockId::Hash(parent_block_hash), allow_empty_block);
+    }
+
+    /// Whether a block without transactions can be created on the parent.
+    fn allows_empty_block(&self, parent_block_hash: BlockHash) -> bool {
+        match self.empty_block {
+            EmptyBlockPolicy::Always => true,
+            EmptyBlockPolicy::Interval(interval) => {
+                let parent_timestamp = match self.client().block_header(&parent_block_hash.into()) {
+                    Some(header) => header.timestamp(),
+                    None => return true,
+                };
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time never goes backward").as_secs();
+                parent_timestamp.saturating_add(interval) <= now
+            }
+            EmptyBlockPolicy::ValidatorSetChange => {
+                let state = match self.client().state_at(parent_block_hash.into()) {
+                    Some(state) => state,
+                    None => return true,
+                };
+                let current = CurrentValidatorSet::load_from_state(&state)
+                    .map(|validators| validators.create_compact_validator_set());
+                let next = NextValidatorSet::load_from_state(&state)
+                    .map(|validators| validators.create_compact_validator_set());
+                match (current, next) {
+                    (Ok(current), Ok(next)) => current != next,
+                    _ => true,
+                }
+            }
+            EmptyBlockPolicy::Never => false,
+        }
+    }
+
+    /// Restarts the timer of the propose step without changing the step.
+    /// It is used to wait for transactions while an empty block is not allowed.
+    fn extend_propose_timer(&mut self) {
+        let expired_token_nonce = self.timeout_token_nonce;
+        self.timeout_token_nonce += 1;
+        self.extension
+            .send(network::Event::SetTimerStep {
+                step: Step::Propose,
+                view: self.view,
+                expired_token_nonce,
+            })
+            .unwrap();
     }
 
     /// Do we need this function?
@@ -650,10 +781,12 @@ impl Worker {
         self.height += 1;
         self.view = 0;
         self.proposal = Proposal::None;
+        self.invalid_proposals.clear();
         self.votes_received = MutTrigger::new(BitSet::new());
         self.finalized_view_of_previous_block =
             self.finalized_view_of_current_block.expect("self.step == Step::Commit");
         self.finalized_view_of_current_block = None;
+        self.update_timeouts();
     }
 
     /// Jump to the height.
@@ -668,9 +801,39 @@ impl Worker {
         self.height = height;
         self.view = 0;
         self.proposal = Proposal::None;
+        self.invalid_proposals.clear();
         self.votes_received = MutTrigger::new(BitSet::new());
         self.finalized_view_of_previous_block = finalized_view_of_previous_height;
         self.finalized_view_of_current_block = None;
+        self.update_timeouts();
+    }
+
+    /// Applies the timeouts decided by the previous block.
+    /// The extension must know them before `move_to_step` sets the timer of the new height.
+    /// Invalid timeouts are ignored, and the timeouts in the app descriptor are used instead.
+    fn update_timeouts(&mut self) {
+        let timeouts = self
+            .client()
+            .chain_params(BlockId::Number(self.height - 1))
+            .and_then(|params| params.timeouts())
+            .filter(|timeouts| match timeouts.verify() {
+                Ok(()) => true,
+                Err(err) => {
+                    cwarn!(ENGINE, "The consensus timeouts of height {} are ignored: {}", self.height, err);
+                    false
+                }
+            })
+            .map_or(self.default_timeouts, TimeoutParams::from);
+        if timeouts == self.timeouts {
+            return
+        }
+        cinfo!(ENGINE, "The consensus timeouts are changed from height {}: {:?}", self.height, timeouts);
+        self.timeouts = timeouts;
+        self.extension
+            .send(network::Event::SetTimeouts {
+                timeouts,
+            })
+            .unwrap();
     }
 
     #[allow(clippy::cognitive_complexity)]
@@ -867,9 +1030,17 @@ impl Worker {
     }
 
     fn generate_and_broadcast_message(&mut self, block_hash: Option<BlockHash>, is_restoring: bool) {
-        if let Some(message) = self.vote_on_block_hash(block_hash).expect("Error while vote") {
+        let message = match self.vote_on_block_hash(block_hash) {
+            Ok(message) => message,
+            Err(err) => {
+                cerror!(ENGINE, "Failed to vote on {:?}: {}", block_hash, err);
+                return
+            }
+        };
+        if let Some(message) = message {
             self.handle_valid_message(&message, is_restoring);
-            if !is_restoring {
+            // The vote is already in the WAL if it exists.
+            if !is_restoring && self.wal.is_none() {
                 self.backup();
             }
             self.broadcast_message(message);
@@ -968,7 +1139,7 @@ impl Worker {
             if !self.votes.is_old_or_known(&message) {
                 if let Err(double_vote) = self.votes.collect(message) {
                     cerror!(ENGINE, "Double vote found on_commit_message: {:?}", double_vote);
-                    self.evidences.insert_double_vote(double_vote);
+                    self.insert_evidence(double_vote.into());
                 }
             }
         }
@@ -1036,47 +1207,152 @@ impl Worker {
         }
     }
 
-    fn backup(&self) {
-        backup(self.client().get_kvdb().as_ref(), BackupView {
-            height: &self.height,
-            view: &self.view,
-            step: &self.step.to_step(),
-            votes: &self.votes.get_all(),
-            finalized_view_of_previous_block: &self.finalized_view_of_previous_block,
-            finalized_view_of_current_block: &self.finalized_view_of_current_block,
-        });
+    /// Appends the entry to the WAL and waits until it is written to the disk.
+    /// It must be called before acting on the entry.
+    fn write_ahead(&mut self, entry: WalEntry) {
+        if let Some(wal) = &mut self.wal {
+            wal.append(&entry).expect("Failed to write the consensus WAL. Some issue with disk?");
+        }
+    }
+
+    fn backup(&mut self) {
+        let wal = match &mut self.wal {
+            Some(wal) => wal,
+            None => {
+                backup(self.client().get_kvdb().as_ref(), BackupView {
+                    height: &self.height,
+                    view: &self.view,
+                    step: &self.step.to_step(),
+                    votes: &self.votes.get_all(),
+                    finalized_view_of_previous_block: &self.finalized_view_of_previous_block,
+                    finalized_view_of_current_block: &self.finalized_view_of_current_block,
+                });
+                return
+            }
+        };
+        let state = WalEntry::State {
+            height: self.height,
+            view: self.view,
+            step: self.step.to_step(),
+            finalized_view_of_previous_block: self.finalized_view_of_previous_block,
+            finalized_view_of_current_block: self.finalized_view_of_current_block,
+        };
+        if self.wal_checkpoint_height == self.height {
+            wal.append(&state).expect("Failed to write the consensus WAL. Some issue with disk?");
+            return
+        }
+        // The votes of the previous height are kept because the seal of the next block needs its precommits.
+        let entries: Vec<_> =
+            std::iter::once(state).chain(self.votes.get_all().into_iter().map(WalEntry::Message)).collect();
+        wal.checkpoint(&entries).expect("Failed to write the consensus WAL. Some issue with disk?");
+        self.wal_checkpoint_height = self.height;
+    }
+
+    /// Returns the entries to replay.
+    /// The backup in the DB is converted to entries if the WAL is empty, which happens after upgrading the node.
+    fn entries_to_restore(&self) -> Vec<WalEntry> {
+        if let Some(wal) = &self.wal {
+            if wal.entries().iter().any(|entry| matches!(entry, WalEntry::State { .. })) {
+                return wal.entries().to_vec()
+            }
+        }
+        let backup = match restore(self.client().get_kvdb().as_ref()) {
+            Some(backup) => backup,
+            None => return Vec::new(),
+        };
+        let state = WalEntry::State {
+            height: backup.height,
+            view: backup.view,
+            step: backup.step,
+            finalized_view_of_previous_block: backup.finalized_view_of_previous_block,
+            finalized_view_of_current_block: backup.finalized_view_of_current_block,
+        };
+        std::iter::once(state).chain(backup.votes.into_iter().map(WalEntry::Message)).collect()
     }
 
     fn restore(&mut self) {
+        let entries = self.entries_to_restore();
+        let state = entries.iter().rev().find_map(|entry| match entry {
+            WalEntry::State {
+                height,
+                view,
+                step,
+                finalized_view_of_previous_block,
+                finalized_view_of_current_block,
+            } => Some((*height, *view, *step, *finalized_view_of_previous_block, *finalized_view_of_current_block)),
+            _ => None,
+        });
+        let (height, view, step, finalized_view_of_previous_block, finalized_view_of_current_block) = match state {
+            Some(state) => state,
+            None => return,
+        };
         let client = self.client();
-        let backup = restore(client.get_kvdb().as_ref());
-        if let Some(backup) = backup {
-            let backup_step = match backup.step {
-                Step::Propose => TendermintState::Propose,
-                Step::Prevote => TendermintState::Prevote,
-                Step::Precommit => TendermintState::Precommit,
-                // If the backuped step is `Commit`, we should start at `Precommit` to update the
-                // chain's best block safely.
-                Step::Commit => TendermintState::Precommit,
-            };
+        let best_block_number = client.best_block_header().number();
+        if height > best_block_number + 1 {
+            cwarn!(
+                ENGINE,
+                "The consensus WAL at height {} is ahead of the chain at {}. Ignore it",
+                height,
+                best_block_number
+            );
+            return
+        }
+        let backup_step = match step {
+            Step::Propose => TendermintState::Propose,
+            Step::Prevote => TendermintState::Prevote,
+            Step::Precommit => TendermintState::Precommit,
+            // If the backuped step is `Commit`, we should start at `Precommit` to update the
+            // chain's best block safely.
+            Step::Commit => TendermintState::Precommit,
+        };
 
-            self.step = backup_step;
-            self.height = backup.height;
-            self.view = backup.view;
-            self.finalized_view_of_previous_block = backup.finalized_view_of_previous_block;
-            self.finalized_view_of_current_block = backup.finalized_view_of_current_block;
+        self.step = backup_step;
+        self.height = height;
+        self.view = view;
+        self.finalized_view_of_previous_block = finalized_view_of_previous_block;
+        self.finalized_view_of_current_block = finalized_view_of_current_block;
+
+        let votes: Vec<_> = entries
+            .iter()
+            .filter_map(|entry| match entry {
+                WalEntry::Message(message) => Some(message.clone()),
+                _ => None,
+            })
+            .collect();
+        if let Some(proposal) = find_proposal(&votes, height, view) {
+            if client.block(&BlockId::Hash(proposal)).is_some() {
+                self.proposal = Proposal::ProposalImported(proposal);
+            }
+        }
 
-            if let Some(proposal) = backup.proposal {
-                if client.block(&BlockId::Hash(proposal)).is_some() {
-                    self.proposal = Proposal::ProposalImported(proposal);
-                }
+        let signer_index = self.signer_index();
+        for vote in votes {
+            // Remember the votes signed before the crash so that this node never signs a conflicting vote.
+            if vote.height() == self.height && Some(vote.signer_index()) == signer_index {
+                self.vote_regression_checker.check(&vote.on);
+            }
+            let bytes = rlp::encode(&vote);
+            if let Err(err) = self.handle_message(&bytes, true) {
+                cinfo!(ENGINE, "Fail to load backuped message {:?}", err);
             }
+        }
 
-            for vote in backup.votes {
-                let bytes = rlp::encode(&vote);
-                if let Err(err) = self.handle_message(&bytes, true) {
-                    cinfo!(ENGINE, "Fail to load backuped message {:?}", err);
-                }
+        if self.wal.is_some() {
+            // Compact the log, which also migrates the backup in the DB.
+            self.wal_checkpoint_height = 0;
+            self.backup();
+        }
+
+        // The node crashed after the timeout was logged but before the transition was finished.
+        if let Some(WalEntry::Timeout {
+            height,
+            view,
+            step,
+        }) = entries.last()
+        {
+            if (*height, *view, *step) == (self.height, self.view, self.step.to_step()) && *step != Step::Commit {
+                cinfo!(ENGINE, "Replay the timeout of {}-{}-{:?}", height, view, step);
+                self.on_step_timeout();
             }
         }
     }
@@ -1116,6 +1392,7 @@ impl Worker {
             cur_view: view,
             precommits,
             precommit_bitset,
+            format: self.seal_format,
         }
     }
 
@@ -1159,7 +1436,10 @@ impl Worker {
         }
         debug_assert_eq!(Ok(self.view), TendermintSealView::new(header.seal()).author_view());
 
-        self.vote_on_header_for_proposal(&header).expect("I'm a proposer");
+        if let Err(err) = self.vote_on_header_for_proposal(&header) {
+            cerror!(ENGINE, "Failed to sign the proposal {}: {}", header.hash(), err);
+            return
+        }
 
         self.step = TendermintState::ProposeWaitImported {
             block: Box::new(closed_block.clone()),
@@ -1188,8 +1468,18 @@ impl Worker {
         }
         self.check_view_proposer(header.parent_hash(), author_view, &proposer)?;
         let seal_view = TendermintSealView::new(header.seal());
+        let seal_format = seal_view.format()?;
+        if seal_format != SealFormat::Individual && seal_format != self.seal_format {
+            cwarn!(
+                ENGINE,
+                "verify_block_external: The header({}) is sealed in {:?}, which is not allowed",
+                header.hash(),
+                seal_format
+            );
+            return Err(BlockError::InvalidSeal.into())
+        }
         let bitset_count = seal_view.bitset()?.count();
-        let precommits_count = seal_view.precommits().item_count()?;
+        let precommits_count = seal_view.precommits_count()?;
 
         if bitset_count < precommits_count {
             cwarn!(
@@ -1217,14 +1507,15 @@ impl Worker {
 
         let mut voted_validators = BitSet::new();
         let parent_hash = header.parent_hash();
+        let mut signatures = Vec::with_capacity(precommits_count);
         for (bitset_index, signature) in seal_view.signatures()? {
             let public = self.validators.get_current(header.parent_hash(), bitset_index);
-            if !verify(&signature, precommit_vote_on.hash().as_ref(), &public) {
-                return Err(EngineError::BlockNotAuthorized(public).into())
-            }
+            signatures.push((signature, public));
             assert!(!voted_validators.is_set(bitset_index), "Double vote");
             voted_validators.set(bitset_index);
         }
+        verify_precommits(seal_format, precommit_vote_on.hash().as_ref(), &signatures)
+            .map_err(EngineError::BlockNotAuthorized)?;
 
         // Genesisblock does not have signatures
         if header.number() == 1 {
@@ -1281,11 +1572,36 @@ impl Worker {
             return
         }
 
+        self.write_ahead(WalEntry::Timeout {
+            height: self.height,
+            view: self.view,
+            step: self.step.to_step(),
+        });
+        self.on_step_timeout();
+    }
+
+    fn on_step_timeout(&mut self) {
         let next_step = match self.step {
+            TendermintState::Propose
+                if self.client().is_mem_pool_empty() && !self.allows_empty_block(self.prev_block_hash()) =>
+            {
+                cdebug!(ENGINE, "Propose timed out, but an empty block is not allowed. Wait for transactions");
+                self.extend_propose_timer();
+                return
+            }
             TendermintState::Propose => {
                 cinfo!(ENGINE, "Propose timeout.");
                 TendermintState::Prevote
             }
+            TendermintState::ProposeWaitBlockGeneration {
+                parent_hash,
+            } if self.empty_block != EmptyBlockPolicy::Always => {
+                // The miner doesn't create an empty block if it is not allowed. Try again with the new transactions.
+                cdebug!(ENGINE, "Propose timed out but block is not generated yet. Try to create a block again");
+                self.update_sealing(parent_hash);
+                self.extend_propose_timer();
+                return
+            }
             TendermintState::ProposeWaitBlockGeneration {
                 ..
             } => {
@@ -1412,6 +1728,10 @@ impl Worker {
                 self.vote_step()
             };
 
+            if !is_restoring {
+                self.write_ahead(WalEntry::Message(message.clone()));
+            }
+
             if message.on.step == current_vote_step {
                 let vote_index = self
                     .validators
@@ -1422,15 +1742,45 @@ impl Worker {
 
             if let Err(double_vote) = self.votes.collect(message.clone()) {
                 cerror!(ENGINE, "Double vote found {:?}", double_vote);
-                self.evidences.insert_double_vote(double_vote);
+                self.insert_evidence(double_vote.into());
                 return Err(EngineError::DoubleVote(sender))
             }
+            if let Some(header) = message.block_hash().and_then(|hash| self.invalid_proposals.get(&hash).cloned()) {
+                cerror!(ENGINE, "Vote on the invalid block found {:?}", message);
+                self.insert_evidence(Evidence::InvalidBlockVote {
+                    vote: message.clone(),
+                    header,
+                });
+            }
             ctrace!(ENGINE, "Handling a valid {:?} from {:?}.", message, sender);
             self.handle_valid_message(&message, is_restoring);
         }
         Ok(())
     }
 
+    /// Sends the state of the worker to the extension, which adds the states of the peers and replies to `result`.
+    fn report_consensus_state(&self, result: crossbeam::Sender<ConsensusState>) {
+        let votes = self.votes.get_all().into_iter().filter(|vote| vote.height() == self.height);
+        let state = ConsensusState {
+            height: self.height,
+            view: self.view,
+            step: self.step.to_step(),
+            state: format!("{:?}", self.step),
+            proposal: self.proposal.block_hash(),
+            lock: self.last_two_thirds_majority.view().map(|view| (view, self.last_two_thirds_majority.block_hash())),
+            votes: StepVotes::collect(votes),
+            peers: Vec::new(),
+            step_deadline: None,
+            empty_proposal_deadline: None,
+        };
+        self.extension
+            .send(network::Event::ReportConsensusState {
+                state: Box::new(state),
+                result,
+            })
+            .unwrap();
+    }
+
     fn fetch_evidences(&mut self) -> Vec<Evidence> {
         self.evidences.fetch_evidences()
     }
@@ -1439,6 +1789,224 @@ impl Worker {
         self.evidences.remove_published_evidences(published);
     }
 
+    /// Collects a new evidence and gossips it.
+    fn insert_evidence(&mut self, evidence: Evidence) {
+        if self.evidences.insert(evidence.clone()) {
+            self.extension
+                .send(network::Event::BroadcastEvidences {
+                    evidences: vec![evidence],
+                })
+                .unwrap();
+        }
+    }
+
+    fn handle_evidences(&mut self, evidences: Vec<Evidence>) {
+        for evidence in evidences {
+            if self.evidences.contains(&evidence) {
+                continue
+            }
+            match self.verify_evidence(&evidence) {
+                Ok(()) => {
+                    cinfo!(ENGINE, "Evidence received {:?}", evidence);
+                    self.insert_evidence(evidence);
+                }
+                Err(err) => cinfo!(ENGINE, "Invalid evidence received: {}", err),
+            }
+        }
+    }
+
+    /// Votes on the proposal are evidences from now on, if every node finds its fault.
+    fn on_invalid_proposal(&mut self, header: Header) {
+        if header.number() != self.height || !self.has_provable_fault(&header) {
+            return
+        }
+        let block_hash = header.hash();
+        let votes: Vec<_> = self
+            .votes
+            .get_all()
+            .into_iter()
+            .filter(|vote| vote.height() == self.height && vote.block_hash() == Some(block_hash))
+            .collect();
+        for vote in votes {
+            cerror!(ENGINE, "Vote on the invalid block found {:?}", vote);
+            self.insert_evidence(Evidence::InvalidBlockVote {
+                vote,
+                header: header.clone(),
+            });
+        }
+        self.invalid_proposals.insert(block_hash, header);
+    }
+
+    /// Checks the faults which are decided only by the header and the validators at its parent.
+    ///
+    /// The other checks of `verify_block_external` are not used for evidences. E.g. the seal formats allowed by
+    /// nodes may differ, and a vote on a block one node rejects is not a misbehavior to the others.
+    fn has_provable_fault(&self, header: &Header) -> bool {
+        if self.verify_header_basic(header).is_err() {
+            return true
+        }
+        let author_view = match TendermintSealView::new(header.seal()).author_view() {
+            Ok(view) => view,
+            Err(_) => return true,
+        };
+        let proposer = header.author();
+        // The proposer can't be checked without the parent state, which is not a fault of the proposer.
+        !self.is_authority(header.parent_hash(), proposer)
+            || matches!(
+                self.check_view_proposer(header.parent_hash(), author_view, proposer),
+                Err(EngineError::NotProposer(_))
+            )
+    }
+
+    fn verify_evidences(&self, header: &Header, evidences: &[Evidence]) -> Result<(), Error> {
+        for (index, evidence) in evidences.iter().enumerate() {
+            if evidences[..index].contains(evidence) {
+                return Err(EngineError::InvalidEvidence("The evidence is duplicated".to_string()).into())
+            }
+            if evidence.height() > header.number() {
+                return Err(EngineError::InvalidEvidence(format!(
+                    "The evidence of height {} is in the block #{}",
+                    evidence.height(),
+                    header.number()
+                ))
+                .into())
+            }
+            self.verify_evidence(evidence)?;
+        }
+        Ok(())
+    }
+
+    fn verify_evidence(&self, evidence: &Evidence) -> Result<(), EngineError> {
+        fn invalid(reason: &str) -> EngineError {
+            EngineError::InvalidEvidence(reason.to_string())
+        }
+
+        let height = evidence.height();
+        if height == 0 {
+            return Err(invalid("Validators don't vote on the genesis block"))
+        }
+        // The validators of the height are determined by the previous block.
+        let prev_block_hash = self
+            .client()
+            .block_header(&BlockId::Number(height - 1))
+            .ok_or_else(|| invalid("The previous block is not imported yet"))?
+            .hash();
+
+        match evidence {
+            Evidence::DoubleVote(double_vote)
+            | Evidence::DoubleProposal(double_vote)
+            | Evidence::LegacyDoubleVote(double_vote) => {
+                let (vote_one, vote_two) = double_vote.votes();
+                if vote_one.round() != vote_two.round() || vote_one.signer_index() != vote_two.signer_index() {
+                    return Err(invalid("The votes are not from the same signer at the same step"))
+                }
+                if vote_one.block_hash() == vote_two.block_hash() {
+                    return Err(invalid("The votes are on the same block"))
+                }
+                let is_proposal = vote_one.round().step == Step::Propose;
+                let is_legacy = matches!(evidence, Evidence::LegacyDoubleVote(_));
+                if !is_legacy && is_proposal != matches!(evidence, Evidence::DoubleProposal(_)) {
+                    return Err(invalid("The kind of the evidence doesn't match the step"))
+                }
+                self.verify_vote_of_evidence(&prev_block_hash, vote_one)?;
+                self.verify_vote_of_evidence(&prev_block_hash, vote_two)
+            }
+            Evidence::InvalidBlockVote {
+                vote,
+                header,
+            } => {
+                if vote.block_hash() != Some(header.hash()) || header.number() != height {
+                    return Err(invalid("The vote is not on the header"))
+                }
+                if header.parent_hash() != &prev_block_hash {
+                    return Err(invalid("The header is not on the canonical chain"))
+                }
+                self.verify_vote_of_evidence(&prev_block_hash, vote)?;
+                if !self.has_provable_fault(header) {
+                    return Err(invalid("The header doesn't have a fault every node finds"))
+                }
+                Ok(())
+            }
+            Evidence::LightClientAttack {
+                conflicting_commit,
+                canonical_commit,
+            } => {
+                if conflicting_commit.number() != canonical_commit.number() {
+                    return Err(invalid("The commits are at different heights"))
+                }
+                if conflicting_commit.parent_hash() == canonical_commit.parent_hash() {
+                    return Err(invalid("The commits are on the same block"))
+                }
+                let conflicting_view = TendermintSealView::new(conflicting_commit.seal())
+                    .parent_block_finalized_view()
+                    .map_err(|_| invalid("The seal of the conflicting commit is malformed"))?;
+                let canonical_view = TendermintSealView::new(canonical_commit.seal())
+                    .parent_block_finalized_view()
+                    .map_err(|_| invalid("The seal of the canonical commit is malformed"))?;
+                if conflicting_view != canonical_view {
+                    return Err(invalid("The commits are at different views"))
+                }
+                self.verify_commit_of_evidence(&prev_block_hash, height, conflicting_commit)?;
+                self.verify_commit_of_evidence(&prev_block_hash, height, canonical_commit)?;
+                let criminals =
+                    equivocators(conflicting_commit, canonical_commit).map_err(|_| invalid("The seal is malformed"))?;
+                if criminals.is_empty() {
+                    return Err(invalid("No one signed both commits"))
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn verify_vote_of_evidence(&self, prev_block_hash: &BlockHash, vote: &ConsensusMessage) -> Result<(), EngineError> {
+        let signer_index = vote.signer_index();
+        let prev_height = vote.height() - 1;
+        if signer_index >= self.validators.count(prev_block_hash) {
+            return Err(EngineError::ValidatorNotExist {
+                height: prev_height,
+                index: signer_index,
+            })
+        }
+        let signer = self.validators.get(prev_block_hash, signer_index);
+        if !vote.verify(&signer) {
+            return Err(EngineError::MessageWithInvalidSignature {
+                height: prev_height,
+                signer_index,
+                pubkey: signer,
+            })
+        }
+        Ok(())
+    }
+
+    /// Verifies the precommits in the seal of the commit, which is a child of a block at the height.
+    fn verify_commit_of_evidence(
+        &self,
+        prev_block_hash: &BlockHash,
+        height: Height,
+        commit: &Header,
+    ) -> Result<(), EngineError> {
+        let seal_view = TendermintSealView::new(commit.seal());
+        let malformed = |_| EngineError::InvalidEvidence("The seal is malformed".to_string());
+        let format = seal_view.format().map_err(malformed)?;
+        let precommit_vote_on = VoteOn {
+            step: VoteStep::new(height, seal_view.parent_block_finalized_view().map_err(malformed)?, Step::Precommit),
+            block_hash: Some(*commit.parent_hash()),
+        };
+        let n_validators = self.validators.count(prev_block_hash);
+        let mut signatures = Vec::new();
+        for (index, signature) in seal_view.signatures().map_err(malformed)? {
+            if index >= n_validators {
+                return Err(EngineError::ValidatorNotExist {
+                    height: height - 1,
+                    index,
+                })
+            }
+            signatures.push((signature, self.validators.get(prev_block_hash, index)));
+        }
+        verify_precommits(format, precommit_vote_on.hash().as_ref(), &signatures)
+            .map_err(EngineError::BlockNotAuthorized)
+    }
+
     fn is_proposal(&self, block_number: BlockNumber, block_hash: BlockHash) -> bool {
         if self.height > block_number {
             return false
@@ -1458,7 +2026,10 @@ impl Worker {
 
     fn repropose_block(&mut self, block: encoded::Block) {
         let header = block.decode_header();
-        self.vote_on_header_for_proposal(&header).expect("I am proposer");
+        if let Err(err) = self.vote_on_header_for_proposal(&header) {
+            cerror!(ENGINE, "Failed to sign the proposal {}: {}", header.hash(), err);
+            return
+        }
         self.proposal = Proposal::new_imported(header.hash());
         self.broadcast_proposal_block(self.view, block);
     }
@@ -1482,8 +2053,8 @@ impl Worker {
             .unwrap();
     }
 
-    fn set_signer(&mut self, ap: Arc<AccountProvider>, pubkey: Public) {
-        self.signer.set_to_keep_decrypted_account(ap, pubkey);
+    fn set_signer(&mut self, signer: Arc<dyn ConsensusSigner>) {
+        self.signer.set_signer(signer);
     }
 
     fn vote_on_block_hash(&mut self, block_hash: Option<BlockHash>) -> Result<Option<ConsensusMessage>, Error> {
@@ -1498,15 +2069,18 @@ impl Worker {
             step: VoteStep::new(self.height, self.view, self.step.to_step()),
             block_hash,
         };
-        assert!(self.vote_regression_checker.check(&on), "Vote should not regress");
+        assert!(self.vote_regression_checker.is_monotonic(&on), "Vote should not regress");
 
-        let signature = self.signer.sign(on.hash())?;
+        let signature = self.signer.sign(&on)?;
+        // The vote is recorded only after it is signed, so a failed signing doesn't block the same vote later.
+        self.vote_regression_checker.check(&on);
 
         let vote = ConsensusMessage {
             signature,
             signer_index,
             on,
         };
+        self.write_ahead(WalEntry::Message(vote.clone()));
 
         self.votes_received.set(vote.signer_index);
         self.votes.collect(vote.clone()).expect("Must not attempt double vote");
@@ -1518,21 +2092,26 @@ impl Worker {
         assert_eq!(header.number(), self.height);
 
         let parent_hash = header.parent_hash();
-        let signer_index = self.validators.proposer_index(*parent_hash, self.view);
+        let signer_index = self
+            .validators
+            .proposer_index(*parent_hash, self.view)
+            .ok_or_else(|| EngineError::StateNotAvailable(*parent_hash))?;
 
         let on = VoteOn {
             step: VoteStep::new(self.height, self.view, Step::Propose),
             block_hash: Some(header.hash()),
         };
-        assert!(self.vote_regression_checker.check(&on), "Vote should not regress");
+        assert!(self.vote_regression_checker.is_monotonic(&on), "Vote should not regress");
 
-        let signature = self.signer.sign(on.hash())?;
+        let signature = self.signer.sign(&on)?;
+        self.vote_regression_checker.check(&on);
 
         let vote = ConsensusMessage {
             signature,
             signer_index,
             on,
         };
+        self.write_ahead(WalEntry::Message(vote.clone()));
 
         self.votes.collect(vote.clone()).expect("Must not attempt double vote on proposal");
         cinfo!(ENGINE, "Voted {:?} as {}th proposer.", vote, signer_index);
@@ -1545,7 +2124,7 @@ impl Worker {
         proposed_view: View,
         signature: Signature,
     ) -> Option<ConsensusMessage> {
-        let signer_index = self.validators.proposer_index(*header.parent_hash(), proposed_view);
+        let signer_index = self.validators.proposer_index(*header.parent_hash(), proposed_view)?;
 
         let on = VoteOn {
             step: VoteStep::new(header.number(), proposed_view, Step::Propose),
@@ -1575,6 +2154,15 @@ impl Worker {
 
         self.send_snapshot_notify(c.as_ref(), enacted.as_slice());
 
+        let published: Vec<_> = enacted
+            .iter()
+            .filter_map(|hash| c.block(&BlockId::Hash(*hash)))
+            .flat_map(|block| block.evidences())
+            .collect();
+        if !published.is_empty() {
+            self.remove_published_evidences(published);
+        }
+
         if self.step.is_commit() && (imported.len() + enacted.len() == 1) {
             let (_, committed_block_hash) = self.step.committed().expect("Commit state always has block_hash");
             if imported.first() == Some(&committed_block_hash) {
@@ -1756,6 +2344,7 @@ impl Worker {
                 cdebug!(ENGINE, "Proposal is already known");
                 return None
             }
+            self.write_ahead(WalEntry::Message(message.clone()));
 
             if number == self.height as u64 && proposed_view == self.view {
                 // The proposer re-proposed its locked proposal.
@@ -1784,7 +2373,7 @@ impl Worker {
 
             if let Err(double_vote) = self.votes.collect(message) {
                 cerror!(ENGINE, "Double Vote found {:?}", double_vote);
-                self.evidences.insert_double_vote(double_vote);
+                self.insert_evidence(double_vote.into());
                 return None
             }
         }
@@ -2109,7 +2698,7 @@ impl Worker {
             if !self.votes.is_old_or_known(&vote) {
                 if let Err(double_vote) = self.votes.collect(vote) {
                     cerror!(ENGINE, "Double vote found on_commit_message: {:?}", double_vote);
-                    self.evidences.insert_double_vote(double_vote);
+                    self.insert_evidence(double_vote.into());
                 }
             }
         }
@@ -2186,3 +2775,123 @@ impl<T> std::ops::DerefMut for MutTrigger<T> {
         &mut self.target
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{ImportBlock, TestBlockChainClient};
+    use crate::snapshot_notify;
+    use rlp::RlpStream;
+
+    fn worker(
+        client: &Arc<TestBlockChainClient>,
+        empty_block: EmptyBlockPolicy,
+    ) -> (Worker, crossbeam::Receiver<network::Event>) {
+        let (extension, events) = crossbeam::unbounded();
+        let client = Arc::clone(client) as Arc<dyn ConsensusClient>;
+        let time_gap_params = TimeGapParams {
+            allowed_past_gap: Duration::from_secs(30),
+            allowed_future_gap: Duration::from_secs(5),
+        };
+        let (snapshot_notify_sender, _) = snapshot_notify::create();
+        let worker = Worker::new(
+            Default::default(),
+            extension,
+            Arc::downgrade(&client),
+            time_gap_params,
+            SealFormat::Individual,
+            Default::default(),
+            empty_block,
+            snapshot_notify_sender,
+        );
+        (worker, events)
+    }
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    /// Imports a block without transactions made at `timestamp` on the best block.
+    fn import_block_at(client: &TestBlockChainClient, timestamp: u64) -> BlockHash {
+        let mut header = Header::new();
+        header.set_parent_hash(*client.last_hash.read());
+        header.set_number(client.numbers.read().len() as BlockNumber);
+        header.set_timestamp(timestamp);
+        let mut rlp = RlpStream::new_list(3);
+        rlp.append(&header);
+        rlp.append_raw(&RlpStream::new_list(0).out(), 1); // evidences
+        rlp.append_raw(&RlpStream::new_list(0).out(), 1); // transactions
+        client.import_block(rlp.out()).unwrap()
+    }
+
+    fn timer_steps(events: &crossbeam::Receiver<network::Event>) -> Vec<(Step, View)> {
+        events
+            .try_iter()
+            .filter_map(|event| match event {
+                network::Event::SetTimerStep {
+                    step,
+                    view,
+                    ..
+                } => Some((step, view)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn allows_empty_block_by_the_policy() {
+        let client = Arc::new(TestBlockChainClient::new());
+        let old = import_block_at(&client, now() - 60);
+        let recent = import_block_at(&client, now());
+
+        assert!(worker(&client, EmptyBlockPolicy::Always).0.allows_empty_block(recent));
+        assert!(!worker(&client, EmptyBlockPolicy::Never).0.allows_empty_block(old));
+        let (interval, _) = worker(&client, EmptyBlockPolicy::Interval(30));
+        assert!(interval.allows_empty_block(old));
+        assert!(!interval.allows_empty_block(recent));
+    }
+
+    #[test]
+    fn skip_empty_proposal_while_empty_block_is_not_allowed() {
+        let client = Arc::new(TestBlockChainClient::new());
+        import_block_at(&client, now());
+        let (mut worker, events) = worker(&client, EmptyBlockPolicy::Interval(30));
+        worker.height = 2;
+        let nonce = worker.timeout_token_nonce;
+
+        worker.on_step_timeout();
+        assert_eq!(Step::Propose, worker.step.to_step());
+        assert_eq!(vec![(Step::Propose, 0)], timer_steps(&events));
+        // The timeout of the extended timer is not expired by the previous one.
+        assert!(worker.is_expired_timeout_token(nonce));
+        assert!(!worker.is_expired_timeout_token(nonce + 1));
+
+        worker.on_step_timeout();
+        assert_eq!(Step::Propose, worker.step.to_step());
+        assert_eq!(vec![(Step::Propose, 0)], timer_steps(&events));
+    }
+
+    #[test]
+    fn move_to_prevote_once_the_interval_has_passed() {
+        let client = Arc::new(TestBlockChainClient::new());
+        import_block_at(&client, now() - 60);
+        let (mut worker, events) = worker(&client, EmptyBlockPolicy::Interval(30));
+        worker.height = 2;
+
+        worker.on_step_timeout();
+        assert_eq!(Step::Prevote, worker.step.to_step());
+        assert_eq!(vec![(Step::Prevote, 0)], timer_steps(&events));
+    }
+
+    #[test]
+    fn never_skip_proposal_when_empty_blocks_are_always_allowed() {
+        let client = Arc::new(TestBlockChainClient::new());
+        import_block_at(&client, now());
+        let (mut worker, events) = worker(&client, EmptyBlockPolicy::Always);
+        worker.height = 2;
+
+        worker.on_step_timeout();
+        assert_eq!(Step::Prevote, worker.step.to_step());
+        assert_eq!(vec![(Step::Prevote, 0)], timer_steps(&events));
+    }
+}
@@ -14,15 +14,13 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::ValidatorSet;
+use super::{check_two_thirds_majority, select_proposer, ValidatorSet};
 use crate::client::ConsensusClient;
 use crate::consensus::bit_set::BitSet;
 use crate::consensus::EngineError;
 use ckey::Ed25519Public as Public;
-use cstate::{CurrentValidatorSet, NextValidatorSet, SimpleValidator};
-use ctypes::util::unexpected::OutOfBounds;
+use cstate::{CurrentValidatorSet, NextValidatorSet, ProposerPriorities, SimpleValidator};
 use ctypes::BlockHash;
-use ctypes::BlockId;
 use parking_lot::RwLock;
 use std::sync::{Arc, Weak};
 
@@ -55,9 +53,9 @@ impl DynamicValidator {
         validators.into_iter().map(|val| *val.pubkey()).collect()
     }
 
-    pub fn proposer_index(&self, parent: BlockHash, proposed_view: u64) -> usize {
-        let propser = self.next_block_proposer(&parent, proposed_view);
-        self.get_index(&parent, &propser).expect("We know propser is included in a validator set")
+    pub fn proposer_index(&self, parent: BlockHash, proposed_view: u64) -> Option<usize> {
+        let propser = self.next_block_proposer(&parent, proposed_view)?;
+        Some(self.get_index(&parent, &propser).expect("We know propser is included in a validator set"))
     }
 
     pub fn get_current(&self, hash: &BlockHash, index: usize) -> Public {
@@ -68,32 +66,42 @@ impl DynamicValidator {
 
     pub fn check_enough_votes_with_current(&self, hash: &BlockHash, votes: &BitSet) -> Result<(), EngineError> {
         let validators = self.current_validators(*hash);
-        let mut voted_weight = 0u64;
-        let n_validators = validators.len();
-        for index in votes.true_index_iter() {
-            assert!(index < n_validators);
-            let validator = validators.get(index).ok_or({
-                EngineError::ValidatorNotExist {
-                    height: 0, // FIXME
-                    index,
-                }
-            })?;
-            voted_weight += validator.weight();
-        }
-        let total_weight: u64 = validators.iter().map(|v| v.weight()).sum();
-        if voted_weight * 3 > total_weight * 2 {
-            Ok(())
-        } else {
-            let threshold = total_weight as usize * 2 / 3;
-            Err(EngineError::BadSealFieldSize(OutOfBounds {
-                min: Some(threshold),
-                max: Some(total_weight as usize),
-                found: voted_weight as usize,
-            }))
+        check_enough_weighted_votes(&validators, votes)
+    }
+
+    /// Returns `None` if the state of the block is pruned or not imported yet.
+    fn proposer_priorities(&self, hash: BlockHash) -> Option<ProposerPriorities> {
+        let client: Arc<dyn ConsensusClient> =
+            self.client.read().as_ref().and_then(Weak::upgrade).expect("Client is not initialized");
+        let state = client.state_at(hash.into())?;
+        match ProposerPriorities::load_from_state(&state) {
+            Ok(priorities) => Some(priorities),
+            Err(err) => {
+                cwarn!(ENGINE, "Cannot load the proposer priorities of {}: {}", hash, err);
+                None
+            }
         }
     }
 }
 
+/// The votes are weighted by the delegations of the validators.
+fn check_enough_weighted_votes(validators: &[SimpleValidator], votes: &BitSet) -> Result<(), EngineError> {
+    let mut voted_weight = 0u64;
+    let n_validators = validators.len();
+    for index in votes.true_index_iter() {
+        assert!(index < n_validators);
+        let validator = validators.get(index).ok_or({
+            EngineError::ValidatorNotExist {
+                height: 0, // FIXME
+                index,
+            }
+        })?;
+        voted_weight += validator.weight();
+    }
+    let total_weight: u64 = validators.iter().map(SimpleValidator::weight).sum();
+    check_two_thirds_majority(voted_weight, total_weight)
+}
+
 impl ValidatorSet for DynamicValidator {
     fn contains(&self, parent: &BlockHash, public: &Public) -> bool {
         self.validators(*parent).into_iter().any(|pubkey| pubkey == *public)
@@ -110,22 +118,17 @@ impl ValidatorSet for DynamicValidator {
         self.validators(*parent).binary_search(public).ok()
     }
 
-    // This code assumes that validator set is not changed.
-    // Later this code should be moved to UpdateConsensus
-    fn next_block_proposer(&self, parent: &BlockHash, view: u64) -> Public {
-        let client: Arc<dyn ConsensusClient> = self.client.read().as_ref().and_then(Weak::upgrade).unwrap();
-        let parent_header = client.block_header(&BlockId::from(*parent)).expect("Parent must be finalized");
-        if parent_header.number() == 0 {
-            return self.get(parent, 0)
-        }
-
-        let proposer = parent_header.author();
-        let grand_parent = parent_header.parent_hash();
-        let prev_proposer_idx =
-            self.get_index(&grand_parent, &proposer).expect("The proposer must be in the validator set");
-        let proposer_index = prev_proposer_idx + 1 + view as usize;
-        ctrace!(ENGINE, "Proposer index: {}", proposer_index);
-        self.get(&parent, proposer_index)
+    /// The proposer is selected by the priorities stored in the parent state, and each view is a round of the
+    /// selection. See `select_proposer`.
+    /// Returns `None` if the state of the parent is not available.
+    fn next_block_proposer(&self, parent: &BlockHash, view: u64) -> Option<Public> {
+        // The priorities are read first, because the validators are read from the same state.
+        let priorities = self.proposer_priorities(*parent)?;
+        let validators: Vec<_> =
+            self.next_validators(*parent).iter().map(|validator| (*validator.pubkey(), validator.weight())).collect();
+        let (proposer, _) = select_proposer(&validators, &priorities, view + 1);
+        ctrace!(ENGINE, "Proposer of view {} on {}: {:?}", view, parent, proposer);
+        Some(proposer)
     }
 
     fn count(&self, parent: &BlockHash) -> usize {
@@ -134,29 +137,7 @@ impl ValidatorSet for DynamicValidator {
 
     fn check_enough_votes(&self, parent: &BlockHash, votes: &BitSet) -> Result<(), EngineError> {
         let validators = self.next_validators(*parent);
-        let mut voted_weight = 0u64;
-        let n_validators = validators.len();
-        for index in votes.true_index_iter() {
-            assert!(index < n_validators);
-            let validator = validators.get(index).ok_or({
-                EngineError::ValidatorNotExist {
-                    height: 0, // FIXME
-                    index,
-                }
-            })?;
-            voted_weight += validator.weight();
-        }
-        let total_weight: u64 = validators.iter().map(SimpleValidator::weight).sum();
-        if voted_weight * 3 > total_weight * 2 {
-            Ok(())
-        } else {
-            let threshold = total_weight as usize * 2 / 3;
-            Err(EngineError::BadSealFieldSize(OutOfBounds {
-                min: Some(threshold),
-                max: Some(total_weight as usize),
-                found: voted_weight as usize,
-            }))
-        }
+        check_enough_weighted_votes(&validators, votes)
     }
 
     /// Allows blockchain state access.
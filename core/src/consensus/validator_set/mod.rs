@@ -18,12 +18,15 @@ use super::BitSet;
 use crate::client::ConsensusClient;
 use crate::consensus::EngineError;
 use ckey::Ed25519Public as Public;
+use ctypes::util::unexpected::OutOfBounds;
 use ctypes::BlockHash;
 use std::sync::Weak;
 
 mod dynamic_validator;
+mod proposer_priority;
 
 pub use self::dynamic_validator::DynamicValidator;
+pub use self::proposer_priority::select_proposer;
 
 /// A validator set.
 pub trait ValidatorSet: Send + Sync {
@@ -37,7 +40,7 @@ pub trait ValidatorSet: Send + Sync {
     /// Draws a validator from nonce modulo number of validators.
     fn get_index(&self, parent: &BlockHash, public: &Public) -> Option<usize>;
 
-    fn next_block_proposer(&self, parent: &BlockHash, view: u64) -> Public;
+    fn next_block_proposer(&self, parent: &BlockHash, view: u64) -> Option<Public>;
 
     /// Returns the current number of validators.
     fn count(&self, parent: &BlockHash) -> usize;
@@ -51,3 +54,40 @@ pub trait ValidatorSet: Send + Sync {
 
     fn next_validators(&self, _hash: &BlockHash) -> Vec<Public>;
 }
+
+/// Checks that the voted weight is more than two thirds of the total weight.
+pub fn check_two_thirds_majority(voted_weight: u64, total_weight: u64) -> Result<(), EngineError> {
+    // Multiply in u128 not to overflow when the delegations are large.
+    if u128::from(voted_weight) * 3 > u128::from(total_weight) * 2 {
+        Ok(())
+    } else {
+        Err(EngineError::BadSealFieldSize(OutOfBounds {
+            min: Some((u128::from(total_weight) * 2 / 3) as usize),
+            max: Some(total_weight as usize),
+            found: voted_weight as usize,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_thirds_majority_is_weighted() {
+        // One validator has 70% of the delegation.
+        assert!(check_two_thirds_majority(70, 100).is_ok());
+        // The other nine validators have 30% of the delegation.
+        assert!(check_two_thirds_majority(30, 100).is_err());
+        // Exactly two thirds is not enough.
+        assert!(check_two_thirds_majority(200, 300).is_err());
+        assert!(check_two_thirds_majority(201, 300).is_ok());
+    }
+
+    #[test]
+    fn two_thirds_majority_of_large_delegations() {
+        let total = u64::MAX;
+        assert!(check_two_thirds_majority(total / 3 * 2 + 2, total).is_ok());
+        assert!(check_two_thirds_majority(total / 3 * 2, total).is_err());
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Stake-weighted proposer rotation, which follows the proposer selection of Tendermint.
+//!
+//! Every round, the priority of each validator increases by its voting power, and the validator with the
+//! highest priority becomes the proposer and its priority decreases by the total voting power.
+//! So a validator proposes in proportion to its voting power.
+
+use ckey::Ed25519Public as Public;
+use cstate::ProposerPriority;
+
+/// The priorities are rescaled so that the difference between the highest and the lowest priorities is at most
+/// this factor times the total voting power.
+const PRIORITY_WINDOW_SIZE_FACTOR: i128 = 2;
+
+/// Selects the proposer after `rounds` rounds from `previous`, and returns it with the updated priorities.
+///
+/// `validators` are the pairs of a public key and its voting power, sorted by the public key.
+/// A validator which is not in `previous` starts with a low priority so that it doesn't propose right after it
+/// joins. If `previous` is empty, every validator starts with zero priority.
+pub fn select_proposer(
+    validators: &[(Public, u64)],
+    previous: &[ProposerPriority],
+    rounds: u64,
+) -> (Public, Vec<ProposerPriority>) {
+    assert!(!validators.is_empty(), "There must be at least one validator");
+    assert_ne!(0, rounds);
+
+    let mut powers: Vec<i128> = validators.iter().map(|(_, power)| i128::from(*power)).collect();
+    if powers.iter().all(|power| *power == 0) {
+        // Validators without any delegation take turns.
+        powers.iter_mut().for_each(|power| *power = 1);
+    }
+    let total_power: i128 = powers.iter().sum();
+
+    let mut priorities: Vec<i128> = validators
+        .iter()
+        .map(|(pubkey, _)| {
+            if previous.is_empty() {
+                return 0
+            }
+            match previous.iter().find(|entry| entry.pubkey == *pubkey) {
+                Some(entry) => i128::from(entry.priority),
+                None => -(total_power + total_power / 8),
+            }
+        })
+        .collect();
+
+    rescale(&mut priorities, PRIORITY_WINDOW_SIZE_FACTOR * total_power);
+    let average = priorities.iter().sum::<i128>().div_euclid(priorities.len() as i128);
+    priorities.iter_mut().for_each(|priority| *priority -= average);
+
+    let mut proposer = 0;
+    for _ in 0..rounds {
+        for (priority, power) in priorities.iter_mut().zip(&powers) {
+            *priority += power;
+        }
+        // The validator with the smaller public key wins a tie.
+        proposer = (0..priorities.len())
+            .filter(|index| powers[*index] != 0)
+            .max_by(|a, b| priorities[*a].cmp(&priorities[*b]).then(b.cmp(a)))
+            .expect("At least one validator has voting power");
+        priorities[proposer] -= total_power;
+    }
+
+    let priorities = validators
+        .iter()
+        .zip(priorities)
+        .map(|((pubkey, _), priority)| ProposerPriority {
+            pubkey: *pubkey,
+            priority: priority.max(i128::from(i64::MIN)).min(i128::from(i64::MAX)) as i64,
+        })
+        .collect();
+    (validators[proposer].0, priorities)
+}
+
+fn rescale(priorities: &mut [i128], window_size: i128) {
+    let max = priorities.iter().max().copied().unwrap_or_default();
+    let min = priorities.iter().min().copied().unwrap_or_default();
+    let diff = max - min;
+    if window_size == 0 || diff <= window_size {
+        return
+    }
+    let ratio = (diff + window_size - 1) / window_size;
+    priorities.iter_mut().for_each(|priority| *priority /= ratio);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validators(powers: &[u64]) -> Vec<(Public, u64)> {
+        let mut pubkeys: Vec<_> = powers.iter().map(|_| Public::random()).collect();
+        pubkeys.sort_unstable();
+        pubkeys.into_iter().zip(powers.iter().copied()).collect()
+    }
+
+    /// Selects the proposers of `heights` blocks, each of which is finalized at the first round.
+    fn proposal_counts(validators: &[(Public, u64)], heights: usize) -> Vec<usize> {
+        let mut counts = vec![0; validators.len()];
+        let mut priorities = Vec::new();
+        for _ in 0..heights {
+            let (proposer, next) = select_proposer(validators, &priorities, 1);
+            priorities = next;
+            counts[validators.iter().position(|(pubkey, _)| *pubkey == proposer).unwrap()] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn equal_powers_rotate_in_order() {
+        let validators = validators(&[10, 10, 10, 10]);
+        let mut priorities = Vec::new();
+        for height in 0..8 {
+            let (proposer, next) = select_proposer(&validators, &priorities, 1);
+            priorities = next;
+            assert_eq!(validators[height % 4].0, proposer);
+        }
+    }
+
+    #[test]
+    fn proposals_are_proportional_to_powers() {
+        let validators = validators(&[1, 2, 7]);
+        assert_eq!(vec![1, 2, 7], proposal_counts(&validators, 10));
+        assert_eq!(vec![10, 20, 70], proposal_counts(&validators, 100));
+    }
+
+    #[test]
+    fn dominant_validator_does_not_starve_the_others() {
+        let validators = validators(&[1, 1, 98]);
+        let counts = proposal_counts(&validators, 100);
+        assert_eq!(vec![1, 1, 98], counts);
+    }
+
+    #[test]
+    fn validator_without_power_never_proposes() {
+        let validators = validators(&[0, 3, 5]);
+        assert_eq!(vec![0, 3, 5], proposal_counts(&validators, 8));
+    }
+
+    #[test]
+    fn later_round_is_the_same_as_failed_rounds() {
+        let validators = validators(&[3, 1, 4, 1, 5]);
+        let (_, first) = select_proposer(&validators, &[], 1);
+        let (_, second) = select_proposer(&validators, &first, 1);
+        let (proposer_of_third_round, _) = select_proposer(&validators, &second, 1);
+        assert_eq!(proposer_of_third_round, select_proposer(&validators, &[], 3).0);
+    }
+
+    #[test]
+    fn new_validator_waits_for_its_turn() {
+        let old = validators(&[10, 10, 10]);
+        let (_, priorities) = select_proposer(&old, &[], 1);
+
+        let newcomer = (Public::random(), 10);
+        let mut validators = old.clone();
+        validators.push(newcomer);
+        validators.sort_unstable();
+        let (proposer, _) = select_proposer(&validators, &priorities, 1);
+        assert_ne!(newcomer.0, proposer);
+    }
+}
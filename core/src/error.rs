@@ -16,6 +16,7 @@
 
 use crate::account_provider::Error as AccountProviderError;
 use crate::consensus::EngineError;
+use crate::consensus::SignerError;
 use cdb::DatabaseError;
 use cio::IoError;
 use ckey::{Ed25519Public as Public, Error as KeyError};
@@ -85,6 +86,8 @@ pub enum BlockError {
     InvalidStateRoot(Mismatch<H256>),
     /// Transactions root header field is invalid.
     InvalidTransactionsRoot(Mismatch<H256>),
+    /// Evidences root header field is invalid.
+    InvalidEvidencesRoot(Mismatch<H256>),
     /// Next validator set hash header field is invalid.
     InvalidNextValidatorSetHash(Mismatch<H256>),
     /// Some low-level aspect of the seal is incorrect.
@@ -113,6 +116,8 @@ pub enum BlockError {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SchemeError {
     InvalidState,
+    /// The app descriptor is different from the one the database is created with.
+    AppDescMismatch(Mismatch<H256>),
 }
 
 impl fmt::Display for SchemeError {
@@ -120,6 +125,7 @@ impl fmt::Display for SchemeError {
         use self::SchemeError::*;
         let msg: String = match self {
             InvalidState => "Genesis state is not same with spec".into(),
+            AppDescMismatch(mis) => format!("The app descriptor is different from the one of the database: {}", mis),
         };
         f.write_fmt(format_args!("Scheme file error ({})", msg))
     }
@@ -134,6 +140,7 @@ impl fmt::Display for BlockError {
             InvalidSealArity(mis) => format!("Block seal in incorrect format: {}", mis),
             InvalidStateRoot(mis) => format!("Invalid state root in header: {}", mis),
             InvalidTransactionsRoot(mis) => format!("Invalid transactions root in header: {}", mis),
+            InvalidEvidencesRoot(mis) => format!("Invalid evidences root in header: {}", mis),
             InvalidNextValidatorSetHash(mis) => format!("Invalid next validator set hash in header: {}", mis),
             InvalidSeal => "Block has invalid seal.".into(),
             InvalidTimestamp(oob) => format!("Invalid timestamp in header: {}", oob),
@@ -167,6 +174,8 @@ pub enum Error {
     Scheme(SchemeError),
     /// Account Provider error.
     AccountProvider(AccountProviderError),
+    /// Consensus signer error.
+    Signer(SignerError),
     Trie(TrieError),
     Runtime(RuntimeError),
     History(HistoryError),
@@ -187,6 +196,7 @@ impl fmt::Display for Error {
             Error::Key(err) => err.fmt(f),
             Error::Scheme(err) => err.fmt(f),
             Error::AccountProvider(err) => err.fmt(f),
+            Error::Signer(err) => err.fmt(f),
             Error::Trie(err) => err.fmt(f),
             Error::Runtime(err) => err.fmt(f),
             Error::History(err) => err.fmt(f),
@@ -256,6 +266,12 @@ impl From<AccountProviderError> for Error {
     }
 }
 
+impl From<SignerError> for Error {
+    fn from(err: SignerError) -> Error {
+        Error::Signer(err)
+    }
+}
+
 impl From<TrieError> for Error {
     fn from(err: TrieError) -> Self {
         Error::Trie(err)
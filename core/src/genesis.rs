@@ -26,20 +26,27 @@ use rlp::RlpStream;
 pub struct Genesis {
     /// The genesis block's extra data field.
     extra_data: Bytes,
+    /// The hash of the app descriptor. It is kept out of the genesis state, so it doesn't change the genesis hash.
+    app_desc_hash: Option<H256>,
     state_root: H256,
 }
 
 impl Genesis {
     // get parameters
-    pub fn new(s: coordinator::app_desc::Genesis, coordinator: &impl Initializer) -> Self {
+    pub fn new(s: coordinator::app_desc::Genesis, app_desc_hash: Option<H256>, coordinator: &impl Initializer) -> Self {
         let db = StateDB::new_with_memorydb();
         let (_, state_root) = Self::initialize_state(db, coordinator).expect("DB error while creating genesis block");
         Genesis {
             extra_data: s.extra_data.map_or_else(Vec::new, Into::into),
+            app_desc_hash,
             state_root,
         }
     }
 
+    pub fn app_desc_hash(&self) -> Option<H256> {
+        self.app_desc_hash
+    }
+
     pub fn initialize_state(db: StateDB, coordinator: &impl Initializer) -> Result<(StateDB, H256), Error> {
         let root = BLAKE_NULL_RLP;
         let mut state = TopLevelState::from_existing(db, root)?;
@@ -91,3 +98,19 @@ impl Genesis {
         ret.out()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coordinator::test_coordinator::TestCoordinator;
+
+    #[test]
+    fn app_desc_hash_does_not_change_the_genesis_hash() {
+        let coordinator = TestCoordinator::default();
+        let without_hash = Genesis::new(Default::default(), None, &coordinator);
+        let with_hash = Genesis::new(Default::default(), Some(H256::random()), &coordinator);
+
+        assert_eq!(without_hash.state_root, with_hash.state_root);
+        assert_eq!(without_hash.header().hash(), with_hash.header().hash());
+    }
+}
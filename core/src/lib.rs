@@ -54,9 +54,12 @@ pub use crate::client::{
     BlockChainClient, BlockChainTrait, ChainNotify, Client, ClientConfig, DatabaseClient, EngineClient, EngineInfo,
     ImportBlock, MiningBlockChainClient, SnapshotClient, StateInfo, TestBlockChainClient,
 };
-pub use crate::consensus::signer::EngineSigner;
 pub use crate::consensus::tendermint::Evidence;
-pub use crate::consensus::{ConsensusEngine, EngineType, NullEngine, Solo, Tendermint, TimeGapParams};
+pub use crate::consensus::{
+    verify_precommits, BitSet, ConsensusEngine, ConsensusSigner, ConsensusState, EngineSigner, EngineType, LocalSigner,
+    NullEngine, PeerConsensusState, RemoteSigner, Seal, SealFormat, SignState, SignerAddress, SignerError, SignerKey,
+    SignerServer, Solo, Step, StepVotes, Tendermint, TendermintSealView, TimeGapParams, VoteOn, VoteStep,
+};
 pub use crate::db::{COL_STATE, NUM_COLUMNS};
 pub use crate::error::{BlockImportError, Error, ImportError};
 pub use crate::miner::{Miner, MinerOptions, MinerService};
@@ -18,13 +18,15 @@ use super::backup;
 use super::mem_pool_types::TransactionPool;
 use crate::transaction::PendingTransactions;
 use crate::Error as CoreError;
+use ckey::Ed25519Public as Public;
 use coordinator::context::StorageAccess;
 use coordinator::engine::TxFilter;
-use coordinator::types::{ErrorCode, FilteredTxs};
+use coordinator::types::{ErrorCode, FilteredTxs, TxSeq};
 use coordinator::{Transaction, TransactionWithMetadata, TxOrigin};
 use ctypes::errors::{HistoryError, SyntaxError};
 use ctypes::{BlockNumber, TxHash};
 use kvdb::{DBTransaction, KeyValueDB};
+use std::collections::{HashMap, HashSet};
 use std::ops::Range;
 use std::sync::Arc;
 
@@ -68,6 +70,8 @@ pub struct MemPool {
     queue_count_limit: usize,
     /// The memory limit of each queue
     queue_memory_limit: usize,
+    /// How long a transaction waiting for the missing sequences is held, in seconds
+    future_lifetime: u64,
     /// Next id that should be assigned to a transaction imported to the pool
     next_transaction_id: u64,
     /// Arc of KeyValueDB in which the backup information is stored.
@@ -79,6 +83,7 @@ impl MemPool {
     pub fn with_limits(
         limit: usize,
         memory_limit: usize,
+        future_lifetime: u64,
         db: Arc<dyn KeyValueDB>,
         tx_filter: Arc<dyn TxFilter>,
     ) -> Self {
@@ -87,6 +92,7 @@ impl MemPool {
             transaction_pool: TransactionPool::new(),
             queue_count_limit: limit,
             queue_memory_limit: memory_limit,
+            future_lifetime,
             next_transaction_id: 0,
             db,
         }
@@ -122,6 +128,58 @@ impl MemPool {
         }
     }
 
+    /// Returns the sequences of the next transactions of the accounts in the state.
+    /// It is empty if the app doesn't tell them.
+    fn next_seqs(&self, state: &mut dyn StorageAccess, accounts: Vec<Public>) -> HashMap<Public, TxSeq> {
+        if accounts.is_empty() {
+            return HashMap::new()
+        }
+        match self.tx_filter.next_seqs(state, &accounts) {
+            Some(seqs) => accounts.into_iter().zip(seqs).collect(),
+            None => HashMap::new(),
+        }
+    }
+
+    /// Removes the pending transactions of the same account and sequence if the new one has a higher priority.
+    ///
+    /// All of them are kept if the app doesn't tell their priorities.
+    fn replace(
+        &mut self,
+        tx: &Transaction,
+        sender: Option<(Public, TxSeq)>,
+        batch: &mut DBTransaction,
+    ) -> Result<(), Error> {
+        let (account, seq) = match sender {
+            Some(sender) => sender,
+            None => return Ok(()),
+        };
+        let pending = self.transaction_pool.queued(&account, seq);
+        if pending.is_empty() {
+            return Ok(())
+        }
+        let priority = match self.tx_filter.priority(tx) {
+            Some(priority) => priority,
+            None => return Ok(()),
+        };
+        let mut to_replace = Vec::with_capacity(pending.len());
+        for pending in pending {
+            match self.tx_filter.priority(&pending.tx) {
+                Some(pending_priority) if priority <= pending_priority => {
+                    return Err(HistoryError::TooCheapToReplace.into())
+                }
+                Some(_) => to_replace.push(pending.hash()),
+                None => return Ok(()),
+            }
+        }
+
+        for hash in to_replace {
+            ctrace!(MEM_POOL, "{:?} is replaced by {:?}", hash, tx.hash());
+            backup::remove_item(batch, &hash);
+            self.transaction_pool.remove(&hash);
+        }
+        Ok(())
+    }
+
     /// Returns current limit of transactions in the pool.
     pub fn limit(&self) -> usize {
         self.queue_count_limit
@@ -132,6 +190,11 @@ impl MemPool {
         self.transaction_pool.len()
     }
 
+    /// Returns the number of transactions held until the transactions of the missing sequences arrive
+    pub fn num_future_transactions(&self) -> usize {
+        self.transaction_pool.future.len()
+    }
+
     /// Add signed transaction to pool to be verified and imported.
     ///
     /// NOTE details_provider methods should be cheap to compute
@@ -148,22 +211,37 @@ impl MemPool {
         let mut insert_results = Vec::with_capacity(transactions.len());
         let mut batch = backup::backup_batch_with_capacity(transactions.len());
 
-        for tx in transactions {
+        let senders: Vec<_> = transactions.iter().map(|tx| self.tx_filter.account_and_seq(tx)).collect();
+        let accounts: HashSet<_> = senders.iter().flatten().map(|(account, _)| *account).collect();
+        let next_seqs = self.next_seqs(state, accounts.into_iter().collect());
+
+        for (tx, sender) in transactions.into_iter().zip(senders) {
             match self.tx_filter.check_transaction(&tx) {
                 Ok(()) => {
                     let id = self.next_transaction_id;
                     self.next_transaction_id += 1;
 
                     let hash = tx.hash();
-                    let tx = TransactionWithMetadata::new(tx, origin, inserted_block_number, inserted_timestamp, id);
                     if self.transaction_pool.contains(&hash) {
                         // This transaction is already in the pool.
                         insert_results.push(Err(HistoryError::TransactionAlreadyImported.into()));
-                    } else {
-                        backup::backup_item(&mut batch, *tx.hash(), &tx);
-                        self.transaction_pool.insert(tx);
-                        insert_results.push(Ok(hash));
+                        continue
                     }
+                    if let Some((account, seq)) = sender {
+                        if next_seqs.get(&account).map_or(false, |next_seq| seq < *next_seq) {
+                            insert_results.push(Err(HistoryError::Old.into()));
+                            continue
+                        }
+                    }
+                    if let Err(err) = self.replace(&tx, sender, &mut batch) {
+                        insert_results.push(Err(err));
+                        continue
+                    }
+
+                    let tx = TransactionWithMetadata::new(tx, origin, inserted_block_number, inserted_timestamp, id);
+                    backup::backup_item(&mut batch, *tx.hash(), &tx);
+                    self.transaction_pool.insert(tx, sender);
+                    insert_results.push(Ok(hash));
                 }
                 Err(err_code) => {
                     // This transaction is invalid.
@@ -171,6 +249,9 @@ impl MemPool {
                 }
             }
         }
+        for (account, next_seq) in next_seqs {
+            self.transaction_pool.hold_future(&account, next_seq);
+        }
         self.enforce_limit(state, &mut batch);
 
         self.db.write(batch).expect("Low level database error. Some issue with disk?");
@@ -193,23 +274,33 @@ impl MemPool {
     }
 
     // Recover MemPool state from db stored data
-    pub fn recover_from_db(&mut self) {
-        let by_hash = backup::recover_to_data(self.db.as_ref());
+    pub fn recover_from_db(&mut self, state: &mut dyn StorageAccess) {
+        let mut items: Vec<_> = backup::recover_to_data(self.db.as_ref()).into_iter().map(|(_, item)| item).collect();
+        // The transactions of the same account and sequence are queued in the order they were inserted.
+        items.sort_unstable_by_key(|item| item.insertion_id);
 
         let mut max_insertion_id = 0u64;
-        for (_hash, item) in by_hash {
+        for item in items {
             if item.insertion_id > max_insertion_id {
                 max_insertion_id = item.insertion_id;
             }
 
-            self.transaction_pool.insert(item);
+            let sender = self.tx_filter.account_and_seq(&item.tx);
+            self.transaction_pool.insert(item, sender);
+        }
+
+        let accounts = self.transaction_pool.accounts().copied().collect();
+        for (account, next_seq) in self.next_seqs(state, accounts) {
+            self.transaction_pool.hold_future(&account, next_seq);
         }
 
         self.next_transaction_id = max_insertion_id + 1;
     }
 
+    /// Returns all the transactions in the pool except the ones waiting for the missing sequences.
     pub fn all_pending_transactions_with_metadata(&self) -> impl Iterator<Item = &TransactionWithMetadata> {
-        self.transaction_pool.pool.values()
+        let transaction_pool = &self.transaction_pool;
+        transaction_pool.pool.iter().filter(move |(hash, _)| !transaction_pool.is_future(hash)).map(|(_, item)| item)
     }
 
     /// Removes invalid transaction identified by hash from pool.
@@ -236,6 +327,32 @@ impl MemPool {
     ) {
         ctrace!(MEM_POOL, "remove_old() called, time: {}, timestamp: {}", current_block_number, current_timestamp);
         let mut batch = backup::backup_batch_with_capacity(0);
+
+        let accounts = self.transaction_pool.accounts().copied().collect();
+        for (account, next_seq) in self.next_seqs(state, accounts) {
+            for hash in self.transaction_pool.stale(&account, next_seq) {
+                backup::remove_item(&mut batch, &hash);
+                self.transaction_pool.remove(&hash);
+            }
+            self.transaction_pool.hold_future(&account, next_seq);
+        }
+
+        let expired: Vec<TxHash> = self
+            .transaction_pool
+            .future
+            .iter()
+            .filter(|hash| {
+                let item = &self.transaction_pool.pool[*hash];
+                item.inserted_timestamp.saturating_add(self.future_lifetime) < current_timestamp
+            })
+            .copied()
+            .collect();
+        for hash in expired {
+            ctrace!(MEM_POOL, "{:?} waited too long for the missing sequences", hash);
+            backup::remove_item(&mut batch, &hash);
+            self.transaction_pool.remove(&hash);
+        }
+
         let to_be_removed: Vec<TxHash> = {
             let transactions: Vec<_> = self.transaction_pool.pool.values().collect();
             let FilteredTxs {
@@ -259,9 +376,7 @@ impl MemPool {
     pub fn pending_transactions(&self, size_limit: usize, range: Range<u64>) -> PendingTransactions {
         let mut current_size: usize = 0;
         let items: Vec<_> = self
-            .transaction_pool
-            .pool
-            .values()
+            .all_pending_transactions_with_metadata()
             .filter(|item| range.contains(&item.inserted_timestamp))
             .take_while(|item| {
                 let encoded_byte_array = rlp::encode(&item.tx);
@@ -281,16 +396,21 @@ impl MemPool {
 
     /// Return all transactions whose timestamp are in the given range in the memory pool.
     pub fn count_pending_transactions(&self, range: Range<u64>) -> usize {
-        self.transaction_pool.pool.values().filter(|t| range.contains(&t.inserted_timestamp)).count()
+        self.all_pending_transactions_with_metadata().filter(|t| range.contains(&t.inserted_timestamp)).count()
     }
 }
 
 #[cfg(test)]
 pub mod test {
-    use crate::miner::mem_pool::MemPool;
+    use crate::miner::mem_pool::{Error, MemPool};
+    use ckey::Ed25519Public as Public;
     use coordinator::context::{StorageAccess, SubStorageAccess};
+    use coordinator::engine::TxFilter;
     use coordinator::test_coordinator::TestCoordinator;
-    use coordinator::{Transaction, TxOrigin};
+    use coordinator::types::{ErrorCode, FilteredTxs, TxPriority, TxSeq};
+    use coordinator::{Transaction, TransactionWithMetadata, TxOrigin};
+    use ctypes::errors::HistoryError;
+    use parking_lot::RwLock;
     use rand::Rng;
     use std::sync::Arc;
 
@@ -305,7 +425,7 @@ pub mod test {
     fn remove_all() {
         let validator = Arc::new(TestCoordinator::default());
         let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
-        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), db, validator);
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 600, db, validator);
 
         let inserted_block_number = 1;
         let inserted_timestamp = 100;
@@ -329,7 +449,7 @@ pub mod test {
     fn add_and_remove_transactions() {
         let validator = Arc::new(TestCoordinator::default());
         let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
-        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), db, validator);
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 600, db, validator);
 
         let inserted_block_number = 1;
         let inserted_timestamp = 100;
@@ -362,7 +482,7 @@ pub mod test {
     fn db_backup_and_recover() {
         let validator = Arc::new(TestCoordinator::default());
         let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
-        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), db.clone(), validator.clone());
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 600, db.clone(), validator.clone());
 
         let inserted_block_number = 1;
         let inserted_timestamp = 100;
@@ -383,8 +503,8 @@ pub mod test {
         let add_result = mem_pool.add(transactions, origin, &mut state, inserted_block_number, inserted_timestamp);
         assert!(add_result.iter().all(|r| r.is_ok()));
 
-        let mut mem_pool_recovered = MemPool::with_limits(8192, usize::max_value(), db, validator);
-        mem_pool_recovered.recover_from_db();
+        let mut mem_pool_recovered = MemPool::with_limits(8192, usize::max_value(), 600, db, validator);
+        mem_pool_recovered.recover_from_db(&mut state);
 
         assert_eq!(mem_pool_recovered.transaction_pool, mem_pool.transaction_pool);
         assert_eq!(mem_pool_recovered.queue_count_limit, mem_pool.queue_count_limit);
@@ -392,6 +512,231 @@ pub mod test {
         assert_eq!(mem_pool_recovered.next_transaction_id, mem_pool.next_transaction_id);
     }
 
+    /// Reads the account index, the sequence and the priority from the first three bytes of the body.
+    struct SeqFilter {
+        accounts: Vec<Public>,
+        next_seqs: RwLock<Vec<TxSeq>>,
+        /// Whether the app tells the priorities of the transactions
+        prioritized: bool,
+    }
+
+    impl SeqFilter {
+        fn new(next_seqs: Vec<TxSeq>) -> Self {
+            Self {
+                accounts: next_seqs.iter().map(|_| Public::random()).collect(),
+                next_seqs: RwLock::new(next_seqs),
+                prioritized: true,
+            }
+        }
+    }
+
+    impl TxFilter for SeqFilter {
+        fn check_transaction(&self, _transaction: &Transaction) -> Result<(), ErrorCode> {
+            Ok(())
+        }
+
+        fn filter_transactions<'a>(
+            &self,
+            storage: &mut dyn StorageAccess,
+            transactions: &mut dyn Iterator<Item = &'a TransactionWithMetadata>,
+            memory_limit: Option<usize>,
+            size_limit: Option<usize>,
+        ) -> FilteredTxs<'a> {
+            TestCoordinator::default().filter_transactions(storage, transactions, memory_limit, size_limit)
+        }
+
+        fn account_and_seq(&self, transaction: &Transaction) -> Option<(Public, TxSeq)> {
+            let body = transaction.body();
+            Some((self.accounts[body[0] as usize], body[1] as TxSeq))
+        }
+
+        fn priority(&self, transaction: &Transaction) -> Option<TxPriority> {
+            if self.prioritized {
+                Some(transaction.body()[2] as TxPriority)
+            } else {
+                None
+            }
+        }
+
+        fn next_seqs(&self, _storage: &mut dyn StorageAccess, accounts: &[Public]) -> Option<Vec<TxSeq>> {
+            let next_seqs = self.next_seqs.read();
+            Some(
+                accounts
+                    .iter()
+                    .map(|account| next_seqs[self.accounts.iter().position(|a| a == account).unwrap()])
+                    .collect(),
+            )
+        }
+    }
+
+    fn create_seq_transaction(account: u8, seq: u8, priority: u8) -> Transaction {
+        let mut body = vec![account, seq, priority];
+        body.extend((0..32).map(|_| rand::random::<u8>()));
+        Transaction::new("Sample".to_string(), body)
+    }
+
+    #[test]
+    fn replace_transaction_of_higher_priority() {
+        let filter = Arc::new(SeqFilter::new(vec![0]));
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 600, db, filter);
+        let mut state = DummyStorage;
+
+        let pending = create_seq_transaction(0, 0, 1);
+        let cheap = create_seq_transaction(0, 0, 1);
+        let expensive = create_seq_transaction(0, 0, 2);
+
+        assert_eq!(vec![Ok(())], mem_pool.add(vec![pending.clone()], TxOrigin::External, &mut state, 1, 100));
+        assert_eq!(
+            vec![Err(Error::History(HistoryError::TooCheapToReplace))],
+            mem_pool.add(vec![cheap.clone()], TxOrigin::External, &mut state, 1, 100)
+        );
+        assert_eq!(vec![Ok(())], mem_pool.add(vec![expensive.clone()], TxOrigin::External, &mut state, 1, 100));
+
+        assert!(!mem_pool.transaction_pool.contains(&pending.hash()));
+        assert!(!mem_pool.transaction_pool.contains(&cheap.hash()));
+        assert!(mem_pool.transaction_pool.contains(&expensive.hash()));
+        assert_eq!(mem_pool.transaction_pool.count, 1);
+        assert_eq!(mem_pool.transaction_pool.mem_usage, expensive.size());
+    }
+
+    #[test]
+    fn keep_both_transactions_without_priorities() {
+        let filter = Arc::new(SeqFilter {
+            prioritized: false,
+            ..SeqFilter::new(vec![0])
+        });
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 600, db, filter.clone());
+        let mut state = DummyStorage;
+
+        let pending = create_seq_transaction(0, 0, 1);
+        let another = create_seq_transaction(0, 0, 2);
+        assert_eq!(vec![Ok(())], mem_pool.add(vec![pending.clone()], TxOrigin::External, &mut state, 1, 100));
+        assert_eq!(vec![Ok(())], mem_pool.add(vec![another.clone()], TxOrigin::External, &mut state, 1, 100));
+
+        assert!(mem_pool.transaction_pool.contains(&pending.hash()));
+        assert!(mem_pool.transaction_pool.contains(&another.hash()));
+        let queued: Vec<_> =
+            mem_pool.transaction_pool.queued(&filter.accounts[0], 0).iter().map(|tx| tx.hash()).collect();
+        assert_eq!(vec![pending.hash(), another.hash()], queued);
+
+        // The other one stays queued when one of them is removed.
+        mem_pool.remove(&[pending.hash()], 1, 100);
+        let queued: Vec<_> =
+            mem_pool.transaction_pool.queued(&filter.accounts[0], 0).iter().map(|tx| tx.hash()).collect();
+        assert_eq!(vec![another.hash()], queued);
+    }
+
+    #[test]
+    fn hold_and_prune_future_transactions_of_the_same_seq_without_priorities() {
+        let filter = Arc::new(SeqFilter {
+            prioritized: false,
+            ..SeqFilter::new(vec![0])
+        });
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 600, db.clone(), filter.clone());
+        let mut state = DummyStorage;
+
+        let futures = vec![create_seq_transaction(0, 1, 0), create_seq_transaction(0, 1, 0)];
+        let add_result = mem_pool.add(futures.clone(), TxOrigin::External, &mut state, 1, 100);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+        assert_eq!(mem_pool.num_future_transactions(), 2);
+        assert_eq!(mem_pool.count_pending_transactions(0..u64::max_value()), 0);
+
+        let mut mem_pool_recovered = MemPool::with_limits(8192, usize::max_value(), 600, db, filter.clone());
+        mem_pool_recovered.recover_from_db(&mut state);
+        assert_eq!(mem_pool_recovered.transaction_pool, mem_pool.transaction_pool);
+
+        let missing = create_seq_transaction(0, 0, 0);
+        assert_eq!(vec![Ok(())], mem_pool.add(vec![missing], TxOrigin::External, &mut state, 1, 100));
+        assert_eq!(mem_pool.num_future_transactions(), 0);
+        assert_eq!(mem_pool.count_pending_transactions(0..u64::max_value()), 3);
+
+        // One of them is included in a block.
+        *filter.next_seqs.write() = vec![2];
+        mem_pool.remove_old(&mut state, 2, 100);
+        assert!(futures.iter().all(|tx| !mem_pool.transaction_pool.contains(&tx.hash())));
+        assert_eq!(mem_pool.num_pending_transactions(), 0);
+    }
+
+    #[test]
+    fn hold_transactions_until_missing_seq_arrives() {
+        let filter = Arc::new(SeqFilter::new(vec![0, 0]));
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 600, db, filter);
+        let mut state = DummyStorage;
+
+        let futures = vec![create_seq_transaction(0, 1, 0), create_seq_transaction(0, 2, 0)];
+        let other = create_seq_transaction(1, 0, 0);
+        let add_result = mem_pool.add(futures, TxOrigin::External, &mut state, 1, 100);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+        assert_eq!(vec![Ok(())], mem_pool.add(vec![other.clone()], TxOrigin::External, &mut state, 1, 100));
+
+        assert_eq!(mem_pool.num_pending_transactions(), 3);
+        assert_eq!(mem_pool.num_future_transactions(), 2);
+        assert_eq!(mem_pool.pending_transactions(usize::max_value(), 0..u64::max_value()).transactions, vec![other]);
+
+        let missing = create_seq_transaction(0, 0, 0);
+        assert_eq!(vec![Ok(())], mem_pool.add(vec![missing], TxOrigin::External, &mut state, 1, 100));
+
+        assert_eq!(mem_pool.num_future_transactions(), 0);
+        assert_eq!(mem_pool.count_pending_transactions(0..u64::max_value()), 4);
+    }
+
+    #[test]
+    fn reject_old_seq_and_expire_future_transactions() {
+        let filter = Arc::new(SeqFilter::new(vec![1]));
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 600, db, filter.clone());
+        let mut state = DummyStorage;
+
+        let old = create_seq_transaction(0, 0, 0);
+        assert_eq!(
+            vec![Err(Error::History(HistoryError::Old))],
+            mem_pool.add(vec![old], TxOrigin::External, &mut state, 1, 100)
+        );
+
+        let ready = create_seq_transaction(0, 1, 0);
+        let future = create_seq_transaction(0, 3, 0);
+        let add_result = mem_pool.add(vec![ready.clone(), future.clone()], TxOrigin::External, &mut state, 1, 100);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+        assert_eq!(mem_pool.num_future_transactions(), 1);
+
+        // The ready one is included in a block.
+        *filter.next_seqs.write() = vec![2];
+        mem_pool.remove_old(&mut state, 2, 700);
+        assert!(!mem_pool.transaction_pool.contains(&ready.hash()));
+        assert!(mem_pool.transaction_pool.contains(&future.hash()));
+
+        mem_pool.remove_old(&mut state, 3, 701);
+        assert!(!mem_pool.transaction_pool.contains(&future.hash()));
+        assert_eq!(mem_pool.num_pending_transactions(), 0);
+    }
+
+    #[test]
+    fn hold_future_transactions_after_recovery() {
+        let filter = Arc::new(SeqFilter::new(vec![0]));
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 600, db.clone(), filter.clone());
+        let mut state = DummyStorage;
+
+        let ready = create_seq_transaction(0, 0, 0);
+        let future = create_seq_transaction(0, 2, 0);
+        let add_result = mem_pool.add(vec![ready.clone(), future], TxOrigin::External, &mut state, 1, 100);
+        assert!(add_result.iter().all(|r| r.is_ok()));
+        assert_eq!(mem_pool.num_future_transactions(), 1);
+
+        let mut mem_pool_recovered = MemPool::with_limits(8192, usize::max_value(), 600, db, filter);
+        mem_pool_recovered.recover_from_db(&mut state);
+
+        assert_eq!(mem_pool_recovered.num_future_transactions(), 1);
+        assert_eq!(
+            mem_pool_recovered.pending_transactions(usize::max_value(), 0..u64::max_value()).transactions,
+            vec![ready]
+        );
+    }
+
     struct DummyStorage;
 
     impl StorageAccess for DummyStorage {
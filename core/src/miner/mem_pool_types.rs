@@ -14,13 +14,22 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use ckey::Ed25519Public as Public;
+use coordinator::types::TxSeq;
 use coordinator::TransactionWithMetadata;
 use ctypes::TxHash;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 #[derive(Debug, PartialEq)]
 pub struct TransactionPool {
     pub pool: HashMap<TxHash, TransactionWithMetadata>,
+    /// The transactions of each account ordered by their sequences.
+    /// There are several transactions of the same sequence only if the app doesn't tell their priorities.
+    pub queues: HashMap<Public, BTreeMap<TxSeq, Vec<TxHash>>>,
+    /// The accounts and the sequences of the transactions in the queues
+    pub senders: HashMap<TxHash, (Public, TxSeq)>,
+    /// The transactions held until the transactions of the missing sequences arrive
+    pub future: HashSet<TxHash>,
     /// Memory usage of the transactions in the queue
     pub mem_usage: usize,
     /// Count of the external transactions in the queue
@@ -31,6 +40,9 @@ impl TransactionPool {
     pub fn new() -> Self {
         Self {
             pool: Default::default(),
+            queues: Default::default(),
+            senders: Default::default(),
+            future: Default::default(),
             mem_usage: 0,
             count: 0,
         }
@@ -38,6 +50,9 @@ impl TransactionPool {
 
     pub fn clear(&mut self) {
         self.pool.clear();
+        self.queues.clear();
+        self.senders.clear();
+        self.future.clear();
         self.mem_usage = 0;
         self.count = 0;
     }
@@ -46,24 +61,85 @@ impl TransactionPool {
         self.pool.len()
     }
 
-    pub fn insert(&mut self, item: TransactionWithMetadata) {
+    /// Inserts the transaction into the queue of the account if `sender` is given.
+    pub fn insert(&mut self, item: TransactionWithMetadata, sender: Option<(Public, TxSeq)>) {
         if !item.origin.is_local() {
             self.mem_usage += item.size();
             self.count += 1;
         }
-        self.pool.insert(item.hash(), item);
+        let hash = item.hash();
+        if let Some((account, seq)) = sender {
+            self.queues.entry(account).or_default().entry(seq).or_default().push(hash);
+            self.senders.insert(hash, (account, seq));
+        }
+        self.pool.insert(hash, item);
     }
 
     pub fn contains(&self, hash: &TxHash) -> bool {
         self.pool.contains_key(hash)
     }
 
+    /// Returns the transactions of the account with the sequence.
+    pub fn queued(&self, account: &Public, seq: TxSeq) -> Vec<&TransactionWithMetadata> {
+        match self.queues.get(account).and_then(|queue| queue.get(&seq)) {
+            Some(hashes) => hashes.iter().map(|hash| &self.pool[hash]).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn accounts(&self) -> impl Iterator<Item = &Public> {
+        self.queues.keys()
+    }
+
+    pub fn is_future(&self, hash: &TxHash) -> bool {
+        self.future.contains(hash)
+    }
+
+    /// Returns the transactions of the account whose sequences are lower than `next_seq`.
+    pub fn stale(&self, account: &Public, next_seq: TxSeq) -> Vec<TxHash> {
+        match self.queues.get(account) {
+            Some(queue) => queue.range(..next_seq).flat_map(|(_, hashes)| hashes).copied().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Holds the transactions of the account which don't follow `next_seq` without a gap.
+    pub fn hold_future(&mut self, account: &Public, next_seq: TxSeq) {
+        let queue = match self.queues.get(account) {
+            Some(queue) => queue,
+            None => return,
+        };
+        let mut expected = next_seq;
+        for (&seq, hashes) in queue.range(next_seq..) {
+            if seq == expected {
+                expected += 1;
+                for hash in hashes {
+                    self.future.remove(hash);
+                }
+            } else {
+                self.future.extend(hashes);
+            }
+        }
+    }
+
     pub fn remove(&mut self, hash: &TxHash) -> bool {
         if let Some(item) = self.pool.remove(hash) {
             if !item.origin.is_local() {
                 self.mem_usage -= item.size();
                 self.count -= 1;
             }
+            if let Some((account, seq)) = self.senders.remove(hash) {
+                let queue = self.queues.get_mut(&account).expect("A sender has its queue");
+                let hashes = queue.get_mut(&seq).expect("A sender is in its queue");
+                hashes.retain(|queued| queued != hash);
+                if hashes.is_empty() {
+                    queue.remove(&seq);
+                }
+                if queue.is_empty() {
+                    self.queues.remove(&account);
+                }
+            }
+            self.future.remove(hash);
             true
         } else {
             false
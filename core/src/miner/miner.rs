@@ -19,13 +19,13 @@ use super::MinerService;
 use crate::account_provider::{AccountProvider, Error as AccountProviderError};
 use crate::block::{ClosedBlock, IsBlock};
 use crate::client::{BlockChainTrait, BlockProducer, EngineInfo, ImportBlock, MiningBlockChainClient};
-use crate::consensus::{ConsensusEngine, EngineType};
+use crate::consensus::{ConsensusEngine, ConsensusSigner, EngineType, LocalSigner};
 use crate::error::Error;
 use crate::transaction::PendingTransactions;
 use crate::types::TransactionId;
 use crate::StateInfo;
 use ckey::Ed25519Public as Public;
-use coordinator::engine::{BlockExecutor, TxFilter};
+use coordinator::engine::{BlockExecutor, HealthChecker, TxFilter};
 use coordinator::{Transaction, TxOrigin};
 use cstate::TopLevelState;
 use ctypes::errors::HistoryError;
@@ -52,6 +52,8 @@ pub struct MinerOptions {
     pub mem_pool_size: usize,
     /// Maximum memory usage of transactions in the queue (current / future).
     pub mem_pool_memory_limit: Option<usize>,
+    /// How long a transaction waiting for the transactions of the missing sequences stays in the mem pool.
+    pub mem_pool_future_lifetime: Duration,
 }
 
 impl Default for MinerOptions {
@@ -62,6 +64,7 @@ impl Default for MinerOptions {
             reseal_min_period: Duration::from_secs(2),
             mem_pool_size: 8192,
             mem_pool_memory_limit: Some(2 * 1024 * 1024),
+            mem_pool_future_lifetime: Duration::from_secs(600),
         }
     }
 }
@@ -82,6 +85,7 @@ pub struct Miner {
     sealing_enabled: AtomicBool,
 
     block_executor: Arc<dyn BlockExecutor>,
+    health_checker: Arc<dyn HealthChecker>,
 }
 
 struct Params {
@@ -128,7 +132,7 @@ impl NextAllowedReseal {
 }
 
 impl Miner {
-    pub fn new<C: 'static + BlockExecutor + TxFilter>(
+    pub fn new<C: 'static + BlockExecutor + TxFilter + HealthChecker>(
         options: MinerOptions,
         engine: Arc<dyn ConsensusEngine>,
         db: Arc<dyn KeyValueDB>,
@@ -137,7 +141,7 @@ impl Miner {
         Arc::new(Self::new_raw(options, engine, db, block_executor))
     }
 
-    pub fn with_engine_for_test<C: 'static + BlockExecutor + TxFilter>(
+    pub fn with_engine_for_test<C: 'static + BlockExecutor + TxFilter + HealthChecker>(
         engine: Arc<dyn ConsensusEngine>,
         db: Arc<dyn KeyValueDB>,
         coordinator: Arc<C>,
@@ -145,15 +149,20 @@ impl Miner {
         Self::new_raw(Default::default(), Arc::clone(&engine), db, coordinator)
     }
 
-    fn new_raw<C: 'static + BlockExecutor + TxFilter>(
+    fn new_raw<C: 'static + BlockExecutor + TxFilter + HealthChecker>(
         options: MinerOptions,
         engine: Arc<dyn ConsensusEngine>,
         db: Arc<dyn KeyValueDB>,
         coordinator: Arc<C>,
     ) -> Self {
         let mem_limit = options.mem_pool_memory_limit.unwrap_or_else(usize::max_value);
-        let mem_pool =
-            Arc::new(RwLock::new(MemPool::with_limits(options.mem_pool_size, mem_limit, db, coordinator.clone())));
+        let mem_pool = Arc::new(RwLock::new(MemPool::with_limits(
+            options.mem_pool_size,
+            mem_limit,
+            options.mem_pool_future_lifetime.as_secs(),
+            db,
+            coordinator.clone(),
+        )));
 
         Self {
             mem_pool,
@@ -162,12 +171,15 @@ impl Miner {
             engine: engine.clone(),
             options,
             sealing_enabled: AtomicBool::new(true),
-            block_executor: coordinator,
+            block_executor: coordinator.clone(),
+            health_checker: coordinator,
         }
     }
 
-    pub fn recover_from_db(&self) {
-        self.mem_pool.write().recover_from_db();
+    /// Recovers the mem pool, and holds the transactions which don't follow the next sequences in the best state.
+    pub fn recover_from_db<C: StateInfo>(&self, chain: &C) {
+        let mut state = chain.state_at(BlockId::Latest).expect("The latest state always exists");
+        self.mem_pool.write().recover_from_db(&mut state);
     }
 
     pub fn get_options(&self) -> &MinerOptions {
@@ -300,11 +312,21 @@ impl MinerService for Miner {
             ctrace!(MINER, "Set author to {:?}", pubkey);
             // Sign test message
             ap.get_unlocked_account(&pubkey)?.sign(&Default::default())?;
-            self.engine.set_signer(ap, pubkey);
+            self.engine.set_signer(Arc::new(LocalSigner::new(&ap, pubkey)?));
         }
         Ok(())
     }
 
+    fn set_author_signer(&self, signer: Arc<dyn ConsensusSigner>) {
+        let pubkey = signer.public();
+        self.params.apply(|params| params.author = pubkey);
+
+        if self.engine_type().need_signer_key() {
+            ctrace!(MINER, "Set author to {:?}", pubkey);
+            self.engine.set_signer(signer);
+        }
+    }
+
     fn get_author(&self) -> Public {
         self.params.get().author
     }
@@ -352,6 +374,11 @@ impl MinerService for Miner {
         C: BlockChainTrait + BlockProducer + EngineInfo + ImportBlock, {
         ctrace!(MINER, "update_sealing: preparing a block");
 
+        if !self.health_checker.crashed_modules().is_empty() {
+            cwarn!(MINER, "update_sealing: no block is produced while the modules are crashed");
+            return
+        }
+
         let block = match self.prepare_and_seal_block(parent_block, chain) {
             Ok(Some(block)) => {
                 if !allow_empty_block && block.block().transactions().is_empty() {
@@ -505,11 +532,11 @@ pub mod test {
     fn check_add_transactions_result_idx() {
         let test_coordinator = Arc::new(TestCoordinator::default());
         let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
-        let genesis = Genesis::new(Default::default(), test_coordinator.as_ref());
+        let genesis = Genesis::new(Default::default(), None, test_coordinator.as_ref());
         let engine = Tendermint::new_for_test();
         let miner = Arc::new(Miner::with_engine_for_test(engine, db.clone(), test_coordinator.clone()));
 
-        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), db.clone(), test_coordinator.clone());
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 600, db.clone(), test_coordinator.clone());
         let client = generate_test_client(db, Arc::clone(&miner), &genesis, test_coordinator).unwrap();
 
         let transaction1 = Transaction::new("sample".to_string(), vec![1, 2, 3, 4, 5]);
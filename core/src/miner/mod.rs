@@ -30,7 +30,7 @@ use std::sync::Arc;
 pub use self::miner::{AuthoringParams, Miner, MinerOptions};
 use crate::account_provider::{AccountProvider, Error as AccountProviderError};
 use crate::client::{BlockChainTrait, BlockProducer, EngineInfo, ImportBlock, MiningBlockChainClient};
-use crate::consensus::EngineType;
+use crate::consensus::{ConsensusSigner, EngineType};
 use crate::error::Error;
 use crate::{PendingTransactions, StateInfo};
 use coordinator::Transaction;
@@ -49,6 +49,10 @@ pub trait MinerService: Send + Sync {
     /// Set the author that we will seal blocks as.
     fn set_author(&self, ap: Arc<AccountProvider>, author: Public) -> Result<(), AccountProviderError>;
 
+    /// Set the signer that we will seal blocks with, which may be a remote signer.
+    /// The public key of the signer becomes the author.
+    fn set_author_signer(&self, signer: Arc<dyn ConsensusSigner>);
+
     ///Get the address of block author.
     fn get_author(&self) -> Public;
 
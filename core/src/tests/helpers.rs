@@ -36,7 +36,7 @@ pub fn get_good_dummy_block() -> Bytes {
 
 pub fn get_good_dummy_block_hash() -> (BlockHash, Bytes) {
     let mut block_header = Header::new();
-    let genesis = Genesis::new(Default::default(), &TestCoordinator::default());
+    let genesis = Genesis::new(Default::default(), None, &TestCoordinator::default());
     block_header.set_timestamp(40);
     block_header.set_number(1);
     block_header.set_parent_hash(genesis.header().hash());
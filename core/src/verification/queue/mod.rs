@@ -354,6 +354,7 @@ impl<K: Kind> VerificationQueue<K> {
                     Error::Block(BlockError::TemporarilyInvalid(_)) => {}
                     // Don't mark a block as bad when the block body is malformed.
                     Error::Block(BlockError::InvalidTransactionsRoot(_)) => {}
+                    Error::Block(BlockError::InvalidEvidencesRoot(_)) => {}
                     _ => {
                         self.verification.bad.lock().insert(h);
                     }
@@ -379,6 +380,20 @@ impl<K: Kind> VerificationQueue<K> {
         result
     }
 
+    /// Puts the drained items back to the front of the queue in the same order.
+    /// They are still being processed, so they are imported again later.
+    pub fn requeue(&self, items: Vec<K::Verified>) {
+        if items.is_empty() {
+            return
+        }
+        let mut verified = self.verification.verified.lock();
+        let requeued_size = items.iter().map(MemUsage::mem_usage).sum::<usize>();
+        for item in items.into_iter().rev() {
+            verified.push_front(item);
+        }
+        self.verification.sizes.verified.fetch_add(requeued_size, AtomicOrdering::SeqCst);
+    }
+
     /// Mark given item as processed.
     /// Returns true if the queue becomes empty.
     pub fn mark_as_good(&self, hashes: &[BlockHash]) -> bool {
@@ -494,9 +509,12 @@ mod tests {
     use cio::IoChannel;
 
     use super::kind::blocks::Unverified;
+    use super::kind::BlockLike;
     use super::{BlockQueue, Config};
     use crate::error::{Error, ImportError};
+    use crate::verification::PreverifiedBlock;
     use crate::{tests::helpers::get_good_dummy_block, ConsensusEngine, Solo};
+    use ctypes::Header;
     use std::sync::Arc;
 
     // create a test block queue.
@@ -545,4 +563,33 @@ mod tests {
             }
         }
     }
+
+    fn preverified_block(number: u64) -> PreverifiedBlock {
+        let mut header = Header::new();
+        header.set_number(number);
+        PreverifiedBlock {
+            header,
+            evidences: Vec::new(),
+            transactions: Vec::new(),
+            bytes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn requeued_blocks_are_drained_again_in_order() {
+        let queue = get_test_queue();
+        let blocks = vec![preverified_block(1), preverified_block(2)];
+        let hashes: Vec<_> = blocks.iter().map(BlockLike::hash).collect();
+
+        queue.requeue(blocks);
+        assert_eq!(2, queue.queue_info().verified_queue_size);
+
+        let drained = queue.drain(1);
+        assert_eq!(vec![hashes[0]], drained.iter().map(BlockLike::hash).collect::<Vec<_>>());
+        queue.requeue(drained);
+
+        let drained = queue.drain(10);
+        assert_eq!(hashes, drained.iter().map(BlockLike::hash).collect::<Vec<_>>());
+        assert_eq!(0, queue.queue_info().verified_queue_size);
+    }
 }
@@ -48,7 +48,11 @@ pub fn verify_block_basic(header: &Header, bytes: &[u8]) -> Result<(), Error> {
         body_rlp.iter().map(|rlp| Ok(rlp.as_raw().to_vec())).collect::<Result<Vec<Bytes>, Error>>()?;
     verify_transactions_root(raw_transactions, header.transactions_root())?;
 
-    // TODO: verify evidences root
+    let evidences_rlp = Rlp::new(bytes).at(1)?;
+    // Reject the evidences of unknown versions here, since the body is decoded without checks later.
+    let _: Vec<Evidence> = evidences_rlp.as_list()?;
+    let raw_evidences = evidences_rlp.iter().map(|rlp| rlp.as_raw().to_vec());
+    verify_evidences_root(raw_evidences, header.evidences_root())?;
     Ok(())
 }
 
@@ -139,6 +143,18 @@ fn verify_transactions_root(
     Ok(())
 }
 
+/// Verify block data against header: evidences root
+fn verify_evidences_root(raw_evidences: impl IntoIterator<Item = Bytes>, evidences_root: &H256) -> Result<(), Error> {
+    let expected = skewed_merkle_root(BLAKE_NULL_RLP, raw_evidences);
+    if &expected != evidences_root {
+        return Err(From::from(BlockError::InvalidEvidencesRoot(Mismatch {
+            expected,
+            found: *evidences_root,
+        })))
+    }
+    Ok(())
+}
+
 /// Phase 2 verification. Perform costly checks such as transaction signatures and block nonce for ethash.
 /// Still operates on a individual block
 /// Returns a `PreverifiedBlock` structure populated with transactions
@@ -15,7 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::verification;
-use crate::consensus::ConsensusEngine;
+use crate::consensus::{ConsensusEngine, Evidence};
 use crate::error::Error;
 use ctypes::{ChainParams, Header};
 
@@ -44,4 +44,14 @@ impl Verifier {
     pub fn verify_block_external(&self, header: &Header, engine: &dyn ConsensusEngine) -> Result<(), Error> {
         engine.verify_block_external(header)
     }
+
+    /// Verify the evidences in a block, inspecting external state.
+    pub fn verify_evidences(
+        &self,
+        header: &Header,
+        evidences: &[Evidence],
+        engine: &dyn ConsensusEngine,
+    ) -> Result<(), Error> {
+        engine.verify_evidences(header, evidences)
+    }
 }
@@ -0,0 +1,305 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::config::Config;
+use crate::json::PasswordFile;
+use crate::run_node::{load_password_file, open_key_directory};
+use ckey::{
+    verify, DerivationPath, Ed25519Private as Private, Ed25519Public as Public, Generator, KeyPairTrait, Message,
+    NetworkId, Password, PlatformAddress, Random, Signature, X25519KeyPair,
+};
+use ckeystore::{KeyStore, SecretStore, SimpleSecretStore};
+use clap::{value_t, values_t, ArgMatches};
+use std::fs;
+use std::str::FromStr;
+
+pub fn run_account_subcommand(matches: &ArgMatches<'_>, config: &Config) -> Result<(), String> {
+    match matches.subcommand() {
+        ("create", Some(matches)) => {
+            let store = open_key_store(config)?;
+            let password = prompt_new_password()?;
+            let pubkey = store.new_account(&password).map_err(|e| format!("Cannot create an account: {}", e))?;
+            println!("{}", PlatformAddress::new_v0(network_id(matches), pubkey));
+            Ok(())
+        }
+        ("list", Some(matches)) => {
+            let store = open_key_store(config)?;
+            let network_id = network_id(matches);
+            for pubkey in store.accounts().map_err(|e| format!("Cannot read the accounts: {}", e))? {
+                println!("{}", PlatformAddress::new_v0(network_id, pubkey));
+            }
+            Ok(())
+        }
+        ("import", Some(matches)) => {
+            let store = open_key_store(config)?;
+            let path = matches.value_of("file").expect("file is required");
+            let content = fs::read(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+            let pubkey = if matches.is_present("raw") {
+                let private = parse_private(&String::from_utf8_lossy(&content))?;
+                let password = prompt_new_password()?;
+                store.insert_account(private, &password)
+            } else {
+                let password = prompt_password(&format!("Password of {}: ", path))?;
+                store.import_wallet(&content, &password, false)
+            }
+            .map_err(|e| format!("Cannot import {}: {}", path, e))?;
+            println!("{}", PlatformAddress::new_v0(network_id(matches), pubkey));
+            Ok(())
+        }
+        ("export", Some(matches)) => {
+            let store = open_key_store(config)?;
+            let (name, pubkey) = account(matches)?;
+            let password = password_of(&load_password_file(&config.password_path)?, &name, &pubkey)?;
+            let key_file =
+                store.export_account(&pubkey, &password).map_err(|e| format!("Cannot export {}: {}", name, e))?;
+            let json = serde_json::to_string_pretty(&key_file).expect("A key file is serializable");
+            match matches.value_of("output") {
+                Some(output) => fs::write(output, json).map_err(|e| format!("Cannot write {}: {}", output, e))?,
+                None => println!("{}", json),
+            }
+            Ok(())
+        }
+        ("change-password", Some(matches)) => {
+            let store = open_key_store(config)?;
+            let (name, pubkey) = account(matches)?;
+            let old_password = prompt_password(&format!("Current password of {}: ", name))?;
+            let new_password = prompt_new_password()?;
+            store
+                .change_password(&pubkey, &old_password, &new_password)
+                .map_err(|e| format!("Cannot change the password of {}: {}", name, e))?;
+            println!("The password of {} is changed", name);
+            Ok(())
+        }
+        ("inspect", Some(matches)) => {
+            let (_, pubkey) = account(matches)?;
+            println!("public key: {}", hex::encode(pubkey.as_ref()));
+            println!("address: {}", PlatformAddress::new_v0(network_id(matches), pubkey));
+            Ok(())
+        }
+        ("sign", Some(matches)) => {
+            let store = open_key_store(config)?;
+            let (name, pubkey) = account(matches)?;
+            let message = message_hash(matches)?;
+            let password = password_of(&load_password_file(&config.password_path)?, &name, &pubkey)?;
+            let signature = store
+                .decrypt_account(&pubkey, &password)
+                .and_then(|account| account.sign(&message).map_err(From::from))
+                .map_err(|e| format!("Cannot sign with {}: {}", name, e))?;
+            println!("{}", hex::encode(signature.as_ref()));
+            Ok(())
+        }
+        ("verify", Some(matches)) => {
+            let (name, pubkey) = account(matches)?;
+            let message = message_hash(matches)?;
+            let signature = parse_signature(matches.value_of("signature").expect("signature is required"))?;
+            if verify(&signature, message.as_ref(), &pubkey) {
+                println!("The signature of {} is valid", name);
+                Ok(())
+            } else {
+                Err(format!("The signature is not signed by {}", name))
+            }
+        }
+        ("import-mnemonic", Some(_)) => {
+            let store = open_key_store(config)?;
+            let phrase = rpassword::prompt_password_stdout("Mnemonic: ")
+                .map_err(|e| format!("Cannot read the mnemonic: {}", e))?;
+            let passphrase = rpassword::prompt_password_stdout("BIP-39 passphrase (empty for none): ")
+                .map_err(|e| format!("Cannot read the passphrase: {}", e))?;
+            let password = prompt_new_password()?;
+            let seed = store
+                .import_mnemonic(&phrase, &passphrase, &password)
+                .map_err(|e| format!("Cannot import the mnemonic: {}", e))?;
+            println!("{}", seed);
+            Ok(())
+        }
+        ("seeds", Some(_)) => {
+            let store = open_key_store(config)?;
+            for seed in store.seeds().map_err(|e| format!("Cannot read the seeds: {}", e))? {
+                println!("{}", seed);
+            }
+            Ok(())
+        }
+        ("derive", Some(matches)) => {
+            let store = open_key_store(config)?;
+            let seed_id = matches.value_of("seed").expect("seed is required");
+            let seed = seed_id.parse().map_err(|_| format!("{} is not a seed ID", seed_id))?;
+            let path = matches.value_of("path").expect("path is required");
+            let path: DerivationPath = path.parse().map_err(|e: ckey::Error| e.to_string())?;
+            let password = prompt_password(&format!("Password of {}: ", seed_id))?;
+            let pubkey = store
+                .derive_account(seed, &path, &password)
+                .map_err(|e| format!("Cannot derive {} from {}: {}", path, seed_id, e))?;
+            println!("{}", PlatformAddress::new_v0(network_id(matches), pubkey));
+            Ok(())
+        }
+        ("generate-network-key", Some(_)) => {
+            let (private, public) = generate_network_key();
+            println!("private key: {}", private);
+            println!("public key: {}", public);
+            Ok(())
+        }
+        ("re-encrypt", Some(matches)) => {
+            let store = open_key_store(config)?;
+            let passwords = load_password_file(&config.password_path)?;
+
+            let accounts = if matches.is_present("account") {
+                values_t!(matches, "account", String)
+                    .unwrap_or_else(|e| e.exit())
+                    .iter()
+                    .map(|account| parse_account(account).map(|pubkey| (account.clone(), pubkey)))
+                    .collect::<Result<Vec<_>, _>>()?
+            } else {
+                store
+                    .accounts()
+                    .map_err(|e| format!("Cannot read the accounts: {}", e))?
+                    .into_iter()
+                    .map(|pubkey| (hex::encode(pubkey.as_ref()), pubkey))
+                    .collect::<Vec<_>>()
+            };
+
+            for (name, pubkey) in accounts {
+                let password = password_of(&passwords, &name, &pubkey)?;
+                store
+                    .re_encrypt(&pubkey, &password)
+                    .map_err(|e| format!("Cannot re-encrypt the key of {}: {}", name, e))?;
+                println!("The key of {} is re-encrypted with Argon2id", name);
+            }
+            Ok(())
+        }
+        _ => Err("Invalid account subcommand".into()),
+    }
+}
+
+fn open_key_store(config: &Config) -> Result<KeyStore, String> {
+    KeyStore::open(open_key_directory(config)?).map_err(|e| format!("Cannot open the key store: {}", e))
+}
+
+fn network_id(matches: &ArgMatches<'_>) -> NetworkId {
+    value_t!(matches, "network-id", NetworkId).unwrap_or_else(|e| e.exit())
+}
+
+/// Returns the given account as it is written and its public key.
+fn account(matches: &ArgMatches<'_>) -> Result<(String, Public), String> {
+    let account = matches.value_of("account").expect("account is required");
+    Ok((account.to_string(), parse_account(account)?))
+}
+
+/// Accepts both a platform address and a hex-encoded public key.
+fn parse_account(account: &str) -> Result<Public, String> {
+    PlatformAddress::from_str(account)
+        .map(PlatformAddress::into_pubkey)
+        .or_else(|_| Public::from_str(account.trim_start_matches("0x")))
+        .map_err(|_| format!("{} is neither a platform address nor a public key", account))
+}
+
+fn parse_private(private: &str) -> Result<Private, String> {
+    hex::decode(private.trim().trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| Private::from_slice(&bytes))
+        .ok_or_else(|| "The private key must be a hex-encoded Ed25519 private key".to_string())
+}
+
+fn parse_signature(signature: &str) -> Result<Signature, String> {
+    hex::decode(signature.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes))
+        .ok_or_else(|| format!("{} is not a hex-encoded Ed25519 signature", signature))
+}
+
+/// Returns the hex-encoded private and public keys of a new x25519 key pair for the network.
+fn generate_network_key() -> (String, String) {
+    let keypair: X25519KeyPair = Random.generate().expect("x25519 context has generation capabilities; qed");
+    (hex::encode(keypair.private().as_ref()), hex::encode(keypair.public().as_ref()))
+}
+
+/// Prefixed to the signed messages, so that a signed message is never a valid transaction or vote.
+const SIGNED_MESSAGE_TAG: &[u8] = b"Foundry Signed Message:\n";
+
+/// The accounts sign the BLAKE2b-256 hash of a message, because a key kept in a token only signs a hash.
+fn message_hash(matches: &ArgMatches<'_>) -> Result<Message, String> {
+    let message = matches.value_of("message").expect("message is required");
+    let bytes = if matches.is_present("hex") {
+        hex::decode(message.trim_start_matches("0x")).map_err(|_| format!("{} is not a hex string", message))?
+    } else {
+        message.as_bytes().to_vec()
+    };
+    Ok(signed_message_hash(&bytes))
+}
+
+fn signed_message_hash(message: &[u8]) -> Message {
+    ccrypto::blake256([SIGNED_MESSAGE_TAG, message].concat())
+}
+
+/// Takes the password from the password file, or asks for it.
+fn password_of(passwords: &PasswordFile, name: &str, pubkey: &Public) -> Result<Password, String> {
+    match passwords.entries().iter().find(|entry| entry.address.pubkey() == pubkey) {
+        Some(entry) => Ok(entry.password.clone()),
+        None => prompt_password(&format!("Password of {}: ", name)),
+    }
+}
+
+fn prompt_password(prompt: &str) -> Result<Password, String> {
+    rpassword::prompt_password_stdout(prompt)
+        .map(Password::from)
+        .map_err(|e| format!("Cannot read the password: {}", e))
+}
+
+fn prompt_new_password() -> Result<Password, String> {
+    let password =
+        rpassword::prompt_password_stdout("New password: ").map_err(|e| format!("Cannot read the password: {}", e))?;
+    let confirmation = rpassword::prompt_password_stdout("Repeat the password: ")
+        .map_err(|e| format!("Cannot read the password: {}", e))?;
+    if password != confirmation {
+        return Err("The passwords do not match".to_string())
+    }
+    Ok(Password::from(password))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn account_is_an_address_or_a_public_key() {
+        let pubkey = Public::random();
+        let address = PlatformAddress::new_v0(NetworkId::default(), pubkey);
+
+        assert_eq!(Ok(pubkey), parse_account(&address.to_string()));
+        assert_eq!(Ok(pubkey), parse_account(&hex::encode(pubkey.as_ref())));
+        assert_eq!(Ok(pubkey), parse_account(&format!("0x{}", hex::encode(pubkey.as_ref()))));
+        assert!(parse_account("not an account").is_err());
+    }
+
+    #[test]
+    fn signed_hash_is_verified() {
+        let keypair: ckey::Ed25519KeyPair = Random.generate().unwrap();
+        let message = signed_message_hash(b"hello");
+        assert_ne!(ccrypto::blake256(b"hello"), message);
+        let signature = ckey::sign(message.as_ref(), keypair.private());
+
+        let parsed = parse_signature(&hex::encode(signature.as_ref())).unwrap();
+        assert!(verify(&parsed, message.as_ref(), keypair.public()));
+        assert_eq!(Ok(keypair.private().clone()), parse_private(&format!("{}\n", hex::encode(keypair.private()))));
+    }
+
+    #[test]
+    fn network_key_is_an_x25519_key_pair() {
+        let (private, public) = generate_network_key();
+        let private = ckey::X25519Private::from_slice(&hex::decode(private).unwrap()).unwrap();
+        assert_eq!(public, hex::encode(private.public_key().as_ref()));
+        assert_ne!(generate_network_key().0, hex::encode(private.as_ref()));
+    }
+}
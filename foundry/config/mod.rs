@@ -18,7 +18,8 @@ use ccore::{MinerOptions, TimeGapParams};
 use cidr::IpCidr;
 use cinformer::InformerConfig;
 use ckey::PlatformAddress;
-use cnetwork::{FilterEntry, NetworkConfig, SocketAddr};
+use cnetwork::{FilterEntry, NatType, NetworkConfig, SocketAddr};
+use csync::snapshot::RetentionPolicy;
 use primitives::H256;
 use std::fmt::Display;
 use std::fs;
@@ -54,6 +55,13 @@ pub struct Config {
     )]
     pub link_desc_path: String,
 
+    #[conf(
+        no_short,
+        long = "desc-path",
+        help = "Specify the combined descriptor path, which is used instead of the app descriptor and the link descriptor."
+    )]
+    pub desc_path: Option<String>,
+
     #[conf(
         short = "i",
         long = "instance-id",
@@ -70,9 +78,36 @@ pub struct Config {
     #[conf(no_short, long = "keys-path", help = "Specify the path for JSON key files to be found")]
     pub keys_path: Option<String>,
 
+    #[conf(
+        no_short,
+        long = "pkcs11-library",
+        help = "Specify the PKCS#11 library path to use the keys in a token instead of the JSON key files."
+    )]
+    pub pkcs11_library: Option<String>,
+
+    #[conf(no_short, long = "pkcs11-token", help = "Specify the label of the PKCS#11 token which keeps the keys.")]
+    pub pkcs11_token: Option<String>,
+
+    #[conf(no_short, long = "consensus-wal-path", help = "Specify the path of the write-ahead log of the consensus.")]
+    pub consensus_wal_path: Option<String>,
+
     #[conf(no_short, long = "password-path", help = "Specify the password file path.")]
     pub password_path: Option<String>,
 
+    #[conf(
+        no_short,
+        long = "remote-signer",
+        help = "Sign consensus messages with the remote signer at the address, unix:<path> or <host>:<port>, instead of the local keystore."
+    )]
+    pub remote_signer: Option<String>,
+
+    #[conf(
+        no_short,
+        long = "remote-signer-key-path",
+        help = "Specify the file of the key shared with the remote signer, 32 bytes in hex."
+    )]
+    pub remote_signer_key_path: Option<String>,
+
     // mining
     #[conf(
         no_short,
@@ -97,6 +132,14 @@ pub struct Config {
     )]
     pub mem_pool_mem_limit: usize,
 
+    #[conf(
+        no_short,
+        long = "mem-pool-future-lifetime",
+        help = "Specify how long a transaction waiting for the transactions of the missing sequences stays in the mem pool. SECS is time measured in seconds.",
+        default = "600"
+    )]
+    pub mem_pool_future_lifetime: u64,
+
     #[conf(
         no_short,
         long = "reseal-on-txs",
@@ -140,9 +183,48 @@ pub struct Config {
     #[conf(no_short, long = "port", help = "Listen for connections on PORT.", default = "3485")]
     pub port: u16,
 
+    #[conf(
+        no_short,
+        long = "public-address",
+        help = "Specify the address advertised to other nodes. If it is not given, it's detected from the NAT gateway or the peers."
+    )]
+    pub public_address: Option<SocketAddr>,
+
+    #[conf(
+        no_short,
+        long = "nat",
+        help = "Specify the protocol used to open the port on the NAT gateway. Options are none, any, upnp and natpmp.",
+        default = "\"none\".to_string()"
+    )]
+    pub nat: String,
+
+    #[conf(
+        no_short,
+        long = "nat-gateway",
+        help = "Specify the NAT-PMP gateway address. The default gateway is used if it is not given."
+    )]
+    pub nat_gateway: Option<Ipv4Addr>,
+
     #[conf(no_short, long = "bootstrap-addresses", help = "Bootstrap addresses to connect.")]
     pub bootstrap_addresses: CommaSeparated<SocketAddr>,
 
+    #[conf(
+        no_short,
+        long = "private-peers",
+        help = "Addresses of the peers which are never gossiped, such as the validator behind sentry nodes."
+    )]
+    pub private_peers: CommaSeparated<SocketAddr>,
+
+    #[conf(no_short, long = "persistent-peers", help = "Addresses of the peers to which the node always reconnects.")]
+    pub persistent_peers: CommaSeparated<SocketAddr>,
+
+    #[conf(
+        no_short,
+        long = "unconditional-peers",
+        help = "Addresses of the peers which are not limited by max-peers. The node always reconnects to them."
+    )]
+    pub unconditional_peers: CommaSeparated<SocketAddr>,
+
     #[conf(
         no_short,
         long = "min-peers",
@@ -168,6 +250,23 @@ pub struct Config {
     #[conf(no_short, long = "snapshot-number", help = "The block number of the snapshot target block.")]
     pub snapshot_number: Option<u64>,
 
+    #[conf(
+        no_short,
+        long = "auto-snapshot",
+        help = "If the chain is empty, sync from the newest snapshot advertised by peers and finalized by the validators trusted from the genesis block or --trusted-checkpoint-hash."
+    )]
+    pub auto_snapshot: bool,
+
+    #[conf(
+        no_short,
+        long = "trusted-checkpoint-hash",
+        help = "The block hash of the checkpoint which --auto-snapshot verifies snapshots from. Use a recent one if the validators changed a lot since the genesis block."
+    )]
+    pub trusted_checkpoint_hash: Option<H256>,
+
+    #[conf(no_short, long = "trusted-checkpoint-number", help = "The block number of the trusted checkpoint.")]
+    pub trusted_checkpoint_number: Option<u64>,
+
     #[conf(negated_arg, no_short, long = "no-tx-relay", help = "Do not relay transactions.")]
     pub tx_relay_enable: bool,
 
@@ -249,6 +348,23 @@ pub struct Config {
     #[conf(no_short, no_long, default = "100000")]
     pub snapshot_expiration: Option<u64>,
 
+    #[conf(no_short, long = "snapshot-keep-last", help = "Keep only the most recent N snapshots.")]
+    pub snapshot_keep_last: Option<usize>,
+
+    #[conf(
+        no_short,
+        long = "snapshot-keep-every",
+        help = "Keep the snapshots whose block number is a multiple of K. Combined with --snapshot-keep-last, a snapshot is kept when either option keeps it."
+    )]
+    pub snapshot_keep_every: Option<u64>,
+
+    #[conf(
+        no_short,
+        long = "snapshot-max-disk-usage",
+        help = "Remove the oldest snapshots when snapshots use more than this size in MiB. The most recent snapshot is always kept."
+    )]
+    pub snapshot_max_disk_usage: Option<u64>,
+
     // Email
     #[conf(negated_arg, no_short, long = "no-email-alarm", help = "Do not use email alarm")]
     pub email_alarm_enable: bool,
@@ -285,6 +401,7 @@ impl Config {
                 0 => None,
                 mem_size => Some(mem_size * 1024 * 1024),
             },
+            mem_pool_future_lifetime: Duration::from_secs(self.mem_pool_future_lifetime),
             reseal_on_own_transaction,
             reseal_on_external_transaction,
             reseal_min_period: Duration::from_millis(self.reseal_min_period),
@@ -334,11 +451,18 @@ impl Config {
 
         let whitelist = make_ipaddr_list(self.whitelist_path.as_ref(), "white")?;
         let blacklist = make_ipaddr_list(self.blacklist_path.as_ref(), "black")?;
+        let nat = NatType::from_str(&self.nat)?;
 
         Ok(NetworkConfig {
             address: self.interface.clone().unwrap(),
             port: self.port,
+            public_address: self.public_address,
+            nat,
+            nat_gateway: self.nat_gateway,
             bootstrap_addresses,
+            private_peers: self.private_peers.inner.clone(),
+            persistent_peers: self.persistent_peers.inner.clone(),
+            unconditional_peers: self.unconditional_peers.inner.clone(),
             min_peers: self.min_peers,
             max_peers: self.max_peers,
             whitelist,
@@ -346,6 +470,15 @@ impl Config {
         })
     }
 
+    pub fn snapshot_retention_policy(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            expiration: self.snapshot_expiration,
+            keep_last: self.snapshot_keep_last,
+            keep_every: self.snapshot_keep_every,
+            max_disk_usage: self.snapshot_max_disk_usage.map(|mib| mib * 1024 * 1024),
+        }
+    }
+
     pub fn create_time_gaps(&self) -> TimeGapParams {
         let allowed_past_gap = Duration::from_millis(self.allowed_past_gap);
         let allowed_future_gap = Duration::from_millis(self.allowed_future_gap);
@@ -16,3 +16,5 @@
 
 pub const DEFAULT_KEYS_PATH: &str = "keys";
 pub const DEFAULT_DB_PATH: &str = "db";
+pub const DEFAULT_CONSENSUS_WAL_PATH: &str = "consensus.wal";
+pub const DEFAULT_SIGN_STATE_PATH: &str = "sign_state";
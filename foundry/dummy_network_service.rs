@@ -58,6 +58,10 @@ impl NetworkControl for DummyNetworkService {
         Err(NetworkControlError::Disabled)
     }
 
+    fn advertised_address(&self) -> Result<Option<SocketAddr>, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
     fn get_peer_count(&self) -> Result<usize, NetworkControlError> {
         Err(NetworkControlError::Disabled)
     }
@@ -28,6 +28,7 @@ use structconf::StructConf;
 pub use crate::run_node::run_node;
 use crate::subcommand::run_subcommand;
 
+mod account;
 mod config;
 mod constants;
 mod dummy_network_service;
@@ -46,7 +47,214 @@ pub fn run() -> Result<(), String> {
         .version(version)
         .author("CodeChain Team <hi@codechain.io>")
         .about("Foundry client")
-        .subcommand(SubCommand::with_name("commit-hash").about("Print the commit hash of the source tree"));
+        .subcommand(SubCommand::with_name("commit-hash").about("Print the commit hash of the source tree"))
+        .subcommand(
+            SubCommand::with_name("snapshot")
+                .about("Export or import snapshot archives")
+                .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("export")
+                        .about("Write a snapshot in the snapshot directory into an archive file")
+                        .arg(
+                            clap::Arg::with_name("block")
+                                .help("The hash or the number of the snapshot block")
+                                .required(true),
+                        )
+                        .arg(
+                            clap::Arg::with_name("output")
+                                .short("o")
+                                .long("output")
+                                .help("The archive file to write. <block hash>.snapshot by default")
+                                .takes_value(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("import")
+                        .about("Extract a snapshot archive into the snapshot directory")
+                        .arg(clap::Arg::with_name("file").help("The archive file to read").required(true)),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("remote-signer")
+                .about("Keep the key of the engine signer and sign the votes requested by a node")
+                .arg(
+                    clap::Arg::with_name("listen")
+                        .long("listen")
+                        .help("The address to listen on, unix:<path> or <host>:<port>")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::with_name("state-path")
+                        .long("state-path")
+                        .help("The file recording the last signed vote. <base path>/sign_state by default")
+                        .takes_value(true),
+                )
+                .arg(
+                    clap::Arg::with_name("key-path")
+                        .long("key-path")
+                        .help("The file of the key shared with the node, 32 bytes in hex")
+                        .takes_value(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("desc")
+                .about("Inspect the app descriptor and the link descriptor")
+                .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("check")
+                        .about("Validate the descriptors without starting a node and print the links between modules")
+                        .arg(
+                            clap::Arg::with_name("format")
+                                .long("format")
+                                .help("The format of the printed links")
+                                .possible_values(&["text", "dot"])
+                                .default_value("text")
+                                .takes_value(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("account")
+                .about("Manage the accounts in the keys directory or the PKCS#11 token")
+                .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("create").about("Create an account with a random key").arg(
+                        clap::Arg::with_name("network-id")
+                            .long("network-id")
+                            .help("The network ID of the printed address")
+                            .default_value("tc")
+                            .takes_value(true),
+                    ),
+                )
+                .subcommand(
+                    SubCommand::with_name("list").about("Print the addresses of the accounts").arg(
+                        clap::Arg::with_name("network-id")
+                            .long("network-id")
+                            .help("The network ID of the printed address")
+                            .default_value("tc")
+                            .takes_value(true),
+                    ),
+                )
+                .subcommand(
+                    SubCommand::with_name("import")
+                        .about("Import a key file, or a hex-encoded private key with --raw")
+                        .arg(clap::Arg::with_name("file").help("The file to read").required(true))
+                        .arg(clap::Arg::with_name("raw").long("raw").help("Read a hex-encoded private key"))
+                        .arg(
+                            clap::Arg::with_name("network-id")
+                                .long("network-id")
+                                .help("The network ID of the printed address")
+                                .default_value("tc")
+                                .takes_value(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("export")
+                        .about("Print the key file of an account")
+                        .arg(
+                            clap::Arg::with_name("account")
+                                .help("The platform address or the public key of the account")
+                                .required(true),
+                        )
+                        .arg(
+                            clap::Arg::with_name("output")
+                                .short("o")
+                                .long("output")
+                                .help("The file to write instead of the standard output")
+                                .takes_value(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("change-password").about("Change the password of an account").arg(
+                        clap::Arg::with_name("account")
+                            .help("The platform address or the public key of the account")
+                            .required(true),
+                    ),
+                )
+                .subcommand(
+                    SubCommand::with_name("inspect")
+                        .about("Print the public key and the address of an account")
+                        .arg(
+                            clap::Arg::with_name("account")
+                                .help("The platform address or the public key of the account")
+                                .required(true),
+                        )
+                        .arg(
+                            clap::Arg::with_name("network-id")
+                                .long("network-id")
+                                .help("The network ID of the printed address")
+                                .default_value("tc")
+                                .takes_value(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("sign")
+                        .about("Sign a message with an account")
+                        .arg(
+                            clap::Arg::with_name("account")
+                                .help("The platform address or the public key of the account")
+                                .required(true),
+                        )
+                        .arg(
+                            clap::Arg::with_name("message")
+                                .help("The message, whose BLAKE2b-256 hash is signed")
+                                .required(true),
+                        )
+                        .arg(clap::Arg::with_name("hex").long("hex").help("Decode the message as a hex string")),
+                )
+                .subcommand(
+                    SubCommand::with_name("verify")
+                        .about("Verify the signature of a message")
+                        .arg(
+                            clap::Arg::with_name("account")
+                                .help("The platform address or the public key of the account")
+                                .required(true),
+                        )
+                        .arg(
+                            clap::Arg::with_name("message")
+                                .help("The message, whose BLAKE2b-256 hash is signed")
+                                .required(true),
+                        )
+                        .arg(clap::Arg::with_name("signature").help("The hex-encoded signature").required(true))
+                        .arg(clap::Arg::with_name("hex").long("hex").help("Decode the message as a hex string")),
+                )
+                .subcommand(
+                    SubCommand::with_name("import-mnemonic")
+                        .about("Import the seed of a BIP-39 mnemonic as an HD wallet and print its ID"),
+                )
+                .subcommand(SubCommand::with_name("seeds").about("Print the IDs of the HD wallets"))
+                .subcommand(
+                    SubCommand::with_name("derive")
+                        .about("Derive an account from an HD wallet with SLIP-0010")
+                        .arg(clap::Arg::with_name("seed").help("The ID of the HD wallet").required(true))
+                        .arg(
+                            clap::Arg::with_name("path")
+                                .help("The derivation path with hardened indices, e.g. m/44'/0'/0'")
+                                .required(true),
+                        )
+                        .arg(
+                            clap::Arg::with_name("network-id")
+                                .long("network-id")
+                                .help("The network ID of the printed address")
+                                .default_value("tc")
+                                .takes_value(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("generate-network-key").about("Generate an x25519 key pair for the network"),
+                )
+                .subcommand(
+                    SubCommand::with_name("re-encrypt")
+                        .about("Encrypt the key files again with Argon2id and upgrade them to version 4 in place")
+                        .arg(
+                            clap::Arg::with_name("account")
+                                .help("The accounts to re-encrypt. All accounts in the keys directory by default")
+                                .multiple(true),
+                        ),
+                ),
+        );
 
     let app = app.arg(
         clap::Arg::with_name("module_arguments")
@@ -68,7 +276,7 @@ pub fn run() -> Result<(), String> {
     };
 
     match matches.subcommand_name() {
-        Some(_) => run_subcommand(&matches),
+        Some(_) => run_subcommand(&matches, &conf, &module_arguments),
         None => run_node(conf, module_arguments),
     }
 }
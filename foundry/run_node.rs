@@ -15,22 +15,26 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::config;
-use crate::constants::{DEFAULT_DB_PATH, DEFAULT_KEYS_PATH};
+use crate::constants::{DEFAULT_CONSENSUS_WAL_PATH, DEFAULT_DB_PATH, DEFAULT_KEYS_PATH};
 use crate::dummy_network_service::DummyNetworkService;
 use crate::json::PasswordFile;
 use ccore::{
     genesis::Genesis, AccountProvider, AccountProviderError, ChainNotify, Client, ClientConfig, ClientService,
-    EngineInfo, EngineType, Miner, MinerService, NullEngine, PeerDb, Solo, Tendermint, NUM_COLUMNS,
+    ConsensusSigner, EngineInfo, EngineType, Miner, MinerService, NullEngine, PeerDb, RemoteSigner, SignerAddress,
+    SignerKey, Solo, Tendermint, NUM_COLUMNS,
 };
 use ccore::{snapshot_notify, ConsensusEngine, EngineClient};
 use cdiscovery::{Config, Discovery};
-use cinformer::{handler::Handler, InformerEventSender, InformerService, MetaIoHandler, PubSubHandler, Session};
+use cinformer::{
+    handler::Handler, Events, InformerEventSender, InformerService, MetaIoHandler, PubSubHandler, Session,
+};
 use ckey::{Ed25519Public as Public, NetworkId, PlatformAddress};
-use ckeystore::accounts_dir::RootDiskDirectory;
+use ckeystore::accounts_dir::{KeyDirectory, Pkcs11Directory, RootDiskDirectory};
 use ckeystore::KeyStore;
 use clogger::{EmailAlarm, LoggerConfig};
 use cnetwork::{Filters, ManagingPeerdb, NetworkConfig, NetworkControl, NetworkService, RoutingTable, SocketAddr};
-use coordinator::{AppDesc, Coordinator, LinkDesc};
+use coordinator::engine::HealthChecker;
+use coordinator::{load_combined_desc, AppDesc, Coordinator, LinkDesc};
 use crossbeam::unbounded;
 use crossbeam_channel as crossbeam;
 use csync::snapshot::Service as SnapshotService;
@@ -41,6 +45,9 @@ use fdlimit::raise_fd_limit;
 use kvdb::KeyValueDB;
 use kvdb_rocksdb::{Database, DatabaseConfig};
 use parking_lot::{Condvar, Mutex};
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::{
     collections::BTreeMap,
@@ -75,7 +82,10 @@ fn network_start(
         network_id,
         timer_loop,
         sockaddress,
+        cfg.public_address,
         cfg.bootstrap_addresses.clone(),
+        cfg.persistent_peers.clone(),
+        cfg.unconditional_peers.clone(),
         cfg.min_peers,
         cfg.max_peers,
         filters,
@@ -84,6 +94,7 @@ fn network_start(
         sender,
     )
     .map_err(|e| format!("Network service error: {:?}", e))?;
+    service.map_port(cfg.nat, cfg.nat_gateway).map_err(|e| format!("Network service error: {:?}", e))?;
 
     Ok(service)
 }
@@ -134,6 +145,27 @@ fn new_miner(
     let miner = Miner::new(config.miner_options()?, engine, db, coordinator);
 
     match miner.engine_type() {
+        EngineType::PBFT if config.remote_signer.is_some() => {
+            let address: SignerAddress = config.remote_signer.as_ref().expect("Checked above").parse()?;
+            let key_path = Path::new(
+                config.remote_signer_key_path.as_ref().ok_or("The key of the remote signer is not specified")?,
+            );
+            let key = SignerKey::read(key_path)
+                .map_err(|e| format!("Cannot read the key of the remote signer {}: {}", key_path.display(), e))?;
+            let signer = RemoteSigner::connect(address.clone(), key)
+                .map_err(|e| format!("Cannot connect to the remote signer {}: {}", address, e))?;
+            if let Some(engine_signer) = &config.engine_signer {
+                if engine_signer.into_pubkey() != signer.public() {
+                    return Err(format!(
+                        "The remote signer {} signs as {:?}, but the engine signer is {}",
+                        address,
+                        signer.public(),
+                        engine_signer
+                    ))
+                }
+            }
+            miner.set_author_signer(Arc::new(signer));
+        }
         EngineType::PBFT => match &config.engine_signer {
             Some(ref engine_signer) => match miner.set_author(ap, (*engine_signer).into_pubkey()) {
                 Err(AccountProviderError::NotUnlocked) => {
@@ -154,27 +186,94 @@ fn new_miner(
     Ok(miner)
 }
 
-fn wait_for_exit() {
-    let exit = Arc::new((Mutex::new(()), Condvar::new()));
+/// Why the node stops.
+#[derive(Clone, Copy)]
+enum Exit {
+    /// Stopped by the user.
+    Stop,
+    /// A module marked with `restart-node-on-crash` crashed.
+    Restart,
+}
+
+fn wait_for_exit(coordinator: &Coordinator, informer: InformerEventSender) -> Exit {
+    let exit = Arc::new((Mutex::new(None), Condvar::new()));
 
     // Handle possible exits
     let e = exit.clone();
     CtrlC::set_handler(move || {
+        *e.0.lock() = Some(Exit::Stop);
         e.1.notify_all();
     });
 
+    // A crash of a module which is not restartable halts the node until it is restarted by the operator.
+    let e = exit.clone();
+    let unrestartable_crashed = AtomicBool::new(false);
+    coordinator.add_crash_listener(Box::new(move |crash| {
+        informer.notify(Events::ModuleCrashed("ModuleCrashed".to_string(), crash.module.clone(), crash.reason.clone()));
+        if !crash.restartable {
+            unrestartable_crashed.store(true, Ordering::SeqCst);
+            return
+        }
+        if !unrestartable_crashed.load(Ordering::SeqCst) {
+            let mut exit = e.0.lock();
+            exit.get_or_insert(Exit::Restart);
+            e.1.notify_all();
+        }
+    }));
+
     // Wait for signal
     let mut l = exit.0.lock();
-    exit.1.wait(&mut l);
+    while l.is_none() {
+        exit.1.wait(&mut l);
+    }
+    l.expect("Checked above")
+}
+
+/// Replaces the current process with a new one started with the same arguments.
+/// All the modules are restarted with the node, because they are linked to each other when the node starts.
+fn restart() -> Result<(), String> {
+    cwarn!(
+        CLIENT,
+        "Restarting the node and all its modules, because the crashed modules are marked with restart-node-on-crash"
+    );
+    let program = std::env::current_exe().map_err(|e| format!("Failed to restart the node: {}", e))?;
+    let e = Command::new(program).args(std::env::args_os().skip(1)).exec();
+    Err(format!("Failed to restart the node: {}", e))
+}
+
+/// Opens the keystore and unlocks the accounts in the password file.
+pub(crate) fn prepare_unlocked_account_provider(config: &config::Config) -> Result<Arc<AccountProvider>, String> {
+    let pf = load_password_file(&config.password_path)?;
+    let ap = prepare_account_provider(open_key_directory(config)?)?;
+    unlock_accounts(&*ap, &pf)?;
+    Ok(ap)
 }
 
-fn prepare_account_provider(keys_path: &str) -> Result<Arc<AccountProvider>, String> {
-    let keystore_dir = RootDiskDirectory::create(keys_path).map_err(|_| "Cannot read key path directory")?;
-    let keystore = KeyStore::open(Box::new(keystore_dir)).map_err(|_| "Cannot open key store")?;
+/// Opens the PKCS#11 token if it is configured, and the keys directory otherwise.
+pub(crate) fn open_key_directory(config: &config::Config) -> Result<Box<dyn KeyDirectory>, String> {
+    Ok(match &config.pkcs11_library {
+        Some(library) => {
+            let token = config.pkcs11_token.as_ref().ok_or_else(|| "pkcs11-token is not specified".to_string())?;
+            Box::new(
+                Pkcs11Directory::open(library, token)
+                    .map_err(|e| format!("Cannot open the PKCS#11 token {}: {}", token, e))?,
+            )
+        }
+        None => Box::new(RootDiskDirectory::create(keys_path(config)).map_err(|_| "Cannot read key path directory")?),
+    })
+}
+
+/// The directory of the key files.
+fn keys_path(config: &config::Config) -> String {
+    config.keys_path.clone().unwrap_or_else(|| config.base_path.clone() + "/" + DEFAULT_KEYS_PATH)
+}
+
+fn prepare_account_provider(keystore_dir: Box<dyn KeyDirectory>) -> Result<Arc<AccountProvider>, String> {
+    let keystore = KeyStore::open(keystore_dir).map_err(|_| "Cannot open key store")?;
     Ok(AccountProvider::new(keystore))
 }
 
-fn load_password_file(path: &Option<String>) -> Result<PasswordFile, String> {
+pub(crate) fn load_password_file(path: &Option<String>) -> Result<PasswordFile, String> {
     let pf = match path.as_ref() {
         Some(path) => {
             let file = fs::File::open(path).map_err(|e| format!("Could not read password file at {}: {}", path, e))?;
@@ -219,7 +318,43 @@ pub fn open_db(cfg: &config::Config, client_config: &ClientConfig) -> Result<Arc
     Ok(db)
 }
 
+/// Reads the app and link descriptors and substitutes the params in them.
+pub fn load_descs(
+    config: &config::Config,
+    module_arguments: &BTreeMap<String, String>,
+) -> Result<(AppDesc, LinkDesc), String> {
+    let (mut app_desc, mut link_desc) = match &config.desc_path {
+        Some(desc_path) => load_combined_desc(Path::new(desc_path))
+            .map_err(|err| format!("Foundry failed to load the descriptor at {}: {:#}", desc_path, err))?,
+        None => {
+            let app_desc_path = &config.app_desc_path;
+            let app_desc = AppDesc::load(Path::new(app_desc_path))
+                .map_err(|err| format!("Foundry failed to load the app descriptor at {}: {:#}", app_desc_path, err))?;
+            let link_desc_path = &config.link_desc_path;
+            let link_desc = LinkDesc::load(Path::new(link_desc_path)).map_err(|err| {
+                format!("Foundry failed to load the link descriptor at {}: {:#}", link_desc_path, err)
+            })?;
+            (app_desc, link_desc)
+        }
+    };
+    app_desc
+        .merge_params(module_arguments)
+        .map_err(|err| format!("Foundry failed to merge params you supplied into the app descriptor. {}", err))?;
+    link_desc
+        .merge_params(module_arguments)
+        .map_err(|err| format!("Foundry failed to merge params you supplied into the link descriptor. {}", err))?;
+    Ok((app_desc, link_desc))
+}
+
 pub fn run_node(config: config::Config, module_arguments: BTreeMap<String, String>) -> Result<(), String> {
+    match start_node(config, module_arguments)? {
+        Exit::Stop => Ok(()),
+        Exit::Restart => restart(),
+    }
+}
+
+/// Runs the node until it is stopped or it needs to be restarted.
+fn start_node(config: config::Config, module_arguments: BTreeMap<String, String>) -> Result<Exit, String> {
     // increase max number of open files
     raise_fd_limit();
 
@@ -227,31 +362,14 @@ pub fn run_node(config: config::Config, module_arguments: BTreeMap<String, Strin
 
     let time_gap_params = config.create_time_gaps();
 
-    let mut app_desc = {
-        let app_desc_path = &config.app_desc_path;
-        let app_desc_string = fs::read_to_string(app_desc_path)
-            .map_err(|err| format!("Foundry failed to read an app desc at {}: {}", app_desc_path, err))?;
-        AppDesc::from_str(&app_desc_string).map_err(|err| format!("Foundry failed to parse app descriptor: {}", err))?
-    };
-    app_desc
-        .merge_params(&module_arguments)
-        .map_err(|err| format!("Foundry failed to merge params you supplied into the app descriptor. {}", err))?;
-    let mut link_desc = {
-        let link_desc_path = &config.link_desc_path;
-        let link_desc_string = fs::read_to_string(link_desc_path)
-            .map_err(|err| format!("Foundry failed to read a link desc at {}: {}", link_desc_path, err))?;
-        LinkDesc::from_str(&link_desc_string)
-            .map_err(|err| format!("Foundry failed to parse link descriptor: {}", err))?
-    };
-    link_desc
-        .merge_params(&module_arguments)
-        .map_err(|err| format!("Foundry failed to merge params you supplied into the link descriptor. {}", err))?;
+    let (app_desc, link_desc) = load_descs(&config, &module_arguments)?;
     let coordinator = Arc::new(
         Coordinator::from_descs(&app_desc, &link_desc)
             .map_err(|err| format!("Failed to parse app descriptor and link descriptor: {}", err))?,
     );
 
-    let genesis = Genesis::new(app_desc.host.genesis, coordinator.as_ref());
+    let app_desc_hash = app_desc.hash();
+    let genesis = Genesis::new(app_desc.host.genesis, Some(app_desc_hash), coordinator.as_ref());
 
     let engine: Arc<dyn ConsensusEngine> = {
         let engine_config = app_desc.host.engine;
@@ -263,19 +381,22 @@ pub fn run_node(config: config::Config, module_arguments: BTreeMap<String, Strin
     };
     engine.register_time_gap_config_to_worker(time_gap_params);
 
-    let pf = load_password_file(&config.password_path)?;
-    let base_path = config.base_path.clone();
-    let keys_path = config.keys_path.as_ref().map(String::clone).unwrap_or_else(|| base_path + "/" + DEFAULT_KEYS_PATH);
-    let ap = prepare_account_provider(&keys_path)?;
-    unlock_accounts(&*ap, &pf)?;
+    let ap = prepare_unlocked_account_provider(&config)?;
 
     let client_config: ClientConfig = Default::default();
     let db = open_db(&config, &client_config)?;
 
     let miner = new_miner(&config, Arc::clone(&engine), ap, Arc::clone(&db), coordinator.clone())?;
-    let client =
-        client_start(&client_config, &timer_loop, db, Arc::clone(&engine), &genesis, miner.clone(), coordinator)?;
-    miner.recover_from_db();
+    let client = client_start(
+        &client_config,
+        &timer_loop,
+        db,
+        Arc::clone(&engine),
+        &genesis,
+        miner.clone(),
+        Arc::clone(&coordinator),
+    )?;
+    miner.recover_from_db(&*client.client());
 
     let engine_graphql_handler = foundry_graphql_engine::EngineLevelGraphQlHandler::new(client.client());
 
@@ -352,6 +473,7 @@ pub fn run_node(config: config::Config, module_arguments: BTreeMap<String, Strin
             let c = client.client();
             let network_id = c.network_id();
             let routing_table = RoutingTable::new();
+            routing_table.add_private_addresses(network_config.private_peers.iter().cloned());
             let peer_db = PeerDb::new(c.get_kvdb());
             let service = network_start(
                 network_id,
@@ -359,7 +481,7 @@ pub fn run_node(config: config::Config, module_arguments: BTreeMap<String, Strin
                 &network_config,
                 Arc::clone(&routing_table),
                 peer_db,
-                informer_event_sender,
+                informer_event_sender.clone(),
             )?;
 
             if config.discovery_enable {
@@ -375,9 +497,23 @@ pub fn run_node(config: config::Config, module_arguments: BTreeMap<String, Strin
                         (Some(hash), Some(num)) => Some((hash, num)),
                         _ => None,
                     };
+                    let auto_snapshot = config.auto_snapshot;
+                    let trusted_checkpoint = match (config.trusted_checkpoint_hash, config.trusted_checkpoint_number) {
+                        (Some(hash), Some(num)) => Some((hash, num)),
+                        _ => None,
+                    };
                     let snapshot_dir = config.snapshot_path.clone();
+                    let informer = informer_event_sender.clone();
                     service.register_extension(move |api| {
-                        BlockSyncExtension::new(client, api, snapshot_target, snapshot_dir)
+                        BlockSyncExtension::new(
+                            client,
+                            api,
+                            snapshot_target,
+                            auto_snapshot,
+                            trusted_checkpoint,
+                            snapshot_dir,
+                            informer,
+                        )
                     })
                 };
                 let sync = Arc::new(BlockSyncSender::from(sync_sender));
@@ -414,22 +550,32 @@ pub fn run_node(config: config::Config, module_arguments: BTreeMap<String, Strin
         let (tx, rx) = snapshot_notify::create();
         client.engine().register_snapshot_notify_sender(tx);
         if config.snapshot_enable {
-            let service = Arc::new(SnapshotService::new(client, rx, config.snapshot_path, config.snapshot_expiration));
+            let retention_policy = config.snapshot_retention_policy();
+            let service = Arc::new(SnapshotService::new(client, rx, config.snapshot_path, retention_policy));
             Some(service)
         } else {
             None
         }
     };
 
+    let consensus_wal_path = config
+        .consensus_wal_path
+        .clone()
+        .unwrap_or_else(|| config.base_path.clone() + "/" + DEFAULT_CONSENSUS_WAL_PATH);
+    client
+        .client()
+        .engine()
+        .register_consensus_wal(Path::new(&consensus_wal_path))
+        .map_err(|e| format!("Failed to open the consensus WAL {}: {}", consensus_wal_path, e))?;
     client.client().engine().complete_register();
 
     cinfo!(TEST_SCRIPT, "Initialization complete");
 
-    wait_for_exit();
+    let exit = wait_for_exit(&coordinator, informer_event_sender);
 
     if let Some(server) = informer_server {
         server.close_handle().close();
     }
 
-    Ok(())
+    Ok(exit)
 }
@@ -14,15 +14,137 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::account::run_account_subcommand;
+use crate::config::Config;
+use crate::constants::DEFAULT_SIGN_STATE_PATH;
+use crate::run_node::{load_descs, prepare_unlocked_account_provider};
+use ccore::{LocalSigner, SignState, SignerAddress, SignerKey, SignerServer};
 use clap::ArgMatches;
+use coordinator::LinkGraph;
+use csync::snapshot::{available_snapshots, export_archive, import_archive, snapshot_dir, Manifest};
+use ctypes::{BlockHash, BlockNumber};
+use primitives::H256;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-pub fn run_subcommand(matches: &ArgMatches<'_>) -> Result<(), String> {
+pub fn run_subcommand(
+    matches: &ArgMatches<'_>,
+    config: &Config,
+    module_arguments: &BTreeMap<String, String>,
+) -> Result<(), String> {
     let subcommand = matches.subcommand.as_ref().unwrap();
     match subcommand.name.as_str() {
         "commit-hash" => {
             println!("{}", env!("VERGEN_SHA"));
             Ok(())
         }
+        "snapshot" => run_snapshot_subcommand(&subcommand.matches, config),
+        "remote-signer" => run_remote_signer(&subcommand.matches, config),
+        "desc" => run_desc_subcommand(&subcommand.matches, config, module_arguments),
+        "account" => run_account_subcommand(&subcommand.matches, config),
         _ => Err("Invalid subcommand.rs".into()),
     }
 }
+
+fn run_snapshot_subcommand(matches: &ArgMatches<'_>, config: &Config) -> Result<(), String> {
+    let root_dir = &config.snapshot_path;
+    match matches.subcommand() {
+        ("export", Some(matches)) => {
+            let block_hash = find_snapshot(root_dir, matches.value_of("block").expect("block is required"))?;
+            let output = matches
+                .value_of("output")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from(format!("{:x}.snapshot", *block_hash)));
+            let manifest = export_archive(root_dir, block_hash, &output)
+                .map_err(|err| format!("Cannot export the snapshot of block {}: {}", block_hash, err))?;
+            println!(
+                "The snapshot of block #{} ({}) is exported to {}",
+                manifest.block_number,
+                manifest.block_hash,
+                output.display()
+            );
+            Ok(())
+        }
+        ("import", Some(matches)) => {
+            let input = Path::new(matches.value_of("file").expect("file is required"));
+            let manifest = import_archive(root_dir, input)
+                .map_err(|err| format!("Cannot import the snapshot archive {}: {}", input.display(), err))?;
+            println!(
+                "The snapshot of block #{} ({}) is imported into {}",
+                manifest.block_number, manifest.block_hash, root_dir
+            );
+            println!(
+                "Start foundry with --snapshot-hash {:x} --snapshot-number {} to restore the state from it",
+                *manifest.block_hash, manifest.block_number
+            );
+            Ok(())
+        }
+        _ => Err("Invalid snapshot subcommand".into()),
+    }
+}
+
+fn run_desc_subcommand(
+    matches: &ArgMatches<'_>,
+    config: &Config,
+    module_arguments: &BTreeMap<String, String>,
+) -> Result<(), String> {
+    match matches.subcommand() {
+        ("check", Some(matches)) => {
+            let (app_desc, link_desc) = load_descs(config, module_arguments)?;
+            let graph = LinkGraph::build(&app_desc, &link_desc)
+                .map_err(|err| format!("The modules in the descriptors cannot be linked:\n{}", err))?;
+            match matches.value_of("format") {
+                Some("dot") => print!("{}", graph.to_dot()),
+                _ => print!("{}", graph),
+            }
+            Ok(())
+        }
+        _ => Err("Invalid desc subcommand".into()),
+    }
+}
+
+fn run_remote_signer(matches: &ArgMatches<'_>, config: &Config) -> Result<(), String> {
+    let address: SignerAddress = matches.value_of("listen").expect("listen is required").parse()?;
+    let state_path = matches
+        .value_of("state-path")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(&config.base_path).join(DEFAULT_SIGN_STATE_PATH));
+    let key_path = Path::new(matches.value_of("key-path").expect("key-path is required"));
+    let key =
+        SignerKey::read(key_path).map_err(|e| format!("Cannot read the signer key {}: {}", key_path.display(), e))?;
+    let engine_signer = config.engine_signer.ok_or("The engine signer is not specified")?;
+
+    let ap = prepare_unlocked_account_provider(config)?;
+    let signer = LocalSigner::new(&ap, engine_signer.into_pubkey()).map_err(|e| {
+        format!(
+            "Cannot use the engine signer {}. Check the keys_path directory and the password file: {}",
+            engine_signer, e
+        )
+    })?;
+    let state = SignState::open(&state_path)
+        .map_err(|e| format!("Cannot read the sign state {}: {}", state_path.display(), e))?;
+    if let Some(last_vote) = state.last_vote() {
+        println!("The last signed vote is {:?}", last_vote.step);
+    }
+
+    let mut server = SignerServer::new(Box::new(signer), state, key);
+    server.run(&address).map_err(|e| format!("The remote signer on {} stopped: {}", address, e))
+}
+
+/// Finds the snapshot by a block number or a block hash.
+fn find_snapshot(root_dir: &str, block: &str) -> Result<BlockHash, String> {
+    if let Ok(number) = block.parse::<BlockNumber>() {
+        return available_snapshots(root_dir)
+            .into_iter()
+            .find(|hash| {
+                Manifest::read(&snapshot_dir(root_dir, hash))
+                    .map(|manifest| manifest.block_number == number)
+                    .unwrap_or(false)
+            })
+            .ok_or_else(|| format!("There is no snapshot of block #{} in {}", number, root_dir))
+    }
+    let hash = H256::from_str(block.trim_start_matches("0x"))
+        .map_err(|_| format!("{} is neither a block number nor a block hash", block))?;
+    Ok(hash.into())
+}
@@ -0,0 +1,184 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::{ConsensusState as CoreConsensusState, PeerConsensusState, StepVotes, VoteStep as CoreVoteStep};
+use ctypes::BlockHash;
+use foundry_graphql_types::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct ConsensusState {
+    state: CoreConsensusState,
+}
+
+impl ConsensusState {
+    pub fn new(state: CoreConsensusState) -> Self {
+        Self {
+            state,
+        }
+    }
+}
+
+#[async_graphql::Object]
+impl ConsensusState {
+    async fn height(&self) -> u64 {
+        self.state.height
+    }
+
+    async fn view(&self) -> u64 {
+        self.state.view
+    }
+
+    async fn step(&self) -> String {
+        format!("{:?}", self.state.step)
+    }
+
+    /// The detailed state of the worker.
+    async fn state(&self) -> &str {
+        &self.state.state
+    }
+
+    async fn proposal(&self) -> Option<GqlH256> {
+        self.state.proposal.map(gql_hash)
+    }
+
+    /// The view of the last two-thirds majority of prevotes.
+    async fn lock_view(&self) -> Option<u64> {
+        self.state.lock.map(|(view, _)| view)
+    }
+
+    /// The block of the last two-thirds majority of prevotes. Null if the majority voted nil.
+    async fn lock_block_hash(&self) -> Option<GqlH256> {
+        self.state.lock.and_then(|(_, block_hash)| block_hash).map(gql_hash)
+    }
+
+    /// The votes of the current height.
+    async fn votes(&self) -> Vec<Votes> {
+        self.state.votes.iter().flat_map(Votes::from_step_votes).collect()
+    }
+
+    async fn peers(&self) -> Vec<Peer> {
+        self.state
+            .peers
+            .iter()
+            .map(|peer| Peer {
+                peer: peer.clone(),
+            })
+            .collect()
+    }
+
+    /// Unix time in milliseconds when the current step times out.
+    async fn step_deadline(&self) -> Option<u64> {
+        self.state.step_deadline.map(unix_millis)
+    }
+
+    /// Unix time in milliseconds when the empty proposal is generated.
+    async fn empty_proposal_deadline(&self) -> Option<u64> {
+        self.state.empty_proposal_deadline.map(unix_millis)
+    }
+}
+
+pub struct VoteStep {
+    vote_step: CoreVoteStep,
+}
+
+#[async_graphql::Object]
+impl VoteStep {
+    async fn height(&self) -> u64 {
+        self.vote_step.height
+    }
+
+    async fn view(&self) -> u64 {
+        self.vote_step.view
+    }
+
+    async fn step(&self) -> String {
+        format!("{:?}", self.vote_step.step)
+    }
+}
+
+/// The votes on a block at a step.
+pub struct Votes {
+    vote_step: CoreVoteStep,
+    block_hash: Option<BlockHash>,
+    signer_indices: Vec<usize>,
+}
+
+impl Votes {
+    fn from_step_votes(step_votes: &StepVotes) -> Vec<Self> {
+        step_votes
+            .votes
+            .iter()
+            .map(|(block_hash, signer_indices)| Votes {
+                vote_step: step_votes.step,
+                block_hash: *block_hash,
+                signer_indices: signer_indices.clone(),
+            })
+            .collect()
+    }
+}
+
+#[async_graphql::Object]
+impl Votes {
+    async fn vote_step(&self) -> VoteStep {
+        VoteStep {
+            vote_step: self.vote_step,
+        }
+    }
+
+    /// Null for the nil votes.
+    async fn block_hash(&self) -> Option<GqlH256> {
+        self.block_hash.map(gql_hash)
+    }
+
+    /// The indices of the signers in the validator set.
+    async fn signer_indices(&self) -> Vec<u64> {
+        self.signer_indices.iter().map(|index| *index as u64).collect()
+    }
+}
+
+pub struct Peer {
+    peer: PeerConsensusState,
+}
+
+#[async_graphql::Object]
+impl Peer {
+    async fn address(&self) -> String {
+        self.peer.node_id.to_string()
+    }
+
+    async fn vote_step(&self) -> VoteStep {
+        VoteStep {
+            vote_step: self.peer.vote_step,
+        }
+    }
+
+    async fn proposal(&self) -> Option<GqlH256> {
+        self.peer.proposal.map(gql_hash)
+    }
+
+    /// The indices of the signers whose votes the peer has.
+    async fn signer_indices(&self) -> Vec<u64> {
+        self.peer.messages.iter().map(|index| *index as u64).collect()
+    }
+}
+
+fn gql_hash(block_hash: BlockHash) -> GqlH256 {
+    GqlH256(*block_hash)
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|duration| duration.as_millis() as u64).unwrap_or_default()
+}
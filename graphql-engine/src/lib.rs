@@ -20,11 +20,13 @@ extern crate clogger;
 extern crate log;
 
 mod block;
+mod consensus;
 mod header;
 
 use block::Block;
 use ccore::Client;
-use ccore::{BlockChainClient, BlockChainTrait};
+use ccore::{BlockChainClient, BlockChainTrait, EngineInfo};
+use consensus::ConsensusState;
 use coordinator::module::{HandleGraphQlRequest, SessionId};
 use foundry_graphql_types::*;
 use remote_trait_object::Service;
@@ -58,6 +60,11 @@ impl QueryRoot {
     async fn transaction(&self, tx_hash: GqlH256) -> Option<u64> {
         self.client.transaction(&ctypes::TxHash::from(tx_hash.0).into()).map(|tx| tx.block_number)
     }
+
+    /// The current state of the consensus, which is used to find out why the network is stalled.
+    async fn consensus_state(&self) -> Option<ConsensusState> {
+        self.client.consensus_state().map(ConsensusState::new)
+    }
 }
 
 #[derive(Clone)]
@@ -50,6 +50,16 @@ impl Subscription {
                 cinfo!(INFORMER, "The event is successfully added to user's interested events");
                 self.interested_events.push(event);
             }
+            "SnapshotSyncProgress" => {
+                let event = EventTags::SnapshotSyncProgress;
+                cinfo!(INFORMER, "The event is successfully added to user's interested events");
+                self.interested_events.push(event);
+            }
+            "ModuleCrashed" => {
+                let event = EventTags::ModuleCrashed;
+                cinfo!(INFORMER, "The event is successfully added to user's interested events");
+                self.interested_events.push(event);
+            }
             "BlockGenerationByNumber" => {
                 let cold_event = EventTags::ColdBlockGenerationNumerical(
                     // FIXME: Handle Unvalid block number
@@ -129,7 +129,12 @@ impl InformerService {
     }
 
     fn compare_event_types(tag: &EventTags, event: &Events) -> bool {
-        matches!((tag, event), (EventTags::PeerAdded, Events::PeerAdded(..)))
+        matches!(
+            (tag, event),
+            (EventTags::PeerAdded, Events::PeerAdded(..))
+                | (EventTags::SnapshotSyncProgress, Events::SnapshotSyncProgress(..))
+                | (EventTags::ModuleCrashed, Events::ModuleCrashed(..))
+        )
     }
 
     pub fn notify_client(&self, popup_event: Events) {
@@ -17,6 +17,8 @@
 #[derive(Clone)]
 pub enum EventTags {
     PeerAdded,
+    SnapshotSyncProgress,
+    ModuleCrashed,
     ColdBlockGenerationNumerical(u64),
     ColdBlockGenerationHash(String),
 }
@@ -24,4 +26,8 @@ pub enum EventTags {
 #[derive(Serialize)]
 pub enum Events {
     PeerAdded(String, String, usize),
+    /// The block hash of the snapshot, the number of downloaded chunks and the number of all chunks.
+    SnapshotSyncProgress(String, String, usize, usize),
+    /// The name of the crashed module and the reason of the crash.
+    ModuleCrashed(String, String, String),
 }
@@ -17,7 +17,7 @@
 extern crate codechain_crypto as ccrypto;
 extern crate codechain_key as ckey;
 
-use ckey::{sign, verify, Ed25519KeyPair, Generator, KeyPairTrait, Message, Random};
+use ckey::{sign, verify, verify_batch, Ed25519KeyPair, Generator, KeyPairTrait, Message, Random};
 use criterion::{criterion_group, criterion_main, Criterion};
 
 fn tendermint_max_step_time(c: &mut Criterion) {
@@ -55,5 +55,30 @@ fn tendermint_max_step_time(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, tendermint_max_step_time);
+fn tendermint_seal_verification(c: &mut Criterion) {
+    let num_validators = 30;
+
+    let message = Message::random();
+    let signatures: Vec<_> = (0..num_validators)
+        .map(|_| {
+            let key_pair: Ed25519KeyPair = Random.generate().unwrap();
+            (sign(message.as_ref(), key_pair.private()), *key_pair.public())
+        })
+        .collect();
+
+    c.bench_function("tendermint_individual_seal_verification", |b| {
+        b.iter(|| {
+            for (signature, public) in &signatures {
+                assert!(verify(signature, message.as_ref(), public));
+            }
+        })
+    });
+    c.bench_function("tendermint_batch_seal_verification", |b| {
+        b.iter(|| {
+            assert_eq!(Ok(()), verify_batch(message.as_ref(), &signatures));
+        })
+    });
+}
+
+criterion_group!(benches, tendermint_max_step_time, tendermint_seal_verification);
 criterion_main!(benches);
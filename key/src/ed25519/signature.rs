@@ -15,11 +15,14 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::{Private, Public};
+use ed25519_zebra::{batch, VerificationKey, VerificationKeyBytes};
 use primitives::H512;
+use rand_core::OsRng;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 pub use sodiumoxide::crypto::sign::SIGNATUREBYTES;
 use sodiumoxide::crypto::sign::{sign_detached, verify_detached, Signature};
+use std::convert::TryFrom;
 use std::fmt;
 
 pub fn sign(message: &[u8], private: &Private) -> Ed25519Signature {
@@ -33,6 +36,41 @@ pub fn verify(signature: &Ed25519Signature, message: &[u8], public: &Public) ->
     verify_detached(signature, message, public)
 }
 
+/// Verifies a signature with the ZIP-215 rules, with which `verify_batch` always agrees.
+///
+/// The rules accept every signature `verify` accepts, and a few more, e.g. the ones with a small order component.
+/// So the nodes must agree on which of the two verifies the signatures they check.
+pub fn verify_zip215(signature: &Ed25519Signature, message: &[u8], public: &Public) -> bool {
+    match VerificationKey::try_from(public.as_ref()) {
+        Ok(key) => key.verify(&zip215_signature(signature), message).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Verifies the signatures of the same message at once with the ZIP-215 rules, and returns the index of an invalid
+/// signature if there is one.
+///
+/// It is faster than `verify_zip215` for each signature. The signatures are verified one by one only to find the
+/// invalid one after the batch verification fails.
+pub fn verify_batch(message: &[u8], signatures: &[(Ed25519Signature, Public)]) -> Result<(), usize> {
+    let mut verifier = batch::Verifier::new();
+    for (signature, public) in signatures {
+        let key = VerificationKeyBytes::try_from(public.as_ref()).expect("A public key has 32 bytes");
+        verifier.queue((key, zip215_signature(signature), message));
+    }
+    if verifier.verify(OsRng).is_ok() {
+        return Ok(())
+    }
+    match signatures.iter().position(|(signature, public)| !verify_zip215(signature, message, public)) {
+        Some(index) => Err(index),
+        None => Ok(()),
+    }
+}
+
+fn zip215_signature(signature: &Ed25519Signature) -> ed25519_zebra::Signature {
+    ed25519_zebra::Signature::try_from(signature.as_ref()).expect("A signature has 64 bytes")
+}
+
 #[derive(Clone, Copy, Hash, PartialEq, Eq)]
 pub struct Ed25519Signature(Signature);
 
@@ -117,6 +155,35 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn verify_batch_of_valid_signatures() {
+        let message = b"message";
+        let signatures: Vec<_> = (0..10)
+            .map(|_| {
+                let private = Private::random();
+                (sign(message, &private), private.public_key())
+            })
+            .collect();
+        assert_eq!(Ok(()), verify_batch(message, &signatures));
+        assert_eq!(Ok(()), verify_batch(message, &[]));
+        assert!(signatures.iter().all(|(signature, public)| verify_zip215(signature, message, public)));
+    }
+
+    #[test]
+    fn verify_batch_with_an_invalid_signature() {
+        let message = b"message";
+        let mut signatures: Vec<_> = (0..10)
+            .map(|_| {
+                let private = Private::random();
+                (sign(message, &private), private.public_key())
+            })
+            .collect();
+        signatures[3].0 = sign(b"another message", &Private::random());
+        signatures[7].0 = Ed25519Signature::random();
+        assert_eq!(Err(3), verify_batch(message, &signatures));
+        assert!(!verify_zip215(&signatures[3].0, message, &signatures[3].1));
+    }
+
     #[test]
     fn signature_rlp() {
         rlp_encode_and_decode_test!(Ed25519Signature::random());
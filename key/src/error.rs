@@ -28,6 +28,8 @@ pub enum Error {
     InvalidPlatformAddressVersion(u8),
     InvalidPlatformAddressFormat(String),
     RlpDecoderError(DecoderError),
+    InvalidMnemonic(String),
+    InvalidDerivationPath(String),
     Custom(String),
 }
 
@@ -44,6 +46,8 @@ impl fmt::Display for Error {
             }
             Error::InvalidPlatformAddressFormat(address) => write!(f, "{} is an invalid platform string", address),
             Error::RlpDecoderError(err) => write!(f, "{}", err),
+            Error::InvalidMnemonic(reason) => write!(f, "Invalid mnemonic: {}", reason),
+            Error::InvalidDerivationPath(path) => write!(f, "{} is an invalid derivation path", path),
 
             Error::Custom(ref s) => write!(f, "{}", s),
         }
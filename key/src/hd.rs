@@ -0,0 +1,201 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! SLIP-0010 derivation of Ed25519 keys from a BIP-39 mnemonic.
+
+use crate::{Ed25519Private as Private, Ed25519Public as Public, Error};
+use bip39::{Language, Mnemonic, Seed};
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha512;
+use sodiumoxide::crypto::sign::{keypair_from_seed, Seed as KeySeed};
+use std::fmt;
+use std::str::FromStr;
+
+/// Ed25519 derives only hardened children, whose indices are at least this offset.
+pub const HARDENED_OFFSET: u32 = 0x8000_0000;
+
+const MASTER_KEY: &[u8] = b"ed25519 seed";
+
+/// Returns the 64-byte seed of an English BIP-39 mnemonic.
+pub fn mnemonic_to_seed(phrase: &str, passphrase: &str) -> Result<Vec<u8>, Error> {
+    let mnemonic =
+        Mnemonic::from_phrase(phrase, Language::English).map_err(|err| Error::InvalidMnemonic(err.to_string()))?;
+    Ok(Seed::new(&mnemonic, passphrase).as_bytes().to_vec())
+}
+
+/// A derivation path such as `m/44'/0'/0'`. Every index is hardened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    pub fn indices(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidDerivationPath(s.to_string());
+        let mut segments = s.split('/');
+        if segments.next() != Some("m") {
+            return Err(invalid())
+        }
+        segments
+            .map(|segment| {
+                let index = segment.strip_suffix('\'').or_else(|| segment.strip_suffix('H')).ok_or_else(invalid)?;
+                let index: u32 = index.parse().map_err(|_| invalid())?;
+                if index >= HARDENED_OFFSET {
+                    return Err(invalid())
+                }
+                Ok(index + HARDENED_OFFSET)
+            })
+            .collect::<Result<_, _>>()
+            .map(DerivationPath)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            write!(f, "/{}'", index - HARDENED_OFFSET)?;
+        }
+        Ok(())
+    }
+}
+
+/// An Ed25519 private key with its chain code.
+pub struct ExtendedPrivate {
+    key: [u8; 32],
+    chain_code: [u8; 32],
+}
+
+impl ExtendedPrivate {
+    /// The master key of a seed.
+    pub fn from_seed(seed: &[u8]) -> Self {
+        Self::from_hmac(MASTER_KEY, &[seed])
+    }
+
+    /// Derives the hardened child of the given index.
+    pub fn derive(&self, index: u32) -> Result<Self, Error> {
+        if index < HARDENED_OFFSET {
+            return Err(Error::InvalidDerivationPath(format!("{} is not hardened", index)))
+        }
+        Ok(Self::from_hmac(&self.chain_code, &[&[0], &self.key, &index.to_be_bytes()]))
+    }
+
+    pub fn derive_path(&self, path: &DerivationPath) -> Self {
+        let mut key = Self {
+            key: self.key,
+            chain_code: self.chain_code,
+        };
+        for index in path.indices() {
+            key = key.derive(*index).expect("The indices of a path are hardened");
+        }
+        key
+    }
+
+    pub fn private(&self) -> Private {
+        let seed = KeySeed::from_slice(&self.key).expect("The key has the length of a seed");
+        let (_, secret) = keypair_from_seed(&seed);
+        secret.into()
+    }
+
+    pub fn public(&self) -> Public {
+        self.private().public_key()
+    }
+
+    pub fn chain_code(&self) -> &[u8; 32] {
+        &self.chain_code
+    }
+
+    fn from_hmac(key: &[u8], data: &[&[u8]]) -> Self {
+        let mut mac = Hmac::<Sha512>::new_varkey(key).expect("HMAC takes a key of any length");
+        for chunk in data {
+            mac.update(chunk);
+        }
+        let output = mac.finalize().into_bytes();
+        let mut extended = Self {
+            key: [0; 32],
+            chain_code: [0; 32],
+        };
+        extended.key.copy_from_slice(&output[..32]);
+        extended.chain_code.copy_from_slice(&output[32..]);
+        extended
+    }
+}
+
+// Zero out the key like `Password` does.
+impl Drop for ExtendedPrivate {
+    fn drop(&mut self) {
+        unsafe {
+            for byte in self.key.iter_mut() {
+                std::ptr::write_volatile(byte, 0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The test vector 1 for ed25519 in SLIP-0010
+    #[test]
+    fn slip10_test_vector() {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = ExtendedPrivate::from_seed(&seed);
+        assert_eq!(hex::encode(master.key), "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7");
+        assert_eq!(
+            hex::encode(master.chain_code()),
+            "90046a93de5380a72b5e45010748567d5ea02bbf6522f979e05c0d8d8ca9fffb"
+        );
+
+        let child = master.derive_path(&"m/0'".parse().unwrap());
+        assert_eq!(hex::encode(child.key), "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3");
+        assert_eq!(hex::encode(child.chain_code()), "8b59aa11380b624e81507a27fedda59fea6d0b779a778918a2fd3590e16e9c69");
+        assert_eq!(hex::encode(child.public()), "8c8a13df77a28f3445213a0f432fde644acaa215fc72dcdf300d5efaa85d350c");
+
+        let grandchild = master.derive_path(&"m/0H/1H".parse().unwrap());
+        assert_eq!(hex::encode(grandchild.key), "b1d0bad404bf35da785a64ca1ac54b2617211d2777696fbffaf208f746ae84f2");
+        assert_eq!(
+            hex::encode(grandchild.chain_code()),
+            "a320425f77d1b5c2505a6b1b27382b37368ee640e3557c315416801243552f14"
+        );
+    }
+
+    #[test]
+    fn non_hardened_index_is_rejected() {
+        let master = ExtendedPrivate::from_seed(&[0; 64]);
+        assert!(master.derive(0).is_err());
+        assert!("m/0".parse::<DerivationPath>().is_err());
+        assert!("44'/0'".parse::<DerivationPath>().is_err());
+        assert_eq!("m/44'/0'/1'", "m/44H/0'/1'".parse::<DerivationPath>().unwrap().to_string());
+    }
+
+    #[test]
+    fn bip39_test_vector() {
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = mnemonic_to_seed(phrase, "TREZOR").unwrap();
+        assert_eq!(
+            hex::encode(seed),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+        );
+        assert!(mnemonic_to_seed("abandon abandon about", "").is_err());
+    }
+}
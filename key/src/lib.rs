@@ -21,6 +21,7 @@ extern crate serde_derive;
 mod ed25519;
 mod encryption;
 mod error;
+mod hd;
 mod keypair;
 mod network;
 mod password;
@@ -29,13 +30,14 @@ mod random;
 mod x25519;
 
 pub use crate::ed25519::{
-    sign, verify, KeyPair as Ed25519KeyPair, Private as Ed25519Private, Public as Ed25519Public, Signature,
-    SIGNATURE_LENGTH,
+    sign, verify, verify_batch, verify_zip215, KeyPair as Ed25519KeyPair, Private as Ed25519Private,
+    Public as Ed25519Public, Signature, SIGNATURE_LENGTH,
 };
 pub use crate::encryption::{
     decrypt, encrypt, KeyPair as EncryptionKeyPair, Private as EncryptionPrivate, Public as EncryptionPublic,
 };
 pub use crate::error::Error;
+pub use crate::hd::{mnemonic_to_seed, DerivationPath, ExtendedPrivate, HARDENED_OFFSET};
 pub use crate::keypair::KeyPair as KeyPairTrait;
 pub use crate::network::NetworkId;
 pub use crate::password::Password;
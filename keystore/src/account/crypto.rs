@@ -15,7 +15,7 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::account::{Aes128Ctr, Cipher, Kdf, Pbkdf2, Prf};
+use crate::account::{Aes128Ctr, Argon2id, Cipher, Kdf, KdfParams, Pbkdf2, Prf, Version};
 use crate::random::Random;
 use crate::{json, Error};
 use ckey::{Ed25519Private as Private, Ed25519Public as Public, Password};
@@ -68,46 +68,74 @@ impl str::FromStr for Crypto {
 
 impl Crypto {
     /// Encrypt account secret
-    pub fn with_secret(secret: &Private, password: &Password, iterations: u32) -> Result<Self, ccrypto::Error> {
-        Crypto::with_plain(secret.as_ref(), password, iterations)
+    pub fn with_secret(secret: &Private, password: &Password, kdf: KdfParams) -> Result<Self, Error> {
+        Crypto::with_plain(secret.as_ref(), password, kdf)
     }
 
     /// Encrypt custom plain data
-    pub fn with_plain(plain: &[u8], password: &Password, iterations: u32) -> Result<Self, ccrypto::Error> {
+    pub fn with_plain(plain: &[u8], password: &Password, kdf: KdfParams) -> Result<Self, Error> {
         let salt: [u8; 32] = Random::random();
         let iv: [u8; 16] = Random::random();
 
-        if let Some(non_zero_iterations) = NonZeroU32::new(iterations) {
-            // two parts of derived key
-            // DK = [ DK[0..15] DK[16..31] ] = [derived_left_bits, derived_right_bits]
-            let (derived_left_bits, derived_right_bits) =
-                ccrypto::derive_key_iterations(&password.as_crypto_password(), &salt, non_zero_iterations);
+        let kdf = match kdf {
+            KdfParams::Pbkdf2 {
+                iterations,
+            } => Kdf::Pbkdf2(Pbkdf2 {
+                dklen: ccrypto::KEY_LENGTH as u32,
+                salt,
+                c: iterations,
+                prf: Prf::HmacSha256,
+            }),
+            KdfParams::Argon2id {
+                memory,
+                iterations,
+                parallelism,
+            } => {
+                let params = Argon2id {
+                    dklen: ccrypto::KEY_LENGTH as u32,
+                    m: memory,
+                    t: iterations,
+                    p: parallelism,
+                    salt,
+                };
+                params.check_bounds().map_err(Error::Custom)?;
+                Kdf::Argon2id(params)
+            }
+        };
 
-            // preallocated (on-stack in case of `Secret`) buffer to hold cipher
-            // length = length(plain) as we are using CTR-approach
-            let plain_len = plain.len();
-            let mut ciphertext: SmallVec<[u8; 64]> = SmallVec::from_vec(vec![0; plain_len]);
+        // two parts of derived key
+        // DK = [ DK[0..15] DK[16..31] ] = [derived_left_bits, derived_right_bits]
+        let (derived_left_bits, derived_right_bits) = derive_key(&kdf, password)?;
 
-            // aes-128-ctr with initial vector of iv
-            ccrypto::aes::encrypt_128_ctr(&derived_left_bits, &iv, plain, &mut *ciphertext)?;
+        // preallocated (on-stack in case of `Secret`) buffer to hold cipher
+        // length = length(plain) as we are using CTR-approach
+        let plain_len = plain.len();
+        let mut ciphertext: SmallVec<[u8; 64]> = SmallVec::from_vec(vec![0; plain_len]);
 
-            let mac = ccrypto::blake256(ccrypto::derive_mac(&derived_right_bits, &*ciphertext));
+        // aes-128-ctr with initial vector of iv
+        ccrypto::aes::encrypt_128_ctr(&derived_left_bits, &iv, plain, &mut *ciphertext)?;
 
-            Ok(Crypto {
-                cipher: Cipher::Aes128Ctr(Aes128Ctr {
-                    iv,
-                }),
-                ciphertext: ciphertext.into_vec(),
-                kdf: Kdf::Pbkdf2(Pbkdf2 {
-                    dklen: ccrypto::KEY_LENGTH as u32,
-                    salt,
-                    c: iterations,
-                    prf: Prf::HmacSha256,
-                }),
-                mac: mac.into(),
-            })
-        } else {
-            Err(ccrypto::Error::ZeroIterations)
+        let mac = ccrypto::blake256(ccrypto::derive_mac(&derived_right_bits, &*ciphertext));
+
+        Ok(Crypto {
+            cipher: Cipher::Aes128Ctr(Aes128Ctr {
+                iv,
+            }),
+            ciphertext: ciphertext.into_vec(),
+            kdf,
+            mac: mac.into(),
+        })
+    }
+
+    /// Check that the key derivation function is allowed in the given version of the file.
+    pub fn check_version(&self, version: Version) -> Result<(), Error> {
+        match (version, &self.kdf) {
+            (Version::V4, Kdf::Argon2id(params)) => params.check_bounds().map_err(Error::InvalidKeyFile),
+            (Version::V4, _) => Err(Error::InvalidKeyFile("Version 4 key files must use Argon2id".to_string())),
+            (Version::V3, Kdf::Argon2id(_)) => {
+                Err(Error::InvalidKeyFile("Argon2id requires a version 4 key file".to_string()))
+            }
+            (Version::V3, _) => Ok(()),
         }
     }
 
@@ -134,16 +162,7 @@ impl Crypto {
     }
 
     fn do_decrypt(&self, password: &Password, expected_len: usize) -> Result<Vec<u8>, Error> {
-        let (derived_left_bits, derived_right_bits) = match self.kdf {
-            Kdf::Pbkdf2(ref params) => {
-                NonZeroU32::new(params.c).map_or(Err(ccrypto::Error::ZeroIterations), |non_zero_c| {
-                    Ok(ccrypto::derive_key_iterations(&password.as_crypto_password(), &params.salt, non_zero_c))
-                })?
-            }
-            Kdf::Scrypt(ref params) => {
-                ccrypto::scrypt::derive_key(&password.as_crypto_password(), &params.salt, params.n, params.p, params.r)?
-            }
-        };
+        let (derived_left_bits, derived_right_bits) = derive_key(&self.kdf, password)?;
 
         let mac = ccrypto::blake256(ccrypto::derive_mac(&derived_right_bits, &self.ciphertext));
 
@@ -166,16 +185,56 @@ impl Crypto {
     }
 }
 
+fn derive_key(kdf: &Kdf, password: &Password) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    match kdf {
+        Kdf::Pbkdf2(params) => NonZeroU32::new(params.c)
+            .map(|non_zero_c| ccrypto::derive_key_iterations(&password.as_crypto_password(), &params.salt, non_zero_c))
+            .ok_or_else(|| ccrypto::Error::ZeroIterations.into()),
+        Kdf::Scrypt(params) => {
+            Ok(ccrypto::scrypt::derive_key(&password.as_crypto_password(), &params.salt, params.n, params.p, params.r)?)
+        }
+        Kdf::Argon2id(params) => {
+            // The parameters come from a key file, so they are checked before allocating the memory.
+            params.check_bounds().map_err(Error::InvalidKeyFile)?;
+            let config = argon2::Config {
+                variant: argon2::Variant::Argon2id,
+                version: argon2::Version::Version13,
+                mem_cost: params.m,
+                time_cost: params.t,
+                lanes: params.p,
+                thread_mode: argon2::ThreadMode::Sequential,
+                secret: &[],
+                ad: &[],
+                hash_length: params.dklen,
+            };
+            let derived = argon2::hash_raw(password.as_crypto_password().0.as_bytes(), &params.salt, &config)
+                .map_err(|err| Error::Custom(format!("Argon2id failed: {}", err)))?;
+            let (left, right) = derived.split_at(derived.len() / 2);
+            Ok((left.to_vec(), right.to_vec()))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{Crypto, Error};
+    use super::{Crypto, Error, Kdf, KdfParams};
     use ckey::{Ed25519KeyPair as KeyPair, Generator, KeyPairTrait, Random};
 
+    const PBKDF2: KdfParams = KdfParams::Pbkdf2 {
+        iterations: 10240,
+    };
+
+    const ARGON2ID: KdfParams = KdfParams::Argon2id {
+        memory: 8 * 1024,
+        iterations: 1,
+        parallelism: 1,
+    };
+
     #[test]
     fn crypto_with_secret_create() {
         let keypair: KeyPair = Random.generate().unwrap();
         let private_key = keypair.private();
-        let crypto = Crypto::with_secret(keypair.private(), &"this is sparta".into(), 10240).unwrap();
+        let crypto = Crypto::with_secret(keypair.private(), &"this is sparta".into(), PBKDF2).unwrap();
         let secret = crypto.secret(&"this is sparta".into()).unwrap();
         assert_eq!(private_key, &secret);
     }
@@ -183,14 +242,14 @@ mod tests {
     #[test]
     fn crypto_with_secret_invalid_password() {
         let keypair: KeyPair = Random.generate().unwrap();
-        let crypto = Crypto::with_secret(keypair.private(), &"this is sparta".into(), 10240).unwrap();
+        let crypto = Crypto::with_secret(keypair.private(), &"this is sparta".into(), PBKDF2).unwrap();
         assert_matches!(crypto.secret(&"this is sparta!".into()), Err(Error::InvalidPassword))
     }
 
     #[test]
     fn crypto_with_null_plain_data() {
         let original_data = b"";
-        let crypto = Crypto::with_plain(&original_data[..], &"this is sparta".into(), 10240).unwrap();
+        let crypto = Crypto::with_plain(&original_data[..], &"this is sparta".into(), PBKDF2).unwrap();
         let decrypted_data = crypto.decrypt(&"this is sparta".into()).unwrap();
         assert_eq!(original_data[..], *decrypted_data);
     }
@@ -198,7 +257,7 @@ mod tests {
     #[test]
     fn crypto_with_tiny_plain_data() {
         let original_data = b"{}";
-        let crypto = Crypto::with_plain(&original_data[..], &"this is sparta".into(), 10240).unwrap();
+        let crypto = Crypto::with_plain(&original_data[..], &"this is sparta".into(), PBKDF2).unwrap();
         let decrypted_data = crypto.decrypt(&"this is sparta".into()).unwrap();
         assert_eq!(original_data[..], *decrypted_data);
     }
@@ -206,8 +265,33 @@ mod tests {
     #[test]
     fn crypto_with_huge_plain_data() {
         let original_data: Vec<_> = (1..65536).map(|i| (i % 256) as u8).collect();
-        let crypto = Crypto::with_plain(&original_data, &"this is sparta".into(), 10240).unwrap();
+        let crypto = Crypto::with_plain(&original_data, &"this is sparta".into(), PBKDF2).unwrap();
         let decrypted_data = crypto.decrypt(&"this is sparta".into()).unwrap();
         assert_eq!(&original_data, &decrypted_data);
     }
+
+    #[test]
+    fn crypto_with_argon2id() {
+        let keypair: KeyPair = Random.generate().unwrap();
+        let crypto = Crypto::with_secret(keypair.private(), &"this is sparta".into(), ARGON2ID).unwrap();
+        assert_matches!(crypto.kdf, Kdf::Argon2id(_));
+        assert_eq!(keypair.private(), &crypto.secret(&"this is sparta".into()).unwrap());
+        assert_matches!(crypto.secret(&"this is sparta!".into()), Err(Error::InvalidPassword));
+    }
+
+    #[test]
+    fn crypto_with_argon2id_out_of_bounds() {
+        let too_weak = KdfParams::Argon2id {
+            memory: 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        assert_matches!(Crypto::with_plain(b"{}", &"this is sparta".into(), too_weak), Err(Error::Custom(_)));
+
+        let mut crypto = Crypto::with_plain(b"{}", &"this is sparta".into(), ARGON2ID).unwrap();
+        if let Kdf::Argon2id(ref mut params) = crypto.kdf {
+            params.m = u32::max_value();
+        }
+        assert_matches!(crypto.decrypt(&"this is sparta".into()), Err(Error::InvalidKeyFile(_)));
+    }
 }
@@ -15,27 +15,55 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use ckey::{sign, Ed25519Private as Private, Ed25519Public as Public, Error as KeyError, Message, Signature};
+use std::sync::Arc;
+
+/// Signs with a private key which is kept outside of the process, e.g. in a hardware token.
+pub trait ExternalSigner: Send + Sync {
+    /// The public key of the private key.
+    fn public(&self) -> Public;
+    /// Sign a message with the private key.
+    fn sign(&self, message: &Message) -> Result<Signature, KeyError>;
+}
 
 /// An opaque wrapper for secret. The inner data of Private implements Drop trait to clear memory.
 #[derive(Clone)]
 pub struct DecryptedAccount {
-    secret: Private,
+    key: Key,
+}
+
+#[derive(Clone)]
+enum Key {
+    Secret(Private),
+    External(Arc<dyn ExternalSigner>),
 }
 
 impl DecryptedAccount {
     pub fn new(secret: Private) -> DecryptedAccount {
         DecryptedAccount {
-            secret,
+            key: Key::Secret(secret),
+        }
+    }
+
+    /// An account whose private key is used only through the signer.
+    pub fn external(signer: Arc<dyn ExternalSigner>) -> DecryptedAccount {
+        DecryptedAccount {
+            key: Key::External(signer),
         }
     }
 
     /// Sign a message.
     pub fn sign(&self, message: &Message) -> Result<Signature, KeyError> {
-        Ok(sign(message.as_ref(), &self.secret))
+        match &self.key {
+            Key::Secret(secret) => Ok(sign(message.as_ref(), secret)),
+            Key::External(signer) => signer.sign(message),
+        }
     }
 
     /// Derive public key.
     pub fn public(&self) -> Result<Public, KeyError> {
-        Ok(self.secret.public_key())
+        match &self.key {
+            Key::Secret(secret) => Ok(secret.public_key()),
+            Key::External(signer) => Ok(signer.public()),
+        }
     }
 }
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
+use super::Version;
 use crate::json;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -38,10 +39,95 @@ pub struct Scrypt {
     pub salt: [u8; 32],
 }
 
+/// Argon2id parameters. `m` is the memory cost in KiB, `t` the number of passes and `p` the degree of parallelism.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Argon2id {
+    pub dklen: u32,
+    pub m: u32,
+    pub t: u32,
+    pub p: u32,
+    pub salt: [u8; 32],
+}
+
+pub const ARGON2ID_MIN_MEMORY: u32 = 8 * 1024;
+pub const ARGON2ID_MAX_MEMORY: u32 = 4 * 1024 * 1024;
+pub const ARGON2ID_MIN_ITERATIONS: u32 = 1;
+pub const ARGON2ID_MAX_ITERATIONS: u32 = 64;
+pub const ARGON2ID_MIN_PARALLELISM: u32 = 1;
+pub const ARGON2ID_MAX_PARALLELISM: u32 = 16;
+
+impl Argon2id {
+    /// Reject the parameters that are too weak to protect a key or too expensive to derive one.
+    pub fn check_bounds(&self) -> Result<(), String> {
+        if self.dklen != ccrypto::KEY_LENGTH as u32 {
+            return Err(format!("Argon2id key length must be {}, got {}", ccrypto::KEY_LENGTH, self.dklen))
+        }
+        if self.m < ARGON2ID_MIN_MEMORY || self.m > ARGON2ID_MAX_MEMORY {
+            return Err(format!(
+                "Argon2id memory cost must be within {}..={} KiB, got {}",
+                ARGON2ID_MIN_MEMORY, ARGON2ID_MAX_MEMORY, self.m
+            ))
+        }
+        if self.t < ARGON2ID_MIN_ITERATIONS || self.t > ARGON2ID_MAX_ITERATIONS {
+            return Err(format!(
+                "Argon2id iterations must be within {}..={}, got {}",
+                ARGON2ID_MIN_ITERATIONS, ARGON2ID_MAX_ITERATIONS, self.t
+            ))
+        }
+        if self.p < ARGON2ID_MIN_PARALLELISM || self.p > ARGON2ID_MAX_PARALLELISM {
+            return Err(format!(
+                "Argon2id parallelism must be within {}..={}, got {}",
+                ARGON2ID_MIN_PARALLELISM, ARGON2ID_MAX_PARALLELISM, self.p
+            ))
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Kdf {
     Pbkdf2(Pbkdf2),
     Scrypt(Scrypt),
+    Argon2id(Argon2id),
+}
+
+/// The key derivation function used to encrypt new keys.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum KdfParams {
+    /// PBKDF2 with HMAC-SHA256, stored in a version 3 key file.
+    Pbkdf2 {
+        iterations: u32,
+    },
+    /// Argon2id, stored in a version 4 key file. `memory` is in KiB.
+    Argon2id {
+        memory: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams::Argon2id {
+            memory: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+        }
+    }
+}
+
+impl KdfParams {
+    /// The key file version that can hold the keys encrypted with these parameters.
+    pub fn version(&self) -> Version {
+        match self {
+            KdfParams::Pbkdf2 {
+                ..
+            } => Version::V3,
+            KdfParams::Argon2id {
+                ..
+            } => Version::V4,
+        }
+    }
 }
 
 impl From<json::Prf> for Prf {
@@ -106,11 +192,36 @@ impl From<Scrypt> for json::Scrypt {
     }
 }
 
+impl From<json::Argon2id> for Argon2id {
+    fn from(json: json::Argon2id) -> Self {
+        Argon2id {
+            dklen: json.dklen,
+            m: json.m,
+            t: json.t,
+            p: json.p,
+            salt: json.salt.into(),
+        }
+    }
+}
+
+impl From<Argon2id> for json::Argon2id {
+    fn from(a: Argon2id) -> Self {
+        Self {
+            dklen: a.dklen,
+            m: a.m,
+            t: a.t,
+            p: a.p,
+            salt: From::from(a.salt),
+        }
+    }
+}
+
 impl From<json::Kdf> for Kdf {
     fn from(json: json::Kdf) -> Self {
         match json {
             json::Kdf::Pbkdf2(params) => Kdf::Pbkdf2(From::from(params)),
             json::Kdf::Scrypt(params) => Kdf::Scrypt(From::from(params)),
+            json::Kdf::Argon2id(params) => Kdf::Argon2id(From::from(params)),
         }
     }
 }
@@ -120,6 +231,7 @@ impl From<Kdf> for json::Kdf {
         match kdf {
             Kdf::Pbkdf2(params) => json::Kdf::Pbkdf2(params.into()),
             Kdf::Scrypt(params) => json::Kdf::Scrypt(params.into()),
+            Kdf::Argon2id(params) => json::Kdf::Argon2id(params.into()),
         }
     }
 }
@@ -19,11 +19,13 @@ mod crypto;
 mod decrypted_account;
 mod kdf;
 mod safe_account;
+mod seed;
 mod version;
 
 pub use self::cipher::{Aes128Ctr, Cipher};
 pub use self::crypto::Crypto;
-pub use self::decrypted_account::DecryptedAccount;
-pub use self::kdf::{Kdf, Pbkdf2, Prf, Scrypt};
+pub use self::decrypted_account::{DecryptedAccount, ExternalSigner};
+pub use self::kdf::{Argon2id, Kdf, KdfParams, Pbkdf2, Prf, Scrypt};
 pub use self::safe_account::SafeAccount;
+pub use self::seed::EncryptedSeed;
 pub use self::version::Version;
@@ -16,9 +16,10 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::crypto::Crypto;
-use crate::account::Version;
+use crate::account::{KdfParams, Version};
 use crate::{json, DecryptedAccount, Error};
-use ckey::{Ed25519KeyPair as KeyPair, Ed25519Public as Public, KeyPairTrait, Password};
+use ckey::{Ed25519KeyPair as KeyPair, Ed25519Private as Private, Ed25519Public as Public, KeyPairTrait, Password};
+use std::convert::TryFrom;
 
 /// Account representation.
 #[derive(Debug, PartialEq, Clone)]
@@ -30,24 +31,29 @@ pub struct SafeAccount {
     /// Account public key
     pub pubkey: Public,
     /// Account private key derivation definition.
-    pub crypto: Crypto,
+    /// `None` if the private key is kept in the key directory, e.g. in a hardware token.
+    pub crypto: Option<Crypto>,
     /// Account filename
     pub filename: Option<String>,
     /// Account metadata
     pub meta: String,
 }
 
-impl From<SafeAccount> for json::KeyFile {
-    fn from(account: SafeAccount) -> Self {
+/// Only the accounts with the encrypted private key can be saved as key files.
+impl TryFrom<SafeAccount> for json::KeyFile {
+    type Error = Error;
+
+    fn try_from(account: SafeAccount) -> Result<Self, Self::Error> {
+        let crypto = account.crypto.ok_or(Error::UnavailableSecret)?;
         let mut pubkey: [u8; 32] = [0; 32];
         pubkey.copy_from_slice(account.pubkey.as_ref());
-        Self {
+        Ok(Self {
             id: From::from(account.id),
             version: account.version.into(),
             pubkey: Some(pubkey.into()),
-            crypto: account.crypto.into(),
+            crypto: crypto.into(),
             meta: Some(account.meta),
-        }
+        })
     }
 }
 
@@ -57,13 +63,13 @@ impl SafeAccount {
         keypair: &KeyPair,
         id: [u8; 16],
         password: &Password,
-        iterations: u32,
+        kdf: KdfParams,
         meta: String,
-    ) -> Result<Self, ccrypto::Error> {
+    ) -> Result<Self, Error> {
         Ok(SafeAccount {
             id,
-            version: Version::V3,
-            crypto: Crypto::with_secret(keypair.private(), password, iterations)?,
+            version: kdf.version(),
+            crypto: Some(Crypto::with_secret(keypair.private(), password, kdf)?),
             pubkey: *keypair.public(),
             filename: None,
             meta,
@@ -78,7 +84,9 @@ impl SafeAccount {
         filename: Option<String>,
         password: Option<&Password>,
     ) -> Result<Self, Error> {
+        let version = Version::from(json.version);
         let crypto = Crypto::from(json.crypto);
+        crypto.check_version(version)?;
         let pubkey = match (json.pubkey, password) {
             (Some(raw_pubkey), Some(password)) => {
                 let pubkey = Public::from_slice(raw_pubkey.as_ref()).ok_or(Error::InvalidSecret)?;
@@ -98,9 +106,9 @@ impl SafeAccount {
 
         Ok(SafeAccount {
             id: json.id.into(),
-            version: json.version.into(),
+            version,
             pubkey,
-            crypto,
+            crypto: Some(crypto),
             filename,
             meta: json.meta.unwrap_or_else(|| "{}".to_string()),
         })
@@ -111,13 +119,13 @@ impl SafeAccount {
         &self,
         old_password: &Password,
         new_password: &Password,
-        iterations: u32,
+        kdf: KdfParams,
     ) -> Result<Self, Error> {
-        let secret = self.crypto.secret(old_password)?;
+        let secret = self.secret(old_password)?;
         let result = SafeAccount {
             id: self.id,
-            version: self.version,
-            crypto: Crypto::with_secret(&secret, new_password, iterations)?,
+            version: kdf.version(),
+            crypto: Some(Crypto::with_secret(&secret, new_password, kdf)?),
             pubkey: self.pubkey,
             filename: self.filename.clone(),
             meta: self.meta.clone(),
@@ -125,13 +133,26 @@ impl SafeAccount {
         Ok(result)
     }
 
+    /// Encrypt the private key again with the given key derivation function, keeping the password.
+    pub fn re_encrypt(&self, password: &Password, kdf: KdfParams) -> Result<Self, Error> {
+        self.change_password(password, password, kdf)
+    }
+
     /// Check if password matches the account.
     pub fn check_password(&self, password: &Password) -> bool {
         self.decrypt(password).is_ok()
     }
 
     pub fn decrypt(&self, password: &Password) -> Result<DecryptedAccount, Error> {
-        Ok(DecryptedAccount::new(self.crypto.secret(password)?))
+        Ok(DecryptedAccount::new(self.secret(password)?))
+    }
+
+    /// Decrypt the private key.
+    pub fn secret(&self, password: &Password) -> Result<Private, Error> {
+        match &self.crypto {
+            Some(crypto) => crypto.secret(password),
+            None => Err(Error::UnavailableSecret),
+        }
     }
 }
 
@@ -146,13 +167,23 @@ mod tests {
 
     use super::*;
 
+    const PBKDF2: KdfParams = KdfParams::Pbkdf2 {
+        iterations: 10240,
+    };
+
+    const ARGON2ID: KdfParams = KdfParams::Argon2id {
+        memory: 8 * 1024,
+        iterations: 1,
+        parallelism: 1,
+    };
+
     #[test]
     fn sign_and_verify_public() {
         let keypair = Random.generate().unwrap();
         let password = &"hello world".into();
         let message = Message::default();
         let account =
-            SafeAccount::create(&keypair, [0u8; 16], password, 10240, "{\"name\":\"Test\"}".to_string()).unwrap();
+            SafeAccount::create(&keypair, [0u8; 16], password, PBKDF2, "{\"name\":\"Test\"}".to_string()).unwrap();
         let signature = account.decrypt(password).unwrap().sign(&message).unwrap();
         assert!(verify(&signature, message.as_ref(), keypair.public()));
     }
@@ -162,10 +193,10 @@ mod tests {
         let keypair = Random.generate().unwrap();
         let first_password = &"hello world".into();
         let sec_password = &"this is sparta".into();
-        let i = 10240;
         let account =
-            SafeAccount::create(&keypair, [0u8; 16], first_password, i, "{\"name\":\"Test\"}".to_string()).unwrap();
-        let new_account = account.change_password(first_password, sec_password, i).unwrap();
+            SafeAccount::create(&keypair, [0u8; 16], first_password, PBKDF2, "{\"name\":\"Test\"}".to_string())
+                .unwrap();
+        let new_account = account.change_password(first_password, sec_password, PBKDF2).unwrap();
         assert!(account.check_password(first_password));
         assert!(!account.check_password(sec_password));
         assert!(!new_account.check_password(first_password));
@@ -201,4 +232,54 @@ mod tests {
         assert_eq!(Public::from_str(pubkey), Ok(safe_account.pubkey));
         assert_eq!(meta, safe_account.meta);
     }
+
+    #[test]
+    fn re_encrypt_upgrades_to_v4() {
+        let keypair = Random.generate().unwrap();
+        let password = &"hello world".into();
+        let mut account =
+            SafeAccount::create(&keypair, [1u8; 16], password, PBKDF2, "{\"name\":\"Test\"}".to_string()).unwrap();
+        account.filename = Some("key".to_string());
+        assert_eq!(Version::V3, account.version);
+
+        let upgraded = account.re_encrypt(password, ARGON2ID).unwrap();
+        assert_eq!(Version::V4, upgraded.version);
+        assert_matches!(upgraded.crypto.as_ref().map(|crypto| &crypto.kdf), Some(crate::account::Kdf::Argon2id(_)));
+        assert_eq!(account.id, upgraded.id);
+        assert_eq!(account.pubkey, upgraded.pubkey);
+        assert_eq!(account.filename, upgraded.filename);
+        assert_eq!(account.meta, upgraded.meta);
+        assert_eq!(account.secret(password).unwrap(), upgraded.secret(password).unwrap());
+
+        let json = KeyFile::try_from(upgraded).unwrap();
+        assert_eq!(json::Version::V4, json.version);
+        let loaded = SafeAccount::from_file(json, None, Some(password)).unwrap();
+        assert_eq!(account.pubkey, loaded.pubkey);
+    }
+
+    #[test]
+    fn from_file_rejects_v4_out_of_bounds() {
+        let keypair = Random.generate().unwrap();
+        let password = &"hello world".into();
+        let account = SafeAccount::create(&keypair, [1u8; 16], password, ARGON2ID, "{}".to_string()).unwrap();
+
+        let mut json = KeyFile::try_from(account.clone()).unwrap();
+        if let json::Kdf::Argon2id(ref mut params) = json.crypto.kdf {
+            params.t = 0;
+        }
+        assert_matches!(SafeAccount::from_file(json, None, None), Err(Error::InvalidKeyFile(_)));
+
+        let mut json = KeyFile::try_from(account).unwrap();
+        json.version = json::Version::V3;
+        assert_matches!(SafeAccount::from_file(json, None, None), Err(Error::InvalidKeyFile(_)));
+    }
+
+    #[test]
+    fn account_without_crypto_is_not_a_key_file() {
+        let keypair = Random.generate().unwrap();
+        let mut account =
+            SafeAccount::create(&keypair, [1u8; 16], &"hello world".into(), PBKDF2, "{}".to_string()).unwrap();
+        account.crypto = None;
+        assert_matches!(KeyFile::try_from(account), Err(Error::UnavailableSecret));
+    }
 }
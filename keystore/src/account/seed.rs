@@ -0,0 +1,146 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::crypto::Crypto;
+use crate::account::{KdfParams, Version};
+use crate::{json, Error};
+use ckey::{DerivationPath, Ed25519KeyPair as KeyPair, ExtendedPrivate, KeyPairTrait, Password};
+
+/// The seed of an HD wallet, encrypted like the private key of an account.
+#[derive(Debug, PartialEq, Clone)]
+pub struct EncryptedSeed {
+    /// Seed ID
+    pub id: [u8; 16],
+    /// Seed file version
+    pub version: Version,
+    /// Encrypted seed
+    pub crypto: Crypto,
+    /// Seed metadata
+    pub meta: String,
+}
+
+impl From<EncryptedSeed> for json::SeedFile {
+    fn from(seed: EncryptedSeed) -> Self {
+        Self {
+            id: From::from(seed.id),
+            version: seed.version.into(),
+            crypto: seed.crypto.into(),
+            meta: Some(seed.meta),
+        }
+    }
+}
+
+impl EncryptedSeed {
+    /// Encrypt a new seed
+    pub fn create(seed: &[u8], id: [u8; 16], password: &Password, kdf: KdfParams, meta: String) -> Result<Self, Error> {
+        Ok(EncryptedSeed {
+            id,
+            version: kdf.version(),
+            crypto: Crypto::with_plain(seed, password, kdf)?,
+            meta,
+        })
+    }
+
+    pub fn from_file(json: json::SeedFile) -> Result<Self, Error> {
+        let version = Version::from(json.version);
+        let crypto = Crypto::from(json.crypto);
+        crypto.check_version(version)?;
+        Ok(EncryptedSeed {
+            id: json.id.into(),
+            version,
+            crypto,
+            meta: json.meta.unwrap_or_else(|| "{}".to_string()),
+        })
+    }
+
+    /// Identifies a seed without revealing it.
+    pub fn fingerprint(seed: &[u8]) -> String {
+        hex::encode(ccrypto::blake256(seed))
+    }
+
+    /// Whether this is the seed of the fingerprint.
+    /// A seed whose metadata has no fingerprint is decrypted with the password to be compared.
+    pub fn has_fingerprint(&self, fingerprint: &str, password: &Password) -> bool {
+        let meta: serde_json::Value = serde_json::from_str(&self.meta).unwrap_or_default();
+        if let Some(known) = meta.get("fingerprint").and_then(serde_json::Value::as_str) {
+            return known == fingerprint
+        }
+        match self.crypto.decrypt(password) {
+            Ok(mut seed) => {
+                let matched = Self::fingerprint(&seed) == fingerprint;
+                for byte in seed.iter_mut() {
+                    *byte = 0;
+                }
+                matched
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Decrypt the seed and derive the key of the path with SLIP-0010.
+    pub fn derive(&self, path: &DerivationPath, password: &Password) -> Result<KeyPair, Error> {
+        let mut seed = self.crypto.decrypt(password)?;
+        let key = ExtendedPrivate::from_seed(&seed).derive_path(path);
+        for byte in seed.iter_mut() {
+            *byte = 0;
+        }
+        Ok(KeyPair::from_private(key.private()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derive_after_reading_the_file() {
+        let seed = ckey::mnemonic_to_seed(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            "",
+        )
+        .unwrap();
+        let password = &"password".into();
+        let kdf = KdfParams::Pbkdf2 {
+            iterations: 1024,
+        };
+        let encrypted = EncryptedSeed::create(&seed, [1u8; 16], password, kdf, "{}".to_string()).unwrap();
+        let loaded = EncryptedSeed::from_file(encrypted.clone().into()).unwrap();
+        assert_eq!(encrypted, loaded);
+
+        let path = "m/44'/0'/0'".parse().unwrap();
+        let expected = ExtendedPrivate::from_seed(&seed).derive_path(&path).public();
+        assert_eq!(&expected, loaded.derive(&path, password).unwrap().public());
+        assert_matches!(loaded.derive(&path, &"wrong".into()), Err(Error::InvalidPassword));
+    }
+
+    #[test]
+    fn fingerprint_identifies_the_seed() {
+        let seed = [1u8; 64];
+        let password = &"password".into();
+        let kdf = KdfParams::Pbkdf2 {
+            iterations: 1024,
+        };
+        let fingerprint = EncryptedSeed::fingerprint(&seed);
+        let meta = serde_json::json!({ "fingerprint": fingerprint }).to_string();
+        let with_fingerprint = EncryptedSeed::create(&seed, [1u8; 16], password, kdf, meta).unwrap();
+        let without_fingerprint = EncryptedSeed::create(&seed, [2u8; 16], password, kdf, "{}".to_string()).unwrap();
+
+        assert!(with_fingerprint.has_fingerprint(&fingerprint, &"wrong".into()));
+        assert!(without_fingerprint.has_fingerprint(&fingerprint, password));
+        assert!(!without_fingerprint.has_fingerprint(&fingerprint, &"wrong".into()));
+        assert!(!with_fingerprint.has_fingerprint(&EncryptedSeed::fingerprint(&[2u8; 64]), password));
+    }
+}
@@ -19,12 +19,14 @@ use crate::json;
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Version {
     V3,
+    V4,
 }
 
 impl From<json::Version> for Version {
     fn from(json: json::Version) -> Self {
         match json {
             json::Version::V3 => Version::V3,
+            json::Version::V4 => Version::V4,
         }
     }
 }
@@ -33,6 +35,7 @@ impl From<Version> for json::Version {
     fn from(version: Version) -> Self {
         match version {
             Version::V3 => json::Version::V3,
+            Version::V4 => json::Version::V4,
         }
     }
 }
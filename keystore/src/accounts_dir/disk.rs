@@ -15,16 +15,19 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use super::KeyDirectory;
+use super::{KeyDirectory, SeedStore};
 use crate::json::Uuid;
-use crate::{json, Error, SafeAccount};
+use crate::{json, EncryptedSeed, Error, SafeAccount};
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::ffi::OsStr;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::{fs, io};
 
 const IGNORED_FILES: &[&str] = &["thumbs.db"];
+/// The subdirectory keeping the seeds of HD wallets
+const SEEDS_DIR: &str = "seeds";
 
 #[cfg(not(windows))]
 fn restrict_permissions_to_owner(file_path: &Path) -> Result<(), i32> {
@@ -43,6 +46,43 @@ fn restrict_permissions_to_owner(_file_path: &Path) -> Result<(), i32> {
     Ok(())
 }
 
+#[cfg(not(windows))]
+fn create_owner_only(file_path: &Path) -> io::Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(file_path)
+}
+
+#[cfg(windows)]
+fn create_owner_only(file_path: &Path) -> io::Result<fs::File> {
+    fs::File::create(file_path)
+}
+
+/// Write a file that only the owner can read. The content is written to a hidden file first and renamed over the
+/// file, so an interrupted write never leaves a truncated file behind.
+fn write_owner_only<F>(dir: &Path, filename: &str, write: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut fs::File) -> Result<(), Error>, {
+    let path = dir.join(filename);
+    let temp_path = dir.join(format!(".{}.tmp", filename));
+    {
+        // save the file
+        let mut file = create_owner_only(&temp_path)?;
+
+        write(&mut file)?;
+
+        file.flush()?;
+
+        restrict_permissions_to_owner(temp_path.as_path()).map_err(|_| Error::Io(io::Error::last_os_error()))?;
+
+        file.sync_all()?;
+    }
+    fs::rename(&temp_path, &path).map_err(|err| {
+        let _ = fs::remove_file(&temp_path);
+        err.into()
+    })
+}
+
 /// Root keys directory implementation
 pub type RootDiskDirectory = DiskDirectory<DiskKeyFileManager>;
 
@@ -168,34 +208,21 @@ where
         mut filename: String,
         dedup: bool,
     ) -> Result<SafeAccount, Error> {
-        // path to keyfile
-        let mut keyfile_path = self.path.join(filename.as_str());
-
         // check for duplicate filename and append random suffix
-        if dedup && keyfile_path.exists() {
+        if dedup && self.path.join(filename.as_str()).exists() {
             let suffix = crate::random::random_string(4);
             filename.push_str(&format!("-{}", suffix));
-            keyfile_path.set_file_name(&filename);
         }
 
         // update account filename
         let original_account = account.clone();
         let mut account = account;
-        account.filename = Some(filename);
-
-        {
-            // save the file
-            let mut file = fs::File::create(&keyfile_path)?;
+        account.filename = Some(filename.clone());
 
+        write_owner_only(&self.path, &filename, |file| {
             // write key content
-            self.key_manager.write(original_account, &mut file).map_err(|e| Error::Custom(format!("{:?}", e)))?;
-
-            file.flush()?;
-
-            restrict_permissions_to_owner(keyfile_path.as_path()).map_err(|_| Error::Io(io::Error::last_os_error()))?;
-
-            file.sync_all()?;
-        }
+            self.key_manager.write(original_account, file).map_err(|e| Error::Custom(format!("{:?}", e)))
+        })?;
 
         Ok(account)
     }
@@ -248,6 +275,45 @@ where
     fn unique_repr(&self) -> Result<u64, Error> {
         self.last_modification_date()
     }
+
+    fn as_seed_store(&self) -> Option<&dyn SeedStore> {
+        Some(self)
+    }
+}
+
+impl<T> SeedStore for DiskDirectory<T>
+where
+    T: KeyFileManager,
+{
+    fn load_seeds(&self) -> Result<Vec<EncryptedSeed>, Error> {
+        let seeds_path = self.path.join(SEEDS_DIR);
+        if !seeds_path.exists() {
+            return Ok(Vec::new())
+        }
+        Ok(fs::read_dir(&seeds_path)?
+            .flat_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| !path.file_name().and_then(OsStr::to_str).map_or(true, |name| name.starts_with('.')))
+            .filter_map(|path| {
+                fs::File::open(&path)
+                    .map_err(Into::into)
+                    .and_then(|file| json::SeedFile::load(file).map_err(|e| Error::InvalidKeyFile(format!("{:?}", e))))
+                    .and_then(EncryptedSeed::from_file)
+                    .map_err(|e| warn!("Invalid seed file: {:?} ({})", path, e))
+                    .ok()
+            })
+            .collect())
+    }
+
+    fn insert_seed(&self, seed: EncryptedSeed) -> Result<(), Error> {
+        let seeds_path = self.path.join(SEEDS_DIR);
+        fs::create_dir_all(&seeds_path)?;
+        let filename = Uuid::from(seed.id).to_string();
+        let seed_file: json::SeedFile = seed.into();
+        write_owner_only(&seeds_path, &filename, |file| {
+            seed_file.write(file).map_err(|e| Error::Custom(format!("{:?}", e)))
+        })
+    }
 }
 
 impl KeyFileManager for DiskKeyFileManager {
@@ -261,7 +327,7 @@ impl KeyFileManager for DiskKeyFileManager {
     fn write<T>(&self, account: SafeAccount, writer: &mut T) -> Result<(), Error>
     where
         T: io::Write, {
-        let key_file: json::KeyFile = account.into();
+        let key_file = json::KeyFile::try_from(account)?;
         key_file.write(writer).map_err(|e| Error::Custom(format!("{:?}", e)))
     }
 }
@@ -277,10 +343,15 @@ fn account_filename(account: &SafeAccount) -> String {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::account::{KdfParams, Version};
     use ckey::{Generator, Random};
     use std::env;
     use tempdir::TempDir;
 
+    const KDF: KdfParams = KdfParams::Pbkdf2 {
+        iterations: 1024,
+    };
+
     #[test]
     fn create_new_account() {
         // given
@@ -292,7 +363,7 @@ mod test {
         let directory = RootDiskDirectory::create(dir.clone()).unwrap();
 
         // when
-        let account = SafeAccount::create(&keypair, [0u8; 16], password, 1024, "{\"name\":\"Test\"}".to_string());
+        let account = SafeAccount::create(&keypair, [0u8; 16], password, KDF, "{\"name\":\"Test\"}".to_string());
         let res = directory.insert(account.unwrap());
 
         // then
@@ -315,7 +386,7 @@ mod test {
 
         // when
         let account =
-            SafeAccount::create(&keypair, [0u8; 16], password, 1024, "{\"name\":\"Test\"}".to_string()).unwrap();
+            SafeAccount::create(&keypair, [0u8; 16], password, KDF, "{\"name\":\"Test\"}".to_string()).unwrap();
         let filename = "test".to_string();
         let dedup = true;
 
@@ -347,11 +418,50 @@ mod test {
 
         let keypair = Random.generate().unwrap();
         let password = &"test pass".into();
-        let account = SafeAccount::create(&keypair, [0u8; 16], password, 1024, "{\"name\":\"Test\"}".to_string());
+        let account = SafeAccount::create(&keypair, [0u8; 16], password, KDF, "{\"name\":\"Test\"}".to_string());
         directory.insert(account.unwrap()).expect("Account should be inserted ok");
 
         let new_hash = directory.files_hash().expect("New files hash should be calculated ok");
 
         assert!(new_hash != hash, "hash of the file list should change once directory content changed");
     }
+
+    #[test]
+    fn update_replaces_the_key_file() {
+        let temp_path = TempDir::new("").unwrap();
+        let directory = RootDiskDirectory::create(&temp_path).unwrap();
+
+        let keypair = Random.generate().unwrap();
+        let password = &"test pass".into();
+        let account = SafeAccount::create(&keypair, [0u8; 16], password, KDF, "{}".to_string()).unwrap();
+        let account = directory.insert(account).unwrap();
+
+        let argon2id = KdfParams::Argon2id {
+            memory: 8 * 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let upgraded = directory.update(account.re_encrypt(password, argon2id).unwrap()).unwrap();
+        assert_eq!(account.filename, upgraded.filename);
+
+        let files = fs::read_dir(&temp_path).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(files.len(), 1, "No temporary file is left");
+        let loaded = directory.load().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].version, Version::V4);
+        assert!(loaded[0].check_password(password));
+    }
+
+    #[test]
+    fn seeds_are_kept_apart_from_the_keys() {
+        let temp_path = TempDir::new("").unwrap();
+        let directory = RootDiskDirectory::create(&temp_path).unwrap();
+        assert_eq!(directory.load_seeds().unwrap(), vec![]);
+
+        let seed = EncryptedSeed::create(&[7u8; 64], [1u8; 16], &"test pass".into(), KDF, "{}".to_string()).unwrap();
+        directory.insert_seed(seed.clone()).unwrap();
+
+        assert_eq!(directory.load_seeds().unwrap(), vec![seed]);
+        assert_eq!(directory.load().unwrap(), vec![]);
+    }
 }
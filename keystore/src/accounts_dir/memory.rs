@@ -14,8 +14,8 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use super::KeyDirectory;
-use crate::{Error, SafeAccount};
+use super::{KeyDirectory, SeedStore};
+use crate::{EncryptedSeed, Error, SafeAccount};
 use ckey::Ed25519Public as Public;
 use parking_lot::RwLock;
 use std::collections::HashMap;
@@ -25,6 +25,7 @@ use std::convert::TryInto;
 #[derive(Default)]
 pub struct MemoryDirectory {
     accounts: RwLock<HashMap<Public, Vec<SafeAccount>>>,
+    seeds: RwLock<Vec<EncryptedSeed>>,
 }
 
 impl KeyDirectory for MemoryDirectory {
@@ -76,4 +77,19 @@ impl KeyDirectory for MemoryDirectory {
         }
         Ok(val)
     }
+
+    fn as_seed_store(&self) -> Option<&dyn SeedStore> {
+        Some(self)
+    }
+}
+
+impl SeedStore for MemoryDirectory {
+    fn load_seeds(&self) -> Result<Vec<EncryptedSeed>, Error> {
+        Ok(self.seeds.read().clone())
+    }
+
+    fn insert_seed(&self, seed: EncryptedSeed) -> Result<(), Error> {
+        self.seeds.write().push(seed);
+        Ok(())
+    }
 }
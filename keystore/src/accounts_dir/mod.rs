@@ -16,11 +16,14 @@
 
 //! Accounts Directory
 
-use crate::{Error, SafeAccount};
+use crate::{EncryptedSeed, Error, ExternalSigner, SafeAccount};
+use ckey::Password;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 mod disk;
 mod memory;
+mod pkcs11;
 
 /// `VaultKeyDirectory::set_key` error
 #[derive(Debug)]
@@ -49,7 +52,35 @@ pub trait KeyDirectory: Send + Sync {
     }
     /// Unique representation of directory account collection
     fn unique_repr(&self) -> Result<u64, Error>;
+    /// Return the key holder if the private keys never leave the directory
+    fn as_key_holder(&self) -> Option<&dyn KeyHolder> {
+        None
+    }
+    /// Return the seed store if the directory keeps the seeds of HD wallets
+    fn as_seed_store(&self) -> Option<&dyn SeedStore> {
+        None
+    }
+}
+
+/// A key directory which keeps the private keys by itself, e.g. a PKCS#11 token.
+///
+/// Its accounts have no encrypted private key. The password of an account is the one of the directory,
+/// e.g. the user PIN of the token, and the private key is used only through the signer.
+pub trait KeyHolder: Send + Sync {
+    /// Generate a new key in the directory
+    fn generate(&self, password: &Password) -> Result<SafeAccount, Error>;
+    /// Check the password and get the signer with the private key of the account
+    fn signer(&self, account: &SafeAccount, password: &Password) -> Result<Arc<dyn ExternalSigner>, Error>;
+}
+
+/// A key directory which also keeps the encrypted seeds of HD wallets.
+pub trait SeedStore: Send + Sync {
+    /// Read the seeds from the directory
+    fn load_seeds(&self) -> Result<Vec<EncryptedSeed>, Error>;
+    /// Insert a new seed to the directory
+    fn insert_seed(&self, seed: EncryptedSeed) -> Result<(), Error>;
 }
 
 pub use self::disk::{DiskKeyFileManager, KeyFileManager, RootDiskDirectory};
 pub use self::memory::MemoryDirectory;
+pub use self::pkcs11::Pkcs11Directory;
@@ -0,0 +1,315 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A key directory backed by a PKCS#11 token, e.g. an HSM or SoftHSM.
+//!
+//! The Ed25519 keys are generated in the token and never leave it. The public keys are read without logging in,
+//! and the user PIN is the password of every account in the token.
+
+use super::{KeyDirectory, KeyHolder};
+use crate::account::Version;
+use crate::random::Random;
+use crate::{Error, ExternalSigner, SafeAccount};
+use ckey::{Ed25519Public as Public, Error as KeyError, Message, Password, Signature};
+use parking_lot::Mutex;
+use pkcs11::errors::Error as Pkcs11Error;
+use pkcs11::types::*;
+use pkcs11::Ctx;
+use std::convert::TryInto;
+use std::path::Path;
+use std::ptr;
+use std::sync::Arc;
+
+// The Ed25519 values defined in PKCS#11 v3.0.
+const CKK_EC_EDWARDS: CK_KEY_TYPE = 0x40;
+const CKM_EC_EDWARDS_KEY_PAIR_GEN: CK_MECHANISM_TYPE = 0x1055;
+const CKM_EDDSA: CK_MECHANISM_TYPE = 0x1057;
+/// The DER encoded OID of Ed25519, 1.3.101.112.
+const ED25519_PARAMS: [u8; 5] = [0x06, 0x03, 0x2b, 0x65, 0x70];
+
+const FIND_BATCH_SIZE: CK_ULONG = 64;
+
+/// Accounts in a PKCS#11 token.
+pub struct Pkcs11Directory {
+    token: Arc<Token>,
+}
+
+impl Pkcs11Directory {
+    /// Open the token with the given label using the PKCS#11 library at `library`.
+    pub fn open<P: AsRef<Path>>(library: P, token_label: &str) -> Result<Self, Error> {
+        let ctx = Ctx::new_and_initialize(library.as_ref()).map_err(pkcs11_error)?;
+        let mut found = None;
+        for slot in ctx.get_slot_list(true).map_err(pkcs11_error)? {
+            let info = ctx.get_token_info(slot).map_err(pkcs11_error)?;
+            if String::from_utf8_lossy(&info.label).trim_end() == token_label {
+                found = Some(slot);
+                break
+            }
+        }
+        let slot = found.ok_or_else(|| Error::Pkcs11(format!("There is no token labeled {}", token_label)))?;
+        let session = ctx.open_session(slot, CKF_SERIAL_SESSION | CKF_RW_SESSION, None, None).map_err(pkcs11_error)?;
+        Ok(Self {
+            token: Arc::new(Token {
+                session: Mutex::new(Session {
+                    ctx,
+                    handle: session,
+                }),
+                pin: Mutex::new(None),
+            }),
+        })
+    }
+}
+
+impl KeyDirectory for Pkcs11Directory {
+    fn load(&self) -> Result<Vec<SafeAccount>, Error> {
+        Ok(self.token.session.lock().public_keys()?.into_iter().map(TokenKey::into_account).collect())
+    }
+
+    fn update(&self, _account: SafeAccount) -> Result<SafeAccount, Error> {
+        Err(Error::Custom("The accounts in a PKCS#11 token cannot be modified".to_string()))
+    }
+
+    fn insert(&self, _account: SafeAccount) -> Result<SafeAccount, Error> {
+        Err(Error::Custom("A key cannot be imported into a PKCS#11 token. Generate a new one in the token".to_string()))
+    }
+
+    fn remove(&self, account: &SafeAccount) -> Result<(), Error> {
+        if self.token.pin.lock().is_none() {
+            return Err(Error::Custom("Unlock an account in the PKCS#11 token to remove a key".to_string()))
+        }
+        let session = self.token.session.lock();
+        let key = session.public_key(&account.pubkey)?;
+        for class in &[CKO_PRIVATE_KEY, CKO_PUBLIC_KEY] {
+            for object in session.find(&key.id, *class)? {
+                session.ctx.destroy_object(session.handle, object).map_err(pkcs11_error)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn unique_repr(&self) -> Result<u64, Error> {
+        let mut val = 0u64;
+        for key in self.token.session.lock().public_keys()? {
+            let bytes = key.public.as_ref();
+            val ^= u64::from_be_bytes(bytes[0..8].try_into().unwrap());
+            val ^= u64::from_be_bytes(bytes[8..16].try_into().unwrap());
+            val ^= u64::from_be_bytes(bytes[16..24].try_into().unwrap());
+            val ^= u64::from_be_bytes(bytes[24..32].try_into().unwrap());
+        }
+        Ok(val)
+    }
+
+    fn as_key_holder(&self) -> Option<&dyn KeyHolder> {
+        Some(self)
+    }
+}
+
+impl KeyHolder for Pkcs11Directory {
+    fn generate(&self, password: &Password) -> Result<SafeAccount, Error> {
+        self.token.login(password)?;
+        let session = self.token.session.lock();
+        let id: [u8; 16] = Random::random();
+        let mechanism = CK_MECHANISM {
+            mechanism: CKM_EC_EDWARDS_KEY_PAIR_GEN,
+            pParameter: ptr::null_mut(),
+            ulParameterLen: 0,
+        };
+        let public_template = vec![
+            CK_ATTRIBUTE::new(CKA_TOKEN).with_bool(&CK_TRUE),
+            CK_ATTRIBUTE::new(CKA_VERIFY).with_bool(&CK_TRUE),
+            CK_ATTRIBUTE::new(CKA_EC_PARAMS).with_bytes(&ED25519_PARAMS),
+            CK_ATTRIBUTE::new(CKA_ID).with_bytes(&id),
+        ];
+        let private_template = vec![
+            CK_ATTRIBUTE::new(CKA_TOKEN).with_bool(&CK_TRUE),
+            CK_ATTRIBUTE::new(CKA_PRIVATE).with_bool(&CK_TRUE),
+            CK_ATTRIBUTE::new(CKA_SENSITIVE).with_bool(&CK_TRUE),
+            CK_ATTRIBUTE::new(CKA_EXTRACTABLE).with_bool(&CK_FALSE),
+            CK_ATTRIBUTE::new(CKA_SIGN).with_bool(&CK_TRUE),
+            CK_ATTRIBUTE::new(CKA_ID).with_bytes(&id),
+        ];
+        let (public_key, _) = session
+            .ctx
+            .generate_key_pair(session.handle, &mechanism, &public_template, &private_template)
+            .map_err(pkcs11_error)?;
+        Ok(session.token_key(public_key)?.into_account())
+    }
+
+    fn signer(&self, account: &SafeAccount, password: &Password) -> Result<Arc<dyn ExternalSigner>, Error> {
+        self.token.login(password)?;
+        let session = self.token.session.lock();
+        let key = session.public_key(&account.pubkey)?;
+        let private_key = session.find(&key.id, CKO_PRIVATE_KEY)?.into_iter().next().ok_or(Error::InvalidAccount)?;
+        Ok(Arc::new(Pkcs11Signer {
+            token: Arc::clone(&self.token),
+            private_key,
+            public: key.public,
+        }))
+    }
+}
+
+struct Token {
+    /// PKCS#11 sessions are not thread safe, and the token is logged out when its last session is closed.
+    /// So every operation is done in this session.
+    session: Mutex<Session>,
+    /// The user PIN the token is logged in with.
+    pin: Mutex<Option<Password>>,
+}
+
+impl Token {
+    /// Log in to the token, or check the PIN if it is logged in already.
+    fn login(&self, password: &Password) -> Result<(), Error> {
+        let mut pin = self.pin.lock();
+        if let Some(pin) = &*pin {
+            return if pin == password {
+                Ok(())
+            } else {
+                Err(Error::InvalidPassword)
+            }
+        }
+        let session = self.session.lock();
+        match session.ctx.login(session.handle, CKU_USER, Some(password.as_crypto_password().0)) {
+            Ok(()) => {}
+            Err(Pkcs11Error::Pkcs11(CKR_PIN_INCORRECT)) => return Err(Error::InvalidPassword),
+            Err(err) => return Err(pkcs11_error(err)),
+        }
+        *pin = Some(password.clone());
+        Ok(())
+    }
+}
+
+struct Session {
+    ctx: Ctx,
+    handle: CK_SESSION_HANDLE,
+}
+
+struct TokenKey {
+    id: Vec<u8>,
+    public: Public,
+    label: String,
+}
+
+impl TokenKey {
+    fn into_account(self) -> SafeAccount {
+        let mut id = [0u8; 16];
+        let len = self.id.len().min(id.len());
+        id[..len].copy_from_slice(&self.id[..len]);
+        SafeAccount {
+            id,
+            version: Version::V3,
+            pubkey: self.public,
+            crypto: None,
+            filename: None,
+            meta: serde_json::json!({ "name": self.label }).to_string(),
+        }
+    }
+}
+
+impl Session {
+    fn public_keys(&self) -> Result<Vec<TokenKey>, Error> {
+        let template = vec![
+            CK_ATTRIBUTE::new(CKA_CLASS).with_ck_ulong(&CKO_PUBLIC_KEY),
+            CK_ATTRIBUTE::new(CKA_KEY_TYPE).with_ck_ulong(&CKK_EC_EDWARDS),
+        ];
+        self.find_objects(template)?.into_iter().map(|object| self.token_key(object)).collect()
+    }
+
+    fn public_key(&self, public: &Public) -> Result<TokenKey, Error> {
+        self.public_keys()?.into_iter().find(|key| key.public == *public).ok_or(Error::InvalidAccount)
+    }
+
+    fn token_key(&self, public_key: CK_OBJECT_HANDLE) -> Result<TokenKey, Error> {
+        let point = self.attribute(public_key, CKA_EC_POINT)?;
+        // The point is usually wrapped in a DER OCTET STRING.
+        let raw = match point.as_slice() {
+            [0x04, 0x20, raw @ ..] if raw.len() == 32 => raw,
+            raw => raw,
+        };
+        Ok(TokenKey {
+            id: self.attribute(public_key, CKA_ID)?,
+            public: Public::from_slice(raw).ok_or_else(|| Error::Pkcs11("Invalid Ed25519 public key".to_string()))?,
+            label: String::from_utf8_lossy(&self.attribute(public_key, CKA_LABEL)?).into_owned(),
+        })
+    }
+
+    fn find(&self, id: &[u8], class: CK_OBJECT_CLASS) -> Result<Vec<CK_OBJECT_HANDLE>, Error> {
+        let template =
+            vec![CK_ATTRIBUTE::new(CKA_CLASS).with_ck_ulong(&class), CK_ATTRIBUTE::new(CKA_ID).with_bytes(id)];
+        self.find_objects(template)
+    }
+
+    fn find_objects(&self, template: Vec<CK_ATTRIBUTE>) -> Result<Vec<CK_OBJECT_HANDLE>, Error> {
+        self.ctx.find_objects_init(self.handle, &template).map_err(pkcs11_error)?;
+        let mut objects = Vec::new();
+        let result = loop {
+            match self.ctx.find_objects(self.handle, FIND_BATCH_SIZE) {
+                Ok(batch) if batch.is_empty() => break Ok(objects),
+                Ok(batch) => objects.extend(batch),
+                Err(err) => break Err(pkcs11_error(err)),
+            }
+        };
+        self.ctx.find_objects_final(self.handle).map_err(pkcs11_error)?;
+        result
+    }
+
+    fn attribute(&self, object: CK_OBJECT_HANDLE, attribute_type: CK_ATTRIBUTE_TYPE) -> Result<Vec<u8>, Error> {
+        // The first call gets the length of the value, and the second one gets the value.
+        let mut template = vec![CK_ATTRIBUTE::new(attribute_type)];
+        self.ctx.get_attribute_value(self.handle, object, &mut template).map_err(pkcs11_error)?;
+        let mut value = vec![0u8; template[0].ulValueLen as usize];
+        let mut template = vec![CK_ATTRIBUTE::new(attribute_type).with_bytes(value.as_mut_slice())];
+        self.ctx.get_attribute_value(self.handle, object, &mut template).map_err(pkcs11_error)?;
+        Ok(value)
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = self.ctx.close_session(self.handle);
+    }
+}
+
+/// Signs with a private key in the token.
+struct Pkcs11Signer {
+    token: Arc<Token>,
+    private_key: CK_OBJECT_HANDLE,
+    public: Public,
+}
+
+impl ExternalSigner for Pkcs11Signer {
+    fn public(&self) -> Public {
+        self.public
+    }
+
+    fn sign(&self, message: &Message) -> Result<Signature, KeyError> {
+        let mechanism = CK_MECHANISM {
+            mechanism: CKM_EDDSA,
+            pParameter: ptr::null_mut(),
+            ulParameterLen: 0,
+        };
+        let session = self.token.session.lock();
+        let signature = session
+            .ctx
+            .sign_init(session.handle, &mechanism, self.private_key)
+            .and_then(|_| session.ctx.sign(session.handle, message.as_ref()))
+            .map_err(|err| KeyError::Custom(format!("PKCS#11 error: {}", err)))?;
+        Signature::from_slice(&signature).ok_or(KeyError::InvalidSignature)
+    }
+}
+
+fn pkcs11_error(err: Pkcs11Error) -> Error {
+    Error::Pkcs11(err.to_string())
+}
@@ -35,6 +35,10 @@ pub enum Error {
     InvalidKeyFile(String),
     /// Account already exists.
     AlreadyExists,
+    /// The private key never leaves its key directory, so it cannot be decrypted.
+    UnavailableSecret,
+    /// The PKCS#11 token failed.
+    Pkcs11(String),
     /// `ckeys` error
     CKey(CKeyError),
     /// `CCrypto` error
@@ -52,6 +56,8 @@ impl fmt::Display for Error {
             Error::InvalidAccount => "Invalid account".into(),
             Error::InvalidKeyFile(ref reason) => format!("Invalid key file: {}", reason),
             Error::AlreadyExists => "Account already exists".into(),
+            Error::UnavailableSecret => "The private key is kept in the key directory and cannot be taken out".into(),
+            Error::Pkcs11(ref reason) => format!("PKCS#11 error: {}", reason),
             Error::CKey(ref err) => err.to_string(),
             Error::CCrypto(ref err) => err.to_string(),
             Error::Custom(ref s) => s.clone(),
@@ -23,6 +23,7 @@ use std::fmt;
 pub enum KdfSer {
     Pbkdf2,
     Scrypt,
+    Argon2id,
 }
 
 impl Serialize for KdfSer {
@@ -32,6 +33,7 @@ impl Serialize for KdfSer {
         match *self {
             KdfSer::Pbkdf2 => serializer.serialize_str("pbkdf2"),
             KdfSer::Scrypt => serializer.serialize_str("scrypt"),
+            KdfSer::Argon2id => serializer.serialize_str("argon2id"),
         }
     }
 }
@@ -59,6 +61,7 @@ impl<'a> Visitor<'a> for KdfSerVisitor {
         match value {
             "pbkdf2" => Ok(KdfSer::Pbkdf2),
             "scrypt" => Ok(KdfSer::Scrypt),
+            "argon2id" => Ok(KdfSer::Argon2id),
             _ => Err(SerdeError::custom(Error::UnsupportedKdf)),
         }
     }
@@ -135,10 +138,20 @@ pub struct Scrypt {
     pub salt: H256,
 }
 
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Argon2id {
+    pub dklen: u32,
+    pub m: u32,
+    pub t: u32,
+    pub p: u32,
+    pub salt: H256,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum KdfSerParams {
     Pbkdf2(Pbkdf2),
     Scrypt(Scrypt),
+    Argon2id(Argon2id),
 }
 
 impl Serialize for KdfSerParams {
@@ -148,6 +161,7 @@ impl Serialize for KdfSerParams {
         match *self {
             KdfSerParams::Pbkdf2(ref params) => params.serialize(serializer),
             KdfSerParams::Scrypt(ref params) => params.serialize(serializer),
+            KdfSerParams::Argon2id(ref params) => params.serialize(serializer),
         }
     }
 }
@@ -162,7 +176,8 @@ impl<'a> Deserialize<'a> for KdfSerParams {
 
         from_value(v.clone())
             .map(KdfSerParams::Pbkdf2)
-            .or_else(|_| from_value(v).map(KdfSerParams::Scrypt))
+            .or_else(|_| from_value(v.clone()).map(KdfSerParams::Scrypt))
+            .or_else(|_| from_value(v).map(KdfSerParams::Argon2id))
             .map_err(|_| D::Error::custom("Invalid KDF algorithm"))
     }
 }
@@ -171,4 +186,5 @@ impl<'a> Deserialize<'a> for KdfSerParams {
 pub enum Kdf {
     Pbkdf2(Pbkdf2),
     Scrypt(Scrypt),
+    Argon2id(Argon2id),
 }
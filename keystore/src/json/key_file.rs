@@ -69,7 +69,7 @@ impl KeyFile {
 
 #[cfg(test)]
 mod tests {
-    use crate::json::{Aes128Ctr, Cipher, Crypto, Kdf, KeyFile, Scrypt, Uuid, Version};
+    use crate::json::{Aes128Ctr, Argon2id, Cipher, Crypto, Kdf, KeyFile, Scrypt, Uuid, Version};
     use std::str::FromStr;
 
     #[test]
@@ -285,4 +285,58 @@ mod tests {
 
         assert_eq!(file, deserialized);
     }
+
+    #[test]
+    fn argon2id_keyfile() {
+        let json = r#"
+		{
+			"pubkey": "0a6902c51384a15d1062cac3a4e62c8d0c2eb02b4de7fa0a304ce4f88ea482d0",
+			"crypto": {
+				"cipher": "aes-128-ctr",
+				"ciphertext": "7203da0676d141b138cd7f8e1a4365f59cc1aa6978dc5443f364ca943d7cb4bc",
+				"cipherparams": {
+					"iv": "b5a7ec855ec9e2c405371356855fec83"
+				},
+				"kdf": "argon2id",
+				"kdfparams": {
+					"dklen": 32,
+					"m": 65536,
+					"t": 3,
+					"p": 1,
+					"salt": "1e8642fdf1f87172492c1412fc62f8db75d796cdfa9c53c3f2b11e44a2a1b209"
+				},
+				"mac": "46325c5d4e8c991ad2683d525c7854da387138b6ca45068985aa4959fa2b8c8f"
+			},
+			"id": "8777d9f6-7860-4b9b-88b7-0b57ee6b3a73",
+			"version": 4,
+			"meta": "{}"
+		}"#;
+
+        let expected = KeyFile {
+            id: Uuid::from_str("8777d9f6-7860-4b9b-88b7-0b57ee6b3a73").unwrap(),
+            version: Version::V4,
+            pubkey: Some("0a6902c51384a15d1062cac3a4e62c8d0c2eb02b4de7fa0a304ce4f88ea482d0".into()),
+            crypto: Crypto {
+                cipher: Cipher::Aes128Ctr(Aes128Ctr {
+                    iv: "b5a7ec855ec9e2c405371356855fec83".into(),
+                }),
+                ciphertext: "7203da0676d141b138cd7f8e1a4365f59cc1aa6978dc5443f364ca943d7cb4bc".into(),
+                kdf: Kdf::Argon2id(Argon2id {
+                    dklen: 32,
+                    m: 65536,
+                    t: 3,
+                    p: 1,
+                    salt: "1e8642fdf1f87172492c1412fc62f8db75d796cdfa9c53c3f2b11e44a2a1b209".into(),
+                }),
+                mac: "46325c5d4e8c991ad2683d525c7854da387138b6ca45068985aa4959fa2b8c8f".into(),
+            },
+            meta: Some("{}".to_string()),
+        };
+
+        let keyfile: KeyFile = serde_json::from_str(json).unwrap();
+        assert_eq!(keyfile, expected);
+
+        let serialized = serde_json::to_string(&keyfile).unwrap();
+        assert_eq!(serde_json::from_str::<KeyFile>(&serialized).unwrap(), expected);
+    }
 }
@@ -0,0 +1,41 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{Crypto, Uuid, Version};
+use std::io::{Read, Write};
+
+/// An encrypted HD wallet seed, from which the accounts are derived.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SeedFile {
+    pub id: Uuid,
+    pub version: Version,
+    pub crypto: Crypto,
+    pub meta: Option<String>,
+}
+
+impl SeedFile {
+    pub fn load<R>(reader: R) -> Result<Self, serde_json::Error>
+    where
+        R: Read, {
+        serde_json::from_reader(reader)
+    }
+
+    pub fn write<W>(&self, writer: &mut W) -> Result<(), serde_json::Error>
+    where
+        W: Write, {
+        serde_json::to_writer(writer, self)
+    }
+}
@@ -22,6 +22,7 @@ use std::fmt;
 #[derive(Debug, PartialEq)]
 pub enum Version {
     V3,
+    V4,
 }
 
 impl Serialize for Version {
@@ -30,6 +31,7 @@ impl Serialize for Version {
         S: Serializer, {
         match *self {
             Version::V3 => serializer.serialize_u64(3),
+            Version::V4 => serializer.serialize_u64(4),
         }
     }
 }
@@ -56,6 +58,7 @@ impl<'a> Visitor<'a> for VersionVisitor {
         E: SerdeError, {
         match value {
             3 => Ok(Version::V3),
+            4 => Ok(Version::V4),
             _ => Err(SerdeError::custom(Error::UnsupportedVersion)),
         }
     }
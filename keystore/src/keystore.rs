@@ -15,15 +15,18 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::account::{DecryptedAccount, SafeAccount};
-use crate::accounts_dir::KeyDirectory;
+use crate::account::{DecryptedAccount, EncryptedSeed, KdfParams, SafeAccount};
+use crate::accounts_dir::{KeyDirectory, SeedStore};
 use crate::json::{self, OpaqueKeyFile, Uuid};
 use crate::random::Random;
 use crate::{Error, SecretStore, SimpleSecretStore};
-use ccrypto::KEY_ITERATIONS;
-use ckey::{Ed25519KeyPair as KeyPair, Ed25519Private as Private, Ed25519Public as Public, KeyPairTrait, Password};
+use ckey::{
+    mnemonic_to_seed, DerivationPath, Ed25519KeyPair as KeyPair, Ed25519Private as Private, Ed25519Public as Public,
+    Generator, KeyPairTrait, Password, Random as RandomKeyPair,
+};
 use parking_lot::{Mutex, RwLock};
 use std::collections::BTreeMap;
+use std::convert::TryFrom;
 use std::mem;
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
@@ -36,13 +39,20 @@ pub struct KeyStore {
 impl KeyStore {
     /// Open a new accounts store with given key directory backend.
     pub fn open(directory: Box<dyn KeyDirectory>) -> Result<Self, Error> {
-        Self::open_with_iterations(directory, KEY_ITERATIONS as u32)
+        Self::open_with_kdf(directory, KdfParams::default())
     }
 
-    /// Open a new account store with given key directory backend and custom number of iterations.
+    /// Open a new account store with given key directory backend and custom number of PBKDF2 iterations.
     pub fn open_with_iterations(directory: Box<dyn KeyDirectory>, iterations: u32) -> Result<Self, Error> {
+        Self::open_with_kdf(directory, KdfParams::Pbkdf2 {
+            iterations,
+        })
+    }
+
+    /// Open a new account store with given key directory backend and key derivation function for new keys.
+    pub fn open_with_kdf(directory: Box<dyn KeyDirectory>, kdf: KdfParams) -> Result<Self, Error> {
         Ok(KeyStore {
-            store: KeyMultiStore::open_with_iterations(directory, iterations)?,
+            store: KeyMultiStore::open_with_kdf(directory, kdf)?,
         })
     }
 
@@ -55,6 +65,35 @@ impl KeyStore {
     pub fn set_refresh_time(&self, time: Duration) {
         self.store.set_refresh_time(time)
     }
+
+    /// Create a new account with a random key.
+    /// If the key directory keeps the keys by itself, the key is generated in it.
+    pub fn new_account(&self, password: &Password) -> Result<Public, Error> {
+        self.store.new_account(password)
+    }
+
+    /// Encrypt the key of the account again with the key derivation function of this store.
+    /// Key files written with an older version are upgraded in place.
+    pub fn re_encrypt(&self, account: &Public, password: &Password) -> Result<(), Error> {
+        self.store.re_encrypt(account, password)
+    }
+
+    /// Import the seed of a BIP-39 mnemonic as an HD wallet. The seed is kept encrypted with the password.
+    /// The ID of the existing wallet is returned if the seed is already imported.
+    pub fn import_mnemonic(&self, phrase: &str, passphrase: &str, password: &Password) -> Result<Uuid, Error> {
+        self.store.import_mnemonic(phrase, passphrase, password)
+    }
+
+    /// Returns the IDs of the HD wallets.
+    pub fn seeds(&self) -> Result<Vec<Uuid>, Error> {
+        self.store.seeds()
+    }
+
+    /// Derive the account of the path from an HD wallet with SLIP-0010.
+    /// The account is inserted with the password of the wallet if it does not exist yet.
+    pub fn derive_account(&self, seed: Uuid, path: &DerivationPath, password: &Password) -> Result<Public, Error> {
+        self.store.derive_account(seed, path, password)
+    }
 }
 
 impl SimpleSecretStore for KeyStore {
@@ -102,12 +141,19 @@ impl SecretStore for KeyStore {
             safe_account.id = Random::random();
         }
 
-        let secret = safe_account.crypto.secret(password).map_err(|_| Error::InvalidPassword)?;
+        let secret = safe_account.secret(password).map_err(|_| Error::InvalidPassword)?;
         safe_account.pubkey = *KeyPair::from_private(secret).public();
         self.store.import(safe_account)
     }
 
     fn test_password(&self, account: &Public, password: &Password) -> Result<bool, Error> {
+        if self.store.dir.as_key_holder().is_some() {
+            return match self.store.decrypt_account(account, password) {
+                Ok(_) => Ok(true),
+                Err(Error::InvalidPassword) => Ok(false),
+                Err(err) => Err(err),
+            }
+        }
         match self.store.get_verified_account(account, password) {
             Ok(_) => Ok(true),
             Err(Error::InvalidPassword) => Ok(false),
@@ -154,7 +200,7 @@ impl SecretStore for KeyStore {
 /// Similar to `KeyStore` but may store many accounts (with different passwords) for the same `Public`
 pub struct KeyMultiStore {
     dir: Box<dyn KeyDirectory>,
-    iterations: u32,
+    kdf: KdfParams,
     // order lock: cache
     cache: RwLock<BTreeMap<Public, Vec<SafeAccount>>>,
     timestamp: Mutex<Timestamp>,
@@ -169,14 +215,21 @@ struct Timestamp {
 impl KeyMultiStore {
     /// Open new multi-accounts store with given key directory backend.
     pub fn open(directory: Box<dyn KeyDirectory>) -> Result<Self, Error> {
-        Self::open_with_iterations(directory, KEY_ITERATIONS as u32)
+        Self::open_with_kdf(directory, KdfParams::default())
     }
 
-    /// Open new multi-accounts store with given key directory backend and custom number of iterations for new keys.
+    /// Open new multi-accounts store with given key directory backend and custom number of PBKDF2 iterations.
     pub fn open_with_iterations(directory: Box<dyn KeyDirectory>, iterations: u32) -> Result<Self, Error> {
+        Self::open_with_kdf(directory, KdfParams::Pbkdf2 {
+            iterations,
+        })
+    }
+
+    /// Open new multi-accounts store with given key directory backend and key derivation function for new keys.
+    pub fn open_with_kdf(directory: Box<dyn KeyDirectory>, kdf: KdfParams) -> Result<Self, Error> {
         let store = KeyMultiStore {
             dir: directory,
-            iterations,
+            kdf,
             cache: Default::default(),
             timestamp: Mutex::new(Timestamp {
                 dir_hash: None,
@@ -199,6 +252,97 @@ impl KeyMultiStore {
         self.timestamp.lock().refresh_time = time;
     }
 
+    /// Create a new account with a random key.
+    /// If the key directory keeps the keys by itself, the key is generated in it.
+    pub fn new_account(&self, password: &Password) -> Result<Public, Error> {
+        match self.dir.as_key_holder() {
+            Some(holder) => {
+                let account = holder.generate(password)?;
+                let account_ref = account.pubkey;
+                self.cache.write().entry(account_ref).or_insert_with(Vec::new).push(account);
+                Ok(account_ref)
+            }
+            None => {
+                let keypair: KeyPair =
+                    RandomKeyPair.generate().expect("ed25519 context has generation capabilities; qed");
+                self.insert_account(keypair.get_private(), password)
+            }
+        }
+    }
+
+    /// Encrypt the keys of the account that match the password again with the key derivation function of this store.
+    pub fn re_encrypt(&self, account_ref: &Public, password: &Password) -> Result<(), Error> {
+        let mut changed_any = false;
+        for account in self.get_safe_accounts(account_ref)? {
+            let new_account = match account.re_encrypt(password, self.kdf) {
+                Ok(new_account) => new_account,
+                Err(Error::InvalidPassword) => continue,
+                Err(err) => return Err(err),
+            };
+            self.update(account_ref, &account, new_account)?;
+            changed_any = true
+        }
+
+        if changed_any {
+            Ok(())
+        } else {
+            Err(Error::InvalidPassword)
+        }
+    }
+
+    /// Import the seed of a BIP-39 mnemonic as an HD wallet. The seed is kept encrypted with the password.
+    /// The ID of the existing wallet is returned if the seed is already imported.
+    pub fn import_mnemonic(&self, phrase: &str, passphrase: &str, password: &Password) -> Result<Uuid, Error> {
+        let seed_store = self.seed_store()?;
+        let mut seed = mnemonic_to_seed(phrase, passphrase)?;
+        let fingerprint = EncryptedSeed::fingerprint(&seed);
+        let meta = serde_json::json!({ "fingerprint": fingerprint });
+        let encrypted = EncryptedSeed::create(&seed, Random::random(), password, self.kdf, meta.to_string());
+        for byte in seed.iter_mut() {
+            *byte = 0;
+        }
+        if let Some(existing) =
+            seed_store.load_seeds()?.into_iter().find(|seed| seed.has_fingerprint(&fingerprint, password))
+        {
+            return Ok(existing.id.into())
+        }
+        let encrypted = encrypted?;
+        let id = encrypted.id;
+        seed_store.insert_seed(encrypted)?;
+        Ok(id.into())
+    }
+
+    /// Returns the IDs of the HD wallets.
+    pub fn seeds(&self) -> Result<Vec<Uuid>, Error> {
+        Ok(self.seed_store()?.load_seeds()?.into_iter().map(|seed| seed.id.into()).collect())
+    }
+
+    /// Derive the account of the path from an HD wallet with SLIP-0010.
+    /// The account is inserted with the password of the wallet if it does not exist yet.
+    pub fn derive_account(&self, seed: Uuid, path: &DerivationPath, password: &Password) -> Result<Public, Error> {
+        let id: [u8; 16] = seed.into();
+        let seed = self
+            .seed_store()?
+            .load_seeds()?
+            .into_iter()
+            .find(|seed| seed.id == id)
+            .ok_or_else(|| Error::Custom(format!("There is no seed {}", Uuid::from(id))))?;
+        let keypair = seed.derive(path, password)?;
+        if self.has_account(keypair.public())? {
+            return Ok(*keypair.public())
+        }
+        let meta = serde_json::json!({
+            "seed": Uuid::from(id).to_string(),
+            "path": path.to_string(),
+        });
+        let account = SafeAccount::create(&keypair, Random::random(), password, self.kdf, meta.to_string())?;
+        self.import(account)
+    }
+
+    fn seed_store(&self) -> Result<&dyn SeedStore, Error> {
+        self.dir.as_seed_store().ok_or_else(|| Error::Custom("The key directory cannot keep seeds".to_string()))
+    }
+
     fn reload_if_changed(&self) -> Result<(), Error> {
         let mut last_timestamp = self.timestamp.lock();
         let now = Instant::now();
@@ -260,7 +404,7 @@ impl KeyMultiStore {
 
     fn get_verified_account(&self, account: &Public, password: &Password) -> Result<VerifiedAccount, Error> {
         for account in self.get_safe_accounts(account)?.into_iter() {
-            match account.crypto.secret(password) {
+            match account.secret(password) {
                 Ok(secret) => {
                     return Ok(VerifiedAccount {
                         account,
@@ -329,7 +473,7 @@ impl SimpleSecretStore for KeyMultiStore {
     fn insert_account(&self, secret: Private, password: &Password) -> Result<Public, Error> {
         let keypair = KeyPair::from_private(secret);
         let id: [u8; 16] = Random::random();
-        let account = SafeAccount::create(&keypair, id, password, self.iterations, "{}".to_string())?;
+        let account = SafeAccount::create(&keypair, id, password, self.kdf, "{}".to_string())?;
         self.import(account)
     }
 
@@ -364,7 +508,7 @@ impl SimpleSecretStore for KeyMultiStore {
     ) -> Result<(), Error> {
         let mut changed_any = false;
         for account in self.get_safe_accounts(account_ref)? {
-            let new_account = match account.change_password(old_password, new_password, self.iterations) {
+            let new_account = match account.change_password(old_password, new_password, self.kdf) {
                 Ok(new_account) => new_account,
                 Err(Error::InvalidPassword) => continue,
                 Err(err) => return Err(err),
@@ -381,10 +525,15 @@ impl SimpleSecretStore for KeyMultiStore {
     }
 
     fn export_account(&self, account_ref: &Public, password: &Password) -> Result<OpaqueKeyFile, Error> {
-        Ok(self.get_verified_account(account_ref, password)?.account.into())
+        let account = self.get_verified_account(account_ref, password)?.account;
+        Ok(json::KeyFile::try_from(account)?.into())
     }
 
     fn decrypt_account(&self, account: &Public, password: &Password) -> Result<DecryptedAccount, Error> {
+        if let Some(holder) = self.dir.as_key_holder() {
+            let account = self.get_safe_account(account)?;
+            return Ok(DecryptedAccount::external(holder.signer(&account, password)?))
+        }
         Ok(DecryptedAccount::new(self.get_verified_account(account, password)?.secret))
     }
 }
@@ -428,6 +577,17 @@ mod tests {
         assert_eq!(store.accounts().unwrap().len(), 1, "Should have one account.");
     }
 
+    #[test]
+    fn new_account_can_sign() {
+        let store = store();
+
+        let address = store.new_account(&"test".into()).unwrap();
+
+        let account = store.decrypt_account(&address, &"test".into()).unwrap();
+        assert_eq!(address, account.public().unwrap());
+        assert!(store.decrypt_account(&address, &"x".into()).is_err());
+    }
+
     #[test]
     fn update_meta() {
         // given
@@ -526,4 +686,77 @@ mod tests {
         // then
         assert!(exported.is_ok(), "Should export single account: {:?}", exported);
     }
+
+    #[test]
+    fn re_encrypt_upgrades_the_key() {
+        // given
+        let argon2id = KdfParams::Argon2id {
+            memory: 8 * 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let store = KeyMultiStore::open_with_kdf(Box::new(MemoryDirectory::default()), argon2id)
+            .expect("MemoryDirectory always load successfuly; qed");
+        let keypair = keypair();
+        let pbkdf2 = KdfParams::Pbkdf2 {
+            iterations: 1024,
+        };
+        let old = SafeAccount::create(&keypair, [0u8; 16], &"test".into(), pbkdf2, "{}".to_string()).unwrap();
+        let address = store.import(old).unwrap();
+
+        // when
+        assert!(store.re_encrypt(&address, &"x".into()).is_err());
+        store.re_encrypt(&address, &"test".into()).unwrap();
+
+        // then
+        let accounts = store.get_safe_accounts(&address).unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].version, crate::account::Version::V4);
+        assert!(store.decrypt_account(&address, &"test".into()).is_ok());
+    }
+
+    #[test]
+    fn derive_accounts_from_mnemonic() {
+        // given
+        let kdf = KdfParams::Pbkdf2 {
+            iterations: 1024,
+        };
+        let store = KeyMultiStore::open_with_kdf(Box::new(MemoryDirectory::default()), kdf)
+            .expect("MemoryDirectory always load successfuly; qed");
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = store.import_mnemonic(phrase, "", &"test".into()).unwrap();
+        assert_eq!(store.seeds().unwrap(), vec![seed]);
+
+        // when
+        let first =
+            store.derive_account(store.seeds().unwrap().remove(0), &"m/44'/0'".parse().unwrap(), &"test".into());
+        let again =
+            store.derive_account(store.seeds().unwrap().remove(0), &"m/44'/0'".parse().unwrap(), &"test".into());
+        let second =
+            store.derive_account(store.seeds().unwrap().remove(0), &"m/44'/1'".parse().unwrap(), &"test".into());
+
+        // then
+        let first = first.unwrap();
+        assert_eq!(first, again.unwrap());
+        assert_ne!(first, second.unwrap());
+        assert_eq!(store.accounts().unwrap().len(), 2);
+        assert!(store.decrypt_account(&first, &"test".into()).is_ok());
+        assert!(store.derive_account(store.seeds().unwrap().remove(0), &"m/0'".parse().unwrap(), &"x".into()).is_err());
+    }
+
+    #[test]
+    fn import_the_same_mnemonic_twice() {
+        let kdf = KdfParams::Pbkdf2 {
+            iterations: 1024,
+        };
+        let store = KeyMultiStore::open_with_kdf(Box::new(MemoryDirectory::default()), kdf)
+            .expect("MemoryDirectory always load successfuly; qed");
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+        let seed = store.import_mnemonic(phrase, "", &"test".into()).unwrap();
+        assert_eq!(seed, store.import_mnemonic(phrase, "", &"another".into()).unwrap());
+        let other = store.import_mnemonic(phrase, "passphrase", &"test".into()).unwrap();
+        assert_ne!(seed, other);
+        assert_eq!(store.seeds().unwrap(), vec![seed, other]);
+    }
 }
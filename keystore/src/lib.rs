@@ -52,7 +52,7 @@ mod keystore;
 mod random;
 mod secret_store;
 
-pub use crate::account::{Crypto, DecryptedAccount, SafeAccount};
+pub use crate::account::{Crypto, DecryptedAccount, EncryptedSeed, ExternalSigner, KdfParams, SafeAccount};
 pub use crate::error::Error;
 pub use crate::import::{import_account, import_accounts};
 pub use crate::json::OpaqueKeyFile as KeyFile;
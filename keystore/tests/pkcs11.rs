@@ -0,0 +1,92 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Runs the keystore with a SoftHSM token.
+//! It is ignored by default. Run it with `cargo test -- --ignored` where SoftHSM is installed,
+//! or the library is given with `SOFTHSM2_LIBRARY`.
+
+extern crate codechain_keystore as ckeystore;
+
+use ckey::{verify, Message};
+use ckeystore::accounts_dir::Pkcs11Directory;
+use ckeystore::{Error, KeyStore, SecretStore, SimpleSecretStore};
+use std::path::PathBuf;
+use std::process::Command;
+use std::{env, fs};
+
+const LIBRARIES: [&str; 3] = [
+    "/usr/lib/softhsm/libsofthsm2.so",
+    "/usr/lib/x86_64-linux-gnu/softhsm/libsofthsm2.so",
+    "/usr/local/lib/softhsm/libsofthsm2.so",
+];
+const TOKEN: &str = "foundry-test";
+const PIN: &str = "1234";
+
+fn softhsm_library() -> Option<PathBuf> {
+    match env::var_os("SOFTHSM2_LIBRARY") {
+        Some(library) => Some(PathBuf::from(library)),
+        None => LIBRARIES.iter().map(PathBuf::from).find(|library| library.exists()),
+    }
+}
+
+/// Makes a token in a new SoftHSM token directory.
+fn init_token() -> PathBuf {
+    let dir = env::temp_dir().join(format!("foundry-softhsm-{}", std::process::id()));
+    let tokens = dir.join("tokens");
+    fs::create_dir_all(&tokens).unwrap();
+    let conf = dir.join("softhsm2.conf");
+    fs::write(&conf, format!("directories.tokendir = {}\n", tokens.display())).unwrap();
+    env::set_var("SOFTHSM2_CONF", &conf);
+
+    let status = Command::new("softhsm2-util")
+        .args(&["--init-token", "--free", "--label", TOKEN, "--pin", PIN, "--so-pin", "0000"])
+        .status()
+        .expect("softhsm2-util is not installed");
+    assert!(status.success());
+    dir
+}
+
+#[test]
+#[ignore]
+fn sign_with_a_key_in_the_token() {
+    let library = softhsm_library().expect("SoftHSM is not installed");
+    let dir = init_token();
+
+    let public = {
+        let store = KeyStore::open(Box::new(Pkcs11Directory::open(&library, TOKEN).unwrap())).unwrap();
+        assert!(matches!(store.new_account(&"wrong".into()), Err(Error::InvalidPassword)));
+        let public = store.new_account(&PIN.into()).unwrap();
+        assert_eq!(vec![public], store.accounts().unwrap());
+
+        let message = Message::random();
+        let signature = store.decrypt_account(&public, &PIN.into()).unwrap().sign(&message).unwrap();
+        assert!(verify(&signature, message.as_ref(), &public));
+
+        assert!(!store.test_password(&public, &"wrong".into()).unwrap());
+        assert!(store.test_password(&public, &PIN.into()).unwrap());
+        assert!(matches!(store.export_account(&public, &PIN.into()), Err(Error::UnavailableSecret)));
+        public
+    };
+
+    // The key stays in the token.
+    let store = KeyStore::open(Box::new(Pkcs11Directory::open(&library, TOKEN).unwrap())).unwrap();
+    assert_eq!(vec![public], store.accounts().unwrap());
+    store.decrypt_account(&public, &PIN.into()).unwrap();
+    store.remove_account(&public).unwrap();
+    assert!(store.accounts().unwrap().is_empty());
+
+    fs::remove_dir_all(dir).unwrap();
+}
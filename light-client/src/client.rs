@@ -0,0 +1,447 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{Error, LightBlock};
+use ccore::{verify_precommits, Step, TendermintSealView, VoteOn, VoteStep};
+use ckey::Ed25519Public as Public;
+use ctypes::{BlockNumber, CompactValidatorSet, Header};
+
+const SEAL_FIELDS: usize = 4;
+
+/// Gives the light blocks to verify, usually by calling `chain_getLightBlock` of a full node.
+pub trait LightBlockSource {
+    fn light_block(&mut self, number: BlockNumber) -> Result<LightBlock, String>;
+}
+
+#[derive(Clone)]
+pub struct LightClient {
+    trusted: Header,
+    next_validator_set: CompactValidatorSet,
+}
+
+impl LightClient {
+    /// Starts from a header and its next validator set, both of which are trusted.
+    ///
+    /// The next validator set hash of the genesis header is not checked, since the genesis block doesn't have it.
+    pub fn new(trusted: Header, next_validator_set: CompactValidatorSet) -> Result<Self, Error> {
+        if trusted.number() != 0 {
+            check_next_validator_set(&trusted, &next_validator_set)?;
+        }
+        Ok(Self {
+            trusted,
+            next_validator_set,
+        })
+    }
+
+    pub fn trusted_header(&self) -> &Header {
+        &self.trusted
+    }
+
+    pub fn next_validator_set(&self) -> &CompactValidatorSet {
+        &self.next_validator_set
+    }
+
+    /// Verifies a newer header and trusts it.
+    ///
+    /// The child of the trusted header is verified with the trusted validator set.
+    /// A later header is trusted only if the validators who signed it have at least one third of the trusted
+    /// delegation. Otherwise it returns `Error::InsufficientOverlap`, and the headers between them should be
+    /// verified first. Its validator set is counted only after it matches the hash in the parent header, which the
+    /// signed header commits to.
+    pub fn verify(&mut self, block: LightBlock) -> Result<(), Error> {
+        let trusted_number = self.trusted.number();
+        if block.header.number() <= trusted_number {
+            return Err(Error::NotNewer {
+                trusted: trusted_number,
+                found: block.header.number(),
+            })
+        }
+        check_next_validator_set(&block.header, &block.next_validator_set)?;
+        if *block.header.parent_hash() != block.parent.hash() {
+            return Err(Error::ParentMismatch)
+        }
+
+        if block.header.number() == trusted_number + 1 {
+            if block.parent.hash() != self.trusted.hash() {
+                return Err(Error::ParentMismatch)
+            }
+            let signers = verify_commit(&block.header, &block.commit, &self.next_validator_set)?;
+            check_two_thirds(&signers, &self.next_validator_set)?;
+        } else {
+            check_next_validator_set(&block.parent, &block.validator_set)?;
+            let signers = verify_commit(&block.header, &block.commit, &block.validator_set)?;
+            check_two_thirds(&signers, &block.validator_set)?;
+            check_one_third_of_trusted(&signers, &self.next_validator_set)?;
+        }
+
+        self.trusted = block.header;
+        self.next_validator_set = block.next_validator_set;
+        Ok(())
+    }
+
+    /// Follows the chain up to `target`, skipping headers as far as the trusted validators allow.
+    ///
+    /// When a header cannot be trusted directly, the header in the middle is verified first.
+    pub fn sync_to(&mut self, target: BlockNumber, source: &mut dyn LightBlockSource) -> Result<(), Error> {
+        let mut pending = vec![target];
+        while let Some(&number) = pending.last() {
+            if number <= self.trusted.number() {
+                pending.pop();
+                continue
+            }
+            let block = source.light_block(number).map_err(Error::Source)?;
+            match self.verify(block) {
+                Ok(()) => {
+                    pending.pop();
+                }
+                Err(Error::InsufficientOverlap {
+                    ..
+                }) => {
+                    let trusted_number = self.trusted.number();
+                    pending.push(trusted_number + (number - trusted_number) / 2);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_next_validator_set(header: &Header, next_validator_set: &CompactValidatorSet) -> Result<(), Error> {
+    if *header.next_validator_set_hash() != next_validator_set.hash() {
+        return Err(Error::InvalidNextValidatorSet {
+            expected: *header.next_validator_set_hash(),
+            found: next_validator_set.hash(),
+        })
+    }
+    Ok(())
+}
+
+/// Verifies the precommits for `header` in the seal of `commit`, and returns the signers.
+fn verify_commit(header: &Header, commit: &Header, validator_set: &CompactValidatorSet) -> Result<Vec<Public>, Error> {
+    if commit.number() != header.number() + 1 || *commit.parent_hash() != header.hash() {
+        return Err(Error::CommitMismatch)
+    }
+    if commit.seal().len() != SEAL_FIELDS {
+        return Err(Error::InvalidSeal(format!("The seal has {} fields", commit.seal().len())))
+    }
+    let seal_view = TendermintSealView::new(commit.seal());
+    let format = seal_view.format()?;
+    if seal_view.bitset()?.count() != seal_view.precommits_count()? {
+        return Err(Error::InvalidSeal("The bitset doesn't match the precommits".to_string()))
+    }
+    let vote_on = VoteOn {
+        step: VoteStep::new(header.number(), seal_view.parent_block_finalized_view()?, Step::Precommit),
+        block_hash: Some(header.hash()),
+    };
+    let message = vote_on.hash();
+
+    let mut signatures = Vec::new();
+    for (index, signature) in seal_view.signatures()? {
+        let validator = validator_set
+            .get(index)
+            .ok_or_else(|| Error::InvalidSeal(format!("There is no validator at index {}", index)))?;
+        signatures.push((signature, validator.public_key));
+    }
+    verify_precommits(format, message.as_ref(), &signatures).map_err(Error::InvalidSignature)?;
+    Ok(signatures.into_iter().map(|(_, public)| public).collect())
+}
+
+fn signed_delegation(signers: &[Public], validator_set: &CompactValidatorSet) -> (u64, u64) {
+    let signed =
+        validator_set.iter().filter(|entry| signers.contains(&entry.public_key)).map(|entry| entry.delegation).sum();
+    let total = validator_set.iter().map(|entry| entry.delegation).sum();
+    (signed, total)
+}
+
+fn check_two_thirds(signers: &[Public], validator_set: &CompactValidatorSet) -> Result<(), Error> {
+    let (signed, total) = signed_delegation(signers, validator_set);
+    if u128::from(signed) * 3 > u128::from(total) * 2 {
+        Ok(())
+    } else {
+        Err(Error::NotEnoughVotes {
+            signed,
+            total,
+        })
+    }
+}
+
+fn check_one_third_of_trusted(signers: &[Public], trusted: &CompactValidatorSet) -> Result<(), Error> {
+    let (signed, total) = signed_delegation(signers, trusted);
+    if u128::from(signed) * 3 >= u128::from(total) {
+        Ok(())
+    } else {
+        Err(Error::InsufficientOverlap {
+            signed,
+            total,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ccore::{BitSet, Seal, SealFormat};
+    use ckey::{sign, Ed25519Private as Private};
+    use ctypes::CompactValidatorEntry;
+    use primitives::H256;
+
+    struct Validators(Vec<(Private, u64)>);
+
+    impl Validators {
+        fn new(delegations: &[u64]) -> Self {
+            Validators(delegations.iter().map(|delegation| (Private::random(), *delegation)).collect())
+        }
+
+        fn set(&self) -> CompactValidatorSet {
+            CompactValidatorSet::new(
+                self.0
+                    .iter()
+                    .map(|(private, delegation)| CompactValidatorEntry {
+                        public_key: private.public_key(),
+                        delegation: *delegation,
+                    })
+                    .collect(),
+            )
+        }
+    }
+
+    /// A chain where `validators[N]` signs the block N.
+    /// The last header is only a commit, and the last validator set is only a next validator set.
+    struct Chain {
+        headers: Vec<Header>,
+        validators: Vec<Validators>,
+    }
+
+    impl Chain {
+        fn new(validators: Vec<Validators>) -> Self {
+            let mut headers: Vec<Header> = Vec::new();
+            for number in 0..validators.len() - 1 {
+                let mut header = match headers.last() {
+                    Some(parent) => {
+                        let mut header = parent.generate_child();
+                        header.set_seal(seal(parent, &validators[number - 1], None));
+                        header
+                    }
+                    None => Header::default(),
+                };
+                header.set_next_validator_set_hash(validators[number + 1].set().hash());
+                headers.push(header);
+            }
+            Self {
+                headers,
+                validators,
+            }
+        }
+
+        fn light_block(&self, number: BlockNumber) -> LightBlock {
+            let number = number as usize;
+            LightBlock {
+                parent: self.headers[number - 1].clone(),
+                header: self.headers[number].clone(),
+                commit: self.headers[number + 1].clone(),
+                validator_set: self.validators[number].set(),
+                next_validator_set: self.validators[number + 1].set(),
+            }
+        }
+    }
+
+    impl LightBlockSource for Chain {
+        fn light_block(&mut self, number: BlockNumber) -> Result<LightBlock, String> {
+            Ok(Chain::light_block(self, number))
+        }
+    }
+
+    /// Creates the seal which has the precommits for `parent` by `signers`, or everyone if it's `None`.
+    fn seal(parent: &Header, validators: &Validators, signers: Option<&[usize]>) -> Vec<Vec<u8>> {
+        let all: Vec<_> = (0..validators.0.len()).collect();
+        let signers = signers.unwrap_or(&all);
+        let vote_on = VoteOn {
+            step: VoteStep::new(parent.number(), 0, Step::Precommit),
+            block_hash: Some(parent.hash()),
+        };
+        Seal::Tendermint {
+            prev_view: 0,
+            cur_view: 0,
+            precommits: signers.iter().map(|index| sign(vote_on.hash().as_ref(), &validators.0[*index].0)).collect(),
+            precommit_bitset: BitSet::new_with_indices(signers),
+            format: SealFormat::Individual,
+        }
+        .seal_fields()
+        .unwrap()
+    }
+
+    fn same_validators(count: usize, delegations: &[u64]) -> Vec<Validators> {
+        let validators = Validators::new(delegations);
+        (0..count).map(|_| Validators(validators.0.clone())).collect()
+    }
+
+    fn trust_genesis(chain: &Chain) -> LightClient {
+        LightClient::new(chain.headers[0].clone(), chain.validators[1].set()).unwrap()
+    }
+
+    #[test]
+    fn verify_sequential_headers() {
+        let chain = Chain::new(same_validators(6, &[10, 20, 30, 40]));
+        let mut client = trust_genesis(&chain);
+        for number in 1..4 {
+            client.verify(chain.light_block(number)).unwrap();
+            assert_eq!(chain.headers[number as usize].hash(), client.trusted_header().hash());
+        }
+    }
+
+    #[test]
+    fn follow_validator_set_changes() {
+        let validators = vec![
+            Validators::new(&[10, 10, 10]),
+            Validators::new(&[10, 10, 10]),
+            Validators::new(&[5, 50]),
+            Validators::new(&[1, 1, 1, 1]),
+            Validators::new(&[7]),
+            Validators::new(&[7]),
+        ];
+        let chain = Chain::new(validators);
+        let mut client = trust_genesis(&chain);
+        for number in 1..4 {
+            client.verify(chain.light_block(number)).unwrap();
+        }
+        assert_eq!(chain.validators[4].set(), *client.next_validator_set());
+    }
+
+    #[test]
+    fn reject_wrong_next_validator_set() {
+        let chain = Chain::new(same_validators(4, &[10, 10, 10]));
+        let mut client = trust_genesis(&chain);
+        let mut block = chain.light_block(1);
+        block.next_validator_set = Validators::new(&[10]).set();
+        assert!(matches!(client.verify(block), Err(Error::InvalidNextValidatorSet { .. })));
+    }
+
+    #[test]
+    fn reject_not_enough_votes() {
+        let chain = Chain::new(same_validators(4, &[10, 10, 10]));
+        let mut client = trust_genesis(&chain);
+
+        let mut block = chain.light_block(1);
+        block.commit.set_seal(seal(&block.header, &chain.validators[1], Some(&[0, 1])));
+        assert_eq!(
+            Err(Error::NotEnoughVotes {
+                signed: 20,
+                total: 30,
+            }),
+            client.verify(block)
+        );
+    }
+
+    #[test]
+    fn reject_forged_signature() {
+        let chain = Chain::new(same_validators(4, &[10, 10, 10]));
+        let mut client = trust_genesis(&chain);
+
+        let mut block = chain.light_block(1);
+        let mut forged = Validators(chain.validators[1].0.clone());
+        forged.0[1].0 = Private::random();
+        block.commit.set_seal(seal(&block.header, &forged, None));
+        assert_eq!(Err(Error::InvalidSignature(chain.validators[1].0[1].0.public_key())), client.verify(block));
+    }
+
+    #[test]
+    fn reject_header_of_another_chain() {
+        let chain = Chain::new(same_validators(4, &[10, 10, 10]));
+        let mut client = trust_genesis(&chain);
+
+        let mut block = chain.light_block(1);
+        block.header.set_state_root(H256::random());
+        assert_eq!(Err(Error::CommitMismatch), client.verify(block));
+
+        let mut block = chain.light_block(1);
+        block.header.set_parent_hash(H256::random().into());
+        assert_eq!(Err(Error::ParentMismatch), client.verify(block));
+
+        let mut block = chain.light_block(2);
+        block.parent.set_state_root(H256::random());
+        assert_eq!(Err(Error::ParentMismatch), client.verify(block));
+    }
+
+    #[test]
+    fn reject_forged_validator_set() {
+        let chain = Chain::new(same_validators(6, &[10, 10, 10]));
+        let mut client = trust_genesis(&chain);
+
+        // Only 20 out of 30 delegation signed block 3, which the forged validator set makes 20 out of 21.
+        let mut block = chain.light_block(3);
+        block.commit.set_seal(seal(&block.header, &chain.validators[3], Some(&[0, 1])));
+        let mut forged = Validators(chain.validators[3].0.clone());
+        forged.0[2].1 = 1;
+        block.validator_set = forged.set();
+        assert_eq!(
+            Err(Error::InvalidNextValidatorSet {
+                expected: chain.validators[3].set().hash(),
+                found: forged.set().hash(),
+            }),
+            client.clone().verify(block.clone())
+        );
+
+        block.validator_set = chain.validators[3].set();
+        assert_eq!(
+            Err(Error::NotEnoughVotes {
+                signed: 20,
+                total: 30,
+            }),
+            client.verify(block)
+        );
+    }
+
+    #[test]
+    fn skip_when_trusted_validators_overlap() {
+        // The validator with 40 out of 100 delegation stays in every validator set.
+        let stayer = Private::random();
+        let validators: Vec<_> = (0..9)
+            .map(|_| {
+                let mut validators = Validators::new(&[30, 30]);
+                validators.0.push((stayer.clone(), 40));
+                validators
+            })
+            .collect();
+        let chain = Chain::new(validators);
+        let mut client = trust_genesis(&chain);
+        client.verify(chain.light_block(6)).unwrap();
+        assert_eq!(6, client.trusted_header().number());
+    }
+
+    #[test]
+    fn bisect_when_trusted_validators_do_not_overlap() {
+        let validators: Vec<_> = (0..11).map(|_| Validators::new(&[10, 10, 10])).collect();
+        let mut chain = Chain::new(validators);
+        let mut client = trust_genesis(&chain);
+        assert!(matches!(client.verify(chain.light_block(8)), Err(Error::InsufficientOverlap { .. })));
+
+        client.sync_to(8, &mut chain).unwrap();
+        assert_eq!(chain.headers[8].hash(), client.trusted_header().hash());
+    }
+
+    #[test]
+    fn light_block_rlp() {
+        let chain = Chain::new(same_validators(4, &[10, 20]));
+        let block = chain.light_block(1);
+        let decoded: LightBlock = rlp::decode(&rlp::encode(&block)).unwrap();
+        assert_eq!(block.parent.hash(), decoded.parent.hash());
+        assert_eq!(block.header.hash(), decoded.header.hash());
+        assert_eq!(block.commit.hash(), decoded.commit.hash());
+        assert_eq!(block.validator_set, decoded.validator_set);
+        assert_eq!(block.next_validator_set, decoded.next_validator_set);
+    }
+}
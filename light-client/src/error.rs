@@ -0,0 +1,91 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ckey::Ed25519Public as Public;
+use ctypes::BlockNumber;
+use primitives::H256;
+use rlp::DecoderError;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// The header is not newer than the trusted header.
+    NotNewer {
+        trusted: BlockNumber,
+        found: BlockNumber,
+    },
+    /// The commit is not the child of the header.
+    CommitMismatch,
+    /// The header is not the child of the given parent or the trusted header.
+    ParentMismatch,
+    InvalidSeal(String),
+    /// The signature of the validator is invalid.
+    InvalidSignature(Public),
+    /// The validator set doesn't match the hash in the header.
+    InvalidNextValidatorSet {
+        expected: H256,
+        found: H256,
+    },
+    /// The signed delegation is not more than two thirds of the validator set.
+    NotEnoughVotes {
+        signed: u64,
+        total: u64,
+    },
+    /// The trusted validators signed less than one third of their delegation, so the header cannot be trusted
+    /// without the headers between them.
+    InsufficientOverlap {
+        signed: u64,
+        total: u64,
+    },
+    /// The source failed to give the header.
+    Source(String),
+}
+
+impl From<DecoderError> for Error {
+    fn from(err: DecoderError) -> Self {
+        Error::InvalidSeal(format!("{:?}", err))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotNewer {
+                trusted,
+                found,
+            } => write!(f, "Header #{} is not newer than the trusted header #{}", found, trusted),
+            Error::CommitMismatch => write!(f, "The commit is not the child of the header"),
+            Error::ParentMismatch => write!(f, "The header is not the child of its parent"),
+            Error::InvalidSeal(err) => write!(f, "Invalid seal: {}", err),
+            Error::InvalidSignature(public) => write!(f, "Invalid signature of {:?}", public),
+            Error::InvalidNextValidatorSet {
+                expected,
+                found,
+            } => write!(f, "The next validator set hash is expected to be {} but {}", expected, found),
+            Error::NotEnoughVotes {
+                signed,
+                total,
+            } => write!(f, "Only {} of {} delegation signed", signed, total),
+            Error::InsufficientOverlap {
+                signed,
+                total,
+            } => write!(f, "Only {} of {} trusted delegation signed", signed, total),
+            Error::Source(err) => write!(f, "Cannot fetch the header: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
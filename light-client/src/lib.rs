@@ -0,0 +1,32 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A light client follows the chain from a trusted header without executing blocks.
+//!
+//! The precommits for a block are in the seal of its child, so a header is verified with its child header,
+//! which is called the commit. A full node serves them through the `chain_getLightBlock` RPC.
+
+extern crate codechain_core as ccore;
+extern crate codechain_key as ckey;
+extern crate codechain_types as ctypes;
+
+mod client;
+mod error;
+mod light_block;
+
+pub use crate::client::{LightBlockSource, LightClient};
+pub use crate::error::Error;
+pub use crate::light_block::LightBlock;
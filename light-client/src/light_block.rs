@@ -0,0 +1,64 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ctypes::{CompactValidatorSet, Header};
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+
+/// Everything needed to verify a header.
+#[derive(Clone, Debug)]
+pub struct LightBlock {
+    /// The parent of `header`, whose next validator set hash is the hash of `validator_set`.
+    pub parent: Header,
+    pub header: Header,
+    /// The child of `header`, whose seal has the precommits for `header`.
+    pub commit: Header,
+    /// The validators who signed the precommits in `commit`.
+    /// Its hash is `parent.next_validator_set_hash()`.
+    pub validator_set: CompactValidatorSet,
+    /// The validators who will sign the child of `header`.
+    /// Its hash is `header.next_validator_set_hash()`.
+    pub next_validator_set: CompactValidatorSet,
+}
+
+impl Encodable for LightBlock {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(5)
+            .append(&self.parent)
+            .append(&self.header)
+            .append(&self.commit)
+            .append(&self.validator_set)
+            .append(&self.next_validator_set);
+    }
+}
+
+impl Decodable for LightBlock {
+    fn decode(rlp: &Rlp<'_>) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 5 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 5,
+            })
+        }
+        Ok(Self {
+            parent: rlp.val_at(0)?,
+            header: rlp.val_at(1)?,
+            commit: rlp.val_at(2)?,
+            validator_set: rlp.val_at(3)?,
+            next_validator_set: rlp.val_at(4)?,
+        })
+    }
+}
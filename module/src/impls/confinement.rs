@@ -0,0 +1,513 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Applies `ResourceLimits` to a module process.
+//!
+//! The rlimits, the network namespace and the seccomp filter are applied in the child between `fork` and `exec`,
+//! so the closure returned by `prepare` must not allocate. Everything it needs is built beforehand in the parent.
+//! The cgroup is created by the parent, which moves the child into it right after spawning it.
+
+use crate::limits::{CgroupLimits, ResourceLimits};
+use anyhow::{anyhow, Context as _};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup/foundry";
+const CPU_MAX_PERIOD: u64 = 100_000;
+
+/// Returns the function to be called in the child process before `exec`.
+pub(crate) fn prepare(limits: &ResourceLimits) -> anyhow::Result<impl FnMut() -> io::Result<()> + Send + Sync> {
+    let rlimits: Vec<(libc::__rlimit_resource_t, u64)> = vec![
+        limits.memory.map(|value| (libc::RLIMIT_AS, value)),
+        limits.cpu_time.map(|value| (libc::RLIMIT_CPU, value)),
+        limits.open_files.map(|value| (libc::RLIMIT_NOFILE, value)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let filter = match &limits.syscalls {
+        Some(syscalls) => Some(seccomp::filter(syscalls)?),
+        None => None,
+    };
+    let unshare_flags = if !limits.no_network {
+        0
+    } else if unsafe { libc::geteuid() } == 0 {
+        libc::CLONE_NEWNET
+    } else {
+        // An unprivileged process needs its own user namespace to create a network namespace.
+        libc::CLONE_NEWUSER | libc::CLONE_NEWNET
+    };
+
+    Ok(move || {
+        for (resource, value) in &rlimits {
+            let rlimit = libc::rlimit {
+                rlim_cur: *value,
+                rlim_max: *value,
+            };
+            if unsafe { libc::setrlimit(*resource, &rlimit) } != 0 {
+                return Err(io::Error::last_os_error())
+            }
+        }
+        if unshare_flags != 0 && unsafe { libc::unshare(unshare_flags) } != 0 {
+            return Err(io::Error::last_os_error())
+        }
+        // The filter must be the last one, because it may deny the system calls made above.
+        if let Some(filter) = &filter {
+            seccomp::install(filter)?;
+        }
+        Ok(())
+    })
+}
+
+/// A cgroup v2 made for a module process. It is removed when dropped.
+pub(crate) struct Cgroup {
+    path: PathBuf,
+}
+
+impl Cgroup {
+    pub(crate) fn create(limits: &CgroupLimits) -> anyhow::Result<Self> {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+        let controllers: Vec<&str> =
+            vec![limits.memory_max.map(|_| "memory"), limits.cpu_max.map(|_| "cpu")].into_iter().flatten().collect();
+        enable_controllers(Path::new(CGROUP_ROOT), &controllers)?;
+
+        let path = PathBuf::from(CGROUP_ROOT).join(format!(
+            "{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&path).with_context(|| format!("Cannot create the cgroup {}", path.display()))?;
+        let cgroup = Self {
+            path,
+        };
+        if let Some(memory_max) = limits.memory_max {
+            cgroup.write("memory.max", &memory_max.to_string())?;
+        }
+        if let Some(cpu_max) = limits.cpu_max {
+            let quota = CPU_MAX_PERIOD * u64::from(cpu_max) / 100;
+            cgroup.write("cpu.max", &format!("{} {}", quota, CPU_MAX_PERIOD))?;
+        }
+        Ok(cgroup)
+    }
+
+    pub(crate) fn add(&self, pid: u32) -> anyhow::Result<()> {
+        self.write("cgroup.procs", &pid.to_string())
+    }
+
+    fn write(&self, file: &str, value: &str) -> anyhow::Result<()> {
+        let path = self.path.join(file);
+        fs::write(&path, value).with_context(|| format!("Cannot write '{}' to {}", value, path.display()))
+    }
+}
+
+/// Lets the cgroups under `root` use the `controllers`.
+/// The controllers must be delegated to `root` by the `cgroup.subtree_control` of its parent.
+fn enable_controllers(root: &Path, controllers: &[&str]) -> anyhow::Result<()> {
+    fs::create_dir_all(root).with_context(|| format!("Cannot create the cgroup {}", root.display()))?;
+    let read = |file: &str| {
+        let path = root.join(file);
+        fs::read_to_string(&path).with_context(|| format!("Cannot read {}, which needs cgroup v2", path.display()))
+    };
+    let available = read("cgroup.controllers")?;
+    let enabled = read("cgroup.subtree_control")?;
+    for controller in controllers {
+        if !available.split_whitespace().any(|name| name == *controller) {
+            return Err(anyhow!(
+                "The {} controller is not delegated to {}. Add it to the cgroup.subtree_control of the parent cgroup",
+                controller,
+                root.display()
+            ))
+        }
+        if !enabled.split_whitespace().any(|name| name == *controller) {
+            let path = root.join("cgroup.subtree_control");
+            let value = format!("+{}", controller);
+            fs::write(&path, &value).with_context(|| format!("Cannot write '{}' to {}", value, path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+impl Drop for Cgroup {
+    fn drop(&mut self) {
+        // It fails while the process is alive, and then the cgroup is left to be cleaned up by the system.
+        let _ = fs::remove_dir(&self.path);
+    }
+}
+
+mod seccomp {
+    use super::*;
+
+    // The classic BPF instructions used by the filter.
+    const BPF_LD_W_ABS: u16 = 0x20;
+    const BPF_JMP_JEQ_K: u16 = 0x15;
+    const BPF_RET_K: u16 = 0x06;
+
+    // The offsets in `struct seccomp_data`.
+    const NR_OFFSET: u32 = 0;
+    const ARCH_OFFSET: u32 = 4;
+
+    const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+    const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xc000_003e;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+    // The system calls newer than the libc crate.
+    #[cfg(target_arch = "x86_64")]
+    const SYS_RSEQ: libc::c_long = 334;
+    #[cfg(target_arch = "aarch64")]
+    const SYS_RSEQ: libc::c_long = 293;
+    const SYS_CLONE3: libc::c_long = 435;
+    const SYS_CLOSE_RANGE: libc::c_long = 436;
+
+    pub(super) type Filter = Vec<libc::sock_filter>;
+
+    fn statement(code: u16, k: u32) -> libc::sock_filter {
+        libc::sock_filter {
+            code,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    fn jump(code: u16, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+        libc::sock_filter {
+            code,
+            jt,
+            jf,
+            k,
+        }
+    }
+
+    /// Builds a filter allowing only the given system calls and the baseline ones.
+    pub(super) fn filter(syscalls: &[String]) -> anyhow::Result<Filter> {
+        let mut numbers = baseline();
+        for name in syscalls {
+            numbers.push(number(name).ok_or_else(|| anyhow!("Unknown system call '{}'", name))?);
+        }
+        numbers.sort_unstable();
+        numbers.dedup();
+
+        let mut filter = vec![
+            statement(BPF_LD_W_ABS, ARCH_OFFSET),
+            jump(BPF_JMP_JEQ_K, AUDIT_ARCH, 1, 0),
+            statement(BPF_RET_K, SECCOMP_RET_KILL_PROCESS),
+            statement(BPF_LD_W_ABS, NR_OFFSET),
+        ];
+        for number in numbers {
+            filter.push(jump(BPF_JMP_JEQ_K, number as u32, 0, 1));
+            filter.push(statement(BPF_RET_K, SECCOMP_RET_ALLOW));
+        }
+        filter.push(statement(BPF_RET_K, SECCOMP_RET_KILL_PROCESS));
+        Ok(filter)
+    }
+
+    pub(super) fn install(filter: &Filter) -> io::Result<()> {
+        let program = libc::sock_fprog {
+            len: filter.len() as u16,
+            filter: filter.as_ptr() as *mut libc::sock_filter,
+        };
+        unsafe {
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                return Err(io::Error::last_os_error())
+            }
+            if libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER, &program as *const libc::sock_fprog) != 0 {
+                return Err(io::Error::last_os_error())
+            }
+        }
+        Ok(())
+    }
+
+    fn number(name: &str) -> Option<libc::c_long> {
+        syscall_table()
+            .iter()
+            .chain(arch_syscall_table())
+            .find(|(syscall, _)| *syscall == name)
+            .map(|(_, number)| *number)
+    }
+
+    /// The system calls allowed to every module.
+    /// `execve` runs the module after the filter is installed, and the others are made by the dynamic loader and
+    /// the Rust runtime before `main` starts or when the process exits, so no module can run without them.
+    fn baseline() -> Vec<libc::c_long> {
+        let mut numbers = vec![
+            libc::SYS_execve,
+            libc::SYS_brk,
+            libc::SYS_mmap,
+            libc::SYS_munmap,
+            libc::SYS_mprotect,
+            libc::SYS_openat,
+            libc::SYS_read,
+            libc::SYS_pread64,
+            libc::SYS_write,
+            libc::SYS_close,
+            libc::SYS_fstat,
+            libc::SYS_newfstatat,
+            libc::SYS_faccessat,
+            libc::SYS_readlinkat,
+            libc::SYS_uname,
+            libc::SYS_prlimit64,
+            libc::SYS_set_tid_address,
+            libc::SYS_set_robust_list,
+            SYS_RSEQ,
+            libc::SYS_rt_sigaction,
+            libc::SYS_rt_sigprocmask,
+            libc::SYS_rt_sigreturn,
+            libc::SYS_sigaltstack,
+            libc::SYS_futex,
+            libc::SYS_getrandom,
+            libc::SYS_sched_getaffinity,
+            libc::SYS_clock_gettime,
+            libc::SYS_getpid,
+            libc::SYS_gettid,
+            libc::SYS_ppoll,
+            libc::SYS_exit,
+            libc::SYS_exit_group,
+        ];
+        #[cfg(target_arch = "x86_64")]
+        numbers.extend_from_slice(&[libc::SYS_arch_prctl, libc::SYS_access, libc::SYS_readlink, libc::SYS_poll]);
+        numbers
+    }
+
+    /// The system calls which exist only in some architectures.
+    #[cfg(target_arch = "x86_64")]
+    fn arch_syscall_table() -> &'static [(&'static str, libc::c_long)] {
+        &[
+            ("arch_prctl", libc::SYS_arch_prctl),
+            ("access", libc::SYS_access),
+            ("open", libc::SYS_open),
+            ("stat", libc::SYS_stat),
+            ("lstat", libc::SYS_lstat),
+            ("readlink", libc::SYS_readlink),
+            ("unlink", libc::SYS_unlink),
+            ("mkdir", libc::SYS_mkdir),
+            ("rmdir", libc::SYS_rmdir),
+            ("rename", libc::SYS_rename),
+            ("getdents", libc::SYS_getdents),
+            ("dup2", libc::SYS_dup2),
+            ("pipe", libc::SYS_pipe),
+            ("poll", libc::SYS_poll),
+            ("select", libc::SYS_select),
+            ("epoll_create", libc::SYS_epoll_create),
+            ("epoll_wait", libc::SYS_epoll_wait),
+            ("eventfd", libc::SYS_eventfd),
+            ("accept", libc::SYS_accept),
+            ("fork", libc::SYS_fork),
+            ("vfork", libc::SYS_vfork),
+            ("time", libc::SYS_time),
+        ]
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    fn arch_syscall_table() -> &'static [(&'static str, libc::c_long)] {
+        &[]
+    }
+
+    fn syscall_table() -> &'static [(&'static str, libc::c_long)] {
+        &[
+            ("read", libc::SYS_read),
+            ("write", libc::SYS_write),
+            ("readv", libc::SYS_readv),
+            ("writev", libc::SYS_writev),
+            ("pread64", libc::SYS_pread64),
+            ("pwrite64", libc::SYS_pwrite64),
+            ("openat", libc::SYS_openat),
+            ("faccessat", libc::SYS_faccessat),
+            ("close", libc::SYS_close),
+            ("close_range", SYS_CLOSE_RANGE),
+            ("fstat", libc::SYS_fstat),
+            ("newfstatat", libc::SYS_newfstatat),
+            ("statx", libc::SYS_statx),
+            ("lseek", libc::SYS_lseek),
+            ("fcntl", libc::SYS_fcntl),
+            ("fsync", libc::SYS_fsync),
+            ("fdatasync", libc::SYS_fdatasync),
+            ("ftruncate", libc::SYS_ftruncate),
+            ("fallocate", libc::SYS_fallocate),
+            ("flock", libc::SYS_flock),
+            ("getdents64", libc::SYS_getdents64),
+            ("mkdirat", libc::SYS_mkdirat),
+            ("renameat", libc::SYS_renameat),
+            ("renameat2", libc::SYS_renameat2),
+            ("ioctl", libc::SYS_ioctl),
+            ("dup", libc::SYS_dup),
+            ("dup3", libc::SYS_dup3),
+            ("pipe2", libc::SYS_pipe2),
+            ("mmap", libc::SYS_mmap),
+            ("munmap", libc::SYS_munmap),
+            ("mprotect", libc::SYS_mprotect),
+            ("mremap", libc::SYS_mremap),
+            ("madvise", libc::SYS_madvise),
+            ("mlock", libc::SYS_mlock),
+            ("munlock", libc::SYS_munlock),
+            ("membarrier", libc::SYS_membarrier),
+            ("brk", libc::SYS_brk),
+            ("rt_sigaction", libc::SYS_rt_sigaction),
+            ("rt_sigprocmask", libc::SYS_rt_sigprocmask),
+            ("rt_sigreturn", libc::SYS_rt_sigreturn),
+            ("sigaltstack", libc::SYS_sigaltstack),
+            ("clone", libc::SYS_clone),
+            ("clone3", SYS_CLONE3),
+            ("wait4", libc::SYS_wait4),
+            ("kill", libc::SYS_kill),
+            ("tgkill", libc::SYS_tgkill),
+            ("prctl", libc::SYS_prctl),
+            ("rseq", SYS_RSEQ),
+            ("uname", libc::SYS_uname),
+            ("futex", libc::SYS_futex),
+            ("set_robust_list", libc::SYS_set_robust_list),
+            ("set_tid_address", libc::SYS_set_tid_address),
+            ("sched_yield", libc::SYS_sched_yield),
+            ("sched_getaffinity", libc::SYS_sched_getaffinity),
+            ("sched_setaffinity", libc::SYS_sched_setaffinity),
+            ("getrusage", libc::SYS_getrusage),
+            ("sysinfo", libc::SYS_sysinfo),
+            ("nanosleep", libc::SYS_nanosleep),
+            ("clock_gettime", libc::SYS_clock_gettime),
+            ("clock_nanosleep", libc::SYS_clock_nanosleep),
+            ("gettimeofday", libc::SYS_gettimeofday),
+            ("getrandom", libc::SYS_getrandom),
+            ("getpid", libc::SYS_getpid),
+            ("getppid", libc::SYS_getppid),
+            ("gettid", libc::SYS_gettid),
+            ("getuid", libc::SYS_getuid),
+            ("geteuid", libc::SYS_geteuid),
+            ("getgid", libc::SYS_getgid),
+            ("getegid", libc::SYS_getegid),
+            ("prlimit64", libc::SYS_prlimit64),
+            ("socket", libc::SYS_socket),
+            ("connect", libc::SYS_connect),
+            ("bind", libc::SYS_bind),
+            ("listen", libc::SYS_listen),
+            ("accept4", libc::SYS_accept4),
+            ("socketpair", libc::SYS_socketpair),
+            ("getsockname", libc::SYS_getsockname),
+            ("getpeername", libc::SYS_getpeername),
+            ("sendto", libc::SYS_sendto),
+            ("recvfrom", libc::SYS_recvfrom),
+            ("sendmsg", libc::SYS_sendmsg),
+            ("recvmsg", libc::SYS_recvmsg),
+            ("sendmmsg", libc::SYS_sendmmsg),
+            ("recvmmsg", libc::SYS_recvmmsg),
+            ("shutdown", libc::SYS_shutdown),
+            ("getsockopt", libc::SYS_getsockopt),
+            ("setsockopt", libc::SYS_setsockopt),
+            ("epoll_create1", libc::SYS_epoll_create1),
+            ("epoll_ctl", libc::SYS_epoll_ctl),
+            ("epoll_pwait", libc::SYS_epoll_pwait),
+            ("eventfd2", libc::SYS_eventfd2),
+            ("ppoll", libc::SYS_ppoll),
+            ("pselect6", libc::SYS_pselect6),
+            ("timerfd_create", libc::SYS_timerfd_create),
+            ("timerfd_settime", libc::SYS_timerfd_settime),
+            ("unlinkat", libc::SYS_unlinkat),
+            ("getcwd", libc::SYS_getcwd),
+            ("readlinkat", libc::SYS_readlinkat),
+            ("exit", libc::SYS_exit),
+            ("exit_group", libc::SYS_exit_group),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `body` in a child process confined by a filter allowing `syscalls`, and returns its wait status.
+    fn run_confined(syscalls: &[&str], body: fn()) -> libc::c_int {
+        let filter = seccomp::filter(&syscalls.iter().map(|name| (*name).to_owned()).collect::<Vec<_>>()).unwrap();
+        match unsafe { libc::fork() } {
+            0 => {
+                // Only the async-signal-safe system calls are made in the forked child.
+                if seccomp::install(&filter).is_err() {
+                    unsafe { libc::_exit(2) }
+                }
+                body();
+                unsafe { libc::_exit(0) }
+            }
+            pid => {
+                assert!(pid > 0, "Cannot fork");
+                let mut status = 0;
+                assert_eq!(pid, unsafe { libc::waitpid(pid, &mut status, 0) });
+                status
+            }
+        }
+    }
+
+    #[test]
+    fn allow_the_baseline_system_calls() {
+        let status = run_confined(&[], || unsafe {
+            libc::syscall(libc::SYS_getpid);
+        });
+        assert!(libc::WIFEXITED(status), "The process is not exited: {}", status);
+        assert_eq!(0, libc::WEXITSTATUS(status));
+    }
+
+    #[test]
+    fn allow_the_given_system_calls() {
+        let status = run_confined(&["getppid"], || unsafe {
+            libc::syscall(libc::SYS_getppid);
+        });
+        assert!(libc::WIFEXITED(status), "The process is not exited: {}", status);
+        assert_eq!(0, libc::WEXITSTATUS(status));
+    }
+
+    #[test]
+    fn kill_the_process_making_a_denied_system_call() {
+        let status = run_confined(&[], || unsafe {
+            libc::syscall(libc::SYS_getppid);
+        });
+        assert!(libc::WIFSIGNALED(status), "The process is not killed: {}", status);
+        assert_eq!(libc::SIGSYS, libc::WTERMSIG(status));
+    }
+
+    #[test]
+    fn reject_unknown_system_calls() {
+        assert!(seccomp::filter(&["no_such_syscall".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn create_a_cgroup_with_the_limits() {
+        if !Path::new("/sys/fs/cgroup/cgroup.controllers").exists() || fs::create_dir_all(CGROUP_ROOT).is_err() {
+            // cgroup v2 is not mounted or not writable.
+            return
+        }
+        let limits = CgroupLimits {
+            memory_max: Some(64 << 20),
+            cpu_max: Some(50),
+        };
+        let available = fs::read_to_string(Path::new(CGROUP_ROOT).join("cgroup.controllers")).unwrap();
+        let delegated = |controller| available.split_whitespace().any(|name| name == controller);
+        if !delegated("memory") || !delegated("cpu") {
+            let err = Cgroup::create(&limits).err().expect("The controllers are not delegated");
+            assert!(format!("{:#}", err).contains("is not delegated"), "{:#}", err);
+            return
+        }
+
+        let cgroup = Cgroup::create(&limits).unwrap();
+        assert_eq!("67108864", fs::read_to_string(cgroup.path.join("memory.max")).unwrap().trim());
+        assert_eq!("50000 100000", fs::read_to_string(cgroup.path.join("cpu.max")).unwrap().trim());
+        let path = cgroup.path.clone();
+        drop(cgroup);
+        assert!(!path.exists());
+    }
+}
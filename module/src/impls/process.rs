@@ -14,20 +14,27 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::confinement::{self, Cgroup};
+use crate::limits::ResourceLimits;
 use crate::link::{self, Linkable, Linker, Port, LINKERS};
-use crate::sandbox::{LoadError, Sandbox, Sandboxer, SANDBOXERS};
+use crate::sandbox::{report_crash, Crash, LoadError, Sandbox, Sandboxer, SANDBOXERS};
 use anyhow::anyhow;
 use crossbeam::thread;
 use foundry_module_rt::coordinator_interface::{FoundryModule, PartialRtoConfig};
 use fproc_sndbx::execution::executor;
 use fproc_sndbx::ipc::Ipc;
 use linkme::distributed_slice;
+use once_cell::sync;
 use parking_lot::Mutex;
 use remote_trait_object::raw_exchange::HandleToExchange;
 use remote_trait_object::{Config as RtoConfig, Context as RtoContext, ServiceToImport};
-use std::io::Cursor;
+use std::collections::HashMap;
+use std::io::{self, Cursor};
 use std::marker::PhantomData;
-use std::path::Path;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 
 #[distributed_slice(SANDBOXERS)]
@@ -63,7 +70,15 @@ impl<E: ExecutionScheme> Sandboxer for ProcessSandboxer<E> {
         path: &dyn AsRef<Path>,
         init: &dyn erased_serde::Serialize,
         exports: &[(&str, &dyn erased_serde::Serialize)],
+        limits: &ResourceLimits,
     ) -> Result<Box<dyn Sandbox>, LoadError> {
+        if E::is_intra() && !limits.is_unlimited() {
+            return Err(LoadError::Other(anyhow!(
+                "The resources of a module can be limited only in its own process: {:?}",
+                path.as_ref()
+            )))
+        }
+
         let mut init_buffer = Vec::<u8>::new();
         let cbor = &mut serde_cbor::Serializer::new(serde_cbor::ser::IoWrite::new(Cursor::new(&mut init_buffer)));
         init.erased_serialize(&mut erased_serde::Serializer::erase(cbor)).unwrap();
@@ -78,7 +93,7 @@ impl<E: ExecutionScheme> Sandboxer for ProcessSandboxer<E> {
             })
             .collect();
 
-        Ok(Box::new(ProcessSandbox::<E>::new(path.as_ref(), &init_buffer, &exports)?))
+        Ok(Box::new(ProcessSandbox::<E>::new(path.as_ref(), &init_buffer, &exports, limits)?))
     }
 }
 
@@ -101,7 +116,7 @@ pub struct MultiProcess;
 
 impl ExecutionScheme for MultiProcess {
     type Ipc = fproc_sndbx::ipc::unix_socket::DomainSocket;
-    type Execution = fproc_sndbx::execution::executor::Executable;
+    type Execution = SupervisedExecutable;
     fn is_intra() -> bool {
         false
     }
@@ -117,24 +132,151 @@ impl ExecutionScheme for SingleProcess {
     }
 }
 
+/// The launches waiting for their executors, by the ids given to `executor::execute` in place of the paths.
+static LAUNCHES: sync::Lazy<Mutex<HashMap<String, Launch>>> = sync::Lazy::new(Default::default);
+
+/// Runs the module at `path` with the launch.
+///
+/// `executor::execute` of the sandbox crate creates the executor with only the path and the arguments of the
+/// module. So a process is executed with the id of its launch, which `SupervisedExecutable::new` looks up.
+fn execute<E: ExecutionScheme>(path: &str, launch: Launch) -> Result<executor::Context<E::Ipc, E::Execution>, String> {
+    if E::is_intra() {
+        // A module in a thread is never limited nor supervised.
+        return executor::execute::<E::Ipc, E::Execution>(path)
+    }
+
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+    let id = format!("launch-{}", NEXT_ID.fetch_add(1, Ordering::SeqCst));
+    LAUNCHES.lock().insert(id.clone(), launch);
+    let context = executor::execute::<E::Ipc, E::Execution>(&id);
+    // The launch is left when the executor fails before taking it.
+    LAUNCHES.lock().remove(&id);
+    context
+}
+
+struct Launch {
+    path: String,
+    limits: ResourceLimits,
+    supervision: Arc<Supervision>,
+}
+
+/// The state of a module process shared by its sandbox and its supervisor thread.
+struct Supervision {
+    path: PathBuf,
+    /// Whether the sandbox asked the module to shut down, so that its exit is not a crash.
+    shutting_down: AtomicBool,
+    /// Whether the supervisor saw the process exit, so that the module can't be called anymore.
+    exited: AtomicBool,
+}
+
+impl Supervision {
+    fn on_exit(&self, status: io::Result<ExitStatus>) {
+        self.exited.store(true, Ordering::SeqCst);
+        if self.shutting_down.load(Ordering::SeqCst) {
+            return
+        }
+        let reason = match status {
+            Ok(status) => match (status.code(), status.signal()) {
+                (Some(code), _) => format!("exited with status {}", code),
+                (None, Some(signal)) => format!("killed by signal {}", signal),
+                (None, None) => status.to_string(),
+            },
+            Err(err) => format!("cannot be waited: {}", err),
+        };
+        report_crash(Crash {
+            path: self.path.clone(),
+            reason,
+        });
+    }
+}
+
+/// Runs a module in a child process with the limits given by `ProcessSandbox::new`,
+/// and watches the process so that a crash is reported instead of going unnoticed.
+pub struct SupervisedExecutable {
+    supervisor: Option<std::thread::JoinHandle<()>>,
+}
+
+impl executor::Executor for SupervisedExecutable {
+    fn new(id: &str, args: &[&str]) -> Result<Self, String> {
+        // Running a module without its limits would silently drop the confinement.
+        let launch = LAUNCHES.lock().remove(id).ok_or_else(|| format!("{} is not a launch given to `execute`", id))?;
+        let path = launch.path;
+
+        let mut command = Command::new(&path);
+        command.args(args);
+        if !launch.limits.is_unlimited() {
+            let pre_exec = confinement::prepare(&launch.limits).map_err(|err| format!("{:#}", err))?;
+            // The closure only makes system calls with the values prepared by the parent.
+            unsafe {
+                command.pre_exec(pre_exec);
+            }
+        }
+        let cgroup =
+            launch.limits.cgroup.as_ref().map(Cgroup::create).transpose().map_err(|err| format!("{:#}", err))?;
+
+        let mut child = command.spawn().map_err(|err| err.to_string())?;
+        if let Some(cgroup) = &cgroup {
+            if let Err(err) = cgroup.add(child.id()) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("{:#}", err))
+            }
+        }
+
+        let supervision = launch.supervision;
+        let supervisor = std::thread::Builder::new()
+            .name(format!("supervisor of {}", path))
+            .spawn(move || {
+                let status = child.wait();
+                drop(cgroup);
+                supervision.on_exit(status);
+            })
+            .map_err(|err| err.to_string())?;
+
+        Ok(Self {
+            supervisor: Some(supervisor),
+        })
+    }
+
+    fn join(&mut self) {
+        if let Some(supervisor) = self.supervisor.take() {
+            supervisor.join().unwrap();
+        }
+    }
+}
+
 pub struct ProcessSandbox<E: ExecutionScheme> {
     _process: Mutex<executor::Context<E::Ipc, E::Execution>>,
+    supervision: Arc<Supervision>,
     /// module should be dropped first before rto_context
     module: Box<dyn FoundryModule>,
     rto_context: remote_trait_object::Context,
 }
 
 impl<E: ExecutionScheme> ProcessSandbox<E> {
-    fn new(path: &Path, init: &[u8], exports: &[(String, Vec<u8>)]) -> Result<Self, LoadError> {
-        let mut process =
-            executor::execute::<E::Ipc, E::Execution>(path.to_str().ok_or_else(|| LoadError::ModuleCorrupted {
-                path: path.to_owned(),
-                source: Some(anyhow!("the path isn't a valid str: {:?}", path)),
-            })?)
-            .map_err(|e| LoadError::ModuleCorrupted {
+    fn new(
+        path: &Path,
+        init: &[u8],
+        exports: &[(String, Vec<u8>)],
+        limits: &ResourceLimits,
+    ) -> Result<Self, LoadError> {
+        let supervision = Arc::new(Supervision {
+            path: path.to_owned(),
+            shutting_down: AtomicBool::new(false),
+            exited: AtomicBool::new(false),
+        });
+        let process = path.to_str().ok_or_else(|| anyhow!("the path isn't a valid str: {:?}", path)).and_then(|path| {
+            let launch = Launch {
                 path: path.to_owned(),
-                source: Some(anyhow!(e)),
-            })?;
+                limits: limits.clone(),
+                supervision: Arc::clone(&supervision),
+            };
+            execute::<E>(path, launch).map_err(|e| anyhow!(e))
+        });
+        let mut process = process.map_err(|e| LoadError::ModuleCorrupted {
+            path: path.to_owned(),
+            source: Some(e),
+        })?;
 
         // TODO: parse init to get proper rto config
         let rto_config = RtoConfig::default_setup();
@@ -147,6 +289,7 @@ impl<E: ExecutionScheme> ProcessSandbox<E> {
 
         Ok(Self {
             _process: Mutex::new(process),
+            supervision,
             rto_context,
             module,
         })
@@ -216,8 +359,12 @@ impl<E: ExecutionScheme> Linkable for ProcessSandbox<E> {
 
 impl<E: ExecutionScheme> Drop for ProcessSandbox<E> {
     fn drop(&mut self) {
+        self.supervision.shutting_down.store(true, Ordering::SeqCst);
         self.rto_context.disable_garbage_collection();
-        self.module.shutdown();
+        // A call to the module would never be answered by the exited process.
+        if !self.supervision.exited.load(Ordering::SeqCst) {
+            self.module.shutdown();
+        }
     }
 }
 
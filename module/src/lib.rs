@@ -18,6 +18,7 @@ extern crate foundry_process_sandbox as fproc_sndbx;
 use linkme::distributed_slice;
 
 pub mod impls;
+pub mod limits;
 pub mod link;
 pub mod sandbox;
 
@@ -0,0 +1,55 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+
+/// Limits on the resources a sandboxed module can use.
+///
+/// Every limit is optional and nothing is limited by default.
+/// A `Sandboxer` that cannot enforce a requested limit must refuse to load the module.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ResourceLimits {
+    /// The maximum size of the virtual memory in bytes (`RLIMIT_AS`).
+    pub memory: Option<u64>,
+    /// The maximum CPU time in seconds (`RLIMIT_CPU`).
+    pub cpu_time: Option<u64>,
+    /// The maximum number of open file descriptors (`RLIMIT_NOFILE`).
+    pub open_files: Option<u64>,
+    /// The caps enforced by a cgroup v2 created for the module.
+    pub cgroup: Option<CgroupLimits>,
+    /// The names of the system calls the module can make. Any other system call kills the module.
+    /// The system calls needed to start and exit a process, such as `mmap` and `exit_group`, are always allowed.
+    pub syscalls: Option<Vec<String>>,
+    /// Runs the module in a new network namespace, which has no network interface but the loopback.
+    pub no_network: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct CgroupLimits {
+    /// The value for `memory.max` in bytes.
+    pub memory_max: Option<u64>,
+    /// The share of a single CPU the module can use, in percent. It is written to `cpu.max`.
+    pub cpu_max: Option<u32>,
+}
+
+impl ResourceLimits {
+    /// Whether nothing is limited.
+    pub fn is_unlimited(&self) -> bool {
+        *self == Self::default()
+    }
+}
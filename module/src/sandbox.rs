@@ -14,9 +14,12 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::limits::ResourceLimits;
 use crate::link::Linkable;
+use crossbeam::channel::{self, Receiver, Sender};
 use linkme::distributed_slice;
 use once_cell::sync;
+use parking_lot::Mutex;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::path::{Path, PathBuf};
@@ -55,12 +58,16 @@ pub trait Sandboxer: Send + Sync {
     /// object for `erased_serde::Serialize` to allow for serialization into whatever format
     /// the receiver likes.
     ///
+    /// The `limits` restrict the resources the module can use. A `Sandboxer` must fail
+    /// instead of loading the module without the limits it cannot enforce.
+    ///
     /// [`Sandbox`]: ./trait.Sandbox.html
     fn load(
         &self,
         path: &dyn AsRef<Path>,
         init: &dyn erased_serde::Serialize,
         exports: &[(&str, &dyn erased_serde::Serialize)],
+        limits: &ResourceLimits,
     ) -> Result<Box<dyn Sandbox>, LoadError>;
 }
 
@@ -85,3 +92,28 @@ pub enum LoadError {
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
+
+/// A module that stopped while its sandbox was still in use.
+#[derive(Clone, Debug)]
+pub struct Crash {
+    /// The path the module was loaded from.
+    pub path: PathBuf,
+    /// How the module stopped, e.g. the signal that killed it.
+    pub reason: String,
+}
+
+static CRASH_SUBSCRIBERS: sync::Lazy<Mutex<Vec<Sender<Crash>>>> = sync::Lazy::new(Default::default);
+
+/// Reports a crashed module to all the subscribers.
+/// A `Sandboxer` calls this when it finds that one of its modules stopped.
+pub fn report_crash(crash: Crash) {
+    CRASH_SUBSCRIBERS.lock().retain(|subscriber| subscriber.send(crash.clone()).is_ok());
+}
+
+/// Returns a receiver of the crashes reported by all the `Sandboxer`s from now on.
+/// Dropping the receiver cancels the subscription.
+pub fn subscribe_crashes() -> Receiver<Crash> {
+    let (sender, receiver) = channel::unbounded();
+    CRASH_SUBSCRIBERS.lock().push(sender);
+    receiver
+}
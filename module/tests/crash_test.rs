@@ -0,0 +1,95 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Kills a module running in the multi-process sandboxer, and checks that the crash is reported.
+//!
+//! The test binary runs itself as the module, so it doesn't use the default test harness.
+
+extern crate codechain_module as cmodule;
+extern crate foundry_process_sandbox as fproc_sndbx;
+
+use cmodule::impls::process::*;
+use cmodule::sandbox::*;
+use foundry_module_rt::UserModule;
+use fproc_sndbx::ipc::unix_socket::DomainSocket;
+use remote_trait_object::raw_exchange::{HandleToExchange, Skeleton};
+use remote_trait_object::Context as RtoContext;
+use std::time::Duration;
+
+/// Set when the test binary is executed as the module.
+const RUN_AS_MODULE: &str = "FOUNDRY_CRASH_TEST_MODULE";
+
+/// A module which tells its process id.
+struct Victim;
+
+impl UserModule for Victim {
+    fn new(_arg: &[u8]) -> Self {
+        Victim
+    }
+
+    fn prepare_service_to_export(&mut self, _ctor_name: &str, _ctor_arg: &[u8]) -> Skeleton {
+        unreachable!("Victim exports nothing")
+    }
+
+    fn import_service(&mut self, _rto_context: &RtoContext, _name: &str, _handle: HandleToExchange) {
+        unreachable!("Victim imports nothing")
+    }
+
+    fn debug(&mut self, _arg: &[u8]) -> Vec<u8> {
+        std::process::id().to_le_bytes().to_vec()
+    }
+}
+
+fn load_victim() -> Box<dyn Sandbox> {
+    let path = std::env::current_exe().unwrap();
+    ProcessSandboxer::<MultiProcess>::new().load(&path, &(), &[], &Default::default()).unwrap()
+}
+
+fn killed_module_is_reported() {
+    let crashes = subscribe_crashes();
+    let mut sandbox = load_victim();
+    let mut pid = [0u8; 4];
+    pid.copy_from_slice(&sandbox.debug(&[]));
+    let pid = u32::from_le_bytes(pid);
+
+    assert_eq!(0, unsafe { libc::kill(pid as libc::pid_t, libc::SIGKILL) });
+
+    let crash = crashes.recv_timeout(Duration::from_secs(10)).expect("The crash is not reported");
+    assert_eq!(std::env::current_exe().unwrap(), crash.path);
+    assert_eq!("killed by signal 9", crash.reason);
+    // The sandbox doesn't ask the dead module to shut down.
+    drop(sandbox);
+}
+
+fn unloaded_module_is_not_reported() {
+    let crashes = subscribe_crashes();
+    let mut sandbox = load_victim();
+    sandbox.debug(&[]);
+    drop(sandbox);
+
+    assert!(crashes.recv_timeout(Duration::from_secs(1)).is_err(), "A module shut down by its sandbox crashed");
+}
+
+fn main() {
+    if std::env::var_os(RUN_AS_MODULE).is_some() {
+        foundry_module_rt::start::<DomainSocket, Victim>(std::env::args().collect());
+        return
+    }
+    std::env::set_var(RUN_AS_MODULE, "1");
+
+    killed_module_is_reported();
+    unloaded_module_is_not_reported();
+}
@@ -108,9 +108,11 @@ fn module_bootstrap1() {
     let exports_ref: Vec<(&str, &dyn erased_serde::Serialize)> =
         exports.iter().map(|(name, i)| (name.as_str(), i as &dyn erased_serde::Serialize)).collect();
 
-    let mut sandbox_a = sandboxer.load(&name_a, &("Annyeong", "Konnichiwa"), &exports_ref).unwrap();
+    let mut sandbox_a =
+        sandboxer.load(&name_a, &("Annyeong", "Konnichiwa"), &exports_ref, &Default::default()).unwrap();
 
-    let mut sandbox_b = sandboxer.load(&name_b, &("Konnichiwa", "Annyeong"), &exports_ref).unwrap();
+    let mut sandbox_b =
+        sandboxer.load(&name_b, &("Konnichiwa", "Annyeong"), &exports_ref, &Default::default()).unwrap();
 
     let mut port_a = sandbox_a.new_port();
     let mut port_b = sandbox_b.new_port();
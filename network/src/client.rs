@@ -58,6 +58,14 @@ impl Api for ClientApi {
         }
     }
 
+    fn disconnect(&self, id: &NodeId) {
+        if let Err(err) = self.p2p_channel.send(P2pMessage::Disconnect(id.into_addr())) {
+            cerror!(NETAPI, "`{}` cannot disconnect {} : {:?}", self.name, id.into_addr(), err);
+        } else {
+            cinfo!(NETAPI, "`{}` disconnects {}", self.name, id.into_addr());
+        }
+    }
+
     fn set_timer(&self, token: TimerToken, duration: Duration) -> NetworkExtensionResult<()> {
         self.timer.schedule_repeat(duration, token)?;
         Ok(())
@@ -287,6 +295,10 @@ mod tests {
             unimplemented!()
         }
 
+        fn disconnect(&self, _id: &NodeId) {
+            unimplemented!()
+        }
+
         fn set_timer(&self, _timer_id: usize, _duration: Duration) -> NetworkExtensionResult<()> {
             unimplemented!()
         }
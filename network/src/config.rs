@@ -17,12 +17,23 @@
 use std::net::Ipv4Addr;
 
 use crate::filters::FilterEntry;
+use crate::nat::NatType;
 use crate::SocketAddr;
 
 pub struct Config {
     pub address: Ipv4Addr,
     pub port: u16,
+    /// The address advertised to the peers. It is detected automatically if it's not given.
+    pub public_address: Option<SocketAddr>,
+    pub nat: NatType,
+    pub nat_gateway: Option<Ipv4Addr>,
     pub bootstrap_addresses: Vec<SocketAddr>,
+    /// The peers whose addresses are never gossiped, e.g. the validator behind sentry nodes.
+    pub private_peers: Vec<SocketAddr>,
+    /// The peers to which the node always reconnects.
+    pub persistent_peers: Vec<SocketAddr>,
+    /// The peers which are not limited by `max_peers`. The node always reconnects to them, too.
+    pub unconditional_peers: Vec<SocketAddr>,
     pub min_peers: usize,
     pub max_peers: usize,
     pub whitelist: Vec<FilterEntry>,
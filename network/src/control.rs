@@ -29,6 +29,7 @@ pub trait Control: Send + Sync {
     fn disconnect(&self, addr: SocketAddr) -> Result<(), Error>;
     fn is_connected(&self, addr: &SocketAddr) -> Result<bool, Error>;
     fn get_port(&self) -> Result<u16, Error>;
+    fn advertised_address(&self) -> Result<Option<SocketAddr>, Error>;
     fn get_peer_count(&self) -> Result<usize, Error>;
     fn established_peers(&self) -> Result<Vec<SocketAddr>, Error>;
 
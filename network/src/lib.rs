@@ -28,6 +28,7 @@ mod client;
 mod config;
 mod extension;
 mod filters;
+mod nat;
 mod node_id;
 mod routing_table;
 mod service;
@@ -48,6 +49,7 @@ pub use crate::node_id::{IntoSocketAddr, NodeId};
 pub use crate::service::{Error as NetworkServiceError, Service as NetworkService};
 
 pub use crate::filters::{FilterEntry, Filters, FiltersControl};
+pub use crate::nat::{ExternalAddress, NatType};
 pub use crate::routing_table::RoutingTable;
 
 pub type EventSender<E> = crossbeam_channel::Sender<E>;
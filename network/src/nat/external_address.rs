@@ -0,0 +1,147 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::SocketAddr;
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+/// An observed IP is trusted only when this many distinct peers agree on it.
+const MIN_AGREEING_OBSERVERS: usize = 2;
+const MAX_OBSERVERS: usize = 32;
+
+/// Decides the address this node advertises to its peers.
+///
+/// The address given by the operator has the highest priority. Otherwise, the address mapped on the NAT gateway
+/// is used, and the IP observed by the peers is used as the last resort.
+pub struct ExternalAddress {
+    public_address: Option<SocketAddr>,
+    listen_port: u16,
+    mapped: RwLock<Option<SocketAddr>>,
+    observations: Mutex<HashMap<IpAddr, IpAddr>>,
+}
+
+impl ExternalAddress {
+    pub fn new(public_address: Option<SocketAddr>, listen_port: u16) -> Self {
+        Self {
+            public_address,
+            listen_port,
+            mapped: Default::default(),
+            observations: Default::default(),
+        }
+    }
+
+    pub fn set_mapped(&self, mapped: Option<SocketAddr>) {
+        *self.mapped.write() = mapped;
+    }
+
+    /// Records that `observer` sees this node at `observed`.
+    pub fn observe(&self, observer: &SocketAddr, observed: &SocketAddr) {
+        // Peers in the same private network cannot tell the external address.
+        if !observer.is_global() || !observed.is_global() {
+            return
+        }
+        let mut observations = self.observations.lock();
+        if observations.len() >= MAX_OBSERVERS && !observations.contains_key(&observer.ip()) {
+            let evicted = *observations.keys().next().expect("observations are not empty");
+            observations.remove(&evicted);
+        }
+        if observations.insert(observer.ip(), observed.ip()) != Some(observed.ip()) {
+            ctrace!(NETWORK, "{} observes this node at {}", observer, observed.ip());
+        }
+    }
+
+    pub fn observed_ip(&self) -> Option<IpAddr> {
+        let observations = self.observations.lock();
+        let mut votes: HashMap<IpAddr, usize> = HashMap::new();
+        for ip in observations.values() {
+            *votes.entry(*ip).or_default() += 1;
+        }
+        votes
+            .into_iter()
+            .filter(|(_, count)| *count >= MIN_AGREEING_OBSERVERS)
+            .max_by_key(|(ip, count)| (*count, *ip))
+            .map(|(ip, _)| ip)
+    }
+
+    pub fn advertised(&self) -> Option<SocketAddr> {
+        if let Some(public_address) = self.public_address {
+            return Some(public_address)
+        }
+        if let Some(mapped) = *self.mapped.read() {
+            return Some(mapped)
+        }
+        self.observed_ip().map(|ip| SocketAddr::new(ip, self.listen_port))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_is_advertised_without_information() {
+        let external = ExternalAddress::new(None, 3485);
+        assert_eq!(None, external.advertised());
+    }
+
+    #[test]
+    fn public_address_has_the_highest_priority() {
+        let public_address = SocketAddr::v4(1, 1, 1, 1, 3485);
+        let external = ExternalAddress::new(Some(public_address), 3485);
+        external.set_mapped(Some(SocketAddr::v4(2, 2, 2, 2, 4000)));
+        assert_eq!(Some(public_address), external.advertised());
+    }
+
+    #[test]
+    fn mapped_address_is_preferred_to_observed_one() {
+        let external = ExternalAddress::new(None, 3485);
+        external.observe(&SocketAddr::v4(5, 5, 5, 5, 3485), &SocketAddr::v4(3, 3, 3, 3, 50000));
+        external.observe(&SocketAddr::v4(6, 6, 6, 6, 3485), &SocketAddr::v4(3, 3, 3, 3, 50001));
+        external.set_mapped(Some(SocketAddr::v4(2, 2, 2, 2, 4000)));
+        assert_eq!(Some(SocketAddr::v4(2, 2, 2, 2, 4000)), external.advertised());
+
+        external.set_mapped(None);
+        assert_eq!(Some(SocketAddr::v4(3, 3, 3, 3, 3485)), external.advertised());
+    }
+
+    #[test]
+    fn single_observer_is_not_trusted() {
+        let external = ExternalAddress::new(None, 3485);
+        external.observe(&SocketAddr::v4(5, 5, 5, 5, 3485), &SocketAddr::v4(3, 3, 3, 3, 50000));
+        external.observe(&SocketAddr::v4(5, 5, 5, 5, 3486), &SocketAddr::v4(3, 3, 3, 3, 50001));
+        assert_eq!(None, external.observed_ip());
+    }
+
+    #[test]
+    fn majority_of_observers_wins() {
+        let external = ExternalAddress::new(None, 3485);
+        external.observe(&SocketAddr::v4(5, 5, 5, 5, 3485), &SocketAddr::v4(3, 3, 3, 3, 50000));
+        external.observe(&SocketAddr::v4(6, 6, 6, 6, 3485), &SocketAddr::v4(3, 3, 3, 3, 50001));
+        external.observe(&SocketAddr::v4(7, 7, 7, 7, 3485), &SocketAddr::v4(3, 3, 3, 3, 50002));
+        external.observe(&SocketAddr::v4(8, 8, 8, 8, 3485), &SocketAddr::v4(4, 4, 4, 4, 50000));
+        external.observe(&SocketAddr::v4(9, 9, 9, 9, 3485), &SocketAddr::v4(4, 4, 4, 4, 50001));
+        assert_eq!(Some("3.3.3.3".parse().unwrap()), external.observed_ip());
+    }
+
+    #[test]
+    fn observations_from_private_peers_are_ignored() {
+        let external = ExternalAddress::new(None, 3485);
+        external.observe(&SocketAddr::v4(192, 168, 0, 2, 3485), &SocketAddr::v4(192, 168, 0, 1, 50000));
+        external.observe(&SocketAddr::v4(192, 168, 0, 3, 3485), &SocketAddr::v4(192, 168, 0, 1, 50001));
+        assert_eq!(None, external.advertised());
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod external_address;
+mod natpmp;
+mod upnp;
+
+pub use self::external_address::ExternalAddress;
+pub use self::natpmp::{NatPmp, NATPMP_PORT};
+pub use self::upnp::{Upnp, SSDP_MULTICAST_ADDR};
+
+use crate::SocketAddr;
+use crossbeam_channel::{RecvTimeoutError, Sender};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::{self, IpAddr, Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const MAPPING_LIFETIME: Duration = Duration::from_secs(20 * 60);
+const UPNP_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NatType {
+    None,
+    Any,
+    Upnp,
+    NatPmp,
+}
+
+impl Default for NatType {
+    fn default() -> Self {
+        NatType::None
+    }
+}
+
+impl FromStr for NatType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(NatType::None),
+            "any" => Ok(NatType::Any),
+            "upnp" => Ok(NatType::Upnp),
+            "natpmp" => Ok(NatType::NatPmp),
+            s => Err(format!("{} isn't a valid NAT type. Possible values are none, any, upnp, natpmp", s)),
+        }
+    }
+}
+
+impl fmt::Display for NatType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NatType::None => write!(f, "none"),
+            NatType::Any => write!(f, "any"),
+            NatType::Upnp => write!(f, "upnp"),
+            NatType::NatPmp => write!(f, "natpmp"),
+        }
+    }
+}
+
+/// A gateway protocol which can open a port on the NAT device.
+pub trait PortMapper: Send {
+    fn name(&self) -> &'static str;
+    fn external_ip(&mut self) -> io::Result<Ipv4Addr>;
+    /// Maps `external_port` on the gateway to `internal` and returns the external port actually mapped,
+    /// which may differ from the requested one.
+    fn add_mapping(&mut self, internal: SocketAddrV4, external_port: u16, lifetime: Duration) -> io::Result<u16>;
+    fn remove_mapping(&mut self, internal: SocketAddrV4, external_port: u16) -> io::Result<()>;
+}
+
+pub fn find_port_mapper(nat_type: NatType, gateway: Option<Ipv4Addr>) -> Option<Box<dyn PortMapper>> {
+    let natpmp = || -> Option<Box<dyn PortMapper>> {
+        let gateway = gateway.or_else(default_gateway)?;
+        let mut mapper = NatPmp::new(net::SocketAddr::new(IpAddr::V4(gateway), NATPMP_PORT));
+        match mapper.external_ip() {
+            Ok(_) => Some(Box::new(mapper)),
+            Err(err) => {
+                cdebug!(NETWORK, "NAT-PMP gateway {} is not available: {}", gateway, err);
+                None
+            }
+        }
+    };
+    let upnp = || -> Option<Box<dyn PortMapper>> {
+        let ssdp_addr = SSDP_MULTICAST_ADDR.parse().expect("SSDP address is valid");
+        match Upnp::discover(ssdp_addr, UPNP_DISCOVERY_TIMEOUT) {
+            Ok(mapper) => Some(Box::new(mapper)),
+            Err(err) => {
+                cdebug!(NETWORK, "UPnP gateway is not available: {}", err);
+                None
+            }
+        }
+    };
+    match nat_type {
+        NatType::None => None,
+        NatType::Upnp => upnp(),
+        NatType::NatPmp => natpmp(),
+        NatType::Any => upnp().or_else(natpmp),
+    }
+}
+
+/// Returns the address of the local interface used to reach `target`.
+pub fn local_address_towards(target: Ipv4Addr) -> io::Result<Ipv4Addr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    // Connecting a UDP socket sends nothing; it only selects the route.
+    socket.connect((target, 9))?;
+    match socket.local_addr()?.ip() {
+        IpAddr::V4(ip) => Ok(ip),
+        IpAddr::V6(_) => Err(io::Error::new(io::ErrorKind::Other, "IPv6 is not supported")),
+    }
+}
+
+pub fn default_gateway() -> Option<Ipv4Addr> {
+    let table = fs::read_to_string("/proc/net/route").ok()?;
+    parse_route_table(&table)
+}
+
+fn parse_route_table(table: &str) -> Option<Ipv4Addr> {
+    table.lines().skip(1).find_map(|line| {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        if fields.len() < 3 || fields[1] != "00000000" {
+            return None
+        }
+        // The kernel prints the address as a native-endian integer of network-order bytes.
+        let gateway = u32::from_str_radix(fields[2], 16).ok()?;
+        let gateway = Ipv4Addr::from(gateway.to_ne_bytes());
+        if gateway.is_unspecified() {
+            None
+        } else {
+            Some(gateway)
+        }
+    })
+}
+
+/// Keeps a port mapping alive until it is dropped.
+pub struct PortMapping {
+    stop: Sender<()>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PortMapping {
+    pub fn start(
+        mut mapper: Box<dyn PortMapper>,
+        internal: SocketAddrV4,
+        external_address: Arc<ExternalAddress>,
+    ) -> io::Result<Self> {
+        let (stop, stop_receiver) = crossbeam_channel::bounded(1);
+        let handle = thread::Builder::new().name("nat".to_string()).spawn(move || {
+            let mut mapped_port = None;
+            loop {
+                let result = mapper
+                    .add_mapping(internal, mapped_port.unwrap_or_else(|| internal.port()), MAPPING_LIFETIME)
+                    .and_then(|port| Ok((mapper.external_ip()?, port)));
+                match result {
+                    Ok((ip, port)) => {
+                        let external = SocketAddr::new(IpAddr::V4(ip), port);
+                        if mapped_port != Some(port) {
+                            cinfo!(NETWORK, "{} mapped {} to {}", mapper.name(), external, internal);
+                        }
+                        mapped_port = Some(port);
+                        if external.is_global() {
+                            external_address.set_mapped(Some(external));
+                        } else {
+                            cwarn!(NETWORK, "{} returned a non-global address {}", mapper.name(), external);
+                            external_address.set_mapped(None);
+                        }
+                    }
+                    Err(err) => {
+                        cwarn!(NETWORK, "Cannot map port {} with {}: {}", internal.port(), mapper.name(), err);
+                        external_address.set_mapped(None);
+                    }
+                }
+                match stop_receiver.recv_timeout(MAPPING_LIFETIME / 2) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => break,
+                }
+            }
+            if let Some(port) = mapped_port {
+                if let Err(err) = mapper.remove_mapping(internal, port) {
+                    cwarn!(NETWORK, "Cannot remove the port mapping of {}: {}", port, err);
+                }
+            }
+            external_address.set_mapped(None);
+        })?;
+        Ok(Self {
+            stop,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Drop for PortMapping {
+    fn drop(&mut self) {
+        let _ = self.stop.send(());
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                cerror!(NETWORK, "The port mapping thread panicked");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_nat_type() {
+        assert_eq!(Ok(NatType::None), "none".parse());
+        assert_eq!(Ok(NatType::Any), "any".parse());
+        assert_eq!(Ok(NatType::Upnp), "upnp".parse());
+        assert_eq!(Ok(NatType::NatPmp), "natpmp".parse());
+        assert!("stun".parse::<NatType>().is_err());
+    }
+
+    #[test]
+    fn default_gateway_from_route_table() {
+        let gateway = Ipv4Addr::new(192, 168, 1, 1);
+        let encoded = format!("{:08X}", u32::from_ne_bytes(gateway.octets()));
+        let table = format!(
+            "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n\
+             eth0\t0001A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0\n\
+             eth0\t00000000\t{}\t0003\t0\t0\t100\t00000000\t0\t0\t0\n",
+            encoded
+        );
+        assert_eq!(Some(gateway), parse_route_table(&table));
+    }
+
+    #[test]
+    fn no_default_gateway_in_route_table() {
+        let table = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n\
+                     eth0\t0001A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0\n";
+        assert_eq!(None, parse_route_table(table));
+    }
+}
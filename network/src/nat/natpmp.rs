@@ -0,0 +1,198 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::PortMapper;
+use std::io;
+use std::net::{self, Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::time::Duration;
+
+// See RFC 6886.
+pub const NATPMP_PORT: u16 = 5351;
+
+const VERSION: u8 = 0;
+const OP_EXTERNAL_ADDRESS: u8 = 0;
+const OP_MAP_TCP: u8 = 2;
+const RESPONSE_BIT: u8 = 0x80;
+
+const EXTERNAL_ADDRESS_RESPONSE_LEN: usize = 12;
+const MAP_RESPONSE_LEN: usize = 16;
+
+const INITIAL_TIMEOUT: Duration = Duration::from_millis(250);
+const MAX_TRIES: usize = 4;
+
+pub struct NatPmp {
+    gateway: net::SocketAddr,
+}
+
+impl NatPmp {
+    pub fn new(gateway: net::SocketAddr) -> Self {
+        Self {
+            gateway,
+        }
+    }
+
+    fn request(&self, request: &[u8], response_len: usize) -> io::Result<[u8; MAP_RESPONSE_LEN]> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.connect(self.gateway)?;
+
+        let mut timeout = INITIAL_TIMEOUT;
+        for _ in 0..MAX_TRIES {
+            socket.send(request)?;
+            socket.set_read_timeout(Some(timeout))?;
+            let mut response = [0u8; MAP_RESPONSE_LEN];
+            match socket.recv(&mut response) {
+                Ok(len) => {
+                    if len < 4 || response[0] != VERSION || response[1] != request[1] | RESPONSE_BIT {
+                        continue
+                    }
+                    let result_code = u16::from_be_bytes([response[2], response[3]]);
+                    if result_code != 0 {
+                        return Err(io::Error::new(
+                            io::ErrorKind::Other,
+                            format!("NAT-PMP gateway returned the result code {}", result_code),
+                        ))
+                    }
+                    if len < response_len {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, "Too short NAT-PMP response"))
+                    }
+                    return Ok(response)
+                }
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut => {
+                    timeout *= 2;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::TimedOut, format!("NAT-PMP gateway {} doesn't respond", self.gateway)))
+    }
+
+    fn map(&self, internal_port: u16, external_port: u16, lifetime: u32) -> io::Result<u16> {
+        let mut request = [0u8; 12];
+        request[0] = VERSION;
+        request[1] = OP_MAP_TCP;
+        request[4..6].copy_from_slice(&internal_port.to_be_bytes());
+        request[6..8].copy_from_slice(&external_port.to_be_bytes());
+        request[8..12].copy_from_slice(&lifetime.to_be_bytes());
+        let response = self.request(&request, MAP_RESPONSE_LEN)?;
+        Ok(u16::from_be_bytes([response[10], response[11]]))
+    }
+}
+
+impl PortMapper for NatPmp {
+    fn name(&self) -> &'static str {
+        "NAT-PMP"
+    }
+
+    fn external_ip(&mut self) -> io::Result<Ipv4Addr> {
+        let response = self.request(&[VERSION, OP_EXTERNAL_ADDRESS], EXTERNAL_ADDRESS_RESPONSE_LEN)?;
+        Ok(Ipv4Addr::new(response[8], response[9], response[10], response[11]))
+    }
+
+    fn add_mapping(&mut self, internal: SocketAddrV4, external_port: u16, lifetime: Duration) -> io::Result<u16> {
+        let lifetime = lifetime.as_secs().min(u64::from(u32::MAX)) as u32;
+        self.map(internal.port(), external_port, lifetime)
+    }
+
+    fn remove_mapping(&mut self, internal: SocketAddrV4, _external_port: u16) -> io::Result<()> {
+        // A mapping is deleted by requesting it again with zero lifetime and zero external port.
+        self.map(internal.port(), 0, 0)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    const EXTERNAL_IP: Ipv4Addr = Ipv4Addr::new(203, 0, 113, 7);
+    const PORT_OFFSET: u16 = 1000;
+
+    /// A stand-in gateway that maps every port to `port + PORT_OFFSET` and drops the first `drop_first` requests.
+    fn start_gateway(drop_first: usize) -> (net::SocketAddr, Arc<AtomicUsize>) {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = socket.local_addr().unwrap();
+        let received = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&received);
+        thread::spawn(move || loop {
+            let mut request = [0u8; 12];
+            let (len, from) = match socket.recv_from(&mut request) {
+                Ok(received) => received,
+                Err(_) => return,
+            };
+            if counter.fetch_add(1, Ordering::SeqCst) < drop_first {
+                continue
+            }
+            let mut response = vec![VERSION, request[1] | RESPONSE_BIT, 0, 0, 0, 0, 0, 1];
+            match (len, request[1]) {
+                (2, OP_EXTERNAL_ADDRESS) => response.extend_from_slice(&EXTERNAL_IP.octets()),
+                (12, OP_MAP_TCP) => {
+                    let internal_port = u16::from_be_bytes([request[4], request[5]]);
+                    let lifetime = u32::from_be_bytes([request[8], request[9], request[10], request[11]]);
+                    let external_port = if lifetime == 0 {
+                        0
+                    } else {
+                        internal_port + PORT_OFFSET
+                    };
+                    response.extend_from_slice(&internal_port.to_be_bytes());
+                    response.extend_from_slice(&external_port.to_be_bytes());
+                    response.extend_from_slice(&lifetime.to_be_bytes());
+                }
+                _ => {
+                    // Unsupported opcode
+                    response[3] = 5;
+                }
+            }
+            socket.send_to(&response, from).unwrap();
+        });
+        (addr, received)
+    }
+
+    #[test]
+    fn external_ip() {
+        let (gateway, _) = start_gateway(0);
+        let mut natpmp = NatPmp::new(gateway);
+        assert_eq!(EXTERNAL_IP, natpmp.external_ip().unwrap());
+    }
+
+    #[test]
+    fn add_and_remove_mapping() {
+        let (gateway, _) = start_gateway(0);
+        let mut natpmp = NatPmp::new(gateway);
+        let internal = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 3485);
+        let external_port = natpmp.add_mapping(internal, 3485, Duration::from_secs(60)).unwrap();
+        assert_eq!(3485 + PORT_OFFSET, external_port);
+        natpmp.remove_mapping(internal, external_port).unwrap();
+    }
+
+    #[test]
+    fn retry_when_the_gateway_drops_requests() {
+        let (gateway, received) = start_gateway(2);
+        let mut natpmp = NatPmp::new(gateway);
+        assert_eq!(EXTERNAL_IP, natpmp.external_ip().unwrap());
+        assert_eq!(3, received.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn error_when_the_gateway_is_silent() {
+        let (gateway, _) = start_gateway(usize::MAX);
+        let mut natpmp = NatPmp::new(gateway);
+        let err = natpmp.external_ip().unwrap_err();
+        assert_eq!(io::ErrorKind::TimedOut, err.kind());
+    }
+}
@@ -0,0 +1,451 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{local_address_towards, PortMapper};
+use std::io::{self, Read, Write};
+use std::net::{self, IpAddr, Ipv4Addr, SocketAddrV4, TcpStream, ToSocketAddrs, UdpSocket};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+pub const SSDP_MULTICAST_ADDR: &str = "239.255.255.250:1900";
+
+const SEARCH_TARGET: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+const SERVICE_TYPES: [&str; 2] =
+    ["urn:schemas-upnp-org:service:WANIPConnection:1", "urn:schemas-upnp-org:service:WANPPPConnection:1"];
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(3);
+const MAPPING_DESCRIPTION: &str = "foundry";
+
+/// A client of an UPnP Internet Gateway Device.
+pub struct Upnp {
+    control_url: HttpUrl,
+    service_type: String,
+    local_ip: Ipv4Addr,
+}
+
+impl Upnp {
+    /// Finds a gateway by sending an SSDP search to `ssdp_addr`.
+    pub fn discover(ssdp_addr: net::SocketAddr, timeout: Duration) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        let search = format!(
+            "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: {}\r\n\r\n",
+            ssdp_addr,
+            SEARCH_TARGET,
+            timeout.as_secs().max(1)
+        );
+        socket.send_to(search.as_bytes(), ssdp_addr)?;
+
+        let deadline = Instant::now() + timeout;
+        let mut last_error = None;
+        loop {
+            let now = Instant::now();
+            if deadline <= now {
+                break
+            }
+            socket.set_read_timeout(Some(deadline - now))?;
+            let mut buf = [0u8; 2048];
+            let len = match socket.recv(&mut buf) {
+                Ok(len) => len,
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut => {
+                    break
+                }
+                Err(err) => return Err(err),
+            };
+            let response = String::from_utf8_lossy(&buf[..len]);
+            if let Some(location) = header_value(&response, "location") {
+                match Self::from_location(location) {
+                    Ok(upnp) => return Ok(upnp),
+                    Err(err) => last_error = Some(err),
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| io::Error::new(io::ErrorKind::TimedOut, "No UPnP gateway responded")))
+    }
+
+    /// Creates a client from the URL of the gateway's device description.
+    pub fn from_location(location: &str) -> io::Result<Self> {
+        let description_url = HttpUrl::from_str(location)?;
+        let description = http_request(&description_url, "GET", None, "")?;
+        let (service_type, control_url) = find_wan_service(&description).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("{} has no WAN connection service", location))
+        })?;
+        let control_url = description_url.join(&control_url)?;
+        let local_ip = match control_url.addr.ip() {
+            IpAddr::V4(ip) => local_address_towards(ip)?,
+            IpAddr::V6(_) => return Err(io::Error::new(io::ErrorKind::Other, "IPv6 is not supported")),
+        };
+        Ok(Self {
+            control_url,
+            service_type,
+            local_ip,
+        })
+    }
+
+    fn soap(&self, action: &str, arguments: &[(&str, String)]) -> io::Result<String> {
+        let arguments: String = arguments
+            .iter()
+            .map(|(name, value)| format!("<{name}>{value}</{name}>", name = name, value = value))
+            .collect();
+        let body = format!(
+            "<?xml version=\"1.0\"?>\r\n\
+             <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+             s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+             <s:Body><u:{action} xmlns:u=\"{service}\">{arguments}</u:{action}></s:Body></s:Envelope>",
+            action = action,
+            service = self.service_type,
+            arguments = arguments
+        );
+        let soap_action = format!("\"{}#{}\"", self.service_type, action);
+        http_request(&self.control_url, "POST", Some(&soap_action), &body)
+    }
+}
+
+impl PortMapper for Upnp {
+    fn name(&self) -> &'static str {
+        "UPnP"
+    }
+
+    fn external_ip(&mut self) -> io::Result<Ipv4Addr> {
+        let response = self.soap("GetExternalIPAddress", &[])?;
+        let ip = tag_content(&response, "NewExternalIPAddress")
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "No external IP in the UPnP response"))?;
+        Ipv4Addr::from_str(ip.trim()).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    fn add_mapping(&mut self, internal: SocketAddrV4, external_port: u16, lifetime: Duration) -> io::Result<u16> {
+        let internal_ip = if internal.ip().is_unspecified() {
+            self.local_ip
+        } else {
+            *internal.ip()
+        };
+        self.soap("AddPortMapping", &[
+            ("NewRemoteHost", String::new()),
+            ("NewExternalPort", external_port.to_string()),
+            ("NewProtocol", "TCP".to_string()),
+            ("NewInternalPort", internal.port().to_string()),
+            ("NewInternalClient", internal_ip.to_string()),
+            ("NewEnabled", "1".to_string()),
+            ("NewPortMappingDescription", MAPPING_DESCRIPTION.to_string()),
+            ("NewLeaseDuration", lifetime.as_secs().to_string()),
+        ])?;
+        Ok(external_port)
+    }
+
+    fn remove_mapping(&mut self, _internal: SocketAddrV4, external_port: u16) -> io::Result<()> {
+        self.soap("DeletePortMapping", &[
+            ("NewRemoteHost", String::new()),
+            ("NewExternalPort", external_port.to_string()),
+            ("NewProtocol", "TCP".to_string()),
+        ])?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct HttpUrl {
+    host: String,
+    addr: net::SocketAddr,
+    path: String,
+}
+
+impl FromStr for HttpUrl {
+    type Err = io::Error;
+
+    fn from_str(url: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a valid HTTP URL", url));
+        let rest = url.trim().strip_prefix("http://").ok_or_else(invalid)?;
+        let (host, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        let addr = if host.contains(':') {
+            host.to_socket_addrs()
+        } else {
+            (host, 80).to_socket_addrs()
+        }
+        .map_err(|_| invalid())?
+        .next()
+        .ok_or_else(invalid)?;
+        Ok(Self {
+            host: host.to_string(),
+            addr,
+            path: path.to_string(),
+        })
+    }
+}
+
+impl HttpUrl {
+    fn join(&self, reference: &str) -> io::Result<Self> {
+        let reference = reference.trim();
+        if reference.starts_with("http://") {
+            return HttpUrl::from_str(reference)
+        }
+        let path = if reference.starts_with('/') {
+            reference.to_string()
+        } else {
+            let base = &self.path[..=self.path.rfind('/').unwrap_or(0)];
+            format!("{}{}", base, reference)
+        };
+        Ok(Self {
+            host: self.host.clone(),
+            addr: self.addr,
+            path,
+        })
+    }
+}
+
+fn http_request(url: &HttpUrl, method: &str, soap_action: Option<&str>, body: &str) -> io::Result<String> {
+    let mut stream = TcpStream::connect_timeout(&url.addr, HTTP_TIMEOUT)?;
+    stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+    stream.set_write_timeout(Some(HTTP_TIMEOUT))?;
+
+    let mut request = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+        method,
+        url.path,
+        url.host,
+        body.len()
+    );
+    if let Some(soap_action) = soap_action {
+        request.push_str("Content-Type: text/xml; charset=\"utf-8\"\r\n");
+        request.push_str(&format!("SOAPAction: {}\r\n", soap_action));
+    }
+    request.push_str("\r\n");
+    request.push_str(body);
+    stream.write_all(request.as_bytes())?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    let response = String::from_utf8_lossy(&response);
+    let header_end = response
+        .find("\r\n\r\n")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Incomplete HTTP response"))?;
+    let (head, body) = (&response[..header_end], &response[header_end + 4..]);
+    let status = head
+        .lines()
+        .next()
+        .and_then(|status_line| status_line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid HTTP status line"))?;
+    let body = match header_value(head, "transfer-encoding") {
+        Some(encoding) if encoding.eq_ignore_ascii_case("chunked") => decode_chunked(body)?,
+        _ => body.to_string(),
+    };
+    if status != 200 {
+        let reason = tag_content(&body, "errorDescription").unwrap_or("");
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("{} {} failed with {} {}", method, url.path, status, reason),
+        ))
+    }
+    Ok(body)
+}
+
+fn decode_chunked(mut body: &str) -> io::Result<String> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid chunked encoding");
+    let mut decoded = String::new();
+    loop {
+        let line_end = body.find("\r\n").ok_or_else(invalid)?;
+        let size = body[..line_end].split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size, 16).map_err(|_| invalid())?;
+        if size == 0 {
+            return Ok(decoded)
+        }
+        let chunk_start = line_end + 2;
+        let chunk = body.get(chunk_start..chunk_start + size).ok_or_else(invalid)?;
+        decoded.push_str(chunk);
+        body = body.get(chunk_start + size + 2..).ok_or_else(invalid)?;
+    }
+}
+
+fn header_value<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines().skip(1).find_map(|line| {
+        let index = line.find(':')?;
+        if line[..index].trim().eq_ignore_ascii_case(name) {
+            Some(line[index + 1..].trim())
+        } else {
+            None
+        }
+    })
+}
+
+fn tag_content<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = xml[start..].find(&close)? + start;
+    Some(&xml[start..end])
+}
+
+fn find_wan_service(description: &str) -> Option<(String, String)> {
+    description.split("<service>").skip(1).find_map(|service| {
+        let service_type = tag_content(service, "serviceType")?.trim();
+        if !SERVICE_TYPES.contains(&service_type) {
+            return None
+        }
+        let control_url = tag_content(service, "controlURL")?.trim();
+        Some((service_type.to_string(), control_url.to_string()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::thread;
+
+    const EXTERNAL_IP: &str = "203.0.113.7";
+
+    fn description() -> String {
+        format!(
+            "<?xml version=\"1.0\"?><root><device><deviceList><device><serviceList>\
+             <service><serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType>\
+             <controlURL>/ctl/L3F</controlURL></service>\
+             <service><serviceType>{}</serviceType><controlURL>/ctl/IPConn</controlURL></service>\
+             </serviceList></device></deviceList></device></root>",
+            SERVICE_TYPES[0]
+        )
+    }
+
+    fn read_request(stream: &mut TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut buf = [0u8; 1024];
+        loop {
+            let len = stream.read(&mut buf).unwrap();
+            request.extend_from_slice(&buf[..len]);
+            let text = String::from_utf8_lossy(&request).to_string();
+            if let Some(header_end) = text.find("\r\n\r\n") {
+                let content_length: usize =
+                    header_value(&text[..header_end], "content-length").map_or(0, |len| len.parse().unwrap());
+                if text.len() >= header_end + 4 + content_length {
+                    return text
+                }
+            }
+            if len == 0 {
+                return String::from_utf8_lossy(&request).to_string()
+            }
+        }
+    }
+
+    /// A stand-in gateway serving the device description and the WANIPConnection control endpoint.
+    fn start_gateway() -> (net::SocketAddr, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&requests);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let request = read_request(&mut stream);
+                let response = if request.starts_with("GET /rootDesc.xml") {
+                    description()
+                } else if request.starts_with("POST /ctl/IPConn") && request.contains("#GetExternalIPAddress") {
+                    format!(
+                        "<s:Envelope><s:Body><u:GetExternalIPAddressResponse>\
+                         <NewExternalIPAddress>{}</NewExternalIPAddress>\
+                         </u:GetExternalIPAddressResponse></s:Body></s:Envelope>",
+                        EXTERNAL_IP
+                    )
+                } else if request.starts_with("POST /ctl/IPConn") {
+                    "<s:Envelope><s:Body></s:Body></s:Envelope>".to_string()
+                } else {
+                    let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+                    continue
+                };
+                log.lock().push(request);
+                // The body is sent chunked to exercise the decoder.
+                let chunked = format!("{:x}\r\n{}\r\n0\r\n\r\n", response.len(), response);
+                let _ = stream
+                    .write_all(format!("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{}", chunked).as_bytes());
+            }
+        });
+        (addr, requests)
+    }
+
+    /// A stand-in SSDP responder pointing at `location`.
+    fn start_ssdp(location: String) -> net::SocketAddr {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0u8; 1024];
+            let (len, from) = socket.recv_from(&mut buf).unwrap();
+            let search = String::from_utf8_lossy(&buf[..len]).to_string();
+            assert!(search.starts_with("M-SEARCH * HTTP/1.1"));
+            assert_eq!(Some(SEARCH_TARGET), header_value(&search, "st"));
+            let response = format!("HTTP/1.1 200 OK\r\nST: {}\r\nLOCATION: {}\r\n\r\n", SEARCH_TARGET, location);
+            socket.send_to(response.as_bytes(), from).unwrap();
+        });
+        addr
+    }
+
+    #[test]
+    fn parse_url() {
+        let url = HttpUrl::from_str("http://127.0.0.1:5000/rootDesc.xml").unwrap();
+        assert_eq!("127.0.0.1:5000", url.host);
+        assert_eq!(net::SocketAddr::from(([127, 0, 0, 1], 5000)), url.addr);
+        assert_eq!("/rootDesc.xml", url.path);
+
+        let url = HttpUrl::from_str("http://127.0.0.1").unwrap();
+        assert_eq!(net::SocketAddr::from(([127, 0, 0, 1], 80)), url.addr);
+        assert_eq!("/", url.path);
+
+        assert!(HttpUrl::from_str("https://127.0.0.1/").is_err());
+    }
+
+    #[test]
+    fn join_url() {
+        let url = HttpUrl::from_str("http://127.0.0.1:5000/igd/rootDesc.xml").unwrap();
+        assert_eq!("/ctl/IPConn", url.join("/ctl/IPConn").unwrap().path);
+        assert_eq!("/igd/ctl", url.join("ctl").unwrap().path);
+        assert_eq!(net::SocketAddr::from(([127, 0, 0, 2], 80)), url.join("http://127.0.0.2/ctl").unwrap().addr);
+    }
+
+    #[test]
+    fn find_wan_ip_connection_service() {
+        assert_eq!(Some((SERVICE_TYPES[0].to_string(), "/ctl/IPConn".to_string())), find_wan_service(&description()));
+        assert_eq!(None, find_wan_service("<root></root>"));
+    }
+
+    #[test]
+    fn decode_chunked_body() {
+        assert_eq!("hello world", decode_chunked("5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n").unwrap());
+        assert!(decode_chunked("5\r\nhel").is_err());
+    }
+
+    #[test]
+    fn discover_and_map() {
+        let (gateway, requests) = start_gateway();
+        let ssdp = start_ssdp(format!("http://{}/rootDesc.xml", gateway));
+
+        let mut upnp = Upnp::discover(ssdp, Duration::from_secs(3)).unwrap();
+        assert_eq!(EXTERNAL_IP.parse::<Ipv4Addr>().unwrap(), upnp.external_ip().unwrap());
+
+        let internal = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 3485);
+        assert_eq!(3485, upnp.add_mapping(internal, 3485, Duration::from_secs(60)).unwrap());
+        upnp.remove_mapping(internal, 3485).unwrap();
+
+        let requests = requests.lock();
+        assert_eq!(4, requests.len());
+        let add = &requests[2];
+        assert!(add.contains(&format!("SOAPAction: \"{}#AddPortMapping\"", SERVICE_TYPES[0])));
+        assert!(add.contains("<NewExternalPort>3485</NewExternalPort>"));
+        assert!(add.contains("<NewInternalClient>127.0.0.1</NewInternalClient>"));
+        assert!(add.contains("<NewLeaseDuration>60</NewLeaseDuration>"));
+        assert!(requests[3].contains("#DeletePortMapping"));
+    }
+}
@@ -37,8 +37,7 @@ impl IncomingConnection {
         }
     }
 
-    pub fn establish(self, session: Session, port: u16) -> Result<EstablishedConnection> {
-        let peer_addr = SocketAddr::new(self.stream.peer_addr()?.ip(), port);
+    pub fn establish(self, session: Session, peer_addr: SocketAddr) -> Result<EstablishedConnection> {
         Ok(EstablishedConnection::new(self.stream, session, peer_addr))
     }
 
@@ -47,9 +46,11 @@ impl IncomingConnection {
     }
 
     pub fn send_ack(&mut self, recipient_pub_key: Public, encrypted_nonce: Bytes) -> usize {
+        let observed_addr = self.stream.peer_addr().ok();
         self.stream.write(&IncomingMessage::Ack {
             recipient_pub_key,
             encrypted_nonce,
+            observed_addr,
         })
     }
 
@@ -70,6 +71,17 @@ impl IncomingConnection {
         Ok(SocketAddr::new(self.stream.peer_addr()?.ip(), port))
     }
 
+    /// Returns the address under which the remote node should be known.
+    /// The advertised address is trusted only when its IP matches the IP the connection came from,
+    /// so that a peer cannot make others gossip an address it doesn't own.
+    pub fn advertised_remote_addr(&self, port: u16, advertised_addr: Option<SocketAddr>) -> Result<SocketAddr> {
+        let observed = self.remote_addr(port)?;
+        Ok(match advertised_addr {
+            Some(advertised_addr) if advertised_addr.ip() == observed.ip() => advertised_addr,
+            _ => observed,
+        })
+    }
+
     pub fn register<Message>(&self, reg: Token, event_loop: &mut EventLoop<IoManager<Message>>) -> io::Result<()>
     where
         Message: Send + Sync + 'static, {
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::SocketAddr;
 use ckey::{NetworkId, X25519Public as Public};
 use primitives::Bytes;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
@@ -24,12 +25,15 @@ pub enum OutgoingMessage {
         initiator_pub_key: Public,
         network_id: NetworkId,
         initiator_port: u16,
+        /// The address the initiator wants to be reached at, if it is known.
+        advertised_addr: Option<SocketAddr>,
     },
     Sync2 {
         initiator_pub_key: Public,
         recipient_pub_key: Public,
         network_id: NetworkId,
         initiator_port: u16,
+        advertised_addr: Option<SocketAddr>,
     },
 }
 
@@ -38,6 +42,8 @@ pub enum IncomingMessage {
     Ack {
         recipient_pub_key: Public,
         encrypted_nonce: Bytes,
+        /// The address of the initiator as seen by the recipient.
+        observed_addr: Option<SocketAddr>,
     },
     Nack,
 }
@@ -54,21 +60,33 @@ impl Encodable for OutgoingMessage {
                 initiator_pub_key,
                 network_id,
                 initiator_port,
+                advertised_addr,
             } => {
-                s.begin_list(4).append(&SYNC1_ID).append(initiator_pub_key).append(network_id).append(initiator_port);
+                s.begin_list(4 + advertised_addr.is_some() as usize)
+                    .append(&SYNC1_ID)
+                    .append(initiator_pub_key)
+                    .append(network_id)
+                    .append(initiator_port);
+                if let Some(advertised_addr) = advertised_addr {
+                    s.append(advertised_addr);
+                }
             }
             OutgoingMessage::Sync2 {
                 initiator_pub_key,
                 recipient_pub_key,
                 network_id,
                 initiator_port,
+                advertised_addr,
             } => {
-                s.begin_list(5)
+                s.begin_list(5 + advertised_addr.is_some() as usize)
                     .append(&SYNC2_ID)
                     .append(initiator_pub_key)
                     .append(recipient_pub_key)
                     .append(network_id)
                     .append(initiator_port);
+                if let Some(advertised_addr) = advertised_addr {
+                    s.append(advertised_addr);
+                }
             }
         }
     }
@@ -79,7 +97,7 @@ impl Decodable for OutgoingMessage {
         match rlp.val_at(0)? {
             SYNC1_ID => {
                 let item_count = rlp.item_count()?;
-                if item_count != 4 {
+                if item_count != 4 && item_count != 5 {
                     return Err(DecoderError::RlpInvalidLength {
                         expected: 4,
                         got: item_count,
@@ -89,11 +107,16 @@ impl Decodable for OutgoingMessage {
                     initiator_pub_key: rlp.val_at(1)?,
                     network_id: rlp.val_at(2)?,
                     initiator_port: rlp.val_at(3)?,
+                    advertised_addr: if item_count == 5 {
+                        Some(rlp.val_at(4)?)
+                    } else {
+                        None
+                    },
                 })
             }
             SYNC2_ID => {
                 let item_count = rlp.item_count()?;
-                if item_count != 5 {
+                if item_count != 5 && item_count != 6 {
                     return Err(DecoderError::RlpInvalidLength {
                         expected: 5,
                         got: item_count,
@@ -104,6 +127,11 @@ impl Decodable for OutgoingMessage {
                     recipient_pub_key: rlp.val_at(2)?,
                     network_id: rlp.val_at(3)?,
                     initiator_port: rlp.val_at(4)?,
+                    advertised_addr: if item_count == 6 {
+                        Some(rlp.val_at(5)?)
+                    } else {
+                        None
+                    },
                 })
             }
             _ => Err(DecoderError::Custom("Invalid id")),
@@ -117,8 +145,15 @@ impl Encodable for IncomingMessage {
             IncomingMessage::Ack {
                 recipient_pub_key,
                 encrypted_nonce,
+                observed_addr,
             } => {
-                s.begin_list(3).append(&ACK_ID).append(recipient_pub_key).append(encrypted_nonce);
+                s.begin_list(3 + observed_addr.is_some() as usize)
+                    .append(&ACK_ID)
+                    .append(recipient_pub_key)
+                    .append(encrypted_nonce);
+                if let Some(observed_addr) = observed_addr {
+                    s.append(observed_addr);
+                }
             }
             IncomingMessage::Nack => {
                 s.begin_list(1).append(&NACK_ID);
@@ -132,7 +167,7 @@ impl Decodable for IncomingMessage {
         match rlp.val_at(0)? {
             ACK_ID => {
                 let item_count = rlp.item_count()?;
-                if item_count != 3 {
+                if item_count != 3 && item_count != 4 {
                     return Err(DecoderError::RlpInvalidLength {
                         expected: 3,
                         got: item_count,
@@ -141,6 +176,11 @@ impl Decodable for IncomingMessage {
                 Ok(IncomingMessage::Ack {
                     recipient_pub_key: rlp.val_at(1)?,
                     encrypted_nonce: rlp.val_at(2)?,
+                    observed_addr: if item_count == 4 {
+                        Some(rlp.val_at(3)?)
+                    } else {
+                        None
+                    },
                 })
             }
             NACK_ID => {
@@ -169,7 +209,18 @@ mod tests {
         rlp_encode_and_decode_test!(OutgoingMessage::Sync1 {
             initiator_pub_key: Public::random(),
             network_id: "ab".into(),
-            initiator_port: 3100
+            initiator_port: 3100,
+            advertised_addr: None,
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_sync1_with_advertised_addr() {
+        rlp_encode_and_decode_test!(OutgoingMessage::Sync1 {
+            initiator_pub_key: Public::random(),
+            network_id: "ab".into(),
+            initiator_port: 3100,
+            advertised_addr: Some(SocketAddr::v4(1, 2, 3, 4, 3485)),
         });
     }
 
@@ -179,7 +230,19 @@ mod tests {
             initiator_pub_key: Public::random(),
             recipient_pub_key: Public::random(),
             network_id: "ab".into(),
-            initiator_port: 3100
+            initiator_port: 3100,
+            advertised_addr: None,
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_sync2_with_advertised_addr() {
+        rlp_encode_and_decode_test!(OutgoingMessage::Sync2 {
+            initiator_pub_key: Public::random(),
+            recipient_pub_key: Public::random(),
+            network_id: "ab".into(),
+            initiator_port: 3100,
+            advertised_addr: Some(SocketAddr::v4(1, 2, 3, 4, 3485)),
         });
     }
 
@@ -188,9 +251,32 @@ mod tests {
         rlp_encode_and_decode_test!(IncomingMessage::Ack {
             recipient_pub_key: Public::random(),
             encrypted_nonce: vec![1, 23, 4, 5, 6],
+            observed_addr: None,
         });
     }
 
+    #[test]
+    fn encode_and_decode_ack_with_observed_addr() {
+        rlp_encode_and_decode_test!(IncomingMessage::Ack {
+            recipient_pub_key: Public::random(),
+            encrypted_nonce: vec![1, 23, 4, 5, 6],
+            observed_addr: Some(SocketAddr::v4(1, 2, 3, 4, 53120)),
+        });
+    }
+
+    #[test]
+    fn sync1_from_old_peer_has_no_advertised_addr() {
+        let mut s = RlpStream::new_list(4);
+        s.append(&SYNC1_ID).append(&Public::random()).append(&NetworkId::from("ab")).append(&3100u16);
+        match rlp::decode::<OutgoingMessage>(&s.out()).unwrap() {
+            OutgoingMessage::Sync1 {
+                advertised_addr,
+                ..
+            } => assert_eq!(None, advertised_addr),
+            message => panic!("Unexpected message {:?}", message),
+        }
+    }
+
     #[test]
     fn encode_and_decode_nack() {
         rlp_encode_and_decode_test!(IncomingMessage::Nack);
@@ -54,19 +54,21 @@ impl OutgoingConnection {
         Ready::writable() | Ready::readable() | UnixReady::hup()
     }
 
-    pub fn send_sync(&mut self, recipient_pub_key: Option<Public>) -> usize {
+    pub fn send_sync(&mut self, recipient_pub_key: Option<Public>, advertised_addr: Option<SocketAddr>) -> usize {
         if let Some(recipient_pub_key) = recipient_pub_key {
             self.stream.write(&OutgoingMessage::Sync2 {
                 initiator_pub_key: self.initiator_pub_key.clone(),
                 network_id: self.network_id,
                 initiator_port: self.initiator_port,
                 recipient_pub_key,
+                advertised_addr,
             })
         } else {
             self.stream.write(&OutgoingMessage::Sync1 {
                 initiator_pub_key: self.initiator_pub_key.clone(),
                 network_id: self.network_id,
                 initiator_port: self.initiator_port,
+                advertised_addr,
             })
         }
     }
@@ -20,6 +20,7 @@ use super::connection::{
 use super::listener::Listener;
 use super::{NegotiationMessage, NetworkMessage};
 use crate::client::Client;
+use crate::nat::ExternalAddress;
 use crate::p2p::connection::Error as P2PConnectionError;
 use crate::session::Session;
 use crate::stream::Stream;
@@ -64,6 +65,7 @@ const LAST_OUTGOING: StreamToken = FIRST_OUTGOING + MAX_OUTGOING_CONNECTIONS - 1
 
 const CREATE_CONNECTIONS: TimerToken = 0;
 const CONNECT_TO_BOOTSTRAP: TimerToken = CREATE_CONNECTIONS + 1;
+const CONNECT_TO_PERSISTENT_PEERS: TimerToken = CONNECT_TO_BOOTSTRAP + 1;
 
 const FIRST_WAIT_SYNC: TimerToken = FIRST_INCOMING;
 const LAST_WAIT_SYNC: TimerToken = LAST_INCOMING;
@@ -75,6 +77,7 @@ const FIRST_TRY_SYNC: TimerToken = FIRST_OUTGOING + 1000;
 const LAST_TRY_SYNC: TimerToken = LAST_OUTGOING + 1000;
 
 const CREATE_CONNECTION_INTERVAL: Duration = Duration::from_secs(3);
+const RECONNECT_PERSISTENT_PEERS_INTERVAL: Duration = Duration::from_secs(10);
 
 const RETRY_SYNC_MAX: Duration = Duration::from_secs(10); // T1
 const RTT: Duration = Duration::from_secs(10); // T2
@@ -91,6 +94,7 @@ pub struct Handler {
 
     network_id: NetworkId,
     socket_address: SocketAddr,
+    external_address: Arc<ExternalAddress>,
     listener: Listener,
 
     inbound_connections: RwLock<HashMap<StreamToken, EstablishedConnection>>,
@@ -103,7 +107,7 @@ pub struct Handler {
     incoming_tokens: Mutex<TokenGenerator>,
     outgoing_tokens: Mutex<TokenGenerator>,
 
-    establishing_incoming_session: Mutex<HashMap<StreamToken, (u16, Session)>>,
+    establishing_incoming_session: Mutex<HashMap<StreamToken, (SocketAddr, Session)>>,
     establishing_outgoing_session: Mutex<HashMap<StreamToken, Session>>,
 
     routing_table: Arc<RoutingTable>,
@@ -115,6 +119,9 @@ pub struct Handler {
     client: Arc<Client>,
 
     bootstrap_addresses: Vec<SocketAddr>,
+    /// The peers to which the node always reconnects, including the unconditional peers.
+    persistent_peers: Vec<SocketAddr>,
+    unconditional_peers: UnconditionalPeers,
 
     network_usage_in_10_seconds: Mutex<HashMap<String, VecDeque<(Instant, usize)>>>,
 
@@ -132,10 +139,13 @@ impl Handler {
         channel: IoChannel<Message>,
         network_id: NetworkId,
         socket_address: SocketAddr,
+        external_address: Arc<ExternalAddress>,
         client: Arc<Client>,
         routing_table: Arc<RoutingTable>,
         filters: Arc<dyn FiltersControl>,
         bootstrap_addresses: Vec<SocketAddr>,
+        persistent_peers: Vec<SocketAddr>,
+        unconditional_peers: Vec<SocketAddr>,
         min_peers: usize,
         max_peers: usize,
         peer_db: Box<dyn ManagingPeerdb>,
@@ -144,12 +154,19 @@ impl Handler {
         if MAX_INBOUND_CONNECTIONS + MAX_OUTBOUND_CONNECTIONS < max_peers {
             return Err(format!("Max peers must be less than {}", MAX_INBOUND_CONNECTIONS + MAX_OUTBOUND_CONNECTIONS))
         }
+        let mut persistent_peers = persistent_peers;
+        for peer in &unconditional_peers {
+            if !persistent_peers.contains(peer) {
+                persistent_peers.push(*peer);
+            }
+        }
         Ok(Self {
             connecting_lock: Default::default(),
             channel,
 
             network_id,
             socket_address,
+            external_address,
             listener: Listener::bind(&socket_address).expect("Cannot listen TCP port"),
 
             inbound_connections: Default::default(),
@@ -176,6 +193,8 @@ impl Handler {
             network_usage_in_10_seconds: Default::default(),
 
             bootstrap_addresses,
+            persistent_peers,
+            unconditional_peers: UnconditionalPeers(unconditional_peers),
             min_peers,
             max_peers,
             peer_db,
@@ -302,6 +321,9 @@ impl IoHandler<Message> for Handler {
         io.register_stream(ACCEPT);
         io.register_timer_once(CREATE_CONNECTIONS, CREATE_CONNECTION_INTERVAL);
         io.register_timer_once(CONNECT_TO_BOOTSTRAP, Duration::default());
+        if !self.persistent_peers.is_empty() {
+            io.register_timer_once(CONNECT_TO_PERSISTENT_PEERS, Duration::default());
+        }
         Ok(())
     }
 
@@ -376,6 +398,23 @@ impl IoHandler<Message> for Handler {
                 const CHECK_BOOTSTRAP_INTERVAL: Duration = Duration::from_secs(15);
                 io.register_timer_once(CONNECT_TO_BOOTSTRAP, CHECK_BOOTSTRAP_INTERVAL);
             }
+            CONNECT_TO_PERSISTENT_PEERS => {
+                let _l = self.connecting_lock.lock();
+                let disconnected = self
+                    .persistent_peers
+                    .iter()
+                    .filter(|addr| !self.routing_table.is_establishing_or_established(addr))
+                    .filter(|addr| !self.routing_table.is_banned(addr))
+                    .filter(|addr| self.filters.is_allowed(&addr.ip()));
+                for addr in disconnected {
+                    cdebug!(NETWORK, "Reconnecting to the persistent peer {}", addr);
+                    if let Err(err) = self.connect(io, *addr) {
+                        self.routing_table.remove(addr);
+                        cwarn!(NETWORK, "Cannot connect to the persistent peer {}: {:?}", addr, err);
+                    }
+                }
+                io.register_timer_once(CONNECT_TO_PERSISTENT_PEERS, RECONNECT_PERSISTENT_PEERS_INTERVAL);
+            }
             FIRST_WAIT_SYNC..=LAST_WAIT_SYNC => {
                 cwarn!(NETWORK, "No sync message from {}", timer);
                 io.deregister_stream(wait_sync_stream(timer));
@@ -396,7 +435,7 @@ impl IoHandler<Message> for Handler {
                             return Err(err.into())
                         }
                     };
-                    let network_message_size = con.send_sync(maybe_remote_public);
+                    let network_message_size = con.send_sync(maybe_remote_public, self.external_address.advertised());
                     {
                         let mut network_usage_in_10_seconds = self.network_usage_in_10_seconds.lock();
                         insert_network_usage(
@@ -506,6 +545,24 @@ impl IoHandler<Message> for Handler {
             } => {
                 let mut inbound_connections = self.inbound_connections.write();
                 let target = connection.peer_addr();
+                if !self.unconditional_peers.contains(target) {
+                    // The connection may have been accepted over `max_peers` only because of its IP address.
+                    let outbound_connections = self.outbound_connections.read();
+                    let established_connections = self.unconditional_peers.count_limited(
+                        outbound_connections.values().chain(inbound_connections.values()).map(|con| con.peer_addr()),
+                    );
+                    if self.max_peers < established_connections + 1 {
+                        cinfo!(
+                            NETWORK,
+                            "Inbound connection from {} is dropped because there are too many connections({} < {})",
+                            target,
+                            self.max_peers,
+                            established_connections + 1
+                        );
+                        self.routing_table.remove(target);
+                        return Ok(())
+                    }
+                }
                 self.peer_db.insert(*target);
                 if let Some(token) = self.inbound_tokens.lock().gen() {
                     let remote_node_id = connection.peer_addr().into();
@@ -634,12 +691,18 @@ impl IoHandler<Message> for Handler {
                         let incoming_connections = self.incoming_connections.write();
                         let outgoing_connections = self.outgoing_connections.read();
 
-                        let current_connections = outbound_connections.len()
-                            + inbound_connections.len()
-                            + incoming_connections.len()
-                            + outgoing_connections.len();
+                        let established_connections = self.unconditional_peers.count_limited(
+                            outbound_connections
+                                .values()
+                                .chain(inbound_connections.values())
+                                .map(|con| con.peer_addr()),
+                        );
+                        let current_connections =
+                            established_connections + incoming_connections.len() + outgoing_connections.len();
 
-                        if self.max_peers < current_connections {
+                        if self.max_peers < current_connections
+                            && !self.unconditional_peers.may_contain(&socket_address)
+                        {
                             cinfo!(
                                 NETWORK,
                                 "New connection from {} is dropped because there are too many connections({} < {})",
@@ -815,8 +878,9 @@ impl IoHandler<Message> for Handler {
                             initiator_pub_key,
                             network_id,
                             initiator_port,
+                            advertised_addr,
                         }) => {
-                            let from = con.remote_addr(initiator_port)?;
+                            let from = con.advertised_remote_addr(initiator_port, advertised_addr)?;
                             if network_id != self.network_id {
                                 io.deregister_stream(stream_token);
                                 should_update.store(false, Ordering::SeqCst);
@@ -827,11 +891,8 @@ impl IoHandler<Message> for Handler {
                             {
                                 cinfo!(NETWORK, "Send ack to {}", from);
                                 let network_message_size = con.send_ack(local_public, encrypted_nonce);
-                                let t = self
-                                    .establishing_incoming_session
-                                    .lock()
-                                    .insert(stream_token, (initiator_port, session));
-                                assert_eq!(None, t, "Cannot establish {}", initiator_port);
+                                let t = self.establishing_incoming_session.lock().insert(stream_token, (from, session));
+                                assert_eq!(None, t, "Cannot establish {}", from);
                                 io.clear_timer(wait_sync_timer(stream_token));
                                 should_update.store(false, Ordering::SeqCst);
                                 io.deregister_stream(stream_token);
@@ -853,8 +914,9 @@ impl IoHandler<Message> for Handler {
                             recipient_pub_key,
                             network_id,
                             initiator_port,
+                            advertised_addr,
                         }) => {
-                            let from = con.remote_addr(initiator_port)?;
+                            let from = con.advertised_remote_addr(initiator_port, advertised_addr)?;
                             if network_id != self.network_id {
                                 should_update.store(false, Ordering::SeqCst);
                                 io.deregister_stream(stream_token);
@@ -866,11 +928,8 @@ impl IoHandler<Message> for Handler {
                             {
                                 cinfo!(NETWORK, "Send ack to {}", from);
                                 let network_message_size = con.send_ack(local_public, encrypted_nonce);
-                                let t = self
-                                    .establishing_incoming_session
-                                    .lock()
-                                    .insert(stream_token, (initiator_port, session));
-                                assert_eq!(None, t, "Cannot establish {}", initiator_port);
+                                let t = self.establishing_incoming_session.lock().insert(stream_token, (from, session));
+                                assert_eq!(None, t, "Cannot establish {}", from);
                                 io.clear_timer(wait_sync_timer(stream_token));
                                 should_update.store(false, Ordering::SeqCst);
                                 io.deregister_stream(stream_token);
@@ -915,12 +974,16 @@ impl IoHandler<Message> for Handler {
                         Some(IncomingMessage::Ack {
                             recipient_pub_key,
                             encrypted_nonce,
+                            observed_addr,
                         }) => {
                             let session = self.routing_table.set_initiator_establish(
                                 from,
                                 recipient_pub_key,
                                 &encrypted_nonce,
                             )?;
+                            if let Some(observed_addr) = observed_addr {
+                                self.external_address.observe(&from, &observed_addr);
+                            }
                             let t = self.establishing_outgoing_session.lock().insert(stream_token, session);
                             assert_eq!(None, t);
                             io.clear_timer(wait_ack_timer(stream_token));
@@ -1157,8 +1220,8 @@ impl IoHandler<Message> for Handler {
                 if let Some(con) = incoming_connections.remove(&stream) {
                     con.deregister(event_loop)?;
                     self.incoming_tokens.lock().restore(stream);
-                    if let Some((port, session)) = self.establishing_incoming_session.lock().remove(&stream) {
-                        let connection = con.establish(session, port)?;
+                    if let Some((peer_addr, session)) = self.establishing_incoming_session.lock().remove(&stream) {
+                        let connection = con.establish(session, peer_addr)?;
                         {
                             let peer_addr = connection.peer_addr();
                             if !self.filters.is_allowed(&peer_addr.ip()) {
@@ -1276,3 +1339,50 @@ fn insert_network_usage(usage_per_extension: &mut VecDeque<(Instant, usize)>, ne
     remove_outdated_network_usage(usage_per_extension, now);
     usage_per_extension.push_back((now + Duration::from_secs(10), network_message_size));
 }
+
+/// The peers which are not limited by `max_peers`.
+struct UnconditionalPeers(Vec<SocketAddr>);
+
+impl UnconditionalPeers {
+    /// An established connection is compared by the listening address of the peer.
+    fn contains(&self, address: &SocketAddr) -> bool {
+        self.0.contains(address)
+    }
+
+    /// An accepted connection is compared by the IP address because the listening port of the peer is not known
+    /// until the handshake. It is compared again by `contains` when it is established.
+    fn may_contain(&self, address: &SocketAddr) -> bool {
+        self.0.iter().any(|peer| peer.ip() == address.ip())
+    }
+
+    /// The number of the connections counted by `max_peers`.
+    fn count_limited<'a>(&self, peers: impl Iterator<Item = &'a SocketAddr>) -> usize {
+        peers.filter(|peer| !self.contains(peer)).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unconditional_peers_are_matched_by_ip_and_port() {
+        let unconditional = SocketAddr::v4(10, 0, 0, 1, 3485);
+        let peers = UnconditionalPeers(vec![unconditional]);
+        assert!(peers.contains(&unconditional));
+        assert!(!peers.contains(&SocketAddr::v4(10, 0, 0, 1, 3486)));
+        assert!(!peers.contains(&SocketAddr::v4(10, 0, 0, 2, 3485)));
+
+        // The port of an accepted connection is an ephemeral one.
+        assert!(peers.may_contain(&SocketAddr::v4(10, 0, 0, 1, 51234)));
+        assert!(!peers.may_contain(&SocketAddr::v4(10, 0, 0, 2, 3485)));
+    }
+
+    #[test]
+    fn unconditional_peers_are_not_counted_by_max_peers() {
+        let unconditional = SocketAddr::v4(10, 0, 0, 1, 3485);
+        let peers = UnconditionalPeers(vec![unconditional]);
+        let established = vec![unconditional, SocketAddr::v4(10, 0, 0, 1, 3486), SocketAddr::v4(10, 0, 0, 2, 3485)];
+        assert_eq!(2, peers.count_limited(established.iter()));
+    }
+}
@@ -23,7 +23,7 @@ use parking_lot::{Mutex, RwLock};
 use primitives::Bytes;
 use rand::rngs::OsRng;
 use rand::Rng;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -122,6 +122,8 @@ impl Default for State {
 
 pub struct RoutingTable {
     entries: RwLock<HashMap<SocketAddr, State>>,
+    /// The addresses which must not be gossiped.
+    private_addresses: RwLock<HashSet<SocketAddr>>,
 
     rng: Mutex<OsRng>,
 }
@@ -130,10 +132,19 @@ impl RoutingTable {
     pub fn new() -> Arc<Self> {
         Arc::new(Self {
             entries: RwLock::new(HashMap::new()),
+            private_addresses: Default::default(),
             rng: Mutex::new(OsRng::new().unwrap()),
         })
     }
 
+    pub fn add_private_addresses<I: IntoIterator<Item = SocketAddr>>(&self, targets: I) {
+        self.private_addresses.write().extend(targets);
+    }
+
+    pub fn is_private(&self, target: &SocketAddr) -> bool {
+        self.private_addresses.read().contains(target)
+    }
+
     pub fn is_banned(&self, target: &SocketAddr) -> bool {
         let entries = self.entries.read();
         matches!(entries.get(target), Some(State::Banned))
@@ -216,9 +227,11 @@ impl RoutingTable {
             .collect()
     }
 
+    /// The addresses to tell the node at `from`. The private addresses are never told to anyone.
     pub fn reachable_addresses(&self, from: &SocketAddr) -> Vec<SocketAddr> {
         let entries = self.entries.read();
-        entries.keys().filter(|addr| from.is_reachable(addr)).cloned().collect()
+        let private_addresses = self.private_addresses.read();
+        entries.keys().filter(|addr| from.is_reachable(addr) && !private_addresses.contains(addr)).cloned().collect()
     }
 
     pub fn touch(&self, target: SocketAddr) -> Option<Public> {
@@ -669,4 +682,26 @@ mod tests {
         let nonce = u128::MAX;
         encrypt_and_decrypt(secret, nonce);
     }
+
+    #[test]
+    fn private_addresses() {
+        let routing_table = RoutingTable::new();
+        let private = SocketAddr::v4(10, 0, 0, 1, 3485);
+        let public = SocketAddr::v4(10, 0, 0, 2, 3485);
+        routing_table.add_private_addresses(vec![private]);
+        assert!(routing_table.is_private(&private));
+        assert!(!routing_table.is_private(&public));
+    }
+
+    #[test]
+    fn private_addresses_are_not_reachable() {
+        let routing_table = RoutingTable::new();
+        let private = SocketAddr::v4(10, 0, 0, 1, 3485);
+        let public = SocketAddr::v4(10, 0, 0, 2, 3485);
+        routing_table.add_private_addresses(vec![private]);
+        routing_table.touch_addresses(vec![private, public]);
+
+        let from = SocketAddr::v4(10, 0, 0, 3, 3485);
+        assert_eq!(vec![public], routing_table.reachable_addresses(&from));
+    }
 }
@@ -17,6 +17,7 @@
 use crate::client::Client;
 use crate::control::{Control, Error as ControlError};
 use crate::filters::{FilterEntry, FiltersControl};
+use crate::nat::{self, ExternalAddress, NatType, PortMapping};
 use crate::routing_table::RoutingTable;
 use crate::{p2p, Api, ManagingPeerdb, NetworkExtension, SocketAddr};
 use cidr::IpCidr;
@@ -25,8 +26,9 @@ use cio::{IoError, IoService};
 use ckey::{NetworkId, X25519Public as Public};
 use crossbeam_channel::Sender;
 use ctimer::TimerLoop;
+use parking_lot::Mutex;
 use std::collections::HashMap;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddrV4};
 use std::sync::Arc;
 
 pub struct Service {
@@ -35,14 +37,21 @@ pub struct Service {
     routing_table: Arc<RoutingTable>,
     p2p_handler: Arc<p2p::Handler>,
     filters_control: Arc<dyn FiltersControl>,
+    address: SocketAddr,
+    external_address: Arc<ExternalAddress>,
+    port_mapping: Mutex<Option<PortMapping>>,
 }
 
 impl Service {
+    #[allow(clippy::too_many_arguments)]
     pub fn start(
         network_id: NetworkId,
         timer_loop: TimerLoop,
         address: SocketAddr,
+        public_address: Option<SocketAddr>,
         bootstrap_addresses: Vec<SocketAddr>,
+        persistent_peers: Vec<SocketAddr>,
+        unconditional_peers: Vec<SocketAddr>,
         min_peers: usize,
         max_peers: usize,
         filters_control: Arc<dyn FiltersControl>,
@@ -53,15 +62,19 @@ impl Service {
         let p2p = IoService::start("P2P")?;
 
         let client = Client::new(p2p.channel(), timer_loop);
+        let external_address = Arc::new(ExternalAddress::new(public_address, address.port()));
 
         let p2p_handler = Arc::new(p2p::Handler::try_new(
             p2p.channel(),
             network_id,
             address,
+            Arc::clone(&external_address),
             Arc::clone(&client),
             Arc::clone(&routing_table),
             Arc::clone(&filters_control),
             bootstrap_addresses,
+            persistent_peers,
+            unconditional_peers,
             min_peers,
             max_peers,
             peer_db,
@@ -75,9 +88,34 @@ impl Service {
             routing_table,
             p2p_handler,
             filters_control,
+            address,
+            external_address,
+            port_mapping: Default::default(),
         }))
     }
 
+    /// Opens the listening port on the NAT gateway and keeps the mapping alive while the service is running.
+    pub fn map_port(&self, nat_type: NatType, gateway: Option<Ipv4Addr>) -> Result<(), Error> {
+        let mapper = match nat::find_port_mapper(nat_type, gateway) {
+            Some(mapper) => mapper,
+            None => {
+                if nat_type != NatType::None {
+                    cwarn!(NETWORK, "Cannot find a NAT gateway supporting {}", nat_type);
+                }
+                return Ok(())
+            }
+        };
+        let ip = match self.address.ip() {
+            IpAddr::V4(ip) => ip,
+            IpAddr::V6(_) => return Err(Error::General("IPv6 is not supported".to_string())),
+        };
+        let internal = SocketAddrV4::new(ip, self.address.port());
+        let mapping = PortMapping::start(mapper, internal, Arc::clone(&self.external_address))
+            .map_err(|err| Error::General(format!("Cannot start the port mapping: {}", err)))?;
+        *self.port_mapping.lock() = Some(mapping);
+        Ok(())
+    }
+
     pub fn register_extension<T, E, F>(&self, factory: F) -> Sender<E>
     where
         T: 'static + Sized + NetworkExtension<E>,
@@ -133,6 +171,10 @@ impl Control for Service {
         Ok(self.p2p_handler.get_port())
     }
 
+    fn advertised_address(&self) -> Result<Option<SocketAddr>, ControlError> {
+        Ok(self.external_address.advertised())
+    }
+
     fn get_peer_count(&self) -> Result<usize, ControlError> {
         Ok(self.p2p_handler.get_peer_count())
     }
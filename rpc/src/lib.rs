@@ -23,6 +23,7 @@ extern crate codechain_network as cnetwork;
 extern crate codechain_state as cstate;
 extern crate codechain_sync as csync;
 extern crate codechain_types as ctypes;
+extern crate foundry_light_client as clight;
 #[macro_use]
 extern crate serde_derive;
 #[macro_use]
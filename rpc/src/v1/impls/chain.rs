@@ -16,9 +16,11 @@
 
 use super::super::errors;
 use super::super::traits::Chain;
-use super::super::types::{Block, BlockNumberAndHash, Transaction, ValidatorSet};
+use super::super::types::{Block, BlockNumberAndHash, ConsensusState, Health, Transaction, ValidatorSet};
 use ccore::{BlockChainClient, EngineInfo};
+use cjson::bytes::Bytes;
 use ckey::{NetworkId, PlatformAddress};
+use clight::LightBlock;
 use ctypes::{BlockHash, BlockId, BlockNumber, TxHash};
 use jsonrpc_core::Result;
 use std::sync::Arc;
@@ -98,4 +100,40 @@ where
         let validator_set_in_core = self.client.validator_set(block_number).map_err(errors::core)?;
         Ok(validator_set_in_core.map(ValidatorSet::from_core))
     }
+
+    fn get_consensus_state(&self) -> Result<Option<ConsensusState>> {
+        Ok(self.client.consensus_state().map(ConsensusState::from_core))
+    }
+
+    fn get_health(&self) -> Result<Health> {
+        Ok(Health::from_core(self.client.crashed_modules()))
+    }
+
+    fn get_light_block(&self, block_number: u64) -> Result<Option<Bytes>> {
+        if block_number == 0 {
+            return Ok(None)
+        }
+        let (parent, header, commit) = match (
+            self.client.block_header(&BlockId::Number(block_number - 1)),
+            self.client.block_header(&BlockId::Number(block_number)),
+            self.client.block_header(&BlockId::Number(block_number + 1)),
+        ) {
+            (Some(parent), Some(header), Some(commit)) => (parent.decode(), header.decode(), commit.decode()),
+            _ => return Ok(None),
+        };
+        let validator_set = self.client.validator_set(Some(block_number)).map_err(errors::core)?;
+        let next_validator_set = self.client.validator_set(Some(block_number + 1)).map_err(errors::core)?;
+        Ok(validator_set.and_then(|validator_set| {
+            next_validator_set.map(|next_validator_set| {
+                let light_block = LightBlock {
+                    parent,
+                    header,
+                    commit,
+                    validator_set,
+                    next_validator_set,
+                };
+                Bytes::new(rlp::encode(&light_block))
+            })
+        }))
+    }
 }
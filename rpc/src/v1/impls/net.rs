@@ -69,6 +69,11 @@ impl Net for NetClient {
         Ok(self.network_control.get_port().map_err(|e| errors::network_control(&e))?)
     }
 
+    fn get_advertised_address(&self) -> Result<Option<net::SocketAddr>> {
+        let address = self.network_control.advertised_address().map_err(|e| errors::network_control(&e))?;
+        Ok(address.map(Into::into))
+    }
+
     fn get_peer_count(&self) -> Result<usize> {
         Ok(self.network_control.get_peer_count().map_err(|e| errors::network_control(&e))?)
     }
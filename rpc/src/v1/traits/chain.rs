@@ -14,7 +14,8 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::super::types::{Block, BlockNumberAndHash, Transaction, ValidatorSet};
+use super::super::types::{Block, BlockNumberAndHash, ConsensusState, Health, Transaction, ValidatorSet};
+use cjson::bytes::Bytes;
 use ckey::{NetworkId, PlatformAddress};
 use ctypes::{BlockHash, BlockNumber, TxHash};
 use jsonrpc_core::Result;
@@ -64,4 +65,17 @@ pub trait Chain {
     /// Return the valid block authors
     #[rpc(name = "chain_getValidatorSet")]
     fn get_validator_set(&self, block_number: Option<u64>) -> Result<Option<ValidatorSet>>;
+
+    /// Return the current state of the consensus, including the collected votes and the states of the peers.
+    #[rpc(name = "chain_getConsensusState")]
+    fn get_consensus_state(&self) -> Result<Option<ConsensusState>>;
+
+    /// Return whether the modules of the app are alive, and the modules crashed if any.
+    #[rpc(name = "chain_getHealth")]
+    fn get_health(&self) -> Result<Health>;
+
+    /// Gets the RLP-encoded light block, which a light client needs to verify the header with given number.
+    /// It returns null until the child of the block is imported.
+    #[rpc(name = "chain_getLightBlock")]
+    fn get_light_block(&self, block_number: u64) -> Result<Option<Bytes>>;
 }
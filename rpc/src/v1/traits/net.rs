@@ -41,6 +41,9 @@ pub trait Net {
     #[rpc(name = "net_getPort")]
     fn get_port(&self) -> Result<u16>;
 
+    #[rpc(name = "net_getAdvertisedAddress")]
+    fn get_advertised_address(&self) -> Result<Option<SocketAddr>>;
+
     #[rpc(name = "net_getPeerCount")]
     fn get_peer_count(&self) -> Result<usize>;
 
@@ -0,0 +1,131 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::{
+    ConsensusState as CoreConsensusState, PeerConsensusState as CorePeerConsensusState, StepVotes as CoreStepVotes,
+    VoteStep as CoreVoteStep,
+};
+use ctypes::BlockHash;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsensusState {
+    height: u64,
+    view: u64,
+    step: String,
+    state: String,
+    proposal: Option<BlockHash>,
+    lock_view: Option<u64>,
+    lock_block_hash: Option<BlockHash>,
+    votes: Vec<StepVotes>,
+    peers: Vec<PeerConsensusState>,
+    /// Unix time in milliseconds.
+    step_deadline: Option<u64>,
+    /// Unix time in milliseconds.
+    empty_proposal_deadline: Option<u64>,
+}
+
+impl ConsensusState {
+    pub fn from_core(state: CoreConsensusState) -> Self {
+        ConsensusState {
+            height: state.height,
+            view: state.view,
+            step: format!("{:?}", state.step),
+            state: state.state,
+            proposal: state.proposal,
+            lock_view: state.lock.map(|(view, _)| view),
+            lock_block_hash: state.lock.and_then(|(_, block_hash)| block_hash),
+            votes: state.votes.into_iter().map(StepVotes::from_core).collect(),
+            peers: state.peers.into_iter().map(PeerConsensusState::from_core).collect(),
+            step_deadline: state.step_deadline.map(unix_millis),
+            empty_proposal_deadline: state.empty_proposal_deadline.map(unix_millis),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoteStep {
+    height: u64,
+    view: u64,
+    step: String,
+}
+
+impl VoteStep {
+    pub fn from_core(vote_step: CoreVoteStep) -> Self {
+        VoteStep {
+            height: vote_step.height,
+            view: vote_step.view,
+            step: format!("{:?}", vote_step.step),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepVotes {
+    vote_step: VoteStep,
+    votes: Vec<BlockVotes>,
+}
+
+impl StepVotes {
+    pub fn from_core(step_votes: CoreStepVotes) -> Self {
+        StepVotes {
+            vote_step: VoteStep::from_core(step_votes.step),
+            votes: step_votes
+                .votes
+                .into_iter()
+                .map(|(block_hash, signer_indices)| BlockVotes {
+                    block_hash,
+                    signer_indices,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockVotes {
+    /// Null for the nil votes.
+    block_hash: Option<BlockHash>,
+    signer_indices: Vec<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerConsensusState {
+    address: String,
+    vote_step: VoteStep,
+    proposal: Option<BlockHash>,
+    signer_indices: Vec<usize>,
+}
+
+impl PeerConsensusState {
+    pub fn from_core(peer: CorePeerConsensusState) -> Self {
+        PeerConsensusState {
+            address: peer.node_id.to_string(),
+            vote_step: VoteStep::from_core(peer.vote_step),
+            proposal: peer.proposal,
+            signer_indices: peer.messages,
+        }
+    }
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|duration| duration.as_millis() as u64).unwrap_or_default()
+}
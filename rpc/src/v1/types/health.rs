@@ -0,0 +1,52 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use coordinator::types::ModuleCrash;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Health {
+    /// False if any module crashed. The node stops producing and importing blocks until it is restarted.
+    healthy: bool,
+    crashed_modules: Vec<CrashedModule>,
+}
+
+impl Health {
+    pub fn from_core(crashes: Vec<ModuleCrash>) -> Self {
+        Health {
+            healthy: crashes.is_empty(),
+            crashed_modules: crashes.into_iter().map(CrashedModule::from_core).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashedModule {
+    module: String,
+    reason: String,
+    restartable: bool,
+}
+
+impl CrashedModule {
+    pub fn from_core(crash: ModuleCrash) -> Self {
+        CrashedModule {
+            module: crash.module,
+            reason: crash.reason,
+            restartable: crash.restartable,
+        }
+    }
+}
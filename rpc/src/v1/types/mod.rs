@@ -15,12 +15,16 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod block;
+mod consensus;
+mod health;
 mod transaction;
 mod unsigned_transaction;
 mod work;
 
 pub use self::block::Block;
 pub use self::block::BlockNumberAndHash;
+pub use self::consensus::ConsensusState;
+pub use self::health::Health;
 pub use self::transaction::{PendingTransactions, Transaction};
 pub use self::unsigned_transaction::UnsignedTransaction;
 pub use self::work::Work;
@@ -15,16 +15,18 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::{StakeKeyBuilder, StateResult, TopLevelState, TopState, TopStateView};
+use ckey::Ed25519Public as Public;
 use ctypes::transaction::Validator;
 use ctypes::{CompactValidatorEntry, CompactValidatorSet};
 use primitives::H256;
-use rlp::{decode_list, encode_list};
+use rlp::{decode_list, encode_list, Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use std::ops::Deref;
 use std::vec;
 
 lazy_static! {
     pub static ref NEXT_VALIDATORS_KEY: H256 = StakeKeyBuilder::new(1).append(&"Validators").into_key();
     pub static ref CURRENT_VALIDATORS_KEY: H256 = StakeKeyBuilder::new(1).append(&"CurrentValidators").into_key();
+    pub static ref PROPOSER_PRIORITIES_KEY: H256 = StakeKeyBuilder::new(1).append(&"ProposerPriorities").into_key();
 }
 
 #[derive(Debug)]
@@ -153,3 +155,73 @@ impl From<CurrentValidators> for Vec<Validator> {
         val.0
     }
 }
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ProposerPriority {
+    pub pubkey: Public,
+    pub priority: i64,
+}
+
+impl Encodable for ProposerPriority {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        // RLP doesn't have signed integers, so the priority is stored in two's complement.
+        s.begin_list(2).append(&self.pubkey).append(&(self.priority as u64));
+    }
+}
+
+impl Decodable for ProposerPriority {
+    fn decode(rlp: &Rlp<'_>) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 2 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 2,
+            })
+        }
+        Ok(Self {
+            pubkey: rlp.val_at(0)?,
+            priority: rlp.val_at::<u64>(1)? as i64,
+        })
+    }
+}
+
+/// The proposer priorities of the validators after the proposer of the block is selected.
+/// The proposer of the next block is selected from them.
+#[derive(Debug, Default)]
+pub struct ProposerPriorities(Vec<ProposerPriority>);
+impl ProposerPriorities {
+    pub fn from_vector(vec: Vec<ProposerPriority>) -> Self {
+        Self(vec)
+    }
+
+    pub fn load_from_state(state: &TopLevelState) -> StateResult<Self> {
+        let key = &*PROPOSER_PRIORITIES_KEY;
+        let priorities = state.action_data(&key)?.map(|data| decode_list(&data)).unwrap_or_default();
+
+        Ok(Self(priorities))
+    }
+
+    pub fn save_to_state(&self, state: &mut TopLevelState) -> StateResult<()> {
+        let key = &*PROPOSER_PRIORITIES_KEY;
+        if !self.is_empty() {
+            state.update_action_data(&key, encode_list(&self.0).to_vec())?;
+        } else {
+            state.remove_action_data(&key);
+        }
+        Ok(())
+    }
+}
+
+impl Deref for ProposerPriorities {
+    type Target = Vec<ProposerPriority>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<ProposerPriorities> for Vec<ProposerPriority> {
+    fn from(val: ProposerPriorities) -> Self {
+        val.0
+    }
+}
@@ -0,0 +1,239 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::snapshot::{ChunkEntry, Manifest};
+use ctypes::BlockHash;
+use primitives::H256;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const MAX_CHUNK_REQUEST_LENGTH: usize = 4;
+const WARNING_ATTEMPTS: u32 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Queued,
+    Downloading,
+    Downloaded,
+}
+
+/// Downloads the chunks listed in a snapshot manifest from several peers at once.
+///
+/// Downloaded chunks are kept in `dir`, so they are not downloaded again after the node restarts.
+pub struct ChunkDownloader {
+    block_hash: BlockHash,
+    dir: PathBuf,
+    chunks: Vec<ChunkEntry>,
+    states: HashMap<H256, (State, u32)>,
+    downloaded: usize,
+}
+
+impl ChunkDownloader {
+    pub fn new(dir: PathBuf, manifest: Manifest) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let mut states = HashMap::with_capacity(manifest.chunks.len());
+        let mut downloaded = 0;
+        for chunk in &manifest.chunks {
+            let is_downloaded = fs::read(dir.join(format!("{:x}", chunk.root)))
+                .map(|compressed| chunk.verify(&compressed))
+                .unwrap_or(false);
+            let state = if is_downloaded {
+                downloaded += 1;
+                State::Downloaded
+            } else {
+                State::Queued
+            };
+            states.insert(chunk.root, (state, 0));
+        }
+        if downloaded != 0 {
+            cinfo!(SYNC, "{} of {} snapshot chunks are already downloaded", downloaded, manifest.chunks.len());
+        }
+        Ok(Self {
+            block_hash: manifest.block_hash,
+            dir,
+            chunks: manifest.chunks,
+            states,
+            downloaded,
+        })
+    }
+
+    pub fn block_hash(&self) -> BlockHash {
+        self.block_hash
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn contains(&self, root: &H256) -> bool {
+        self.states.contains_key(root)
+    }
+
+    pub fn is_downloaded(&self, root: &H256) -> bool {
+        self.states.get(root).map(|(state, _)| *state == State::Downloaded).unwrap_or(false)
+    }
+
+    /// Returns the queued chunks to request to a peer. `priority` is requested first if it is queued.
+    pub fn create_request(&mut self, priority: Option<&H256>) -> Option<Vec<H256>> {
+        let mut roots = Vec::new();
+        let candidates = priority.into_iter().chain(self.chunks.iter().map(|chunk| &chunk.root));
+        for root in candidates {
+            if let Some((state, _)) = self.states.get_mut(root) {
+                if *state != State::Queued {
+                    continue
+                }
+                *state = State::Downloading;
+                roots.push(*root);
+                if roots.len() >= MAX_CHUNK_REQUEST_LENGTH {
+                    break
+                }
+            }
+        }
+        if roots.is_empty() {
+            None
+        } else {
+            Some(roots)
+        }
+    }
+
+    /// Verifies the downloaded chunk with the manifest and writes it to the download directory.
+    pub fn import(&mut self, root: &H256, compressed: &[u8]) -> Result<(), String> {
+        let entry = match self.chunks.iter().find(|chunk| chunk.root == *root) {
+            Some(entry) => entry,
+            None => return Err(format!("Chunk {} is not in the manifest", root)),
+        };
+        let (state, attempts) = self.states.get_mut(root).expect("Every chunk in the manifest has a state");
+        if *state != State::Downloading {
+            return Ok(())
+        }
+        if !entry.verify(compressed) {
+            *state = State::Queued;
+            *attempts += 1;
+            if *attempts >= WARNING_ATTEMPTS {
+                cwarn!(SYNC, "Chunk {} failed to be downloaded {} times", root, attempts);
+            }
+            return Err(format!("Chunk {} doesn't match the manifest", root))
+        }
+        if let Err(err) = fs::write(self.dir.join(format!("{:x}", root)), compressed) {
+            *state = State::Queued;
+            return Err(format!("Failed to write chunk {}: {}", root, err))
+        }
+        *state = State::Downloaded;
+        self.downloaded += 1;
+        Ok(())
+    }
+
+    pub fn reset_downloading(&mut self, roots: &[H256]) {
+        cdebug!(SYNC, "Remove downloading chunks {:?}", roots);
+        for root in roots {
+            if let Some((state, attempts)) = self.states.get_mut(root) {
+                if *state == State::Downloading {
+                    *state = State::Queued;
+                    *attempts += 1;
+                }
+            }
+        }
+    }
+
+    /// Returns the number of downloaded chunks and the number of all chunks.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.downloaded, self.chunks.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    /// Returns the directory for the chunks, which is removed with the `TempDir`.
+    fn test_dir() -> (TempDir, PathBuf) {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path().join("chunks");
+        (temp, dir)
+    }
+
+    fn compress(contents: &[u8]) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        snap::Writer::new(&mut compressed).write_all(contents).unwrap();
+        compressed
+    }
+
+    fn manifest(chunks: &[&[u8]]) -> Manifest {
+        Manifest {
+            block_hash: H256::random().into(),
+            block_number: 10,
+            state_root: H256::random(),
+            chunks: chunks.iter().map(|chunk| ChunkEntry::new(H256::random(), &compress(chunk)).unwrap()).collect(),
+        }
+    }
+
+    #[test]
+    fn request_priority_first() {
+        let (_temp, dir) = test_dir();
+        let manifest = manifest(&[b"1", b"2", b"3", b"4", b"5", b"6"]);
+        let roots: Vec<_> = manifest.chunks.iter().map(|chunk| chunk.root).collect();
+        let mut downloader = ChunkDownloader::new(dir.clone(), manifest).unwrap();
+
+        assert_eq!(Some(vec![roots[4], roots[0], roots[1], roots[2]]), downloader.create_request(Some(&roots[4])));
+        assert_eq!(Some(vec![roots[3], roots[5]]), downloader.create_request(Some(&roots[4])));
+        assert_eq!(None, downloader.create_request(None));
+
+        downloader.reset_downloading(&[roots[1]]);
+        assert_eq!(Some(vec![roots[1]]), downloader.create_request(None));
+    }
+
+    #[test]
+    fn import_verifies_chunks() {
+        let (_temp, dir) = test_dir();
+        let manifest = manifest(&[b"first", b"second"]);
+        let roots: Vec<_> = manifest.chunks.iter().map(|chunk| chunk.root).collect();
+        let mut downloader = ChunkDownloader::new(dir.clone(), manifest).unwrap();
+        downloader.create_request(None).unwrap();
+
+        assert!(downloader.import(&roots[0], b"forged").is_err());
+        assert!(!downloader.is_downloaded(&roots[0]));
+        downloader.import(&roots[1], &compress(b"second")).unwrap();
+        assert!(downloader.is_downloaded(&roots[1]));
+        assert_eq!(compress(b"second"), fs::read(dir.join(format!("{:x}", roots[1]))).unwrap());
+        assert_eq!((1, 2), downloader.progress());
+
+        assert_eq!(Some(vec![roots[0]]), downloader.create_request(None));
+        downloader.import(&roots[0], &compress(b"first")).unwrap();
+        assert_eq!((2, 2), downloader.progress());
+    }
+
+    #[test]
+    fn resume_with_downloaded_chunks() {
+        let (_temp, dir) = test_dir();
+        let manifest = manifest(&[b"first", b"second"]);
+        let roots: Vec<_> = manifest.chunks.iter().map(|chunk| chunk.root).collect();
+        {
+            let mut downloader = ChunkDownloader::new(dir.clone(), manifest.clone()).unwrap();
+            downloader.create_request(None).unwrap();
+            downloader.import(&roots[0], &compress(b"first")).unwrap();
+        }
+        // A partially written file is downloaded again.
+        fs::write(dir.join(format!("{:x}", roots[1])), b"sec").unwrap();
+
+        let mut downloader = ChunkDownloader::new(dir.clone(), manifest).unwrap();
+        assert_eq!((1, 2), downloader.progress());
+        assert_eq!(Some(vec![roots[1]]), downloader.create_request(None));
+    }
+}
@@ -15,7 +15,9 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod body;
+mod chunk;
 mod header;
 
 pub use self::body::BodyDownloader;
+pub use self::chunk::ChunkDownloader;
 pub use self::header::HeaderDownloader;
@@ -14,22 +14,24 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::downloader::{BodyDownloader, HeaderDownloader};
-use super::message::{Message, RequestMessage, ResponseMessage};
-use crate::snapshot::snapshot_path;
+use super::downloader::{BodyDownloader, ChunkDownloader, HeaderDownloader};
+use super::message::{AdvertisedSnapshot, Message, RequestMessage, ResponseMessage};
+use crate::snapshot::{available_snapshots, download_dir, snapshot_dir, snapshot_path, Manifest, SnapshotBlock};
 use ccore::encoded::Header as EncodedHeader;
 use ccore::{
     Block, BlockChainClient, BlockChainTrait, BlockImportError, BlockStatus, ChainNotify, Client, EngineInfo, Evidence,
     ImportBlock, ImportError, StateInfo,
 };
 use cdb::AsHashDB;
+use cinfo_courier::{Events as InformerEvents, InformerEventSender};
+use clight::{LightBlock, LightBlockSource, LightClient};
 use cnetwork::{Api, EventSender, IntoSocketAddr, NetworkExtension, NodeId};
 use codechain_crypto::BLAKE_NULL_RLP;
 use coordinator::Transaction;
 use cstate::{TopLevelState, TopStateView};
 use ctimer::TimerToken;
 use ctypes::header::Seal;
-use ctypes::{BlockHash, BlockId, BlockNumber, StorageId, SyncHeader};
+use ctypes::{BlockHash, BlockId, BlockNumber, Header, StorageId, SyncHeader};
 use kvdb::DBTransaction;
 use merkle_trie::snapshot::{ChunkDecompressor, Restore as SnapshotRestore};
 use merkle_trie::{skewed_merkle_root, Trie, TrieFactory};
@@ -37,6 +39,7 @@ use primitives::{H256, U256};
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
 use rlp::{Encodable, Rlp};
+use std::cmp::Reverse;
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::fs;
@@ -44,7 +47,7 @@ use std::iter::once;
 use std::mem::discriminant;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use token_generator::TokenGenerator;
 
 const SYNC_TIMER_TOKEN: TimerToken = 0;
@@ -55,6 +58,10 @@ const SYNC_EXPIRE_TOKEN_END: TimerToken = SYNC_EXPIRE_TOKEN_BEGIN + SYNC_EXPIRE_
 const SYNC_TIMER_INTERVAL: u64 = 1000;
 const SYNC_EXPIRE_REQUEST_INTERVAL: u64 = 15000;
 
+const MAX_ADVERTISED_SNAPSHOTS: usize = 8;
+/// A node falls back to the full sync if no peer advertises an acceptable snapshot in this time.
+const SNAPSHOT_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[derive(Debug, PartialEq)]
 pub struct TokenInfo {
     node_id: NodeId,
@@ -63,6 +70,10 @@ pub struct TokenInfo {
 
 #[derive(Debug)]
 enum State {
+    SnapshotDiscovery {
+        started_at: Instant,
+        rejected: HashSet<BlockHash>,
+    },
     SnapshotHeader(BlockHash, u64),
     SnapshotBody {
         header: EncodedHeader,
@@ -80,9 +91,15 @@ enum State {
 }
 
 impl State {
-    fn initial(client: &Client, snapshot_target: Option<(H256, u64)>) -> Self {
+    fn initial(client: &Client, snapshot_target: Option<(H256, u64)>, auto_snapshot: bool) -> Self {
         let (hash, num) = match snapshot_target {
             Some((h, n)) => (h.into(), n),
+            None if auto_snapshot && client.best_block_header().number() == 0 => {
+                return State::SnapshotDiscovery {
+                    started_at: Instant::now(),
+                    rejected: Default::default(),
+                }
+            }
             None => return State::Full,
         };
         let header = match client.block_header(&num.into()) {
@@ -130,6 +147,10 @@ impl State {
 
     fn next(&self, client: &Client) -> Self {
         match self {
+            // No snapshot is found. The node syncs from the genesis block.
+            State::SnapshotDiscovery {
+                ..
+            } => State::Full,
             State::SnapshotHeader(hash, _) => {
                 let header = client.block_header(&(*hash).into()).expect("Snapshot header is imported");
                 State::SnapshotBody {
@@ -200,6 +221,18 @@ pub struct Extension {
     last_request: u64,
     seq: u64,
     snapshot_dir: String,
+    peer_snapshots: HashMap<NodeId, Vec<AdvertisedSnapshot>>,
+    chunk_downloader: Option<ChunkDownloader>,
+    /// Set when a chunk verified by the manifest is invalid. The chunks are requested one by one after that.
+    manifest_rejected: bool,
+    trusted_checkpoint: Option<(BlockHash, BlockNumber)>,
+    /// Trusts the checkpoint, or the genesis block if no checkpoint is configured. A snapshot is selected only if
+    /// its block is verified from it.
+    snapshot_anchor: Option<LightClient>,
+    /// The light blocks received to verify the snapshot candidate. The blocks between the anchor and the candidate
+    /// are also received when the validators changed too much to verify the candidate directly.
+    snapshot_light_blocks: HashMap<BlockNumber, LightBlock>,
+    informer: InformerEventSender,
 }
 
 impl Extension {
@@ -207,12 +240,25 @@ impl Extension {
         client: Arc<Client>,
         api: Box<dyn Api>,
         snapshot_target: Option<(H256, u64)>,
+        auto_snapshot: bool,
+        trusted_checkpoint: Option<(H256, u64)>,
         snapshot_dir: String,
+        informer: InformerEventSender,
     ) -> Extension {
         api.set_timer(SYNC_TIMER_TOKEN, Duration::from_millis(SYNC_TIMER_INTERVAL)).expect("Timer set succeeds");
 
-        let state = State::initial(&client, snapshot_target);
+        let state = State::initial(&client, snapshot_target, auto_snapshot);
         cdebug!(SYNC, "Initial state is {:?}", state);
+        let trusted_checkpoint = trusted_checkpoint.map(|(hash, number)| (BlockHash::from(hash), number));
+        let snapshot_anchor = match (&state, trusted_checkpoint) {
+            (
+                State::SnapshotDiscovery {
+                    ..
+                },
+                None,
+            ) => genesis_anchor(&client),
+            _ => None,
+        };
         let mut header = client.best_header();
         let mut hollow_headers = vec![header.decode()];
         while client.block_body(&BlockId::Hash(header.hash())).is_none() {
@@ -250,36 +296,48 @@ impl Extension {
             last_request: Default::default(),
             seq: Default::default(),
             snapshot_dir,
+            peer_snapshots: Default::default(),
+            chunk_downloader: None,
+            manifest_rejected: false,
+            trusted_checkpoint,
+            snapshot_anchor,
+            snapshot_light_blocks: Default::default(),
+            informer,
         }
     }
 
     fn move_state(&mut self) {
         let next_state = self.state.next(&self.client);
         cdebug!(SYNC, "Transitioning the state to {:?}", next_state);
-        if let State::Full = next_state {
-            let best_hash = match &self.state {
-                State::SnapshotHeader(hash, _) => *hash,
+        let prev_state = std::mem::replace(&mut self.state, next_state);
+        if let State::Full = self.state {
+            let best_hash = match &prev_state {
+                State::SnapshotDiscovery {
+                    ..
+                } => None,
+                State::SnapshotHeader(hash, _) => Some(*hash),
                 State::SnapshotBody {
                     header,
                     ..
-                } => header.hash(),
+                } => Some(header.hash()),
                 State::SnapshotTopChunk {
                     block,
                     ..
-                } => *block,
+                } => Some(*block),
                 State::SnapShotModuleChunk {
                     block,
                     ..
-                } => *block,
+                } => Some(*block),
                 State::Full => panic!("Trying to transit the state from State::Full"),
             };
-            self.client.force_update_best_block(&best_hash);
-            for downloader in self.header_downloaders.values_mut() {
-                downloader.update_pivot(best_hash);
+            if let Some(best_hash) = best_hash {
+                self.client.force_update_best_block(&best_hash);
+                for downloader in self.header_downloaders.values_mut() {
+                    downloader.update_pivot(best_hash);
+                }
             }
             self.send_status_broadcast();
         }
-        self.state = next_state;
     }
 
     fn dismiss_request(&mut self, id: &NodeId, request_id: u64) {
@@ -301,6 +359,8 @@ impl Extension {
                     seq: U256::from(self.seq),
                     best_hash: chain_info.best_proposal_block_hash,
                     genesis_hash: chain_info.genesis_hash,
+                    snapshots: self.advertised_snapshots(),
+                    app_desc_hash: self.client.app_desc_hash(),
                 }
                 .rlp_bytes(),
             ),
@@ -314,6 +374,8 @@ impl Extension {
         }
 
         let chain_info = self.client.chain_info();
+        let snapshots = self.advertised_snapshots();
+        let app_desc_hash = self.client.app_desc_hash();
         for id in self.connected_nodes.iter() {
             self.api.send(
                 id,
@@ -322,6 +384,8 @@ impl Extension {
                         seq: U256::from(self.seq),
                         best_hash: chain_info.best_proposal_block_hash,
                         genesis_hash: chain_info.genesis_hash,
+                        snapshots: snapshots.clone(),
+                        app_desc_hash,
                     }
                     .rlp_bytes(),
                 ),
@@ -330,6 +394,23 @@ impl Extension {
         }
     }
 
+    /// Returns the most recent snapshots this node can serve.
+    fn advertised_snapshots(&self) -> Vec<AdvertisedSnapshot> {
+        let mut snapshots: Vec<_> = available_snapshots(&self.snapshot_dir)
+            .into_iter()
+            .filter_map(|block_hash| {
+                let block_number = self.client.block_number(&BlockId::Hash(block_hash))?;
+                Some(AdvertisedSnapshot {
+                    block_hash,
+                    block_number,
+                })
+            })
+            .collect();
+        snapshots.sort_unstable_by_key(|snapshot| Reverse(snapshot.block_number));
+        snapshots.truncate(MAX_ADVERTISED_SNAPSHOTS);
+        snapshots
+    }
+
     fn send_header_request(&mut self, id: &NodeId, request: RequestMessage) {
         if let Some(requests) = self.requests.get_mut(id) {
             ctrace!(SYNC, "Send header request to {}", id);
@@ -377,32 +458,90 @@ impl Extension {
         self.check_sync_variable();
     }
 
-    fn send_chunk_request(&mut self, block: &BlockHash, root: &H256) {
-        let have_chunk_request =
-            self.requests.values().flatten().any(|r| matches!(r, (_, RequestMessage::StateChunk(..))));
+    fn send_request_with_timeout(&mut self, id: &NodeId, request: RequestMessage) {
+        if let Some(requests) = self.requests.get_mut(id) {
+            cdebug!(SYNC, "Request to {} {:?}", id, request);
+            let request_id = self.last_request;
+            self.last_request += 1;
+            requests.push((request_id, request.clone()));
+            self.api.send(id, Arc::new(Message::Request(request_id, request).rlp_bytes()));
 
-        if !have_chunk_request {
-            let mut peer_ids: Vec<_> = self.header_downloaders.keys().cloned().collect();
-            peer_ids.shuffle(&mut thread_rng());
-            if let Some(id) = peer_ids.first() {
-                if let Some(requests) = self.requests.get_mut(&id) {
-                    let req = RequestMessage::StateChunk(*block, vec![*root]);
-                    cdebug!(SYNC, "Request chunk to {} {:?}", id, req);
-                    let request_id = self.last_request;
-                    self.last_request += 1;
-                    requests.push((request_id, req.clone()));
-                    self.api.send(id, Arc::new(Message::Request(request_id, req).rlp_bytes()));
-
-                    let token = &self.tokens[id];
-                    let token_info = self.tokens_info.get_mut(token).unwrap();
-
-                    let _ = self.api.clear_timer(*token);
-                    self.api
-                        .set_timer_once(*token, Duration::from_millis(SYNC_EXPIRE_REQUEST_INTERVAL))
-                        .expect("Timer set succeeds");
-                    token_info.request_id = Some(request_id);
+            let token = &self.tokens[id];
+            let token_info = self.tokens_info.get_mut(token).unwrap();
+
+            let _ = self.api.clear_timer(*token);
+            self.api
+                .set_timer_once(*token, Duration::from_millis(SYNC_EXPIRE_REQUEST_INTERVAL))
+                .expect("Timer set succeeds");
+            token_info.request_id = Some(request_id);
+        }
+    }
+
+    /// Returns the peers without a pending request to ask the snapshot of `block`, and whether they advertise it.
+    fn idle_snapshot_peers(&self, block: &BlockHash) -> (Vec<NodeId>, bool) {
+        let is_holder = |id: &NodeId| {
+            self.peer_snapshots
+                .get(id)
+                .map(|snapshots| snapshots.iter().any(|snapshot| snapshot.block_hash == *block))
+                .unwrap_or(false)
+        };
+        let is_idle = |id: &NodeId| {
+            self.tokens
+                .get(id)
+                .and_then(|token| self.tokens_info.get(token))
+                .map(|token_info| token_info.request_id.is_none())
+                .unwrap_or(false)
+        };
+        // The peers which don't advertise snapshots may be old ones, so they are asked when no peer advertises it.
+        let advertised = self.header_downloaders.keys().any(is_holder);
+        let mut peers: Vec<_> = self
+            .header_downloaders
+            .keys()
+            .filter(|id| (!advertised || is_holder(*id)) && is_idle(*id))
+            .cloned()
+            .collect();
+        peers.shuffle(&mut thread_rng());
+        (peers, advertised)
+    }
+
+    /// Sends chunk requests to the idle peers. `next_root` is the chunk which the restoration waits for.
+    fn send_chunk_requests(&mut self, block: BlockHash, next_root: H256) {
+        let (peers, advertised) = self.idle_snapshot_peers(&block);
+        let mut peers = peers.into_iter();
+
+        if let Some(downloader) = self.chunk_downloader.as_mut().filter(|downloader| downloader.contains(&next_root)) {
+            let mut requests = Vec::new();
+            for id in peers {
+                match downloader.create_request(Some(&next_root)) {
+                    Some(roots) => requests.push((id, RequestMessage::StateChunk(block, roots))),
+                    None => break,
                 }
             }
+            for (id, request) in requests {
+                self.send_request_with_timeout(&id, request);
+            }
+            return
+        }
+
+        // Without the manifest, the chunks are requested one by one.
+        let is_requested =
+            self.requests.values().flatten().any(
+                |(_, request)| matches!(request, RequestMessage::StateChunk(_, roots) if roots.contains(&next_root)),
+            );
+        if !is_requested {
+            if let Some(id) = peers.next() {
+                self.send_request_with_timeout(&id, RequestMessage::StateChunk(block, vec![next_root]));
+            }
+        }
+        let is_manifest_requested = self
+            .requests
+            .values()
+            .flatten()
+            .any(|(_, request)| matches!(request, RequestMessage::SnapshotManifest(..)));
+        if advertised && self.chunk_downloader.is_none() && !self.manifest_rejected && !is_manifest_requested {
+            if let Some(id) = peers.next() {
+                self.send_request_with_timeout(&id, RequestMessage::SnapshotManifest(block));
+            }
         }
     }
 
@@ -425,7 +564,9 @@ impl Extension {
             let chunk_requests: Vec<RequestMessage> = requests
                 .iter()
                 .filter_map(|r| match r {
-                    (_, RequestMessage::StateChunk(..)) => Some(r.1.clone()),
+                    (_, RequestMessage::StateChunk(..)) | (_, RequestMessage::SnapshotManifest(..)) => {
+                        Some(r.1.clone())
+                    }
                     _ => None,
                 })
                 .collect();
@@ -509,10 +650,17 @@ impl NetworkExtension<Event> for Extension {
             cinfo!(SYNC, "Peer removed #{}", id);
 
             self.header_downloaders.remove(id);
+            self.peer_snapshots.remove(id);
 
             for (_, request) in self.requests.remove(id).into_iter().flatten() {
-                if let RequestMessage::Bodies(hashes) = request {
-                    self.body_downloader.reset_downloading(&hashes);
+                match request {
+                    RequestMessage::Bodies(hashes) => self.body_downloader.reset_downloading(&hashes),
+                    RequestMessage::StateChunk(_, roots) => {
+                        if let Some(downloader) = &mut self.chunk_downloader {
+                            downloader.reset_downloading(&roots);
+                        }
+                    }
+                    _ => {}
                 }
             }
 
@@ -537,7 +685,9 @@ impl NetworkExtension<Event> for Extension {
                     seq,
                     best_hash,
                     genesis_hash,
-                } => self.on_peer_status(id, seq, best_hash, genesis_hash),
+                    snapshots,
+                    app_desc_hash,
+                } => self.on_peer_status(id, seq, best_hash, genesis_hash, snapshots, app_desc_hash),
                 Message::Request(request_id, request) => self.on_peer_request(id, request_id, request),
                 Message::Response(request_id, response) => self.on_peer_response(id, request_id, response),
             }
@@ -553,7 +703,15 @@ impl NetworkExtension<Event> for Extension {
                 peer_ids.shuffle(&mut thread_rng());
 
                 match self.state {
-                    State::SnapshotHeader(_, num) => {
+                    State::SnapshotDiscovery {
+                        ..
+                    } => self.discover_snapshot(),
+                    State::SnapshotHeader(hash, num) => {
+                        if let Some(block) = self.local_snapshot_block(&hash) {
+                            cinfo!(SYNC, "Import the snapshot headers from the local snapshot of block {}", hash);
+                            self.import_snapshot_headers(block.parent, block.header);
+                            return
+                        }
                         for id in &peer_ids {
                             self.send_header_request(id, RequestMessage::Headers {
                                 start_number: num - 1,
@@ -565,10 +723,16 @@ impl NetworkExtension<Event> for Extension {
                         ref header,
                         ..
                     } => {
+                        let hash = header.hash();
+                        if let Some(block) = self.local_snapshot_block(&hash) {
+                            cinfo!(SYNC, "Import the snapshot body from the local snapshot of block {}", hash);
+                            self.import_snapshot_body(block.evidences, block.transactions);
+                            return
+                        }
                         for id in &peer_ids {
                             if let Some(requests) = self.requests.get_mut(id) {
                                 ctrace!(SYNC, "Send snapshot body request to {}", id);
-                                let request = RequestMessage::Bodies(vec![header.hash()]);
+                                let request = RequestMessage::Bodies(vec![hash]);
                                 let request_id = self.last_request;
                                 self.last_request += 1;
                                 requests.push((request_id, request.clone()));
@@ -586,26 +750,11 @@ impl NetworkExtension<Event> for Extension {
                         }
                     }
                     State::SnapshotTopChunk {
-                        block,
-                        ref mut restore,
-                    } => {
-                        if let Some(root) = restore.next_to_feed() {
-                            self.send_chunk_request(&block, &root);
-                        } else {
-                            self.move_state();
-                        }
-                    }
-                    State::SnapShotModuleChunk {
-                        block,
-                        ref mut restore,
                         ..
-                    } => {
-                        if let Some(root) = restore.next_to_feed() {
-                            self.send_chunk_request(&block, &root);
-                        } else {
-                            self.move_state();
-                        }
                     }
+                    | State::SnapShotModuleChunk {
+                        ..
+                    } => self.request_next_chunk(),
                     State::Full => {
                         for id in &peer_ids {
                             let request =
@@ -646,7 +795,15 @@ impl NetworkExtension<Event> for Extension {
                             RequestMessage::Bodies(hashes) => {
                                 self.body_downloader.reset_downloading(&hashes);
                             }
-                            _ => unreachable!(),
+                            RequestMessage::StateChunk(_, roots) => {
+                                if let Some(downloader) = &mut self.chunk_downloader {
+                                    downloader.reset_downloading(&roots);
+                                }
+                            }
+                            RequestMessage::Headers {
+                                ..
+                            }
+                            | RequestMessage::SnapshotManifest(..) => {}
                         }
                     }
                 }
@@ -735,8 +892,30 @@ impl Extension {
 }
 
 impl Extension {
-    fn on_peer_status(&mut self, from: &NodeId, seq: U256, best_hash: BlockHash, genesis_hash: BlockHash) {
+    fn on_peer_status(
+        &mut self,
+        from: &NodeId,
+        seq: U256,
+        best_hash: BlockHash,
+        genesis_hash: BlockHash,
+        mut snapshots: Vec<AdvertisedSnapshot>,
+        app_desc_hash: Option<H256>,
+    ) {
         // Validity check
+        if let (Some(theirs), Some(ours)) = (app_desc_hash, self.client.app_desc_hash()) {
+            if theirs != ours {
+                cwarn!(
+                    SYNC,
+                    "Peer {} runs a different app descriptor({}), ours is {}. Disconnect it",
+                    from,
+                    theirs,
+                    ours
+                );
+                self.on_node_removed(from);
+                self.api.disconnect(from);
+                return
+            }
+        }
         if genesis_hash != self.client.chain_info().genesis_hash {
             cinfo!(SYNC, "Genesis hash mismatch with peer {}", from);
             return
@@ -753,9 +932,196 @@ impl Extension {
                 e.insert(HeaderDownloader::new(self.client.clone(), seq, best_hash));
             }
         }
+        snapshots.truncate(MAX_ADVERTISED_SNAPSHOTS);
+        self.peer_snapshots.insert(*from, snapshots);
         cinfo!(SYNC, "Peer #{} status update: seq: {}, best_hash: {}", from, seq, best_hash);
     }
 
+    /// Returns the newest snapshot advertised by peers except the rejected ones, and the peers holding it.
+    fn snapshot_candidate(&self, rejected: &HashSet<BlockHash>) -> Option<(AdvertisedSnapshot, Vec<NodeId>)> {
+        // The headers before and after the snapshot block are needed to verify it.
+        let candidate = self
+            .peer_snapshots
+            .values()
+            .flatten()
+            .filter(|snapshot| snapshot.block_number >= 2 && !rejected.contains(&snapshot.block_hash))
+            .max_by_key(|snapshot| snapshot.block_number)
+            .cloned()?;
+        let holders = self
+            .peer_snapshots
+            .iter()
+            .filter(|(_, snapshots)| snapshots.contains(&candidate))
+            .map(|(id, _)| *id)
+            .collect();
+        Some((candidate, holders))
+    }
+
+    fn has_header_request(&self, id: &NodeId) -> bool {
+        self.requests
+            .get(id)
+            .map(|requests| requests.iter().any(|(_, request)| matches!(request, RequestMessage::Headers { .. })))
+            .unwrap_or(false)
+    }
+
+    fn discover_snapshot(&mut self) {
+        let started_at = match &self.state {
+            State::SnapshotDiscovery {
+                started_at,
+                ..
+            } => *started_at,
+            _ => return,
+        };
+        let request = match (&self.snapshot_anchor, &self.state) {
+            (
+                Some(anchor),
+                State::SnapshotDiscovery {
+                    rejected,
+                    ..
+                },
+            ) => self.snapshot_candidate(rejected).and_then(|(snapshot, holders)| {
+                let number = verify_snapshot_block(anchor, &snapshot, &self.snapshot_light_blocks).ok()??;
+                cdebug!(
+                    SYNC,
+                    "Request headers around block #{} to verify the snapshot block #{}",
+                    number,
+                    snapshot.block_number
+                );
+                Some((
+                    RequestMessage::Headers {
+                        start_number: number - 1,
+                        max_count: 3,
+                    },
+                    holders,
+                ))
+            }),
+            (None, _) if started_at.elapsed() < SNAPSHOT_DISCOVERY_TIMEOUT => {
+                self.trusted_checkpoint.map(|(_, number)| {
+                    cdebug!(SYNC, "Request headers of the trusted checkpoint #{}", number);
+                    (
+                        RequestMessage::Headers {
+                            start_number: number,
+                            max_count: 2,
+                        },
+                        self.header_downloaders.keys().cloned().collect(),
+                    )
+                })
+            }
+            _ => None,
+        };
+        match request {
+            Some((request, holders)) => {
+                // A peer is asked again only after it responds or the request expires.
+                let idle: Vec<_> = holders.into_iter().filter(|id| !self.has_header_request(id)).collect();
+                if let Some(id) = idle.choose(&mut thread_rng()) {
+                    self.send_request_with_timeout(id, request);
+                }
+            }
+            None if started_at.elapsed() >= SNAPSHOT_DISCOVERY_TIMEOUT => {
+                cinfo!(SYNC, "No peer advertises an acceptable snapshot. Sync from the genesis block");
+                self.move_state();
+            }
+            None => {}
+        }
+    }
+
+    fn on_checkpoint_headers(&mut self, from: &NodeId, headers: Vec<SyncHeader>) {
+        let (hash, number) = self.trusted_checkpoint.expect("Checkpoint headers are requested only if it's configured");
+        if headers.len() != 2 || headers[0].hash() != hash || !is_parent(&headers[0], &headers[1]) {
+            cdebug!(SYNC, "Peer {} responded with invalid headers for the trusted checkpoint #{}", from, number);
+            return
+        }
+        // LightClient::new checks it with the next validator set hash of the checkpoint.
+        let next_validator_set = match headers[1].prev_validator_set() {
+            Some(validator_set) => validator_set.clone(),
+            None => {
+                cdebug!(SYNC, "Peer {} responded without the validator set of the trusted checkpoint", from);
+                return
+            }
+        };
+        let header = headers.into_iter().next().expect("There are two headers").into();
+        match LightClient::new(header, next_validator_set) {
+            Ok(anchor) => {
+                cinfo!(SYNC, "Snapshots are verified from the trusted checkpoint #{} ({})", number, hash);
+                self.snapshot_anchor = Some(anchor);
+            }
+            Err(err) => {
+                cwarn!(SYNC, "Peer {} responded with an invalid validator set of the checkpoint: {}", from, err)
+            }
+        }
+    }
+
+    fn on_snapshot_candidate_headers(&mut self, from: &NodeId, headers: Vec<SyncHeader>) {
+        let anchor = match &self.snapshot_anchor {
+            Some(anchor) => anchor,
+            None => {
+                self.on_checkpoint_headers(from, headers);
+                return
+            }
+        };
+        let snapshot = match &self.state {
+            State::SnapshotDiscovery {
+                rejected,
+                ..
+            } => self.snapshot_candidate(rejected).map(|(snapshot, _)| snapshot),
+            _ => None,
+        };
+        let snapshot = match snapshot {
+            Some(snapshot) => snapshot,
+            None => return,
+        };
+        let number = match verify_snapshot_block(anchor, &snapshot, &self.snapshot_light_blocks) {
+            Ok(Some(number)) => number,
+            _ => return,
+        };
+        if headers.len() != 3 || headers[1].number() != number {
+            cdebug!(SYNC, "Peer {} responded with headers which are not around block #{}", from, number);
+            return
+        }
+        if number == snapshot.block_number && headers[1].hash() != snapshot.block_hash {
+            cdebug!(SYNC, "Peer {} responded with headers of a snapshot it doesn't advertise", from);
+            return
+        }
+        let block = match snapshot_light_block(headers) {
+            Ok(block) => block,
+            Err(err) => {
+                cdebug!(SYNC, "Peer {} responded with invalid headers around block #{}: {}", from, number, err);
+                return
+            }
+        };
+        self.snapshot_light_blocks.insert(number, block);
+
+        match verify_snapshot_block(anchor, &snapshot, &self.snapshot_light_blocks) {
+            Ok(None) => {
+                cinfo!(SYNC, "Snapshot of block #{} ({}) is selected", snapshot.block_number, snapshot.block_hash);
+                self.snapshot_light_blocks.clear();
+                self.state = State::SnapshotHeader(snapshot.block_hash, snapshot.block_number);
+                self.manifest_rejected = false;
+                cdebug!(SYNC, "Transitioning the state to {:?}", self.state);
+            }
+            Ok(Some(next)) => {
+                cdebug!(SYNC, "Block #{} should be verified before the snapshot block #{}", next, snapshot.block_number)
+            }
+            Err(err) => {
+                cwarn!(
+                    SYNC,
+                    "Snapshot of block #{} ({}) is not finalized by the validators trusted from block #{}: {}",
+                    snapshot.block_number,
+                    snapshot.block_hash,
+                    anchor.trusted_header().number(),
+                    err
+                );
+                self.snapshot_light_blocks.clear();
+                if let State::SnapshotDiscovery {
+                    rejected,
+                    ..
+                } = &mut self.state
+                {
+                    rejected.insert(snapshot.block_hash);
+                }
+            }
+        }
+    }
+
     fn on_peer_request(&self, from: &NodeId, id: u64, request: RequestMessage) {
         if !self.connected_nodes.contains(from) {
             cinfo!(SYNC, "Request from invalid peer #{} received", from);
@@ -782,6 +1148,9 @@ impl Extension {
             RequestMessage::StateChunk(block_hash, chunk_root) => {
                 self.create_state_chunk_response(block_hash, chunk_root)
             }
+            RequestMessage::SnapshotManifest(block_hash) => {
+                ResponseMessage::SnapshotManifest(Manifest::read(&snapshot_dir(&self.snapshot_dir, &block_hash)).ok())
+            }
         };
 
         self.api.send(from, Arc::new(Message::Response(id, response).rlp_bytes()));
@@ -796,6 +1165,7 @@ impl Extension {
             RequestMessage::StateChunk {
                 ..
             } => true,
+            RequestMessage::SnapshotManifest(..) => true,
         }
     }
 
@@ -944,6 +1314,20 @@ impl Extension {
                     self.dismiss_request(from, id);
                     self.on_chunk_response(from, &roots, &chunks);
                 }
+                ResponseMessage::SnapshotManifest(manifest) => {
+                    if let Some(token) = self.tokens.get(from) {
+                        if let Some(token_info) = self.tokens_info.get_mut(token) {
+                            if token_info.request_id.is_none() {
+                                ctrace!(SYNC, "Expired before handling response");
+                                return
+                            }
+                            self.api.clear_timer(*token).expect("Timer clear succeed");
+                            token_info.request_id = None;
+                        }
+                    }
+                    self.dismiss_request(from, id);
+                    self.on_manifest_response(from, manifest);
+                }
             }
         }
     }
@@ -1006,6 +1390,9 @@ impl Extension {
                 // Check length
                 roots.len() == chunks.len()
             }
+            (RequestMessage::SnapshotManifest(block_hash), ResponseMessage::SnapshotManifest(manifest)) => {
+                manifest.as_ref().map(|manifest| manifest.block_hash == *block_hash).unwrap_or(true)
+            }
             _ => {
                 cwarn!(SYNC, "Invalid response type");
                 false
@@ -1016,6 +1403,9 @@ impl Extension {
     fn on_header_response(&mut self, from: &NodeId, mut headers: Vec<SyncHeader>) {
         ctrace!(SYNC, "Received header response from({}) with length({})", from, headers.len());
         match self.state {
+            State::SnapshotDiscovery {
+                ..
+            } => self.on_snapshot_candidate_headers(from, headers),
             State::SnapshotHeader(hash, _) => {
                 if headers.len() != 2 || headers[1].hash() != hash {
                     cdebug!(
@@ -1027,28 +1417,8 @@ impl Extension {
                     )
                 } else {
                     let header = headers.pop().expect("headers.len() == 2");
-                    let header_hash = header.hash();
                     let parent = headers.pop().expect("headers.len() == 1");
-                    let parent_hash = parent.hash();
-                    match self.client.import_trusted_header(parent.into()) {
-                        Ok(_)
-                        | Err(BlockImportError::Import(ImportError::AlreadyInChain))
-                        | Err(BlockImportError::Import(ImportError::AlreadyQueued)) => {}
-                        Err(err) => {
-                            cwarn!(SYNC, "Cannot import header({}): {:?}", parent_hash, err);
-                            return
-                        }
-                    }
-                    match self.client.import_trusted_header(header.into()) {
-                        Ok(_)
-                        | Err(BlockImportError::Import(ImportError::AlreadyInChain))
-                        | Err(BlockImportError::Import(ImportError::AlreadyQueued)) => {}
-                        Err(err) => {
-                            cwarn!(SYNC, "Cannot import header({}): {:?}", header_hash, err);
-                            return
-                        }
-                    }
-                    self.move_state();
+                    self.import_snapshot_headers(parent.into(), header.into());
                 }
             }
             State::Full => {
@@ -1153,31 +1523,11 @@ impl Extension {
 
         match &self.state {
             State::SnapshotBody {
-                header,
+                ..
             } => {
                 let (evidences, transactions) =
-                    bodies.first().expect("Body response in SnapshotBody state has only one body");
-                let new_evidences_root = skewed_merkle_root(BLAKE_NULL_RLP, evidences.iter().map(Encodable::rlp_bytes));
-                let new_transactions_root =
-                    skewed_merkle_root(BLAKE_NULL_RLP, transactions.iter().map(Encodable::rlp_bytes));
-                if header.transactions_root() == new_transactions_root && header.evidences_root() == new_evidences_root
-                {
-                    let block = Block {
-                        header: header.decode(),
-                        evidences: evidences.clone(),
-                        transactions: transactions.clone(),
-                    };
-                    match self.client.import_trusted_block(&block) {
-                        Ok(_) | Err(BlockImportError::Import(ImportError::AlreadyInChain)) => {
-                            self.move_state();
-                        }
-                        Err(BlockImportError::Import(ImportError::AlreadyQueued)) => {}
-                        // FIXME: handle import errors
-                        Err(err) => {
-                            cwarn!(SYNC, "Cannot import block({}): {:?}", header.hash(), err);
-                        }
-                    }
-                }
+                    bodies.into_iter().next().expect("Body response in SnapshotBody state has only one body");
+                self.import_snapshot_body(evidences, transactions);
             }
             State::Full => {
                 self.body_downloader.import_bodies(hashes, bodies);
@@ -1197,69 +1547,348 @@ impl Extension {
 
     fn on_chunk_response(&mut self, from: &NodeId, roots: &[H256], chunks: &[Vec<u8>]) {
         assert_eq!(roots.len(), chunks.len());
-        let (block, restore) = match self.state {
+        let next = match &mut self.state {
             State::SnapshotTopChunk {
-                block,
-                ref mut restore,
-            } => (block, restore),
-            State::SnapShotModuleChunk {
-                block,
-                ref mut restore,
+                restore,
                 ..
-            } => (block, restore),
+            }
+            | State::SnapShotModuleChunk {
+                restore,
+                ..
+            } => restore.next_to_feed(),
             _ => return,
         };
-        assert_eq!(roots.len(), chunks.len());
+        let previous_progress = self.chunk_downloader.as_ref().map(ChunkDownloader::progress);
         for (r, c) in roots.iter().zip(chunks) {
             if c.is_empty() {
                 cdebug!(SYNC, "Peer {} sent empty response for chunk request {}", from, r);
+                if let Some(downloader) = &mut self.chunk_downloader {
+                    downloader.reset_downloading(&[*r]);
+                }
                 continue
             }
-            let decompressor = ChunkDecompressor::from_slice(c);
-            let raw_chunk = match decompressor.decompress() {
-                Ok(chunk) => chunk,
-                Err(e) => {
-                    cwarn!(SYNC, "Decode failed for chunk response from peer {}: {}", from, e);
-                    continue
+            if let Some(downloader) = self.chunk_downloader.as_mut().filter(|downloader| downloader.contains(r)) {
+                if let Err(err) = downloader.import(r, c) {
+                    cwarn!(SYNC, "Invalid chunk response from peer {}: {}", from, err);
+                }
+            } else if next == Some(*r) {
+                if let Err(err) = self.feed_chunk(*r, c) {
+                    cwarn!(SYNC, "Invalid chunk response from peer {}: {}", from, err);
+                }
+            }
+        }
+        if let Some((previous, _)) = previous_progress {
+            self.report_chunk_progress(previous);
+        }
+        self.request_next_chunk();
+    }
+
+    fn on_manifest_response(&mut self, from: &NodeId, manifest: Option<Manifest>) {
+        let block = match &self.state {
+            State::SnapshotTopChunk {
+                block,
+                ..
+            }
+            | State::SnapShotModuleChunk {
+                block,
+                ..
+            } => *block,
+            _ => return,
+        };
+        if self.chunk_downloader.is_some() || self.manifest_rejected {
+            return
+        }
+        let manifest = match manifest {
+            Some(manifest) => manifest,
+            None => {
+                cdebug!(SYNC, "Peer {} doesn't have the snapshot manifest of block {}", from, block);
+                return
+            }
+        };
+        let header = self.client.block_header(&block.into()).expect("Snapshot header must exist");
+        if manifest.block_hash != block || manifest.state_root != header.state_root() {
+            cwarn!(SYNC, "Peer {} sent a snapshot manifest which doesn't match the block {}", from, block);
+            return
+        }
+        let chunk_count = manifest.chunks.len();
+        match ChunkDownloader::new(download_dir(&self.snapshot_dir, &block), manifest) {
+            Ok(downloader) => {
+                cinfo!(SYNC, "Download {} snapshot chunks of block {}", chunk_count, block);
+                self.chunk_downloader = Some(downloader);
+            }
+            Err(err) => {
+                cwarn!(SYNC, "Cannot create the snapshot download directory: {}", err);
+                return
+            }
+        }
+        self.report_chunk_progress(0);
+        self.request_next_chunk();
+    }
+
+    /// Reports the download progress. It is logged whenever another 10% is downloaded.
+    fn report_chunk_progress(&self, previous: usize) {
+        let downloader = match &self.chunk_downloader {
+            Some(downloader) => downloader,
+            None => return,
+        };
+        let (downloaded, total) = downloader.progress();
+        if total != 0 && (previous == 0 || previous * 10 / total != downloaded * 10 / total) {
+            cinfo!(SYNC, "Downloaded {}/{} snapshot chunks of block {}", downloaded, total, downloader.block_hash());
+        }
+        self.informer.notify(InformerEvents::SnapshotSyncProgress(
+            "SnapshotSyncProgress".to_string(),
+            format!("{}", downloader.block_hash()),
+            downloaded,
+            total,
+        ));
+    }
+
+    /// Returns the snapshot block in the local snapshot directory, e.g. the one imported from an archive.
+    fn local_snapshot_block(&self, hash: &BlockHash) -> Option<SnapshotBlock> {
+        let block = SnapshotBlock::read(&snapshot_dir(&self.snapshot_dir, hash)).ok()?;
+        if block.header.hash() != *hash || *block.header.parent_hash() != block.parent.hash() {
+            cwarn!(SYNC, "The local snapshot block doesn't match the snapshot target {}", hash);
+            return None
+        }
+        Some(block)
+    }
+
+    fn import_snapshot_headers(&mut self, parent: Header, header: Header) {
+        let parent_hash = parent.hash();
+        match self.client.import_trusted_header(parent) {
+            Ok(_)
+            | Err(BlockImportError::Import(ImportError::AlreadyInChain))
+            | Err(BlockImportError::Import(ImportError::AlreadyQueued)) => {}
+            Err(err) => {
+                cwarn!(SYNC, "Cannot import header({}): {:?}", parent_hash, err);
+                return
+            }
+        }
+        let header_hash = header.hash();
+        match self.client.import_trusted_header(header) {
+            Ok(_)
+            | Err(BlockImportError::Import(ImportError::AlreadyInChain))
+            | Err(BlockImportError::Import(ImportError::AlreadyQueued)) => {}
+            Err(err) => {
+                cwarn!(SYNC, "Cannot import header({}): {:?}", header_hash, err);
+                return
+            }
+        }
+        self.move_state();
+    }
+
+    fn import_snapshot_body(&mut self, evidences: Vec<Evidence>, transactions: Vec<Transaction>) {
+        let header = match &self.state {
+            State::SnapshotBody {
+                header,
+            } => header.clone(),
+            _ => return,
+        };
+        let new_evidences_root = skewed_merkle_root(BLAKE_NULL_RLP, evidences.iter().map(Encodable::rlp_bytes));
+        let new_transactions_root = skewed_merkle_root(BLAKE_NULL_RLP, transactions.iter().map(Encodable::rlp_bytes));
+        if header.transactions_root() != new_transactions_root || header.evidences_root() != new_evidences_root {
+            cdebug!(SYNC, "The body doesn't match the snapshot header {}", header.hash());
+            return
+        }
+        let block = Block {
+            header: header.decode(),
+            evidences,
+            transactions,
+        };
+        match self.client.import_trusted_block(&block) {
+            Ok(_) | Err(BlockImportError::Import(ImportError::AlreadyInChain)) => {
+                self.move_state();
+            }
+            Err(BlockImportError::Import(ImportError::AlreadyQueued)) => {}
+            // FIXME: handle import errors
+            Err(err) => {
+                cwarn!(SYNC, "Cannot import block({}): {:?}", header.hash(), err);
+            }
+        }
+    }
+
+    /// Restores the chunk into the state DB.
+    fn feed_chunk(&mut self, root: H256, compressed: &[u8]) -> Result<(), String> {
+        let restore = match &mut self.state {
+            State::SnapshotTopChunk {
+                restore,
+                ..
+            }
+            | State::SnapShotModuleChunk {
+                restore,
+                ..
+            } => restore,
+            _ => return Err("No snapshot is being restored".to_string()),
+        };
+        let raw_chunk =
+            ChunkDecompressor::from_slice(compressed).decompress().map_err(|e| format!("Decode failed: {}", e))?;
+        let recovered = raw_chunk.recover(root).map_err(|e| e.to_string())?;
+
+        let batch = {
+            let mut state_db = self.client.state_db().write();
+            let hash_db = state_db.as_hashdb_mut();
+            restore.feed(hash_db, recovered);
+
+            let mut batch = DBTransaction::new();
+            state_db
+                .journal_under(&mut batch, 0, H256::zero())
+                .map_err(|e| format!("Failed to write state chunk to database: {}", e))?;
+            batch
+        };
+        self.client.db().write_buffered(batch);
+        match self.client.db().flush() {
+            Ok(_) => cdebug!(SYNC, "Wrote state chunk to database: {}", root),
+            Err(e) => cwarn!(SYNC, "Failed to flush database: {}", e),
+        }
+        Ok(())
+    }
+
+    /// Reads the chunk downloaded before the node restarted or imported from an archive.
+    fn local_chunk(&self, block: &BlockHash, root: &H256) -> Option<Vec<u8>> {
+        fs::read(download_dir(&self.snapshot_dir, block).join(format!("{:x}", root)))
+            .or_else(|_| fs::read(snapshot_path(&self.snapshot_dir, block, root)))
+            .ok()
+    }
+
+    /// Feeds the chunks available locally, and requests the missing ones to peers.
+    fn request_next_chunk(&mut self) {
+        loop {
+            let (block, next) = match &mut self.state {
+                State::SnapshotTopChunk {
+                    block,
+                    restore,
+                }
+                | State::SnapShotModuleChunk {
+                    block,
+                    restore,
+                    ..
+                } => (*block, restore.next_to_feed()),
+                _ => {
+                    // The state is restored.
+                    self.discard_chunk_downloader();
+                    return
                 }
             };
-            let recovered = match raw_chunk.recover(*r) {
-                Ok(chunk) => chunk,
-                Err(e) => {
-                    cwarn!(SYNC, "Invalid chunk response from peer {}: {}", from, e);
+            let root = match next {
+                Some(root) => root,
+                None => {
+                    self.move_state();
                     continue
                 }
             };
-
-            let batch = {
-                let mut state_db = self.client.state_db().write();
-                let hash_db = state_db.as_hashdb_mut();
-                restore.feed(hash_db, recovered);
-
-                let mut batch = DBTransaction::new();
-                match state_db.journal_under(&mut batch, 0, H256::zero()) {
-                    Ok(_) => batch,
-                    Err(e) => {
-                        cwarn!(SYNC, "Failed to write state chunk to database: {}", e);
-                        continue
+            if let Some(compressed) = self.local_chunk(&block, &root) {
+                match self.feed_chunk(root, &compressed) {
+                    Ok(()) => continue,
+                    Err(err) => {
+                        cwarn!(SYNC, "Invalid chunk {} in the local snapshot: {}", root, err);
+                        let _ = fs::remove_file(download_dir(&self.snapshot_dir, &block).join(format!("{:x}", root)));
+                        if self
+                            .chunk_downloader
+                            .as_ref()
+                            .map(|downloader| downloader.is_downloaded(&root))
+                            .unwrap_or(false)
+                        {
+                            cwarn!(SYNC, "The snapshot manifest of block {} is invalid", block);
+                            self.manifest_rejected = true;
+                            self.discard_chunk_downloader();
+                        }
                     }
                 }
-            };
-            self.client.db().write_buffered(batch);
-            match self.client.db().flush() {
-                Ok(_) => cdebug!(SYNC, "Wrote state chunk to database: {}", r),
-                Err(e) => cwarn!(SYNC, "Failed to flush database: {}", e),
             }
+            self.send_chunk_requests(block, root);
+            return
         }
+    }
 
-        if let Some(root) = restore.next_to_feed() {
-            self.send_chunk_request(&block, &root);
-        } else {
-            self.move_state();
+    fn discard_chunk_downloader(&mut self) {
+        if let Some(downloader) = self.chunk_downloader.take() {
+            if let Err(err) = fs::remove_dir_all(downloader.dir()) {
+                cwarn!(SYNC, "Cannot remove the snapshot download directory: {}", err);
+            }
+        }
+    }
+}
+
+/// Trusts the genesis block and the validator set at it. It's only available before any block is imported.
+fn genesis_anchor(client: &Client) -> Option<LightClient> {
+    let genesis = client.block_header(&BlockId::Number(0))?.decode();
+    match client.validator_set(None) {
+        Ok(Some(validator_set)) => LightClient::new(genesis, validator_set).ok(),
+        Ok(None) => None,
+        Err(err) => {
+            cwarn!(SYNC, "Cannot read the genesis validator set: {}", err);
+            None
+        }
+    }
+}
+
+fn is_parent(parent: &Header, child: &Header) -> bool {
+    *child.parent_hash() == parent.hash() && child.number() == parent.number() + 1
+}
+
+/// Makes the light block of the snapshot block from the headers of its parent, itself and its child.
+fn snapshot_light_block(headers: Vec<SyncHeader>) -> Result<LightBlock, String> {
+    if headers.len() != 3 {
+        return Err(format!("{} headers are given", headers.len()))
+    }
+    if let Some(pair) = headers.windows(2).find(|pair| !is_parent(&pair[0], &pair[1])) {
+        return Err(format!("Block #{} is not the parent of block #{}", pair[0].number(), pair[1].number()))
+    }
+    // The previous validator set of a block is the validator set that signs it.
+    // The validator sets are given by the peer, so LightClient checks them with the hashes in the headers.
+    let validator_set = headers[1].prev_validator_set().cloned().ok_or("There is no validator set of the snapshot")?;
+    let next_validator_set =
+        headers[2].prev_validator_set().cloned().ok_or("There is no next validator set of the snapshot")?;
+    let mut headers = headers.into_iter().map(Header::from);
+    Ok(LightBlock {
+        parent: headers.next().expect("There are three headers"),
+        header: headers.next().expect("There are three headers"),
+        commit: headers.next().expect("There are three headers"),
+        validator_set,
+        next_validator_set,
+    })
+}
+
+/// Serves the received light blocks, and remembers the one which is not received yet.
+struct ReceivedLightBlocks<'a> {
+    blocks: &'a HashMap<BlockNumber, LightBlock>,
+    missing: Option<BlockNumber>,
+}
+
+impl LightBlockSource for ReceivedLightBlocks<'_> {
+    fn light_block(&mut self, number: BlockNumber) -> Result<LightBlock, String> {
+        match self.blocks.get(&number) {
+            Some(block) => Ok(block.clone()),
+            None => {
+                self.missing = Some(number);
+                Err(format!("Light block #{} is not received yet", number))
+            }
         }
     }
 }
 
+/// Verifies the snapshot block from the anchor with `LightClient::sync_to`, which verifies the blocks in the middle
+/// first when the validators changed too much. Returns the number of the light block to receive next, or `None` if
+/// the snapshot block is verified.
+fn verify_snapshot_block(
+    anchor: &LightClient,
+    snapshot: &AdvertisedSnapshot,
+    received: &HashMap<BlockNumber, LightBlock>,
+) -> Result<Option<BlockNumber>, String> {
+    // The anchor is cloned since it should stay for the next candidates if this one is rejected.
+    let mut client = anchor.clone();
+    let mut source = ReceivedLightBlocks {
+        blocks: received,
+        missing: None,
+    };
+    match client.sync_to(snapshot.block_number, &mut source) {
+        Ok(()) if client.trusted_header().hash() == snapshot.block_hash => Ok(None),
+        Ok(()) => Err(format!("Block #{} is not the snapshot block", snapshot.block_number)),
+        Err(clight::Error::Source(_)) if source.missing.is_some() => Ok(source.missing),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
 pub struct BlockSyncSender(EventSender<Event>);
 
 impl From<EventSender<Event>> for BlockSyncSender {
@@ -1287,3 +1916,142 @@ impl ChainNotify for BlockSyncSender {
             .unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ccore::{BitSet, SealFormat, Step, VoteOn, VoteStep};
+    use ckey::{sign, Ed25519Private as Private};
+    use ctypes::{CompactValidatorEntry, CompactValidatorSet};
+
+    fn snapshot_headers() -> Vec<SyncHeader> {
+        let mut parent = Header::default();
+        parent.set_number(9);
+        let snapshot = parent.generate_child();
+        let child = snapshot.generate_child();
+        vec![parent, snapshot, child]
+            .into_iter()
+            .map(|header| SyncHeader::new(header, Some(CompactValidatorSet::new(Vec::new()))))
+            .collect()
+    }
+
+    #[test]
+    fn snapshot_light_block_from_linked_headers() {
+        let headers = snapshot_headers();
+        let snapshot_hash = headers[1].hash();
+        let block = snapshot_light_block(headers).unwrap();
+        assert_eq!(snapshot_hash, block.header.hash());
+        assert_eq!(snapshot_hash, *block.commit.parent_hash());
+    }
+
+    #[test]
+    fn reject_child_of_another_block() {
+        let mut headers = snapshot_headers();
+        let mut unrelated = headers[1].generate_child();
+        unrelated.set_parent_hash(H256::random().into());
+        headers[2] = SyncHeader::new(unrelated, Some(CompactValidatorSet::new(Vec::new())));
+        assert!(snapshot_light_block(headers).is_err());
+    }
+
+    #[test]
+    fn reject_child_with_wrong_number() {
+        let mut headers = snapshot_headers();
+        let mut skipped = headers[1].generate_child();
+        skipped.set_number(headers[1].number() + 2);
+        headers[2] = SyncHeader::new(skipped, Some(CompactValidatorSet::new(Vec::new())));
+        assert!(snapshot_light_block(headers).is_err());
+    }
+
+    fn validator_set(validators: &[Private]) -> CompactValidatorSet {
+        CompactValidatorSet::new(
+            validators
+                .iter()
+                .map(|private| CompactValidatorEntry {
+                    public_key: private.public_key(),
+                    delegation: 10,
+                })
+                .collect(),
+        )
+    }
+
+    /// Makes the light blocks of a chain whose validators are all replaced at every block, and trusts its genesis.
+    fn chain_replacing_validators(length: BlockNumber) -> (LightClient, HashMap<BlockNumber, LightBlock>) {
+        // validators[N] signs the block N.
+        let validators: Vec<Vec<Private>> =
+            (0..length + 2).map(|_| (0..3).map(|_| Private::random()).collect()).collect();
+        let mut headers = vec![Header::default()];
+        for number in 1..length + 2 {
+            let parent = &headers[number as usize - 1];
+            let vote_on = VoteOn {
+                step: VoteStep::new(parent.number(), 0, Step::Precommit),
+                block_hash: Some(parent.hash()),
+            };
+            let precommits =
+                validators[number as usize - 1].iter().map(|private| sign(vote_on.hash().as_ref(), private)).collect();
+            let mut header = parent.generate_child();
+            header.set_seal(
+                ccore::Seal::Tendermint {
+                    prev_view: 0,
+                    cur_view: 0,
+                    precommits,
+                    precommit_bitset: BitSet::new_with_indices(&[0, 1, 2]),
+                    format: SealFormat::Individual,
+                }
+                .seal_fields()
+                .unwrap(),
+            );
+            header.set_next_validator_set_hash(validator_set(&validators[number as usize + 1]).hash());
+            headers.push(header);
+        }
+        let sync_header = |number: BlockNumber| {
+            SyncHeader::new(headers[number as usize].clone(), Some(validator_set(&validators[number as usize])))
+        };
+        let blocks = (1..=length)
+            .map(|number| {
+                let block =
+                    snapshot_light_block(vec![sync_header(number - 1), sync_header(number), sync_header(number + 1)]);
+                (number, block.unwrap())
+            })
+            .collect();
+        (LightClient::new(headers[0].clone(), validator_set(&validators[1])).unwrap(), blocks)
+    }
+
+    #[test]
+    fn verify_snapshot_block_through_replaced_validators() {
+        let (anchor, blocks) = chain_replacing_validators(8);
+        let snapshot = AdvertisedSnapshot {
+            block_hash: blocks[&8].header.hash(),
+            block_number: 8,
+        };
+        assert!(matches!(anchor.clone().verify(blocks[&8].clone()), Err(clight::Error::InsufficientOverlap { .. })));
+
+        let mut received = HashMap::new();
+        while let Some(number) = verify_snapshot_block(&anchor, &snapshot, &received).unwrap() {
+            assert!(received.insert(number, blocks[&number].clone()).is_none());
+        }
+        assert_eq!(8, received.len());
+    }
+
+    #[test]
+    fn reject_snapshot_block_with_forged_validator_set() {
+        let (anchor, mut blocks) = chain_replacing_validators(8);
+        let snapshot = AdvertisedSnapshot {
+            block_hash: blocks[&8].header.hash(),
+            block_number: 8,
+        };
+        // The forged validator set has the signers of block 8 and a validator trusted by the anchor.
+        let mut forged: Vec<_> = blocks[&8].validator_set.iter().cloned().collect();
+        forged.push(anchor.next_validator_set().iter().next().cloned().unwrap());
+        blocks.get_mut(&8).unwrap().validator_set = CompactValidatorSet::new(forged);
+
+        let mut received = HashMap::new();
+        let err = loop {
+            match verify_snapshot_block(&anchor, &snapshot, &received) {
+                Ok(Some(number)) => received.insert(number, blocks[&number].clone()),
+                Ok(None) => panic!("The forged validator set is accepted"),
+                Err(err) => break err,
+            };
+        };
+        assert!(err.contains("next validator set hash"), "{}", err);
+    }
+}
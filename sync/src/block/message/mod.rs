@@ -14,8 +14,8 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use ctypes::BlockHash;
-use primitives::U256;
+use ctypes::{BlockHash, BlockNumber};
+use primitives::{H256, U256};
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 
 mod request;
@@ -34,6 +34,8 @@ pub enum MessageID {
     Bodies = 0x05,
     GetStateChunk = 0x0a,
     StateChunk = 0x0b,
+    GetSnapshotManifest = 0x0c,
+    SnapshotManifest = 0x0d,
 }
 
 impl Encodable for MessageID {
@@ -53,17 +55,51 @@ impl Decodable for MessageID {
             0x05 => Ok(MessageID::Bodies),
             0x0a => Ok(MessageID::GetStateChunk),
             0x0b => Ok(MessageID::StateChunk),
+            0x0c => Ok(MessageID::GetSnapshotManifest),
+            0x0d => Ok(MessageID::SnapshotManifest),
             _ => Err(DecoderError::Custom("Unexpected MessageID Value")),
         }
     }
 }
 
+/// A snapshot which the sender can serve.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdvertisedSnapshot {
+    pub block_hash: BlockHash,
+    pub block_number: BlockNumber,
+}
+
+impl Encodable for AdvertisedSnapshot {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2).append(&self.block_hash).append(&self.block_number);
+    }
+}
+
+impl Decodable for AdvertisedSnapshot {
+    fn decode(rlp: &Rlp<'_>) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 2 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 2,
+            })
+        }
+        Ok(Self {
+            block_hash: rlp.val_at(0)?,
+            block_number: rlp.val_at(1)?,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub enum Message {
     Status {
         seq: U256,
         best_hash: BlockHash,
         genesis_hash: BlockHash,
+        snapshots: Vec<AdvertisedSnapshot>,
+        /// The hash of the app descriptor the chain is created with.
+        app_desc_hash: Option<H256>,
     },
     Request(u64, RequestMessage),
     Response(u64, ResponseMessage),
@@ -76,14 +112,28 @@ impl Encodable for Message {
                 seq,
                 best_hash,
                 genesis_hash,
+                snapshots,
+                app_desc_hash,
             } => {
                 s.begin_list(2);
                 s.append(&MessageID::Status);
 
-                s.begin_list(3);
+                // The optional items at the end are omitted when they are empty, to be compatible with the old peers.
+                let item_count = if app_desc_hash.is_some() {
+                    5
+                } else {
+                    3 + !snapshots.is_empty() as usize
+                };
+                s.begin_list(item_count);
                 s.append(seq);
                 s.append(best_hash);
                 s.append(genesis_hash);
+                if item_count >= 4 {
+                    s.append_list(snapshots);
+                }
+                if let Some(app_desc_hash) = app_desc_hash {
+                    s.append(app_desc_hash);
+                }
             }
             Message::Request(request_id, request) => {
                 s.begin_list(3);
@@ -116,9 +166,9 @@ impl Decodable for Message {
                 let message = rlp.at(1)?;
 
                 let message_item_count = message.item_count()?;
-                if message_item_count != 3 {
+                if message_item_count < 3 || message_item_count > 5 {
                     return Err(DecoderError::RlpIncorrectListLen {
-                        expected: 3,
+                        expected: 5,
                         got: message_item_count,
                     })
                 }
@@ -127,6 +177,16 @@ impl Decodable for Message {
                     seq: message.val_at(0)?,
                     best_hash: message.val_at(1)?,
                     genesis_hash: message.val_at(2)?,
+                    snapshots: if message_item_count >= 4 {
+                        message.list_at(3)?
+                    } else {
+                        Vec::new()
+                    },
+                    app_desc_hash: if message_item_count == 5 {
+                        Some(message.val_at(4)?)
+                    } else {
+                        None
+                    },
                 })
             }
             _ => {
@@ -170,6 +230,65 @@ mod tests {
             seq: U256::zero(),
             best_hash: H256::default().into(),
             genesis_hash: H256::default().into(),
+            snapshots: Vec::new(),
+            app_desc_hash: None,
+        };
+        let encoded = rlp::encode(&status_message);
+        let decoded: Message = rlp::decode(&encoded).unwrap();
+
+        assert_eq_by_debug(&status_message, &decoded)
+    }
+
+    #[test]
+    fn status_message_with_snapshots_rlp() {
+        let status_message = Message::Status {
+            seq: U256::from(3),
+            best_hash: H256::random().into(),
+            genesis_hash: H256::random().into(),
+            snapshots: vec![
+                AdvertisedSnapshot {
+                    block_hash: H256::random().into(),
+                    block_number: 200,
+                },
+                AdvertisedSnapshot {
+                    block_hash: H256::random().into(),
+                    block_number: 100,
+                },
+            ],
+            app_desc_hash: None,
+        };
+        let encoded = rlp::encode(&status_message);
+        let decoded: Message = rlp::decode(&encoded).unwrap();
+
+        assert_eq_by_debug(&status_message, &decoded)
+    }
+
+    #[test]
+    fn status_message_without_snapshots_is_compatible_with_old_format() {
+        let best_hash: BlockHash = H256::random().into();
+        let genesis_hash: BlockHash = H256::random().into();
+        let mut s = RlpStream::new_list(2);
+        s.append(&MessageID::Status);
+        s.begin_list(3).append(&U256::from(1)).append(&best_hash).append(&genesis_hash);
+
+        let status_message = Message::Status {
+            seq: U256::from(1),
+            best_hash,
+            genesis_hash,
+            snapshots: Vec::new(),
+            app_desc_hash: None,
+        };
+        assert_eq!(s.out(), status_message.rlp_bytes());
+    }
+
+    #[test]
+    fn status_message_with_app_desc_hash_rlp() {
+        let status_message = Message::Status {
+            seq: U256::from(3),
+            best_hash: H256::random().into(),
+            genesis_hash: H256::random().into(),
+            snapshots: Vec::new(),
+            app_desc_hash: Some(H256::random()),
         };
         let encoded = rlp::encode(&status_message);
         let decoded: Message = rlp::decode(&encoded).unwrap();
@@ -27,6 +27,7 @@ pub enum RequestMessage {
     },
     Bodies(Vec<BlockHash>),
     StateChunk(BlockHash, Vec<H256>),
+    SnapshotManifest(BlockHash),
 }
 
 impl Encodable for RequestMessage {
@@ -48,6 +49,10 @@ impl Encodable for RequestMessage {
                 s.append(block_hash);
                 s.append_list(merkle_roots);
             }
+            RequestMessage::SnapshotManifest(block_hash) => {
+                s.begin_list(1);
+                s.append(block_hash);
+            }
         };
     }
 }
@@ -62,6 +67,7 @@ impl RequestMessage {
             RequestMessage::StateChunk {
                 ..
             } => MessageID::GetStateChunk,
+            RequestMessage::SnapshotManifest(..) => MessageID::GetSnapshotManifest,
         }
     }
 
@@ -91,6 +97,16 @@ impl RequestMessage {
                 }
                 RequestMessage::StateChunk(rlp.val_at(0)?, rlp.list_at(1)?)
             }
+            MessageID::GetSnapshotManifest => {
+                let item_count = rlp.item_count()?;
+                if item_count != 1 {
+                    return Err(DecoderError::RlpIncorrectListLen {
+                        got: item_count,
+                        expected: 1,
+                    })
+                }
+                RequestMessage::SnapshotManifest(rlp.val_at(0)?)
+            }
             _ => return Err(DecoderError::Custom("Unknown message id detected")),
         };
 
@@ -130,4 +146,10 @@ mod tests {
         let message = RequestMessage::StateChunk(H256::default().into(), vec![H256::default()]);
         assert_eq!(message, decode_bytes(message.message_id(), message.rlp_bytes().as_ref()));
     }
+
+    #[test]
+    fn request_snapshot_manifest_message_rlp() {
+        let message = RequestMessage::SnapshotManifest(H256::default().into());
+        assert_eq!(message, decode_bytes(message.message_id(), message.rlp_bytes().as_ref()));
+    }
 }
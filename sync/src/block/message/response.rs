@@ -15,6 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::MessageID;
+use crate::snapshot::Manifest;
 use ccore::Evidence;
 use coordinator::Transaction;
 use ctypes::SyncHeader;
@@ -25,6 +26,8 @@ pub enum ResponseMessage {
     Headers(Vec<SyncHeader>),
     Bodies(Vec<(Vec<Evidence>, Vec<Transaction>)>),
     StateChunk(Vec<Vec<u8>>),
+    /// None if the peer doesn't have the snapshot.
+    SnapshotManifest(Option<Manifest>),
 }
 
 impl Encodable for ResponseMessage {
@@ -57,6 +60,13 @@ impl Encodable for ResponseMessage {
             ResponseMessage::StateChunk(chunks) => {
                 s.append_list::<Vec<u8>, Vec<u8>>(chunks);
             }
+            ResponseMessage::SnapshotManifest(Some(manifest)) => {
+                s.begin_list(1);
+                s.append(manifest);
+            }
+            ResponseMessage::SnapshotManifest(None) => {
+                s.begin_list(0);
+            }
         };
     }
 }
@@ -71,6 +81,7 @@ impl ResponseMessage {
             ResponseMessage::StateChunk {
                 ..
             } => MessageID::StateChunk,
+            ResponseMessage::SnapshotManifest(..) => MessageID::SnapshotManifest,
         }
     }
 
@@ -107,6 +118,16 @@ impl ResponseMessage {
                 ResponseMessage::Bodies(bodies)
             }
             MessageID::StateChunk => ResponseMessage::StateChunk(rlp.as_list()?),
+            MessageID::SnapshotManifest => {
+                let item_count = rlp.item_count()?;
+                if item_count > 1 {
+                    return Err(DecoderError::RlpIncorrectListLen {
+                        got: item_count,
+                        expected: 1,
+                    })
+                }
+                ResponseMessage::SnapshotManifest(rlp.as_list()?.pop())
+            }
             _ => return Err(DecoderError::Custom("Unknown message id detected")),
         };
 
@@ -117,8 +138,10 @@ impl ResponseMessage {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::snapshot::ChunkEntry;
     use coordinator::Transaction;
     use ctypes::Header;
+    use primitives::H256;
     use rlp::{Encodable, Rlp};
 
     pub fn decode_bytes(id: MessageID, bytes: &[u8]) -> ResponseMessage {
@@ -158,4 +181,22 @@ mod tests {
         let message = ResponseMessage::StateChunk(vec![]);
         assert_eq_by_debug(&message, &decode_bytes(message.message_id(), message.rlp_bytes().as_ref()));
     }
+
+    #[test]
+    fn snapshot_manifest_message_rlp() {
+        let message = ResponseMessage::SnapshotManifest(None);
+        assert_eq_by_debug(&message, &decode_bytes(message.message_id(), message.rlp_bytes().as_ref()));
+
+        let message = ResponseMessage::SnapshotManifest(Some(Manifest {
+            block_hash: H256::random().into(),
+            block_number: 10,
+            state_root: H256::random(),
+            chunks: vec![ChunkEntry {
+                root: H256::random(),
+                size: 5,
+                hash: H256::random(),
+            }],
+        }));
+        assert_eq_by_debug(&message, &decode_bytes(message.message_id(), message.rlp_bytes().as_ref()));
+    }
 }
@@ -22,6 +22,7 @@ extern crate codechain_network as cnetwork;
 extern crate codechain_state as cstate;
 extern crate codechain_timer as ctimer;
 extern crate codechain_types as ctypes;
+extern crate foundry_light_client as clight;
 #[macro_use]
 extern crate log;
 
@@ -0,0 +1,223 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A snapshot archive is a single file which has everything needed to start a node from a snapshot.
+//!
+//! ```text
+//! magic (8 bytes) | version (1 byte) | length of the index (8 bytes, big endian)
+//! | index: rlp([manifest, snapshot block]) | blake256(index) (32 bytes)
+//! | compressed chunks in the order of the manifest
+//! ```
+//!
+//! The checksum covers the index, and the index has the hash of every chunk.
+
+use super::{snapshot_dir, Manifest, SnapshotBlock, MANIFEST_FILE_NAME};
+use codechain_crypto::blake256;
+use ctypes::BlockHash;
+use primitives::H256;
+use rlp::{Rlp, RlpStream};
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"FNDRSNAP";
+const ARCHIVE_VERSION: u8 = 1;
+const MAX_INDEX_SIZE: u64 = 1 << 30;
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Writes the snapshot of `block_hash` in `root_dir` into `output`.
+pub fn export_archive(root_dir: &str, block_hash: BlockHash, output: &Path) -> io::Result<Manifest> {
+    let dir = snapshot_dir(root_dir, &block_hash);
+    let manifest = Manifest::read(&dir)?;
+    manifest.verify_chunks(&dir)?;
+    let block = SnapshotBlock::read(&dir)?;
+
+    let index = {
+        let mut s = RlpStream::new_list(2);
+        s.append(&manifest);
+        s.append(&block);
+        s.out()
+    };
+
+    let mut writer = BufWriter::new(fs::File::create(output)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[ARCHIVE_VERSION])?;
+    writer.write_all(&(index.len() as u64).to_be_bytes())?;
+    writer.write_all(&index)?;
+    writer.write_all(blake256(&index).as_ref())?;
+    for chunk in &manifest.chunks {
+        writer.write_all(&fs::read(dir.join(format!("{:x}", chunk.root)))?)?;
+    }
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    Ok(manifest)
+}
+
+/// Extracts the archive `input` into `root_dir`.
+///
+/// The manifest is written at the end, so the extracted snapshot is not used until every chunk is written.
+pub fn import_archive(root_dir: &str, input: &Path) -> io::Result<Manifest> {
+    let mut reader = BufReader::new(fs::File::open(input)?);
+
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("Not a snapshot archive"))
+    }
+    let mut version = [0u8; 1];
+    reader.read_exact(&mut version)?;
+    if version[0] != ARCHIVE_VERSION {
+        return Err(invalid_data(format!("Unsupported snapshot archive version {}", version[0])))
+    }
+    let mut index_len = [0u8; 8];
+    reader.read_exact(&mut index_len)?;
+    let index_len = u64::from_be_bytes(index_len);
+    if index_len > MAX_INDEX_SIZE {
+        return Err(invalid_data("Too large snapshot archive index"))
+    }
+    let mut index = Vec::new();
+    (&mut reader).take(index_len).read_to_end(&mut index)?;
+    if index.len() as u64 != index_len {
+        return Err(io::ErrorKind::UnexpectedEof.into())
+    }
+    let mut checksum = H256::zero();
+    reader.read_exact(checksum.as_mut())?;
+    if blake256(&index) != checksum {
+        return Err(invalid_data("The checksum of the snapshot archive doesn't match"))
+    }
+
+    let rlp = Rlp::new(&index);
+    let decode_error = |err| invalid_data(format!("Invalid snapshot archive index: {:?}", err));
+    let manifest: Manifest = rlp.val_at(0).map_err(decode_error)?;
+    let block: SnapshotBlock = rlp.val_at(1).map_err(decode_error)?;
+    if block.header.hash() != manifest.block_hash
+        || block.header.number() != manifest.block_number
+        || *block.header.state_root() != manifest.state_root
+        || *block.header.parent_hash() != block.parent.hash()
+    {
+        return Err(invalid_data("The block in the snapshot archive doesn't match the manifest"))
+    }
+
+    let dir = snapshot_dir(root_dir, &manifest.block_hash);
+    if dir.join(MANIFEST_FILE_NAME).exists() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, "The snapshot already exists"))
+    }
+    fs::create_dir_all(&dir)?;
+    for chunk in &manifest.chunks {
+        let mut compressed = Vec::new();
+        (&mut reader).take(chunk.size).read_to_end(&mut compressed)?;
+        if compressed.len() as u64 != chunk.size {
+            return Err(io::ErrorKind::UnexpectedEof.into())
+        }
+        if !chunk.verify(&compressed) {
+            return Err(invalid_data(format!("Chunk {:x} in the snapshot archive is corrupted", chunk.root)))
+        }
+        fs::write(dir.join(format!("{:x}", chunk.root)), &compressed)?;
+    }
+    block.write(&dir)?;
+    manifest.write(&dir)?;
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::ChunkEntry;
+    use super::*;
+    use ctypes::Header;
+    use tempfile::TempDir;
+
+    fn create_snapshot(root_dir: &str) -> Manifest {
+        let parent = Header::default();
+        let mut header = parent.generate_child();
+        header.set_state_root(H256::random());
+        let block = SnapshotBlock {
+            parent,
+            header,
+            evidences: Vec::new(),
+            transactions: Vec::new(),
+        };
+        let dir = snapshot_dir(root_dir, &block.header.hash());
+        fs::create_dir_all(&dir).unwrap();
+        let chunks = vec![b"first chunk".to_vec(), b"second chunk".to_vec()]
+            .into_iter()
+            .map(|contents| {
+                let root = H256::random();
+                let mut compressed = Vec::new();
+                snap::Writer::new(&mut compressed).write_all(&contents).unwrap();
+                fs::write(dir.join(format!("{:x}", root)), &compressed).unwrap();
+                ChunkEntry::new(root, &compressed).unwrap()
+            })
+            .collect();
+        let manifest = Manifest {
+            block_hash: block.header.hash(),
+            block_number: block.header.number(),
+            state_root: *block.header.state_root(),
+            chunks,
+        };
+        block.write(&dir).unwrap();
+        manifest.write(&dir).unwrap();
+        manifest
+    }
+
+    #[test]
+    fn export_and_import() {
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("source");
+        let destination = dir.path().join("destination");
+        let archive = dir.path().join("snapshot.archive");
+        let manifest = create_snapshot(source.to_str().unwrap());
+
+        assert_eq!(manifest, export_archive(source.to_str().unwrap(), manifest.block_hash, &archive).unwrap());
+        assert_eq!(manifest, import_archive(destination.to_str().unwrap(), &archive).unwrap());
+
+        let imported = snapshot_dir(destination.to_str().unwrap(), &manifest.block_hash);
+        assert_eq!(manifest, Manifest::read(&imported).unwrap());
+        manifest.verify_chunks(&imported).unwrap();
+        assert_eq!(manifest.block_hash, SnapshotBlock::read(&imported).unwrap().header.hash());
+    }
+
+    #[test]
+    fn reject_corrupted_archive() {
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("source");
+        let destination = dir.path().join("destination");
+        let archive = dir.path().join("snapshot.archive");
+        let manifest = create_snapshot(source.to_str().unwrap());
+        export_archive(source.to_str().unwrap(), manifest.block_hash, &archive).unwrap();
+
+        let mut bytes = fs::read(&archive).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        fs::write(&archive, &bytes).unwrap();
+
+        let err = import_archive(destination.to_str().unwrap(), &archive).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        let imported = snapshot_dir(destination.to_str().unwrap(), &manifest.block_hash);
+        assert!(!imported.join(MANIFEST_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn reject_unknown_file() {
+        let dir = TempDir::new().unwrap();
+        let archive = dir.path().join("snapshot.archive");
+        fs::write(&archive, b"This is not a snapshot archive").unwrap();
+        let err = import_archive(dir.path().join("destination").to_str().unwrap(), &archive).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+    }
+}
@@ -0,0 +1,83 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::Evidence;
+use coordinator::Transaction;
+use ctypes::Header;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+pub const BLOCK_FILE_NAME: &str = "block";
+const BLOCK_TEMP_FILE_NAME: &str = "block.tmp";
+
+/// The snapshot block and its parent header.
+///
+/// A node starting from a snapshot imports them before restoring the state.
+#[derive(Debug)]
+pub struct SnapshotBlock {
+    pub parent: Header,
+    /// The header with its seal.
+    pub header: Header,
+    pub evidences: Vec<Evidence>,
+    pub transactions: Vec<Transaction>,
+}
+
+impl SnapshotBlock {
+    pub fn read(snapshot_dir: &Path) -> io::Result<Self> {
+        let bytes = fs::read(snapshot_dir.join(BLOCK_FILE_NAME))?;
+        rlp::decode(&bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err)))
+    }
+
+    pub fn write(&self, snapshot_dir: &Path) -> io::Result<()> {
+        let temp_path = snapshot_dir.join(BLOCK_TEMP_FILE_NAME);
+        {
+            let mut file = fs::File::create(&temp_path)?;
+            file.write_all(&self.rlp_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(&temp_path, snapshot_dir.join(BLOCK_FILE_NAME))
+    }
+}
+
+impl Encodable for SnapshotBlock {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(4)
+            .append(&self.parent)
+            .append(&self.header)
+            .append_list(&self.evidences)
+            .append_list(&self.transactions);
+    }
+}
+
+impl Decodable for SnapshotBlock {
+    fn decode(rlp: &Rlp<'_>) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 4 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 4,
+            })
+        }
+        Ok(Self {
+            parent: rlp.val_at(0)?,
+            header: rlp.val_at(1)?,
+            evidences: rlp.list_at(2)?,
+            transactions: rlp.list_at(3)?,
+        })
+    }
+}
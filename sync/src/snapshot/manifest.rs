@@ -0,0 +1,251 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use codechain_crypto::blake256;
+use ctypes::{BlockHash, BlockNumber};
+use primitives::H256;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+pub const MANIFEST_FILE_NAME: &str = "manifest";
+const MANIFEST_TEMP_FILE_NAME: &str = "manifest.tmp";
+const MANIFEST_VERSION: u8 = 1;
+
+/// A compressed chunk file in a snapshot directory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChunkEntry {
+    /// The merkle root of the chunk, which is also the name of the chunk file.
+    pub root: H256,
+    /// The size of the compressed chunk file in bytes.
+    pub size: u64,
+    /// The hash of the decompressed chunk.
+    pub hash: H256,
+}
+
+impl ChunkEntry {
+    pub fn new(root: H256, compressed: &[u8]) -> io::Result<Self> {
+        Ok(Self {
+            root,
+            size: compressed.len() as u64,
+            hash: chunk_hash(compressed)?,
+        })
+    }
+
+    /// Checks that the compressed chunk has the contents in the manifest.
+    ///
+    /// The size is not checked, since the same chunk can be compressed into different bytes.
+    pub fn verify(&self, compressed: &[u8]) -> bool {
+        chunk_hash(compressed).map(|hash| hash == self.hash).unwrap_or(false)
+    }
+}
+
+/// Hashes the decompressed contents of a chunk file, which is compressed in the snappy frame format.
+fn chunk_hash(compressed: &[u8]) -> io::Result<H256> {
+    let mut contents = Vec::new();
+    snap::Reader::new(compressed).read_to_end(&mut contents)?;
+    Ok(blake256(&contents))
+}
+
+/// Describes a complete snapshot.
+///
+/// The manifest is written after all chunk files are written, so a snapshot directory without a manifest is
+/// an incomplete one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Manifest {
+    pub block_hash: BlockHash,
+    pub block_number: BlockNumber,
+    pub state_root: H256,
+    pub chunks: Vec<ChunkEntry>,
+}
+
+impl Manifest {
+    pub fn total_size(&self) -> u64 {
+        self.chunks.iter().map(|chunk| chunk.size).sum()
+    }
+
+    pub fn read(snapshot_dir: &Path) -> io::Result<Self> {
+        let bytes = fs::read(snapshot_dir.join(MANIFEST_FILE_NAME))?;
+        rlp::decode(&bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err)))
+    }
+
+    /// Writes the manifest into `snapshot_dir` atomically.
+    ///
+    /// The manifest is written to a temporary file first and renamed, so readers see either no manifest or
+    /// a complete one.
+    pub fn write(&self, snapshot_dir: &Path) -> io::Result<()> {
+        let temp_path = snapshot_dir.join(MANIFEST_TEMP_FILE_NAME);
+        {
+            let mut file = fs::File::create(&temp_path)?;
+            file.write_all(&self.rlp_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(&temp_path, snapshot_dir.join(MANIFEST_FILE_NAME))?;
+        // Persist the rename itself.
+        fs::File::open(snapshot_dir)?.sync_all()
+    }
+
+    /// Checks that every chunk file in `snapshot_dir` exists and is not modified.
+    pub fn verify_chunks(&self, snapshot_dir: &Path) -> io::Result<()> {
+        for chunk in &self.chunks {
+            let bytes = fs::read(snapshot_dir.join(format!("{:x}", chunk.root)))?;
+            if !chunk.verify(&bytes) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Chunk {:x} doesn't match the manifest", chunk.root),
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Encodable for ChunkEntry {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3).append(&self.root).append(&self.size).append(&self.hash);
+    }
+}
+
+impl Decodable for ChunkEntry {
+    fn decode(rlp: &Rlp<'_>) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 3 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 3,
+            })
+        }
+        Ok(Self {
+            root: rlp.val_at(0)?,
+            size: rlp.val_at(1)?,
+            hash: rlp.val_at(2)?,
+        })
+    }
+}
+
+impl Encodable for Manifest {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(5)
+            .append(&MANIFEST_VERSION)
+            .append(&self.block_hash)
+            .append(&self.block_number)
+            .append(&self.state_root)
+            .append_list(&self.chunks);
+    }
+}
+
+impl Decodable for Manifest {
+    fn decode(rlp: &Rlp<'_>) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 5 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 5,
+            })
+        }
+        let version: u8 = rlp.val_at(0)?;
+        if version != MANIFEST_VERSION {
+            return Err(DecoderError::Custom("Unknown snapshot manifest version"))
+        }
+        Ok(Self {
+            block_hash: rlp.val_at(1)?,
+            block_number: rlp.val_at(2)?,
+            state_root: rlp.val_at(3)?,
+            chunks: rlp.list_at(4)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn compress(contents: &[u8]) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        snap::Writer::new(&mut compressed).write_all(contents).unwrap();
+        compressed
+    }
+
+    fn write_chunk(dir: &Path, root: H256, contents: &[u8]) -> ChunkEntry {
+        let compressed = compress(contents);
+        fs::write(dir.join(format!("{:x}", root)), &compressed).unwrap();
+        ChunkEntry::new(root, &compressed).unwrap()
+    }
+
+    fn manifest(chunks: Vec<ChunkEntry>) -> Manifest {
+        Manifest {
+            block_hash: H256::random().into(),
+            block_number: 10,
+            state_root: H256::random(),
+            chunks,
+        }
+    }
+
+    #[test]
+    fn encode_and_decode() {
+        let manifest = manifest(vec![ChunkEntry::new(H256::random(), &compress(b"chunk")).unwrap()]);
+        assert_eq!(manifest, rlp::decode(&manifest.rlp_bytes()).unwrap());
+    }
+
+    #[test]
+    fn write_and_read() {
+        let dir = TempDir::new().unwrap();
+        let manifest = manifest(vec![write_chunk(dir.path(), H256::random(), b"chunk")]);
+        manifest.write(dir.path()).unwrap();
+        assert!(!dir.path().join(MANIFEST_TEMP_FILE_NAME).exists());
+        assert_eq!(manifest, Manifest::read(dir.path()).unwrap());
+        assert_eq!(compress(b"chunk").len() as u64, manifest.total_size());
+    }
+
+    #[test]
+    fn hash_decompressed_contents() {
+        let entry = ChunkEntry::new(H256::random(), &compress(b"chunk")).unwrap();
+        assert_eq!(blake256(b"chunk"), entry.hash);
+        assert!(entry.verify(&compress(b"chunk")));
+        assert!(!entry.verify(&compress(b"chnuk")));
+        assert!(!entry.verify(b"chunk"));
+    }
+
+    #[test]
+    fn verify_intact_chunks() {
+        let dir = TempDir::new().unwrap();
+        let manifest = manifest(vec![
+            write_chunk(dir.path(), H256::random(), b"first"),
+            write_chunk(dir.path(), H256::random(), b"second"),
+        ]);
+        manifest.verify_chunks(dir.path()).unwrap();
+    }
+
+    #[test]
+    fn detect_modified_chunk() {
+        let dir = TempDir::new().unwrap();
+        let root = H256::random();
+        let manifest = manifest(vec![write_chunk(dir.path(), root, b"chunk")]);
+        fs::write(dir.path().join(format!("{:x}", root)), compress(b"chnuk")).unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, manifest.verify_chunks(dir.path()).unwrap_err().kind());
+    }
+
+    #[test]
+    fn detect_missing_chunk() {
+        let dir = TempDir::new().unwrap();
+        let root = H256::random();
+        let manifest = manifest(vec![write_chunk(dir.path(), root, b"chunk")]);
+        fs::remove_file(dir.path().join(format!("{:x}", root))).unwrap();
+        assert_eq!(io::ErrorKind::NotFound, manifest.verify_chunks(dir.path()).unwrap_err().kind());
+    }
+}
@@ -14,15 +14,26 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod archive;
+mod block;
+mod manifest;
+mod retention;
+
+pub use self::archive::{export_archive, import_archive};
+pub use self::block::{SnapshotBlock, BLOCK_FILE_NAME};
+pub use self::manifest::{ChunkEntry, Manifest, MANIFEST_FILE_NAME};
+pub use self::retention::{RetentionPolicy, StoredSnapshot};
+
 use ccore::snapshot_notify::{NotifyReceiverSource, ReceiverCanceller};
-use ccore::{BlockChainClient, BlockChainTrait, Client};
+use ccore::{encoded, BlockChainClient, BlockChainTrait, Client};
 use cdb::{AsHashDB, HashDB};
 use cstate::{StateDB, TopLevelState, TopStateView};
 use ctypes::{BlockHash, BlockId};
 use merkle_trie::snapshot::{ChunkCompressor, Error as SnapshotError, Snapshot};
 use primitives::H256;
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 use std::thread::{spawn, JoinHandle};
@@ -39,6 +50,14 @@ pub fn snapshot_dir(root_dir: &str, block: &BlockHash) -> PathBuf {
     path
 }
 
+/// The directory to keep the chunks downloaded from peers until the state is restored.
+pub fn download_dir(root_dir: &str, block: &BlockHash) -> PathBuf {
+    let mut path = PathBuf::new();
+    path.push(root_dir);
+    path.push(format!("{:x}.download", **block));
+    path
+}
+
 pub fn snapshot_path(root_dir: &str, block: &BlockHash, chunk_root: &H256) -> PathBuf {
     let mut path = snapshot_dir(root_dir, block);
     path.push(format!("{:x}", chunk_root));
@@ -50,40 +69,51 @@ impl Service {
         client: Arc<Client>,
         notify_receiver_source: NotifyReceiverSource,
         root_dir: String,
-        expiration: Option<u64>,
+        retention_policy: RetentionPolicy,
     ) -> Self {
         let NotifyReceiverSource(canceller, receiver) = notify_receiver_source;
         let join_handle = spawn(move || {
             cinfo!(SYNC, "Snapshot service is on");
+            verify_snapshots(&root_dir);
             while let Ok(block_hash) = receiver.recv() {
                 cinfo!(SYNC, "Snapshot is requested for block: {}", block_hash);
-                let state_root = if let Some(header) = client.block_header(&BlockId::Hash(block_hash)) {
-                    header.state_root()
+                let header = if let Some(header) = client.block_header(&BlockId::Hash(block_hash)) {
+                    header
                 } else {
                     cerror!(SYNC, "There isn't corresponding header for the requested block hash: {}", block_hash,);
                     continue
                 };
+                let block = match snapshot_block(&client, &header) {
+                    Some(block) => block,
+                    None => {
+                        cerror!(
+                            SYNC,
+                            "There isn't the parent header or the body of the requested block: {}",
+                            block_hash
+                        );
+                        continue
+                    }
+                };
                 {
                     let db_lock = client.state_db().read();
-                    if let Err(err) = snapshot(&db_lock, block_hash, state_root, &root_dir) {
+                    if let Err(err) = snapshot(&db_lock, &block, &root_dir) {
                         cerror!(
                             SYNC,
                             "Snapshot request failed for block: {}, chunk_root: {}, err: {}",
                             block_hash,
-                            state_root,
+                            header.state_root(),
                             err
                         );
+                        // A snapshot without a manifest is useless.
+                        cleanup_snapshot(&root_dir, block_hash);
                     } else {
                         cinfo!(SYNC, "Snapshot is ready for block: {}", block_hash)
                     }
                 }
 
-                if let Some(expiration) = expiration {
-                    if let Err(err) = cleanup_expired(&client, &root_dir, expiration) {
-                        cerror!(SYNC, "Snapshot cleanup error after block hash {}, err: {}", block_hash, err);
-                    }
+                if let Err(err) = prune(&client, &root_dir, &retention_policy) {
+                    cerror!(SYNC, "Snapshot pruning error after block hash {}, err: {}", block_hash, err);
                 }
-                // TODO: Prune old snapshots
             }
             cinfo!(SYNC, "Snapshot service is stopped")
         });
@@ -94,8 +124,22 @@ impl Service {
         }
     }
 }
-fn snapshot(db: &StateDB, block_hash: BlockHash, root: H256, dir: &str) -> Result<(), SnapshotError> {
-    snapshot_trie(db.as_hashdb(), block_hash, root, dir)?;
+
+fn snapshot_block(client: &Client, header: &encoded::Header) -> Option<SnapshotBlock> {
+    let parent = client.block_header(&BlockId::Hash(header.parent_hash()))?;
+    let body = client.block_body(&BlockId::Hash(header.hash()))?;
+    Some(SnapshotBlock {
+        parent: parent.decode(),
+        header: header.decode(),
+        evidences: body.evidences(),
+        transactions: body.transactions(),
+    })
+}
+
+fn snapshot(db: &StateDB, block: &SnapshotBlock, dir: &str) -> Result<(), SnapshotError> {
+    let block_hash = block.header.hash();
+    let root = *block.header.state_root();
+    let mut chunks = snapshot_trie(db.as_hashdb(), block_hash, root, dir)?;
 
     let top_state = TopLevelState::from_existing(db.clone(&root), root)?;
     let module_roots = {
@@ -104,31 +148,52 @@ fn snapshot(db: &StateDB, block_hash: BlockHash, root: H256, dir: &str) -> Resul
         (0..module_num).map(|n| top_state.module_root(n))
     };
     for sr in module_roots {
-        snapshot_trie(db.as_hashdb(), block_hash, sr?.expect("Module root must exist"), dir)?;
+        chunks.extend(snapshot_trie(db.as_hashdb(), block_hash, sr?.expect("Module root must exist"), dir)?);
     }
+
+    let manifest = Manifest {
+        block_hash,
+        block_number: block.header.number(),
+        state_root: root,
+        chunks,
+    };
+    let snapshot_dir = snapshot_dir(dir, &block_hash);
+    block.write(&snapshot_dir)?;
+    manifest.write(&snapshot_dir)?;
     Ok(())
 }
 
-fn snapshot_trie(db: &dyn HashDB, block_hash: BlockHash, root: H256, root_dir: &str) -> Result<(), SnapshotError> {
+fn snapshot_trie(
+    db: &dyn HashDB,
+    block_hash: BlockHash,
+    root: H256,
+    root_dir: &str,
+) -> Result<Vec<ChunkEntry>, SnapshotError> {
     let snapshot_dir = snapshot_dir(root_dir, &block_hash);
     fs::create_dir_all(snapshot_dir)?;
 
+    let mut entries = Vec::new();
     for chunk in Snapshot::from_hashdb(db, root) {
-        let chunk_path = snapshot_path(root_dir, &block_hash, &chunk.root);
-        let chunk_file = fs::File::create(chunk_path)?;
-        let compressor = ChunkCompressor::new(chunk_file);
+        let mut compressed = Vec::new();
+        let compressor = ChunkCompressor::new(&mut compressed);
         compressor.compress_chunk(&chunk)?;
+        fs::write(snapshot_path(root_dir, &block_hash, &chunk.root), &compressed)?;
+        entries.push(ChunkEntry::new(chunk.root, &compressed)?);
     }
 
-    Ok(())
+    Ok(entries)
 }
 
-fn cleanup_expired(client: &Client, root_dir: &str, expiration: u64) -> Result<(), SnapshotError> {
+/// Returns the block hashes of the snapshot directories in `root_dir`, and the directories left by interrupted
+/// cleanups.
+fn stored_snapshots(root_dir: &str) -> io::Result<(Vec<BlockHash>, Vec<PathBuf>)> {
+    let mut hashes = Vec::new();
+    let mut leftovers = Vec::new();
     for entry in fs::read_dir(root_dir)? {
         let entry = match entry {
             Ok(entry) => entry,
             Err(err) => {
-                cerror!(SYNC, "Snapshot cleanup can't retrieve entry. err: {}", err);
+                cerror!(SYNC, "Snapshot directory can't retrieve entry. err: {}", err);
                 continue
             }
         };
@@ -138,7 +203,7 @@ fn cleanup_expired(client: &Client, root_dir: &str, expiration: u64) -> Result<(
             Ok(true) => {}
             Ok(false) => continue,
             Err(err) => {
-                cerror!(SYNC, "Snapshot cleanup can't retrieve file info: {}, err: {}", path.to_string_lossy(), err);
+                cerror!(SYNC, "Snapshot directory can't retrieve file info: {}, err: {}", path.to_string_lossy(), err);
                 continue
             }
         }
@@ -147,21 +212,98 @@ fn cleanup_expired(client: &Client, root_dir: &str, expiration: u64) -> Result<(
             Some(n) => n,
             None => continue,
         };
-        let hash = match H256::from_str(name) {
-            Ok(h) => BlockHash::from(h),
+        if name.ends_with(".old") {
+            leftovers.push(path);
+            continue
+        }
+        match H256::from_str(name) {
+            Ok(h) => hashes.push(BlockHash::from(h)),
             Err(_) => continue,
-        };
-        let number = if let Some(number) = client.block_number(&BlockId::Hash(hash)) {
-            number
-        } else {
-            cerror!(SYNC, "Snapshot cleanup can't retrieve block number for block_hash: {}", hash);
+        }
+    }
+    Ok((hashes, leftovers))
+}
+
+/// Returns the block hashes of the complete snapshots in `root_dir`.
+pub fn available_snapshots(root_dir: &str) -> Vec<BlockHash> {
+    if !Path::new(root_dir).exists() {
+        return Vec::new()
+    }
+    match stored_snapshots(root_dir) {
+        Ok((hashes, _)) => {
+            hashes.into_iter().filter(|hash| snapshot_dir(root_dir, hash).join(MANIFEST_FILE_NAME).exists()).collect()
+        }
+        Err(err) => {
+            cerror!(SYNC, "Can't read the snapshot directory, err: {}", err);
+            Vec::new()
+        }
+    }
+}
+
+/// Removes the corrupted snapshots and the incomplete ones, which have no manifest.
+fn verify_snapshots(root_dir: &str) {
+    if !Path::new(root_dir).exists() {
+        return
+    }
+    let (hashes, leftovers) = match stored_snapshots(root_dir) {
+        Ok(result) => result,
+        Err(err) => {
+            cerror!(SYNC, "Snapshot verification can't read the snapshot directory, err: {}", err);
+            return
+        }
+    };
+    for path in leftovers {
+        if let Err(err) = fs::remove_dir_all(&path) {
+            cerror!(SYNC, "Snapshot cleanup: removing {} failed, reason: {}", path.to_string_lossy(), err);
+        }
+    }
+    for hash in hashes {
+        let dir = snapshot_dir(root_dir, &hash);
+        if !dir.join(MANIFEST_FILE_NAME).exists() {
+            cwarn!(SYNC, "Snapshot of block {} has no manifest. It will be removed", hash);
+            cleanup_snapshot(root_dir, hash);
             continue
-        };
+        }
+        let result = Manifest::read(&dir).and_then(|manifest| {
+            if manifest.block_hash != hash {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "The manifest is for another block"))
+            }
+            manifest.verify_chunks(&dir)
+        });
+        match result {
+            Ok(()) => cdebug!(SYNC, "Snapshot of block {} is verified", hash),
+            Err(err) => {
+                cwarn!(SYNC, "Snapshot of block {} is incomplete or corrupted. It will be removed. err: {}", hash, err);
+                cleanup_snapshot(root_dir, hash);
+            }
+        }
+    }
+}
 
-        if number + expiration < client.best_block_header().number() {
-            cleanup_snapshot(root_dir, hash)
+fn prune(client: &Client, root_dir: &str, policy: &RetentionPolicy) -> Result<(), SnapshotError> {
+    let mut snapshots = Vec::new();
+    let (hashes, _) = stored_snapshots(root_dir)?;
+    for hash in hashes {
+        match Manifest::read(&snapshot_dir(root_dir, &hash)) {
+            Ok(manifest) => snapshots.push(StoredSnapshot {
+                block_hash: hash,
+                block_number: manifest.block_number,
+                size: manifest.total_size(),
+            }),
+            // Snapshots are made on this thread, so a snapshot without a manifest is an incomplete one.
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+                cwarn!(SYNC, "Snapshot of block {} has no manifest. It will be removed", hash);
+                cleanup_snapshot(root_dir, hash);
+            }
+            Err(err) => cerror!(SYNC, "Snapshot pruning can't read the manifest of block {}, err: {}", hash, err),
         }
     }
+
+    let best_block_number = client.best_block_header().number();
+    for hash in policy.select_to_remove(&snapshots, best_block_number) {
+        cinfo!(SYNC, "Snapshot of block {} is removed by the retention policy", hash);
+        cleanup_snapshot(root_dir, hash)
+    }
     Ok(())
 }
 
@@ -0,0 +1,183 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ctypes::{BlockHash, BlockNumber};
+use std::cmp::Reverse;
+
+/// Decides which snapshots are kept.
+///
+/// A snapshot is removed if any of the rules rejects it. The most recent snapshot is never removed by
+/// `keep_last`, `keep_every` and `max_disk_usage`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RetentionPolicy {
+    /// Remove snapshots older than this number of blocks.
+    pub expiration: Option<u64>,
+    /// Keep the most recent N snapshots.
+    pub keep_last: Option<usize>,
+    /// Keep the snapshots whose block number is a multiple of K.
+    /// If it is given together with `keep_last`, a snapshot is kept when either rule keeps it.
+    pub keep_every: Option<u64>,
+    /// Remove the oldest snapshots until the total size of snapshots is less than or equal to this, in bytes.
+    pub max_disk_usage: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StoredSnapshot {
+    pub block_hash: BlockHash,
+    pub block_number: BlockNumber,
+    pub size: u64,
+}
+
+impl RetentionPolicy {
+    /// Returns the snapshots to remove.
+    pub fn select_to_remove(&self, snapshots: &[StoredSnapshot], best_block_number: BlockNumber) -> Vec<BlockHash> {
+        let mut snapshots = snapshots.to_vec();
+        snapshots.sort_unstable_by_key(|snapshot| Reverse(snapshot.block_number));
+
+        let mut kept = Vec::with_capacity(snapshots.len());
+        let mut removed = Vec::new();
+        for (index, snapshot) in snapshots.into_iter().enumerate() {
+            let is_expired = self
+                .expiration
+                .map(|expiration| snapshot.block_number + expiration < best_block_number)
+                .unwrap_or(false);
+            if is_expired || (index != 0 && !self.is_kept_by_count(index, snapshot.block_number)) {
+                removed.push(snapshot.block_hash);
+            } else {
+                kept.push(snapshot);
+            }
+        }
+
+        if let Some(max_disk_usage) = self.max_disk_usage {
+            let mut total_size: u64 = kept.iter().map(|snapshot| snapshot.size).sum();
+            while total_size > max_disk_usage && kept.len() > 1 {
+                let oldest = kept.pop().expect("There are at least two snapshots");
+                total_size -= oldest.size;
+                removed.push(oldest.block_hash);
+            }
+        }
+        removed
+    }
+
+    fn is_kept_by_count(&self, index: usize, block_number: BlockNumber) -> bool {
+        if self.keep_last.is_none() && self.keep_every.is_none() {
+            return true
+        }
+        let kept_as_recent = self.keep_last.map(|keep_last| index < keep_last).unwrap_or(false);
+        let kept_as_periodic =
+            self.keep_every.map(|keep_every| keep_every != 0 && block_number % keep_every == 0).unwrap_or(false);
+        kept_as_recent || kept_as_periodic
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitives::H256;
+
+    fn snapshot(block_number: BlockNumber, size: u64) -> StoredSnapshot {
+        StoredSnapshot {
+            block_hash: H256::random().into(),
+            block_number,
+            size,
+        }
+    }
+
+    fn removed_numbers(policy: RetentionPolicy, snapshots: &[StoredSnapshot], best: BlockNumber) -> Vec<BlockNumber> {
+        let mut numbers: Vec<_> = policy
+            .select_to_remove(snapshots, best)
+            .into_iter()
+            .map(|hash| snapshots.iter().find(|s| s.block_hash == hash).unwrap().block_number)
+            .collect();
+        numbers.sort_unstable();
+        numbers
+    }
+
+    #[test]
+    fn default_policy_keeps_everything() {
+        let snapshots = [snapshot(10, 1), snapshot(20, 1), snapshot(30, 1)];
+        assert_eq!(Vec::<BlockNumber>::new(), removed_numbers(RetentionPolicy::default(), &snapshots, 1000));
+    }
+
+    #[test]
+    fn expiration() {
+        let policy = RetentionPolicy {
+            expiration: Some(15),
+            ..Default::default()
+        };
+        let snapshots = [snapshot(10, 1), snapshot(20, 1), snapshot(30, 1)];
+        assert_eq!(vec![10], removed_numbers(policy, &snapshots, 30));
+    }
+
+    #[test]
+    fn keep_last() {
+        let policy = RetentionPolicy {
+            keep_last: Some(2),
+            ..Default::default()
+        };
+        let snapshots = [snapshot(20, 1), snapshot(40, 1), snapshot(10, 1), snapshot(30, 1)];
+        assert_eq!(vec![10, 20], removed_numbers(policy, &snapshots, 40));
+    }
+
+    #[test]
+    fn keep_every() {
+        let policy = RetentionPolicy {
+            keep_every: Some(100),
+            ..Default::default()
+        };
+        let snapshots = [snapshot(100, 1), snapshot(150, 1), snapshot(200, 1), snapshot(250, 1)];
+        // The most recent one is always kept.
+        assert_eq!(vec![150], removed_numbers(policy, &snapshots, 250));
+    }
+
+    #[test]
+    fn keep_last_and_keep_every() {
+        let policy = RetentionPolicy {
+            keep_last: Some(2),
+            keep_every: Some(100),
+            ..Default::default()
+        };
+        let snapshots = [
+            snapshot(100, 1),
+            snapshot(150, 1),
+            snapshot(200, 1),
+            snapshot(250, 1),
+            snapshot(300, 1),
+            snapshot(350, 1),
+        ];
+        assert_eq!(vec![150, 250], removed_numbers(policy, &snapshots, 350));
+    }
+
+    #[test]
+    fn max_disk_usage_removes_the_oldest_first() {
+        let policy = RetentionPolicy {
+            max_disk_usage: Some(25),
+            ..Default::default()
+        };
+        let snapshots = [snapshot(10, 10), snapshot(20, 10), snapshot(30, 10)];
+        assert_eq!(vec![10], removed_numbers(policy, &snapshots, 30));
+    }
+
+    #[test]
+    fn max_disk_usage_keeps_the_most_recent_one() {
+        let policy = RetentionPolicy {
+            max_disk_usage: Some(5),
+            ..Default::default()
+        };
+        let snapshots = [snapshot(10, 10), snapshot(20, 10)];
+        assert_eq!(vec![10], removed_numbers(policy, &snapshots, 20));
+    }
+}
@@ -54,6 +54,10 @@ impl UserModule for Module {
                 assert_empty_arg(ctor_arg).unwrap();
                 Skeleton::new(self.service_handler.get_stateful())
             }
+            "get-seq" => {
+                assert_empty_arg(ctor_arg).unwrap();
+                Skeleton::new(Arc::clone(&self.service_handler) as Arc<dyn GetSeq>)
+            }
             "get-account-and-seq" => {
                 assert_empty_arg(ctor_arg).unwrap();
                 Skeleton::new(Box::new(super::types::GetAccountAndSeq) as Box<dyn crate::sorting::GetAccountAndSeq>)
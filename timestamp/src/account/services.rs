@@ -17,7 +17,7 @@
 use super::state_machine::*;
 use super::types::*;
 use super::ServiceHandler;
-use crate::common::SignedTransaction;
+use crate::common::{SignedTransaction, TxSeq};
 pub use ckey::{Ed25519Private as Private, Ed25519Public as Public};
 use coordinator::module::*;
 use coordinator::types::*;
@@ -60,6 +60,12 @@ impl AccountManager for ServiceHandler {
     }
 }
 
+impl GetSeq for ServiceHandler {
+    fn get_seq(&self, session: SessionId, public: &Public) -> TxSeq {
+        self.get_account(session, public, true).map(|account| account.seq).unwrap_or_default()
+    }
+}
+
 impl TxOwner for ServiceHandler {
     fn block_opened(&self, _: SessionId, _: &Header) -> Result<(), HeaderError> {
         Ok(())
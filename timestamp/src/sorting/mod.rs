@@ -18,20 +18,13 @@ mod module;
 pub mod services;
 
 use crate::account::services::AccountManager;
-use crate::common::*;
 pub use ckey::Ed25519Public as Public;
-use coordinator::Transaction;
+pub use coordinator::module::GetAccountAndSeq;
 pub use module::Module;
 use parking_lot::RwLock;
 use remote_trait_object::raw_exchange::import_null_proxy;
-use remote_trait_object::{service, Service};
 use std::collections::HashMap;
 
-#[service]
-pub trait GetAccountAndSeq: Service {
-    fn get_account_and_seq(&self, tx: &Transaction) -> Result<(Public, TxSeq), ()>;
-}
-
 struct ServiceHandler {
     account_manager: RwLock<Box<dyn AccountManager>>,
     get_account_and_seqs: RwLock<HashMap<String, Box<dyn GetAccountAndSeq>>>,
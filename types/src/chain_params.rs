@@ -29,6 +29,49 @@ pub struct ChainParams {
     max_body_size: u64,
     /// Snapshot creation period in unit of block numbers.
     snapshot_period: u64,
+    /// Timeouts of the consensus steps. The timeouts in the app descriptor are used if it is not given.
+    #[serde(default)]
+    timeouts: Option<ConsensusTimeouts>,
+}
+
+/// Timeouts of the consensus steps in milliseconds.
+/// The timeouts decided by a block are applied from the next height.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, RlpEncodable, RlpDecodable)]
+pub struct ConsensusTimeouts {
+    pub propose: u64,
+    pub propose_delta: u64,
+    pub prevote: u64,
+    pub prevote_delta: u64,
+    pub precommit: u64,
+    pub precommit_delta: u64,
+    pub commit: u64,
+}
+
+/// The longest consensus timeout in milliseconds. A longer one would stall the chain for too long.
+pub const MAX_CONSENSUS_TIMEOUT: u64 = 10 * 60 * 1000;
+
+impl ConsensusTimeouts {
+    /// Checks that every timeout is positive and not longer than `MAX_CONSENSUS_TIMEOUT`.
+    pub fn verify(&self) -> Result<(), String> {
+        let timeouts = [
+            ("propose", self.propose),
+            ("propose_delta", self.propose_delta),
+            ("prevote", self.prevote),
+            ("prevote_delta", self.prevote_delta),
+            ("precommit", self.precommit),
+            ("precommit_delta", self.precommit_delta),
+            ("commit", self.commit),
+        ];
+        for (name, timeout) in timeouts.iter() {
+            if *timeout == 0 || *timeout > MAX_CONSENSUS_TIMEOUT {
+                return Err(format!(
+                    "The {} timeout should be in 1..={}ms, but it is {}ms",
+                    name, MAX_CONSENSUS_TIMEOUT, timeout
+                ))
+            }
+        }
+        Ok(())
+    }
 }
 
 impl ChainParams {
@@ -44,6 +87,12 @@ impl ChainParams {
     pub fn snapshot_period(&self) -> u64 {
         self.snapshot_period
     }
+    pub fn timeouts(&self) -> Option<ConsensusTimeouts> {
+        self.timeouts
+    }
+    pub fn set_timeouts(&mut self, timeouts: Option<ConsensusTimeouts>) {
+        self.timeouts = timeouts;
+    }
 
     pub fn default_for_test() -> Self {
         Self {
@@ -51,40 +100,53 @@ impl ChainParams {
             network_id: NetworkId::from_str("dt").unwrap(),
             max_body_size: 100_000,
             snapshot_period: 1000,
+            timeouts: None,
         }
     }
 }
 
 impl Encodable for ChainParams {
     fn rlp_append(&self, s: &mut RlpStream) {
-        s.begin_list(4)
+        // The timeouts are omitted if they are not given, so that the encoding stays the same as before they were added.
+        let size = if self.timeouts.is_some() {
+            5
+        } else {
+            4
+        };
+        s.begin_list(size)
             .append(&self.max_extra_data_size)
             .append(&self.network_id)
             .append(&self.max_body_size)
             .append(&self.snapshot_period);
+        if let Some(timeouts) = &self.timeouts {
+            s.append(timeouts);
+        }
     }
 }
 
 impl Decodable for ChainParams {
     fn decode(rlp: &Rlp<'_>) -> Result<Self, DecoderError> {
         let size = rlp.item_count()?;
-        if size != 4 {
-            return Err(DecoderError::RlpIncorrectListLen {
-                expected: 4,
-                got: size,
-            })
+        if size != 4 && size != 5 {
+            return Err(DecoderError::Custom("The chain params must have 4 items, or 5 items with the timeouts"))
         }
 
         let max_extra_data_size = rlp.val_at(0)?;
         let network_id = rlp.val_at(1)?;
         let max_body_size = rlp.val_at(2)?;
         let snapshot_period = rlp.val_at(3)?;
+        let timeouts = if size == 5 {
+            Some(rlp.val_at(4)?)
+        } else {
+            None
+        };
 
         Ok(Self {
             max_extra_data_size,
             network_id,
             max_body_size,
             snapshot_period,
+            timeouts,
         })
     }
 }
@@ -106,4 +168,69 @@ mod tests {
         params.max_body_size = 123;
         rlp_encode_and_decode_test!(params);
     }
+
+    #[test]
+    fn rlp_with_timeouts() {
+        let mut params = ChainParams::default_for_test();
+        params.set_timeouts(Some(ConsensusTimeouts {
+            propose: 3000,
+            propose_delta: 1000,
+            prevote: 1000,
+            prevote_delta: 500,
+            precommit: 1000,
+            precommit_delta: 500,
+            commit: 2000,
+        }));
+        rlp_encode_and_decode_test!(params);
+    }
+
+    #[test]
+    fn decode_params_without_timeouts() {
+        let params = ChainParams::default_for_test();
+        let mut s = RlpStream::new_list(4);
+        s.append(&params.max_extra_data_size)
+            .append(&params.network_id)
+            .append(&params.max_body_size)
+            .append(&params.snapshot_period);
+        let encoded = s.out();
+        assert_eq!(&encoded[..], &rlp::encode(&params)[..]);
+        assert_eq!(params, rlp::decode(&encoded).unwrap());
+    }
+
+    #[test]
+    fn reject_params_with_wrong_item_count() {
+        let params = ChainParams::default_for_test();
+        let mut s = RlpStream::new_list(3);
+        s.append(&params.max_extra_data_size).append(&params.network_id).append(&params.max_body_size);
+        assert_eq!(
+            Err(DecoderError::Custom("The chain params must have 4 items, or 5 items with the timeouts")),
+            rlp::decode::<ChainParams>(&s.out())
+        );
+    }
+
+    #[test]
+    fn verify_timeouts() {
+        let timeouts = ConsensusTimeouts {
+            propose: 3000,
+            propose_delta: 1000,
+            prevote: 1000,
+            prevote_delta: 500,
+            precommit: 1000,
+            precommit_delta: 500,
+            commit: 2000,
+        };
+        assert_eq!(Ok(()), timeouts.verify());
+        assert!(ConsensusTimeouts {
+            prevote: 0,
+            ..timeouts
+        }
+        .verify()
+        .is_err());
+        assert!(ConsensusTimeouts {
+            commit: MAX_CONSENSUS_TIMEOUT + 1,
+            ..timeouts
+        }
+        .verify()
+        .is_err());
+    }
 }
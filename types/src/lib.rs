@@ -43,7 +43,7 @@ pub struct TransactionLocation {
 
 pub use block_hash::BlockHash;
 pub use block_id::BlockId;
-pub use chain_params::ChainParams;
+pub use chain_params::{ChainParams, ConsensusTimeouts};
 pub use header::Header;
 pub use sync_header::SyncHeader;
 pub use tx_hash::TxHash;